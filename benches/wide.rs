@@ -0,0 +1,67 @@
+//! Reading wide files, like response matrices with a column per corrector.
+//!
+//! `cargo bench --bench wide` reads files of 5000 and 20000 real columns, and one whose rows
+//! lack half of their fields, read as nulls. The throughput is reported in bytes, to compare
+//! with narrow files of the same size.
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::fmt::Write;
+use std::io::Cursor;
+use tfs::{MissingFields, TfsHeaderParser, TfsReadOptions};
+
+/// A file with a `NAME` column and `columns` real columns, every row with only `fields` of them.
+fn wide_file(columns: usize, rows: usize, fields: usize) -> Vec<u8> {
+    let mut file = String::from("@ TYPE             %s \"RESPONSE\"\n* NAME");
+    for column in 0..columns {
+        write!(file, " KQ{}", column).unwrap();
+    }
+    file.push_str("\n$ %s");
+    file.push_str(&" %le".repeat(columns));
+    for row in 0..rows {
+        write!(file, "\n \"BPM.{}\"", row).unwrap();
+        for column in 0..fields {
+            write!(file, " {:.16e}", (row * columns + column) as f64 * 1e-7).unwrap();
+        }
+    }
+    file.push('\n');
+    file.into_bytes()
+}
+
+fn read(bytes: &[u8], options: &TfsReadOptions) {
+    let frame = TfsHeaderParser::with_options(Cursor::new(bytes), options.clone())
+        .parse::<f64>()
+        .unwrap()
+        .finish()
+        .unwrap();
+    assert!(frame.column_count() > 1);
+}
+
+fn bench_wide(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wide");
+    group.sample_size(10);
+    let files = [
+        (
+            "5000 columns",
+            wide_file(5000, 200, 5000),
+            MissingFields::Error,
+        ),
+        (
+            "20000 columns",
+            wide_file(20000, 50, 20000),
+            MissingFields::Error,
+        ),
+        (
+            "5000 columns, half missing",
+            wide_file(5000, 200, 2500),
+            MissingFields::Null,
+        ),
+    ];
+    for (name, bytes, missing_fields) in files {
+        let options = TfsReadOptions::new().missing_fields(missing_fields);
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_function(name, |b| b.iter(|| read(&bytes, &options)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_wide);
+criterion_main!(benches);
@@ -0,0 +1,9 @@
+//! Feeds arbitrary bytes to the parser, which has to reject them with an error instead of
+//! panicking: `cargo fuzz run parse`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = tfs::testing::parse_bytes(data);
+});
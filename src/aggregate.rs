@@ -0,0 +1,329 @@
+//! One-pass summaries of files too large to load at once.
+//!
+//! An [`Aggregator`] folds the values of a column into a single number, chunk by chunk. A
+//! [`Summary`] reads a tfs file in chunks, feeds every chunk to its aggregators and drops it
+//! before reading the next. The result is a frame with a row per aggregator, with its name in
+//! `NAME` and its result in `VALUE`:
+//!
+//! ```
+//! # use tfs::aggregate::{Count, CountWhere, Max, Mean, Summary};
+//! let summary = Summary::new()
+//!     .with(Count::rows())
+//!     .with(Mean::of("BETX"))
+//!     .with(Max::of("BETY"))
+//!     .with(CountWhere::of("S", |s| s > 1.0))
+//!     .read::<f64, _>("test/test.tfs")
+//!     .unwrap();
+//! assert_eq!(summary.len(), 4);
+//! ```
+//!
+//! Missing values and `NaN`s are skipped, like in [`ColumnStats`](crate::ColumnStats).
+use polars::prelude::{DataFrame, NumericNative};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::options::TfsReadOptions;
+use crate::record::ColumnValue;
+use crate::stages::TfsHeaderParser;
+use crate::tfsdataframe::{Properties, TfsDataFrame, NROWS_KEY};
+
+/// Rows read at once by a [`Summary`].
+const CHUNK_ROWS: usize = 8192;
+
+/// Folds the rows of a frame into a single number, one chunk of rows at a time.
+pub trait Aggregator {
+    /// The name of the result, e.g. `MEAN(BETX)`.
+    fn name(&self) -> String;
+
+    /// Folds the rows of `chunk` into the result.
+    fn update(&mut self, chunk: &DataFrame) -> anyhow::Result<()>;
+
+    /// The result over the rows folded so far.
+    fn value(&self) -> f64;
+}
+
+/// The values of the column `name` of `chunk` as reals, without missing values and `NaN`s.
+fn reals(chunk: &DataFrame, name: &str) -> anyhow::Result<Vec<f64>> {
+    let column = chunk.column(name)?.as_materialized_series();
+    Ok(Option::<f64>::from_column(column)?
+        .into_iter()
+        .flatten()
+        .filter(|v| !v.is_nan())
+        .collect())
+}
+
+/// The number of rows.
+#[derive(Debug, Clone, Default)]
+pub struct Count {
+    count: usize,
+}
+
+impl Count {
+    pub fn rows() -> Count {
+        Count::default()
+    }
+}
+
+impl Aggregator for Count {
+    fn name(&self) -> String {
+        "COUNT".to_owned()
+    }
+
+    fn update(&mut self, chunk: &DataFrame) -> anyhow::Result<()> {
+        self.count += chunk.height();
+        Ok(())
+    }
+
+    fn value(&self) -> f64 {
+        self.count as f64
+    }
+}
+
+/// The sum of a column.
+#[derive(Debug, Clone)]
+pub struct Sum {
+    column: String,
+    sum: f64,
+}
+
+impl Sum {
+    pub fn of(column: &str) -> Sum {
+        Sum {
+            column: column.to_owned(),
+            sum: 0.0,
+        }
+    }
+}
+
+impl Aggregator for Sum {
+    fn name(&self) -> String {
+        format!("SUM({})", self.column)
+    }
+
+    fn update(&mut self, chunk: &DataFrame) -> anyhow::Result<()> {
+        self.sum += reals(chunk, &self.column)?.iter().sum::<f64>();
+        Ok(())
+    }
+
+    fn value(&self) -> f64 {
+        self.sum
+    }
+}
+
+/// The mean of a column, `NaN` without values.
+#[derive(Debug, Clone)]
+pub struct Mean {
+    column: String,
+    sum: f64,
+    count: usize,
+}
+
+impl Mean {
+    pub fn of(column: &str) -> Mean {
+        Mean {
+            column: column.to_owned(),
+            sum: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl Aggregator for Mean {
+    fn name(&self) -> String {
+        format!("MEAN({})", self.column)
+    }
+
+    fn update(&mut self, chunk: &DataFrame) -> anyhow::Result<()> {
+        let values = reals(chunk, &self.column)?;
+        self.sum += values.iter().sum::<f64>();
+        self.count += values.len();
+        Ok(())
+    }
+
+    fn value(&self) -> f64 {
+        match self.count {
+            0 => f64::NAN,
+            count => self.sum / count as f64,
+        }
+    }
+}
+
+/// The minimum of a column, `NaN` without values.
+#[derive(Debug, Clone)]
+pub struct Min {
+    column: String,
+    min: f64,
+}
+
+impl Min {
+    pub fn of(column: &str) -> Min {
+        Min {
+            column: column.to_owned(),
+            min: f64::NAN,
+        }
+    }
+}
+
+impl Aggregator for Min {
+    fn name(&self) -> String {
+        format!("MIN({})", self.column)
+    }
+
+    fn update(&mut self, chunk: &DataFrame) -> anyhow::Result<()> {
+        self.min = reals(chunk, &self.column)?
+            .into_iter()
+            .fold(self.min, f64::min);
+        Ok(())
+    }
+
+    fn value(&self) -> f64 {
+        self.min
+    }
+}
+
+/// The maximum of a column, `NaN` without values.
+#[derive(Debug, Clone)]
+pub struct Max {
+    column: String,
+    max: f64,
+}
+
+impl Max {
+    pub fn of(column: &str) -> Max {
+        Max {
+            column: column.to_owned(),
+            max: f64::NAN,
+        }
+    }
+}
+
+impl Aggregator for Max {
+    fn name(&self) -> String {
+        format!("MAX({})", self.column)
+    }
+
+    fn update(&mut self, chunk: &DataFrame) -> anyhow::Result<()> {
+        self.max = reals(chunk, &self.column)?
+            .into_iter()
+            .fold(self.max, f64::max);
+        Ok(())
+    }
+
+    fn value(&self) -> f64 {
+        self.max
+    }
+}
+
+/// The number of values of a column for which a predicate holds.
+pub struct CountWhere {
+    column: String,
+    predicate: Box<dyn Fn(f64) -> bool>,
+    count: usize,
+}
+
+impl CountWhere {
+    pub fn of<F: Fn(f64) -> bool + 'static>(column: &str, predicate: F) -> CountWhere {
+        CountWhere {
+            column: column.to_owned(),
+            predicate: Box::new(predicate),
+            count: 0,
+        }
+    }
+}
+
+impl Aggregator for CountWhere {
+    fn name(&self) -> String {
+        format!("COUNT_WHERE({})", self.column)
+    }
+
+    fn update(&mut self, chunk: &DataFrame) -> anyhow::Result<()> {
+        let values = reals(chunk, &self.column)?;
+        self.count += values.into_iter().filter(|v| (self.predicate)(*v)).count();
+        Ok(())
+    }
+
+    fn value(&self) -> f64 {
+        self.count as f64
+    }
+}
+
+/// Aggregators run together over the rows of a file or frame, see the
+/// [module documentation](self).
+#[derive(Default)]
+pub struct Summary {
+    aggregators: Vec<Box<dyn Aggregator>>,
+}
+
+impl Summary {
+    pub fn new() -> Summary {
+        Summary::default()
+    }
+
+    pub fn with<A: Aggregator + 'static>(mut self, aggregator: A) -> Self {
+        self.aggregators.push(Box::new(aggregator));
+        self
+    }
+
+    /// Reads the tfs file at `path` chunk by chunk and returns the results.
+    pub fn read<T, P>(self, path: P) -> anyhow::Result<TfsDataFrame<T>>
+    where
+        T: std::str::FromStr + NumericNative,
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+        P: AsRef<Path>,
+    {
+        self.read_with(path, &TfsReadOptions::default())
+    }
+
+    /// Like [`Summary::read`], parsing the file according to `options`.
+    pub fn read_with<T, P>(
+        mut self,
+        path: P,
+        options: &TfsReadOptions,
+    ) -> anyhow::Result<TfsDataFrame<T>>
+    where
+        T: std::str::FromStr + NumericNative,
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+        P: AsRef<Path>,
+    {
+        let reader = BufReader::new(File::open(path.as_ref())?);
+        let mut body = TfsHeaderParser::with_options(reader, options.clone()).parse::<T>()?;
+        while body.read_rows(CHUNK_ROWS)? > 0 {
+            let chunk = body.take_rows()?;
+            for aggregator in &mut self.aggregators {
+                aggregator.update(&chunk)?;
+            }
+        }
+
+        let mut properties = body.header().properties.clone();
+        properties.shift_remove(NROWS_KEY);
+        self.results(properties)
+    }
+
+    /// Runs the aggregators over the rows of `df` and returns the results.
+    pub fn of_frame<T>(mut self, df: &TfsDataFrame<T>) -> anyhow::Result<TfsDataFrame<T>>
+    where
+        T: std::str::FromStr + NumericNative,
+    {
+        let full = df.df()?;
+        for aggregator in &mut self.aggregators {
+            aggregator.update(&full)?;
+        }
+        let mut properties = df.properties.clone();
+        properties.shift_remove(NROWS_KEY);
+        self.results(properties)
+    }
+
+    fn results<T>(&self, properties: Properties<T>) -> anyhow::Result<TfsDataFrame<T>>
+    where
+        T: std::str::FromStr + NumericNative,
+    {
+        let names: Vec<String> = self.aggregators.iter().map(|a| a.name()).collect();
+        let values: Vec<f64> = self.aggregators.iter().map(|a| a.value()).collect();
+        let df = DataFrame::new(vec![
+            String::to_column("NAME", names).into(),
+            f64::to_column("VALUE", values).into(),
+        ])?;
+        Ok(TfsDataFrame::new(properties, df))
+    }
+}
@@ -0,0 +1,254 @@
+//! Aperture tables of MAD-X and the beam-stay-clear.
+//!
+//! MAD-X describes the aperture of an element by its shape in `APERTYPE` and up to four sizes
+//! in `APER_1` to `APER_4`, in metres. [`read_aperture`] reads a table with these columns, e.g.
+//! the output of `APERTURE` or a twiss with the aperture columns selected. [`beam_stay_clear`]
+//! joins it with a twiss on `NAME` and computes how many beam sizes fit into the aperture:
+//!
+//! ```text
+//! N1X = (AX - |X| - closed orbit - |DX| δ) / sqrt((1 + beta-beating) BETX EX)
+//! ```
+//!
+//! where `AX` is the horizontal half width of the aperture, and `N1Y` alike. `N1` is the
+//! smaller of both. The planes are treated independently, which overestimates the clearance of
+//! round apertures on the diagonal.
+//!
+//! ```
+//! # use tfs::{DataValue, TfsDataFrame};
+//! # use tfs::aperture::{beam_stay_clear, BeamParameters};
+//! let twiss = TfsDataFrame::<f64>::new(
+//!     Vec::new(),
+//!     tfs::polars::df!("NAME" => ["MQ1", "MB1"], "BETX" => [100.0, 25.0], "BETY" => [25.0, 100.0])
+//!         .unwrap(),
+//! );
+//! let aperture = TfsDataFrame::<f64>::new(
+//!     Vec::new(),
+//!     tfs::polars::df!(
+//!         "NAME" => ["MQ1", "MB1"],
+//!         "APERTYPE" => ["CIRCLE", "RECTANGLE"],
+//!         "APER_1" => [0.02, 0.02],
+//!         "APER_2" => [0.0, 0.01],
+//!         "APER_3" => [0.0, 0.0],
+//!         "APER_4" => [0.0, 0.0]
+//!     )
+//!     .unwrap(),
+//! );
+//! let beam = BeamParameters::new(1e-6, 1e-6);
+//! let clearance = beam_stay_clear(&twiss, &aperture, &beam).unwrap();
+//! let n1 = clearance.column("N1").unwrap().f64().unwrap();
+//! assert!((n1.get(0).unwrap() - 2.0).abs() < 1e-12);
+//! assert!((n1.get(1).unwrap() - 1.0).abs() < 1e-12);
+//! ```
+use polars::prelude::{DataFrame, NumericNative};
+use std::path::Path;
+
+use crate::join::JoinType;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// The columns describing the aperture of the elements.
+pub const APERTURE_COLUMNS: [&str; 6] =
+    ["NAME", "APERTYPE", "APER_1", "APER_2", "APER_3", "APER_4"];
+
+/// The shapes of apertures with a beam-stay-clear, the values of `APERTYPE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApertureShape {
+    /// A circle of radius `APER_1`.
+    Circle,
+    /// An ellipse with the half axes `APER_1` and `APER_2`.
+    Ellipse,
+    /// A rectangle with the half widths `APER_1` and `APER_2`.
+    Rectangle,
+    /// The intersection of the rectangle `APER_1`, `APER_2` and the ellipse `APER_3`, `APER_4`,
+    /// also written as `LHCSCREEN`.
+    RectEllipse,
+}
+
+impl ApertureShape {
+    /// The shape of an `APERTYPE`, ignoring case. `None` for other shapes and `NONE`.
+    pub fn parse(apertype: &str) -> Option<ApertureShape> {
+        match apertype.trim().to_ascii_uppercase().as_str() {
+            "CIRCLE" => Some(ApertureShape::Circle),
+            "ELLIPSE" => Some(ApertureShape::Ellipse),
+            "RECTANGLE" => Some(ApertureShape::Rectangle),
+            "RECTELLIPSE" | "LHCSCREEN" => Some(ApertureShape::RectEllipse),
+            _ => None,
+        }
+    }
+
+    /// The horizontal and vertical half widths of the aperture with the sizes `aper`.
+    pub fn half_widths(&self, aper: [f64; 4]) -> (f64, f64) {
+        match self {
+            ApertureShape::Circle => (aper[0], aper[0]),
+            ApertureShape::Ellipse | ApertureShape::Rectangle => (aper[0], aper[1]),
+            ApertureShape::RectEllipse => (aper[0].min(aper[2]), aper[1].min(aper[3])),
+        }
+    }
+}
+
+/// The beam of a beam-stay-clear computation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeamParameters {
+    /// Geometric emittances in metres.
+    pub emittance_x: f64,
+    pub emittance_y: f64,
+    /// Relative momentum deviation, multiplied with the dispersion.
+    pub momentum_spread: f64,
+    /// Closed orbit error in metres, in addition to `X` and `Y` of the twiss.
+    pub closed_orbit: f64,
+    /// Relative beta-beating, e.g. `0.1` for 10%.
+    pub beta_beating: f64,
+}
+
+impl BeamParameters {
+    pub fn new(emittance_x: f64, emittance_y: f64) -> BeamParameters {
+        BeamParameters {
+            emittance_x,
+            emittance_y,
+            momentum_spread: 0.0,
+            closed_orbit: 0.0,
+            beta_beating: 0.0,
+        }
+    }
+
+    pub fn momentum_spread(mut self, delta: f64) -> Self {
+        self.momentum_spread = delta;
+        self
+    }
+
+    pub fn closed_orbit(mut self, metres: f64) -> Self {
+        self.closed_orbit = metres;
+        self
+    }
+
+    pub fn beta_beating(mut self, relative: f64) -> Self {
+        self.beta_beating = relative;
+        self
+    }
+
+    /// The number of beam sizes between the beam and the half width `half_width`, `None` if a
+    /// value is missing.
+    fn clearance(
+        &self,
+        half_width: Option<f64>,
+        beta: Option<f64>,
+        emittance: f64,
+        orbit: Option<f64>,
+        disp: Option<f64>,
+    ) -> Option<f64> {
+        let offset = orbit?.abs() + self.closed_orbit + disp?.abs() * self.momentum_spread;
+        Some((half_width? - offset) / ((1.0 + self.beta_beating) * beta? * emittance).sqrt())
+    }
+}
+
+/// Fails if `df` lacks one of the [`APERTURE_COLUMNS`].
+pub fn check_aperture<T>(df: &TfsDataFrame<T>) -> anyhow::Result<()>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    let names = df.column_names();
+    let missing: Vec<&str> = APERTURE_COLUMNS
+        .iter()
+        .filter(|c| !names.contains(c))
+        .copied()
+        .collect();
+    anyhow::ensure!(
+        missing.is_empty(),
+        "the aperture table lacks the columns {}",
+        missing.join(", ")
+    );
+    Ok(())
+}
+
+/// Reads the aperture table at `path` and checks its columns, see [`check_aperture`].
+pub fn read_aperture<T, P>(path: P) -> anyhow::Result<TfsDataFrame<T>>
+where
+    T: std::str::FromStr + NumericNative,
+    <T as std::str::FromStr>::Err: std::fmt::Debug,
+    P: AsRef<Path>,
+{
+    let df = TfsDataFrame::open(path)?;
+    check_aperture(&df)?;
+    Ok(df)
+}
+
+/// The beam-stay-clear of the elements in both `twiss` and `aperture`, see the
+/// [module documentation](self). The result has the header of `twiss` and the columns `NAME`,
+/// `S` (if in the twiss), `APERTYPE`, the half widths `AX` and `AY`, `N1X`, `N1Y` and `N1`.
+/// Elements with an aperture of another shape get missing values.
+///
+/// The twiss needs `BETX` and `BETY`, `X`, `Y`, `DX` and `DY` are taken as `0` if missing.
+pub fn beam_stay_clear<T>(
+    twiss: &TfsDataFrame<T>,
+    aperture: &TfsDataFrame<T>,
+    beam: &BeamParameters,
+) -> anyhow::Result<TfsDataFrame<T>>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    check_aperture(aperture)?;
+    let columns = APERTURE_COLUMNS
+        .iter()
+        .map(|name| Ok(aperture.column(name)?.clone().into()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let aperture = TfsDataFrame::<T>::new(Vec::new(), DataFrame::new(columns)?);
+    let joined = twiss.join(&aperture, "NAME", JoinType::Inner, ("_TWISS", ""))?;
+
+    let names = joined.column_names();
+    let reals = |name: &str| -> anyhow::Result<Vec<Option<f64>>> {
+        if names.contains(&name) {
+            Option::<f64>::from_column(joined.column(name)?)
+        } else {
+            Ok(vec![Some(0.0); joined.len()])
+        }
+    };
+    anyhow::ensure!(
+        names.contains(&"BETX") && names.contains(&"BETY"),
+        "the twiss lacks BETX or BETY"
+    );
+    let [betx, bety, x, y, dx, dy] = ["BETX", "BETY", "X", "Y", "DX", "DY"].map(reals);
+    let (betx, bety, x, y, dx, dy) = (betx?, bety?, x?, y?, dx?, dy?);
+    let aper = ["APER_1", "APER_2", "APER_3", "APER_4"].map(reals);
+    let aper = aper.into_iter().collect::<anyhow::Result<Vec<_>>>()?;
+    let apertypes = Option::<String>::from_column(joined.column("APERTYPE")?)?;
+
+    let mut half_widths = (Vec::new(), Vec::new());
+    let mut n1 = (Vec::new(), Vec::new(), Vec::new());
+    for row in 0..joined.len() {
+        let shape = apertypes[row].as_deref().and_then(ApertureShape::parse);
+        let sizes = [aper[0][row], aper[1][row], aper[2][row], aper[3][row]];
+        let (ax, ay) = match (shape, sizes) {
+            (Some(shape), [Some(a1), Some(a2), Some(a3), Some(a4)]) => {
+                let (ax, ay) = shape.half_widths([a1, a2, a3, a4]);
+                (Some(ax), Some(ay))
+            }
+            _ => (None, None),
+        };
+        let n1x = beam.clearance(ax, betx[row], beam.emittance_x, x[row], dx[row]);
+        let n1y = beam.clearance(ay, bety[row], beam.emittance_y, y[row], dy[row]);
+        half_widths.0.push(ax);
+        half_widths.1.push(ay);
+        n1.0.push(n1x);
+        n1.1.push(n1y);
+        n1.2.push(n1x.zip(n1y).map(|(n1x, n1y)| n1x.min(n1y)));
+    }
+
+    let mut columns = vec![joined.column("NAME")?.clone().into()];
+    if names.contains(&"S") {
+        columns.push(joined.column("S")?.clone().into());
+    }
+    columns.push(joined.column("APERTYPE")?.clone().into());
+    for (name, values) in [
+        ("AX", half_widths.0),
+        ("AY", half_widths.1),
+        ("N1X", n1.0),
+        ("N1Y", n1.1),
+        ("N1", n1.2),
+    ] {
+        columns.push(Option::<f64>::to_column(name, values).into());
+    }
+    Ok(TfsDataFrame::new(
+        twiss.properties.clone(),
+        DataFrame::new(columns)?,
+    ))
+}
@@ -0,0 +1,95 @@
+//! Conversion to and from Arrow record batches.
+//!
+//! The batches are those of arrow-rs, the Arrow implementation DataFusion, Ballista and Arrow
+//! Flight are built on. The columns are handed over through the Arrow C data interface, without
+//! copying the data. The header is not part of the batch and has to be carried separately:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let batch = df.to_arrow().unwrap();
+//! assert_eq!(batch.num_rows(), 5);
+//!
+//! let back = TfsDataFrame::from_arrow(&batch, df.properties.clone()).unwrap();
+//! assert!(back.diff(&df).unwrap().is_empty());
+//! ```
+use std::sync::Arc;
+
+use arrow_array::ffi::{FFI_ArrowArray, FFI_ArrowSchema};
+use arrow_array::{make_array, Array, ArrayRef};
+use arrow_schema::{Field, Schema};
+use polars::prelude::{CompatLevel, DataFrame, NumericNative, Series};
+use polars_arrow::ffi::{ArrowArray, ArrowSchema};
+
+use crate::dataframe::DataValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+pub use arrow_array::RecordBatch;
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Converts the columns into a record batch. Strings are converted to `LargeUtf8` arrays,
+    /// which unlike string views are understood by all Arrow implementations.
+    pub fn to_arrow(&self) -> anyhow::Result<RecordBatch> {
+        let mut df = self.df()?.into_owned();
+        df.as_single_chunk();
+        let mut fields = Vec::new();
+        let mut arrays = Vec::new();
+        for column in df.get_columns() {
+            let array = column
+                .as_materialized_series()
+                .to_arrow(0, CompatLevel::oldest());
+            let field = polars_arrow::datatypes::Field::new(
+                column.name().clone(),
+                array.dtype().clone(),
+                true,
+            );
+            let schema = polars_arrow::ffi::export_field_to_c(&field);
+            let array = polars_arrow::ffi::export_array_to_c(array);
+            // SAFETY: both structs are the `repr(C)` structs of the C data interface, the
+            // ownership of the exported buffers moves to arrow-rs with them.
+            let (schema, array) = unsafe {
+                (
+                    std::mem::transmute::<ArrowSchema, FFI_ArrowSchema>(schema),
+                    std::mem::transmute::<ArrowArray, FFI_ArrowArray>(array),
+                )
+            };
+            fields.push(Field::try_from(&schema)?);
+            // SAFETY: the array was exported together with `schema` above.
+            let data = unsafe { arrow_array::ffi::from_ffi(array, &schema)? };
+            arrays.push(make_array(data));
+        }
+        Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+    }
+
+    /// Builds a frame from the columns of `batch` and the header `properties`.
+    pub fn from_arrow<P>(batch: &RecordBatch, properties: P) -> anyhow::Result<TfsDataFrame<T>>
+    where
+        P: IntoIterator<Item = (String, DataValue<T>)>,
+    {
+        let columns = batch
+            .schema()
+            .fields()
+            .iter()
+            .zip(batch.columns())
+            .map(|(field, array)| import_column(field, array))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(TfsDataFrame::new(properties, DataFrame::new(columns)?))
+    }
+}
+
+/// Imports the arrow-rs `array` as the column `field` of a polars frame.
+fn import_column(field: &Field, array: &ArrayRef) -> anyhow::Result<polars::prelude::Column> {
+    let schema = FFI_ArrowSchema::try_from(field)?;
+    let array = FFI_ArrowArray::new(&array.to_data());
+    // SAFETY: see `to_arrow`, the structs are moved the other way.
+    let (schema, array) = unsafe {
+        (
+            std::mem::transmute::<FFI_ArrowSchema, ArrowSchema>(schema),
+            std::mem::transmute::<FFI_ArrowArray, ArrowArray>(array),
+        )
+    };
+    // SAFETY: `array` and `schema` were exported by arrow-rs from the same column.
+    let field = unsafe { polars_arrow::ffi::import_field_from_c(&schema)? };
+    let array = unsafe { polars_arrow::ffi::import_array_from_c(array, field.dtype().clone())? };
+    Ok(Series::from_arrow(field.name.clone(), array)?.into())
+}
@@ -0,0 +1,156 @@
+//! Frames of both LHC beams.
+//!
+//! LHC analyses usually treat both beams alike: the same files per beam, the same steps on
+//! both and a comparison at the end. A [`BeamPair`] holds a frame per beam, applies a step to
+//! both with [`BeamPair::map_both`] and compares them with [`BeamPair::diff_beams`]. Element
+//! names differ by their beam suffix, `MQ.12R1.B1` and `MQ.12R1.B2`, and are translated with
+//! [`beam_translator`]; names without suffix like `IP1` are common to both beams:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::beams::BeamPair;
+//! let frame = |names: [&str; 2], betx: [f64; 2]| {
+//!     TfsDataFrame::<f64>::new(
+//!         Vec::new(),
+//!         tfs::polars::df!("NAME" => names, "BETX" => betx).unwrap(),
+//!     )
+//! };
+//! let pair = BeamPair::new(
+//!     frame(["IP1", "BPM.7R1.B1"], [0.3, 120.0]),
+//!     frame(["IP1", "BPM.7R1.B2"], [0.3, 100.0]),
+//! );
+//! let diff = pair.diff_beams("NAME").unwrap();
+//! let betx = diff.column("BETX").unwrap().f64().unwrap();
+//! assert_eq!((betx.get(0), betx.get(1)), (Some(0.0), Some(20.0)));
+//! ```
+use polars::prelude::{DataFrame, NumericNative};
+use std::fmt;
+use std::path::Path;
+
+use crate::join::JoinType;
+use crate::naming::NameRule;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// One of the two beams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Beam {
+    B1,
+    B2,
+}
+
+impl Beam {
+    pub fn other(&self) -> Beam {
+        match self {
+            Beam::B1 => Beam::B2,
+            Beam::B2 => Beam::B1,
+        }
+    }
+
+    fn number(&self) -> u8 {
+        match self {
+            Beam::B1 => 1,
+            Beam::B2 => 2,
+        }
+    }
+}
+
+impl fmt::Display for Beam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "B{}", self.number())
+    }
+}
+
+/// Translates the names of the other beam to the names of `beam` by replacing their suffix,
+/// e.g. `BPM.7R1.B2` to `BPM.7R1.B1` for beam 1. Names without the suffix of the other beam are
+/// unknown to it.
+pub fn beam_translator(beam: Beam) -> NameRule {
+    let pattern = format!(r"^(.*)\.([bB]){}$", beam.other().number());
+    NameRule::new(&pattern, &format!("$1.${{2}}{}", beam.number())).expect("a valid pattern")
+}
+
+/// A frame per beam, see the [module documentation](self).
+#[derive(Debug)]
+pub struct BeamPair<T: std::str::FromStr + NumericNative> {
+    pub b1: TfsDataFrame<T>,
+    pub b2: TfsDataFrame<T>,
+}
+
+impl<T: std::str::FromStr + NumericNative> BeamPair<T> {
+    pub fn new(b1: TfsDataFrame<T>, b2: TfsDataFrame<T>) -> BeamPair<T> {
+        BeamPair { b1, b2 }
+    }
+
+    /// Opens the files of both beams.
+    pub fn open<P: AsRef<Path>>(b1: P, b2: P) -> anyhow::Result<BeamPair<T>>
+    where
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        Ok(BeamPair::new(
+            TfsDataFrame::open(b1)?,
+            TfsDataFrame::open(b2)?,
+        ))
+    }
+
+    pub fn get(&self, beam: Beam) -> &TfsDataFrame<T> {
+        match beam {
+            Beam::B1 => &self.b1,
+            Beam::B2 => &self.b2,
+        }
+    }
+
+    pub fn get_mut(&mut self, beam: Beam) -> &mut TfsDataFrame<T> {
+        match beam {
+            Beam::B1 => &mut self.b1,
+            Beam::B2 => &mut self.b2,
+        }
+    }
+
+    /// Applies `f` to the frame of each beam, beam 1 first, and returns the pair of the results.
+    pub fn map_both<U, F>(&self, mut f: F) -> anyhow::Result<BeamPair<U>>
+    where
+        U: std::str::FromStr + NumericNative,
+        F: FnMut(Beam, &TfsDataFrame<T>) -> anyhow::Result<TfsDataFrame<U>>,
+    {
+        Ok(BeamPair::new(
+            f(Beam::B1, &self.b1)?,
+            f(Beam::B2, &self.b2)?,
+        ))
+    }
+
+    /// Compares the beams on the key column `on`: the result has the header of beam 1, the key
+    /// column and, for every real column of both frames, the value of beam 1 minus the one of
+    /// beam 2. Text keys of beam 2 are translated to beam 1 names first, see
+    /// [`beam_translator`], only keys in both beams are kept.
+    pub fn diff_beams(&self, on: &str) -> anyhow::Result<TfsDataFrame<T>> {
+        let b2 = self.b2.df()?.into_owned();
+        let mut b2 = self.b2.with_rows(b2);
+        if b2.column(on)?.dtype().is_string() {
+            b2.translate_column(on, &beam_translator(Beam::B1))?;
+        }
+        let joined = self.b1.join(&b2, on, JoinType::Inner, ("_B1", "_B2"))?;
+
+        let b2_names = b2.column_names();
+        let mut columns = vec![joined.column(on)?.clone().into()];
+        for name in self.b1.column_names() {
+            let is_real = |df: &TfsDataFrame<T>| -> anyhow::Result<bool> {
+                Ok(df.column(name)?.dtype().is_float())
+            };
+            if name == on || !b2_names.contains(&name) || !is_real(&self.b1)? || !is_real(&b2)? {
+                continue;
+            }
+            let b1_values = Option::<f64>::from_column(joined.column(&format!("{}_B1", name))?)?;
+            let b2_values = Option::<f64>::from_column(joined.column(&format!("{}_B2", name))?)?;
+            let diff: Vec<Option<f64>> = b1_values
+                .into_iter()
+                .zip(b2_values)
+                .map(|(b1, b2)| Some(b1? - b2?))
+                .collect();
+            columns.push(Option::<f64>::to_column(name, diff).into());
+        }
+        Ok(TfsDataFrame::new(
+            self.b1.properties.clone(),
+            DataFrame::new(columns)?,
+        ))
+    }
+}
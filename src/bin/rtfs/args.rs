@@ -0,0 +1,69 @@
+//! Command line arguments of the subcommands.
+use std::collections::HashMap;
+
+/// The arguments of a subcommand: positional arguments, options with a value (`--key value` or
+/// `--key=value`) and flags (`--key`).
+#[derive(Debug, Default)]
+pub struct Args {
+    pub positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    /// Parses `args`, the options in `with_value` take a value, all others are flags. `-o` is
+    /// short for `--output`.
+    pub fn parse(args: &[String], with_value: &[&str]) -> anyhow::Result<Args> {
+        let mut parsed = Args::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let key = match arg.as_str() {
+                "-o" => "output",
+                "--" => {
+                    parsed.positional.extend(args.by_ref().cloned());
+                    break;
+                }
+                arg => match arg.strip_prefix("--") {
+                    Some(key) => key,
+                    None => {
+                        parsed.positional.push(arg.to_owned());
+                        continue;
+                    }
+                },
+            };
+
+            if let Some((key, value)) = key.split_once('=') {
+                anyhow::ensure!(with_value.contains(&key), "unknown option --{}", key);
+                parsed.options.insert(key.to_owned(), value.to_owned());
+            } else if with_value.contains(&key) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--{} needs a value", key))?;
+                parsed.options.insert(key.to_owned(), value.clone());
+            } else {
+                parsed.flags.push(key.to_owned());
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn option(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+
+    pub fn flag(&self, key: &str) -> bool {
+        self.flags.iter().any(|flag| flag == key)
+    }
+
+    /// Fails if a flag other than `known` was given.
+    pub fn check_flags(&self, known: &[&str]) -> anyhow::Result<()> {
+        match self
+            .flags
+            .iter()
+            .find(|flag| !known.contains(&flag.as_str()))
+        {
+            Some(flag) => anyhow::bail!("unknown option --{}", flag),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! `rtfs completions`, shell completion scripts.
+//!
+//! The scripts complete the commands, their options and otherwise file names. They are loaded
+//! with e.g. `source <(rtfs completions bash)` in `~/.bashrc`.
+use crate::args::Args;
+
+/// The commands with their options, `true` for options that take a value.
+const COMMANDS: &[(&str, &[(&str, bool)])] = &[
+    (
+        "join",
+        &[
+            ("on", true),
+            ("how", true),
+            ("suffixes", true),
+            ("validate", true),
+            ("null-keys", true),
+            ("nearest", true),
+            ("output", true),
+            ("format", true),
+            ("precision", true),
+        ],
+    ),
+    (
+        "concat",
+        &[
+            ("fill-missing", false),
+            ("label", true),
+            ("output", true),
+            ("format", true),
+            ("precision", true),
+        ],
+    ),
+    (
+        "filter",
+        &[
+            ("where", true),
+            ("output", true),
+            ("format", true),
+            ("precision", true),
+        ],
+    ),
+    (
+        "convert",
+        &[
+            ("from", true),
+            ("output", true),
+            ("format", true),
+            ("precision", true),
+        ],
+    ),
+    (
+        "sql",
+        &[("output", true), ("format", true), ("precision", true)],
+    ),
+    ("validate", &[("output", true)]),
+    ("completions", &[]),
+    ("help", &[]),
+];
+
+const SHELLS: [&str; 3] = ["bash", "zsh", "fish"];
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    args.check_flags(&[])?;
+    let [shell] = args.positional.as_slice() else {
+        anyhow::bail!("usage: rtfs completions bash|zsh|fish");
+    };
+    let script = match shell.as_str() {
+        "bash" => bash(),
+        "zsh" => zsh(),
+        "fish" => fish(),
+        shell => anyhow::bail!("unknown shell '{}', expected one of {:?}", shell, SHELLS),
+    };
+    print!("{}", script);
+    Ok(())
+}
+
+fn names() -> String {
+    COMMANDS
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn options(options: &[(&str, bool)]) -> String {
+    options
+        .iter()
+        .map(|(option, _)| format!("--{}", option))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn bash() -> String {
+    let mut cases = String::new();
+    for (name, opts) in COMMANDS {
+        cases += &format!("        {}) opts=\"{}\" ;;\n", name, options(opts));
+    }
+    format!(
+        r#"_rtfs() {{
+    local cur=${{COMP_WORDS[COMP_CWORD]}}
+    if [ "$COMP_CWORD" -eq 1 ]; then
+        COMPREPLY=($(compgen -W "{names}" -- "$cur"))
+        return
+    fi
+    local opts=""
+    case "${{COMP_WORDS[1]}}" in
+{cases}    esac
+    if [ "${{COMP_WORDS[1]}}" = completions ]; then
+        COMPREPLY=($(compgen -W "{shells}" -- "$cur"))
+    elif [[ $cur == -* ]]; then
+        COMPREPLY=($(compgen -W "$opts" -- "$cur"))
+    else
+        COMPREPLY=($(compgen -f -- "$cur"))
+    fi
+}}
+complete -o filenames -F _rtfs rtfs
+"#,
+        names = names(),
+        cases = cases,
+        shells = SHELLS.join(" "),
+    )
+}
+
+fn zsh() -> String {
+    let mut cases = String::new();
+    for (name, opts) in COMMANDS {
+        cases += &format!("        {}) opts=({}) ;;\n", name, options(opts));
+    }
+    format!(
+        r#"#compdef rtfs
+_rtfs() {{
+    if (( CURRENT == 2 )); then
+        compadd -- {names}
+        return
+    fi
+    local -a opts
+    case $words[2] in
+{cases}    esac
+    if [[ $words[2] == completions ]]; then
+        compadd -- {shells}
+    elif [[ $PREFIX == -* ]]; then
+        compadd -- $opts
+    else
+        _files
+    fi
+}}
+compdef _rtfs rtfs
+"#,
+        names = names(),
+        cases = cases,
+        shells = SHELLS.join(" "),
+    )
+}
+
+fn fish() -> String {
+    let mut script = format!(
+        "complete -c rtfs -f -n __fish_use_subcommand -a \"{}\"\n\
+         complete -c rtfs -f -n \"__fish_seen_subcommand_from completions\" -a \"{}\"\n",
+        names(),
+        SHELLS.join(" ")
+    );
+    for (name, opts) in COMMANDS {
+        for (option, takes_value) in opts.iter() {
+            script += &format!(
+                "complete -c rtfs -n \"__fish_seen_subcommand_from {}\" -l {}{}\n",
+                name,
+                option,
+                if *takes_value { " -r" } else { "" }
+            );
+        }
+    }
+    script
+}
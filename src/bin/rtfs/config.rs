@@ -0,0 +1,122 @@
+//! The configuration file `~/.config/rtfs.toml`.
+//!
+//! ```toml
+//! # output format of join, concat, filter and sql: tfs, markdown or latex
+//! format = "markdown"
+//! # decimals of real numbers in markdown and latex output
+//! precision = 4
+//! # directories where file arguments are looked up if they aren't found
+//! search_paths = ["~/twiss", "/data/optics"]
+//! ```
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Deserialize;
+use tfs::render::FloatFormat;
+
+/// How results are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Tfs,
+    Markdown,
+    Latex,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tfs" => Ok(Format::Tfs),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "latex" | "tex" => Ok(Format::Latex),
+            _ => anyhow::bail!("unknown format '{}', expected tfs, markdown or latex", s),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+    pub format: Format,
+    pub precision: Option<usize>,
+    pub search_paths: Vec<PathBuf>,
+}
+
+/// The entries of the configuration file as written.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    format: Option<String>,
+    precision: Option<usize>,
+    #[serde(default)]
+    search_paths: Vec<String>,
+}
+
+impl Config {
+    /// The path of the configuration file, in `$XDG_CONFIG_HOME` or `~/.config`.
+    pub fn path() -> Option<PathBuf> {
+        match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("rtfs.toml")),
+            _ => home().map(|home| home.join(".config").join("rtfs.toml")),
+        }
+    }
+
+    /// Reads the configuration file, the defaults if there is none.
+    pub fn load() -> anyhow::Result<Config> {
+        match Config::path() {
+            Some(path) if path.is_file() => {
+                let text = std::fs::read_to_string(&path)?;
+                Config::parse(&text).map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))
+            }
+            _ => Ok(Config::default()),
+        }
+    }
+
+    /// Parses the configuration.
+    fn parse(text: &str) -> anyhow::Result<Config> {
+        let file: ConfigFile = toml::from_str(text)?;
+        Ok(Config {
+            format: match file.format {
+                Some(format) => format.parse()?,
+                None => Format::default(),
+            },
+            precision: file.precision,
+            search_paths: file.search_paths.iter().map(|p| expand(p)).collect(),
+        })
+    }
+
+    /// The format of real numbers in markdown and latex output.
+    pub fn float_format(&self) -> FloatFormat {
+        self.precision.map_or(FloatFormat::Auto, FloatFormat::Fixed)
+    }
+
+    /// Looks up a file argument: `path` itself if it exists or is absolute, otherwise the first
+    /// search path containing it.
+    pub fn find(&self, path: &str) -> PathBuf {
+        let path = Path::new(path);
+        if path.exists() || path.is_absolute() {
+            return path.to_owned();
+        }
+        self.search_paths
+            .iter()
+            .map(|dir| dir.join(path))
+            .find(|candidate| candidate.exists())
+            .unwrap_or_else(|| path.to_owned())
+    }
+}
+
+fn home() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Replaces a leading `~` with the home directory.
+fn expand(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ if path == "~" => home().unwrap_or_else(|| PathBuf::from(path)),
+        _ => PathBuf::from(path),
+    }
+}
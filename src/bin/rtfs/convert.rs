@@ -0,0 +1,22 @@
+//! `rtfs convert`
+use tfs::FormatRegistry;
+
+use crate::args::Args;
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    args.check_flags(&[])?;
+    let [path] = args.positional.as_slice() else {
+        anyhow::bail!("usage: rtfs convert <file> [--from FORMAT] [-o output]");
+    };
+
+    let path = crate::config()?.find(path);
+    let registry = FormatRegistry::default();
+    let format = match args.option("from") {
+        Some(from) => registry.get(from)?,
+        None => registry.detect(&path)?,
+    };
+    let df = format
+        .read(&path)
+        .map_err(|err| anyhow::anyhow!("{}: {:#}", path.display(), err))?;
+    crate::emit(&df, args)
+}
@@ -0,0 +1,13 @@
+//! `rtfs filter`
+use crate::args::Args;
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    args.check_flags(&[])?;
+    let ([path], Some(expression)) = (args.positional.as_slice(), args.option("where")) else {
+        anyhow::bail!("usage: rtfs filter <file> --where EXPR [-o output]");
+    };
+
+    let mut df = crate::open(path)?;
+    df.filter_expr(expression)?;
+    crate::emit(&df, args)
+}
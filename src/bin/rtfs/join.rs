@@ -0,0 +1,70 @@
+//! `rtfs join` and `rtfs concat`
+use tfs::join::{JoinOptions, JoinType, JoinValidation, NullKeys};
+use tfs::TfsDataFrame;
+
+use crate::args::Args;
+
+pub fn run_join(args: &Args) -> anyhow::Result<()> {
+    args.check_flags(&[])?;
+    let [left, right] = args.positional.as_slice() else {
+        anyhow::bail!("usage: rtfs join <left> <right> [--on NAME[,NAME...]] [--how inner|left|outer] [--suffixes ,_right] [--validate 1:1|1:m|m:1|m:m] [--null-keys unmatched|match|error] [--nearest TOLERANCE] [-o output]");
+    };
+
+    let nearest: Option<f64> = args
+        .option("nearest")
+        .map(|tolerance| {
+            tolerance
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid tolerance '{}'", tolerance))
+        })
+        .transpose()?;
+    let on = args
+        .option("on")
+        .unwrap_or(if nearest.is_some() { "S" } else { "NAME" });
+    let how: JoinType = args.option("how").unwrap_or("inner").parse()?;
+    let suffixes = args.option("suffixes").unwrap_or(",_right");
+    let (left_suffix, right_suffix) = suffixes
+        .split_once(',')
+        .ok_or_else(|| anyhow::anyhow!("--suffixes needs two suffixes separated by a comma"))?;
+
+    let validate: JoinValidation = args.option("validate").unwrap_or("m:m").parse()?;
+    let null_keys: NullKeys = args.option("null-keys").unwrap_or("unmatched").parse()?;
+
+    let options = JoinOptions::new()
+        .how(how)
+        .suffixes(left_suffix, right_suffix)
+        .validate(validate)
+        .null_keys(null_keys);
+    let (left, right) = (crate::open(left)?, crate::open(right)?);
+    let joined = match nearest {
+        Some(tolerance) => left.join_nearest(&right, on, tolerance, &options)?,
+        None => {
+            let on: Vec<&str> = on.split(',').collect();
+            left.join_with(&right, &on, &options)?
+        }
+    };
+    crate::emit(&joined, args)
+}
+
+pub fn run_concat(args: &Args) -> anyhow::Result<()> {
+    args.check_flags(&["fill-missing"])?;
+    anyhow::ensure!(
+        !args.positional.is_empty(),
+        "usage: rtfs concat <files...> [--fill-missing] [--label COLUMN] [-o output]"
+    );
+
+    let frames = args
+        .positional
+        .iter()
+        .map(|path| crate::open(path))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let stacked = match args.option("label") {
+        Some(_) if args.flag("fill-missing") => {
+            anyhow::bail!("--label and --fill-missing can't be combined")
+        }
+        Some(column) => TfsDataFrame::concat_labeled(&frames, column, &args.positional)?,
+        None if args.flag("fill-missing") => TfsDataFrame::concat_diagonal(&frames)?,
+        None => TfsDataFrame::concat(&frames)?,
+    };
+    crate::emit(&stacked, args)
+}
@@ -0,0 +1,180 @@
+//! `rtfs`, command line tools for tfs files.
+use std::io::Write;
+use std::process::ExitCode;
+use std::sync::OnceLock;
+
+use tfs::TfsDataFrame;
+
+mod args;
+mod completions;
+mod config;
+mod convert;
+mod filter;
+mod join;
+#[cfg(feature = "sql")]
+mod sql;
+mod validate;
+
+use args::Args;
+use config::{Config, Format};
+
+const USAGE: &str = "\
+usage: rtfs <command> [arguments]
+
+commands:
+    join <left> <right> [--on NAME[,NAME...]] [--how inner|left|outer] [--suffixes ,_right]
+         [--validate 1:1|1:m|m:1|m:m] [--null-keys unmatched|match|error] [--nearest TOLERANCE]
+         [-o output]
+        joins the columns of two files on one or more key columns, separated by commas. Columns
+        in both files get the left and right suffix, separated by a comma. --validate fails if a
+        key appears more than once where the relation says 1, --null-keys decides whether null
+        keys match each other. With --nearest rows match the row with the nearest value of the
+        key, by default S, if it is at most TOLERANCE away
+    concat <files...> [--fill-missing] [--label COLUMN] [-o output]
+        stacks the rows of files with the same columns, or with --fill-missing of files with
+        different columns, missing values are null. --label adds the column COLUMN with the
+        file each row comes from
+    filter <file> --where EXPR [-o output]
+        keeps the rows for which EXPR holds, e.g. \"S > 500 && NAME =~ 'BPM.*B1'\". Columns are
+        compared with numbers, quoted strings or other columns (== != < <= > >=), or matched
+        against regular expressions (=~ !~), and conditions combined with && || ! and ( )
+    convert <file> [--from tfs|elegant|sdds|pyat|tao] [-o output]
+        converts a file to tfs, in the format given with --from or recognised by its extension
+        and content: an ascii SDDS file, an elegant twiss with its columns and parameters
+        renamed to MAD-X (betax to BETX, psix in units of 2π to MUX, nux to Q1, ...), an optics
+        summary exported from pyAT or the output of Tao's `show lattice`, both renamed the same
+        way. All other commands read these formats as well
+    sql <query> [paths...] [-o output]
+        runs an SQL query over tfs files (needs the `sql` feature). Files are registered as
+        tables named after their file stem, directories as one table named after the
+        directory. Files can also be named in the query: SELECT * FROM 'twiss.tfs'
+    validate <schema.json> <files...> [-o output]
+        checks files against a schema of required header entries and columns, their kinds and
+        ranges of values, see `tfs::schema`. Prints a JSON report per file and fails if any file
+        doesn't match
+    completions bash|zsh|fish
+        prints a completion script for the shell, e.g. `source <(rtfs completions bash)`
+    help
+        prints this message
+
+Results are written to the output file (-o, --output) or to stdout, as tfs or with
+--format markdown|latex as a table, with --precision decimals of real numbers. validate writes
+JSON.
+
+Defaults for --format and --precision are read from ~/.config/rtfs.toml (or
+$XDG_CONFIG_HOME/rtfs.toml), as well as search_paths, directories where files are looked up if
+they aren't found:
+
+    format = \"markdown\"
+    precision = 4
+    search_paths = [\"~/twiss\"]";
+
+/// Options of the commands writing a result with [`emit`].
+const OUTPUT_OPTIONS: [&str; 3] = ["output", "format", "precision"];
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((command, args)) = args.split_first() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let result = match command.as_str() {
+        "join" => Args::parse(
+            args,
+            &with_output(&["on", "how", "suffixes", "validate", "null-keys", "nearest"]),
+        )
+        .and_then(|args| join::run_join(&args)),
+        "concat" => {
+            Args::parse(args, &with_output(&["label"])).and_then(|args| join::run_concat(&args))
+        }
+        "filter" => Args::parse(args, &with_output(&["where"])).and_then(|args| filter::run(&args)),
+        "convert" => {
+            Args::parse(args, &with_output(&["from"])).and_then(|args| convert::run(&args))
+        }
+        "sql" => run_sql(args),
+        "validate" => Args::parse(args, &["output"]).and_then(|args| validate::run(&args)),
+        "completions" => Args::parse(args, &[]).and_then(|args| completions::run(&args)),
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        command => Err(anyhow::anyhow!(
+            "unknown command '{}', see `rtfs help`",
+            command
+        )),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("rtfs: {:#}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(feature = "sql")]
+fn run_sql(args: &[String]) -> anyhow::Result<()> {
+    sql::run(&Args::parse(args, &OUTPUT_OPTIONS)?)
+}
+
+#[cfg(not(feature = "sql"))]
+fn run_sql(_args: &[String]) -> anyhow::Result<()> {
+    anyhow::bail!("rtfs was built without the `sql` feature")
+}
+
+/// The configuration file, read when a command first needs it so that a broken file doesn't
+/// break `help` or `completions`.
+fn config() -> anyhow::Result<&'static Config> {
+    if let Some(config) = CONFIG.get() {
+        return Ok(config);
+    }
+    let config = Config::load()?;
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+fn with_output(options: &[&'static str]) -> Vec<&'static str> {
+    [&OUTPUT_OPTIONS, options].concat()
+}
+
+/// Opens the file at `path` in any known format, see `tfs::format`. It is looked up in the
+/// search paths if it doesn't exist.
+fn open(path: &str) -> anyhow::Result<TfsDataFrame<f64>> {
+    TfsDataFrame::open_auto(config()?.find(path))
+        .map_err(|err| anyhow::anyhow!("{}: {:#}", path, err))
+}
+
+/// Writes `df` in the `--format` to the `--output` file, or to stdout.
+fn emit(df: &TfsDataFrame<f64>, args: &Args) -> anyhow::Result<()> {
+    let format = match args.option("format") {
+        Some(format) => format.parse()?,
+        None => config()?.format,
+    };
+    let float_format = match args.option("precision") {
+        Some(precision) => tfs::render::FloatFormat::Fixed(
+            precision
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid --precision '{}'", precision))?,
+        ),
+        None => config()?.float_format(),
+    };
+
+    let mut output: Box<dyn Write> = match args.option("output") {
+        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    match format {
+        Format::Tfs => df.write_to(&mut output)?,
+        Format::Markdown => write!(
+            output,
+            "{}",
+            df.to_markdown(df.len())?.float_format(float_format)
+        )?,
+        Format::Latex => write!(output, "{}", df.to_latex(&df.column_names(), float_format)?)?,
+    }
+    output.flush()?;
+    Ok(())
+}
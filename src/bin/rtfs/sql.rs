@@ -0,0 +1,27 @@
+//! `rtfs sql`
+use tfs::sql::TfsSqlContext;
+
+use crate::args::Args;
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    args.check_flags(&[])?;
+    let Some((query, paths)) = args.positional.split_first() else {
+        anyhow::bail!("usage: rtfs sql <query> [paths...] [-o output]");
+    };
+
+    let config = crate::config()?;
+    let mut context = TfsSqlContext::new();
+    for path in paths.iter().map(|path| config.find(path)) {
+        if path.is_dir() {
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or_else(|| anyhow::anyhow!("invalid directory name {}", path.display()))?;
+            context.register_dir(name, &path)?;
+        } else {
+            context.register_file(&path)?;
+        }
+    }
+
+    crate::emit(&context.query(query)?, args)
+}
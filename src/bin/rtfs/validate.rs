@@ -0,0 +1,60 @@
+//! `rtfs validate`
+use std::io::Write;
+
+use serde::Serialize;
+use tfs::schema::{Schema, Violation};
+
+use crate::args::Args;
+
+/// The result for one file, printed as JSON.
+#[derive(Serialize)]
+struct FileReport<'a> {
+    file: &'a str,
+    valid: bool,
+    /// Why the file couldn't be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    violations: Vec<Violation>,
+}
+
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    args.check_flags(&[])?;
+    let Some((schema, files)) = args.positional.split_first().filter(|(_, f)| !f.is_empty()) else {
+        anyhow::bail!("usage: rtfs validate <schema.json> <files...> [-o output]");
+    };
+    let schema = Schema::open(schema).map_err(|err| anyhow::anyhow!("{}: {}", schema, err))?;
+
+    let mut reports = Vec::new();
+    for file in files {
+        let report = match crate::open(file).and_then(|df| df.validate(&schema)) {
+            Ok(violations) => FileReport {
+                file,
+                valid: violations.is_empty(),
+                error: None,
+                violations,
+            },
+            Err(err) => FileReport {
+                file,
+                valid: false,
+                error: Some(format!("{:#}", err)),
+                violations: Vec::new(),
+            },
+        };
+        reports.push(report);
+    }
+
+    let json = serde_json::to_string_pretty(&reports)?;
+    match args.option("output") {
+        Some(path) => std::fs::write(path, json + "\n")?,
+        None => writeln!(std::io::stdout().lock(), "{}", json)?,
+    }
+
+    let invalid = reports.iter().filter(|r| !r.valid).count();
+    anyhow::ensure!(
+        invalid == 0,
+        "{} of {} files don't match the schema",
+        invalid,
+        reports.len()
+    );
+    Ok(())
+}
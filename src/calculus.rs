@@ -0,0 +1,108 @@
+//! Derivatives and integrals of columns along the beam line, e.g. the slope of the orbit or the
+//! integrated dispersion.
+//!
+//! [`TfsDataFrame::gradient`] and [`TfsDataFrame::integrate`] work along an increasing column,
+//! usually [`S_COLUMN`], and return new columns like the [running operations](crate::cumulative):
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::calculus::S_COLUMN;
+//! # use tfs::polars::prelude::*;
+//! let df = TfsDataFrame::<f64>::new(
+//!     vec![],
+//!     df!("S" => [0.0, 1.0, 3.0], "X" => [0.0, 1.0, 9.0]).unwrap(),
+//! );
+//! let slope = df.gradient("X", S_COLUMN).unwrap();
+//! assert_eq!(slope.f64().unwrap().to_vec(), [Some(1.0), Some(2.0), Some(4.0)]);
+//!
+//! let area = df.integrate("X", S_COLUMN).unwrap();
+//! assert_eq!(area.f64().unwrap().to_vec(), [Some(0.0), Some(0.5), Some(10.5)]);
+//! ```
+//!
+//! The gradient is the second order central difference, one-sided at the ends. Rows at the same
+//! position, like markers next to thin elements, take their neighbours from the closest other
+//! positions. Missing values stay missing and are bridged by the neighbouring rows.
+use polars::prelude::NumericNative;
+use polars::series::Series;
+
+use crate::cumulative::numbers;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// The longitudinal position, the usual column to differentiate and integrate along.
+pub const S_COLUMN: &str = "S";
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// The derivative of the numeric column `name` with respect to the column `wrt`, named
+    /// `GRADIENT(name)`, see the [module documentation](self). Rows without other positions
+    /// to compare with are missing.
+    pub fn gradient(&self, name: &str, wrt: &str) -> anyhow::Result<Series> {
+        let s = positions(self, wrt)?;
+        let f = numbers(self, name)?;
+        let present: Vec<usize> = (0..f.len()).filter(|i| f[*i].is_some()).collect();
+
+        let mut gradient = vec![None; f.len()];
+        let value = |j: usize| f[j].unwrap_or(f64::NAN);
+        for (p, &i) in present.iter().enumerate() {
+            let previous = present[..p].iter().rev().find(|j| s[**j] < s[i]);
+            let next = present[p + 1..].iter().find(|k| s[**k] > s[i]);
+            gradient[i] = match (previous, next) {
+                (Some(&j), Some(&k)) => {
+                    let (h1, h2) = (s[i] - s[j], s[k] - s[i]);
+                    Some(
+                        (h1 * h1 * value(k) - h2 * h2 * value(j) + (h2 * h2 - h1 * h1) * value(i))
+                            / (h1 * h2 * (h1 + h2)),
+                    )
+                }
+                (Some(&j), None) => Some((value(i) - value(j)) / (s[i] - s[j])),
+                (None, Some(&k)) => Some((value(k) - value(i)) / (s[k] - s[i])),
+                (None, None) => None,
+            };
+        }
+        Ok(Option::<f64>::to_column(
+            &format!("GRADIENT({})", name),
+            gradient,
+        ))
+    }
+
+    /// The integral of the numeric column `name` along the column `wrt` by the trapezoidal
+    /// rule, from zero at the first row, named `INTEGRAL(name)`.
+    pub fn integrate(&self, name: &str, wrt: &str) -> anyhow::Result<Series> {
+        let s = positions(self, wrt)?;
+        let mut total = 0.0;
+        let mut last: Option<(f64, f64)> = None;
+        let integral: Vec<Option<f64>> = numbers(self, name)?
+            .into_iter()
+            .zip(s)
+            .map(|(f, s)| {
+                let f = f?;
+                if let Some((last_s, last_f)) = last {
+                    total += (f + last_f) / 2.0 * (s - last_s);
+                }
+                last = Some((s, f));
+                Some(total)
+            })
+            .collect();
+        Ok(Option::<f64>::to_column(
+            &format!("INTEGRAL({})", name),
+            integral,
+        ))
+    }
+}
+
+/// The values of the column `wrt`, which must be present and increasing.
+fn positions<T>(df: &TfsDataFrame<T>, wrt: &str) -> anyhow::Result<Vec<f64>>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    let s = numbers(df, wrt)?
+        .into_iter()
+        .map(|s| s.ok_or_else(|| anyhow::anyhow!("the column '{}' has missing values", wrt)))
+        .collect::<anyhow::Result<Vec<f64>>>()?;
+    anyhow::ensure!(
+        s.windows(2).all(|pair| pair[0] <= pair[1]),
+        "the column '{}' is not increasing",
+        wrt
+    );
+    Ok(s)
+}
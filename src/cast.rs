@@ -0,0 +1,262 @@
+//! Converting columns and header entries to other types.
+//!
+//! Files often declare every number as `%le`, although some columns hold counts like `TURN`.
+//! [`TfsDataFrame::cast_column`] converts a single column, [`TfsDataFrame::coerce_schema`] every
+//! column and header entry to the kind its [`Rule`](crate::schema::Rule) asks for. Values that
+//! can't be converted exactly, `NaN`, reals with a fraction, numbers too large for the new type
+//! or text that isn't a number, become missing values and are counted in a [`CastReport`]:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::polars::prelude::DataType;
+//! let mut df = TfsDataFrame::<f64>::new(
+//!     Vec::new(),
+//!     tfs::polars::df!("TURN" => [1.0, 2.0, f64::NAN, 3.5]).unwrap(),
+//! );
+//! let report = df.cast_column("TURN", DataType::Int64).unwrap();
+//! assert_eq!((report.nan, report.fractional), (1, 1));
+//! assert_eq!(report.first_lost, Some(2));
+//! assert_eq!(df.column("TURN").unwrap().null_count(), 2);
+//! ```
+use polars::prelude::{BooleanChunked, DataType, NewChunkedArray, NumericNative};
+use polars::series::Series;
+use std::fmt;
+
+use crate::dataframe::DataValue;
+use crate::record::ColumnValue;
+use crate::schema::Schema;
+use crate::tfsdataframe::TfsDataFrame;
+use crate::types::ColumnKind;
+
+/// Whether a [`CastReport`] concerns a header entry or a column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastTarget {
+    Header,
+    Column,
+}
+
+/// The values lost converting a column or header entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CastReport {
+    pub target: CastTarget,
+    pub name: String,
+    pub from: DataType,
+    pub to: DataType,
+    /// Numbers outside of the range of the new type.
+    pub overflow: usize,
+    /// `NaN` or infinite reals converted to integers.
+    pub nan: usize,
+    /// Reals with a fraction converted to integers.
+    pub fractional: usize,
+    /// Text that isn't a value of the new type.
+    pub invalid: usize,
+    /// The row of the first lost value, `0` for header entries.
+    pub first_lost: Option<usize>,
+}
+
+impl CastReport {
+    fn new(target: CastTarget, name: &str, from: DataType, to: DataType) -> CastReport {
+        CastReport {
+            target,
+            name: name.to_owned(),
+            from,
+            to,
+            overflow: 0,
+            nan: 0,
+            fractional: 0,
+            invalid: 0,
+            first_lost: None,
+        }
+    }
+
+    /// `true` if every value was converted exactly.
+    pub fn is_lossless(&self) -> bool {
+        self.first_lost.is_none()
+    }
+}
+
+impl fmt::Display for CastReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let target = match self.target {
+            CastTarget::Header => "header entry",
+            CastTarget::Column => "column",
+        };
+        write!(f, "{} '{}' {} -> {}", target, self.name, self.from, self.to)?;
+        let Some(first) = self.first_lost else {
+            return write!(f, ": lossless");
+        };
+        let lost: Vec<String> = [
+            (self.overflow, "overflowing"),
+            (self.nan, "NaN"),
+            (self.fractional, "fractional"),
+            (self.invalid, "invalid"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, reason)| format!("{} {}", count, reason))
+        .collect();
+        write!(f, ": {}", lost.join(", "))?;
+        match self.target {
+            CastTarget::Header => write!(f, ", kept unchanged"),
+            CastTarget::Column => write!(f, ", the first in row {}", first),
+        }
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Converts the column `name` to `dtype`. Values that can't be converted exactly become
+    /// missing, see the [module documentation](crate::cast). The lineage of the column is kept.
+    pub fn cast_column(&mut self, name: &str, dtype: DataType) -> anyhow::Result<CastReport> {
+        let column = self.column(name)?;
+        let mut report = CastReport::new(CastTarget::Column, name, column.dtype().clone(), dtype);
+        let cast = cast_series(column, &mut report)?;
+
+        let lineage = self.lineage.remove(name);
+        self.set_column(cast)?;
+        if let Some(lineage) = lineage {
+            self.lineage.insert(name.to_owned(), lineage);
+        }
+        Ok(report)
+    }
+
+    /// Converts the columns and header entries of the frame to the kinds of the rules of
+    /// `schema`, and returns a report for each one converted. Missing columns and entries and
+    /// rules without a kind are skipped.
+    ///
+    /// Header entries that can't be converted exactly are kept unchanged.
+    pub fn coerce_schema(&mut self, schema: &Schema) -> anyhow::Result<Vec<CastReport>> {
+        let mut reports = Vec::new();
+        for rule in &schema.headers {
+            let (Some(kind), Some(value)) = (rule.kind, self.properties.get(&rule.name)) else {
+                continue;
+            };
+            let from = value_kind(value);
+            if from == kind {
+                continue;
+            }
+            let mut report =
+                CastReport::new(CastTarget::Header, &rule.name, from.dtype(), kind.dtype());
+            match coerce_value(value, kind) {
+                Ok(value) => {
+                    self.properties.insert(rule.name.clone(), value);
+                }
+                Err(lost) => {
+                    *lost(&mut report) += 1;
+                    report.first_lost = Some(0);
+                }
+            }
+            reports.push(report);
+        }
+
+        for rule in &schema.columns {
+            let Some(kind) = rule.kind else {
+                continue;
+            };
+            if !self.column_names().contains(&rule.name.as_str()) {
+                continue;
+            }
+            if ColumnKind::of_dtype(self.column(&rule.name)?.dtype()) != kind {
+                reports.push(self.cast_column(&rule.name, kind.dtype())?);
+            }
+        }
+        Ok(reports)
+    }
+}
+
+/// Casts `column` to `report.to`, counting the lost values in `report`.
+fn cast_series(column: &Series, report: &mut CastReport) -> anyhow::Result<Series> {
+    let mut lost = vec![false; column.len()];
+    if column.dtype().is_float() && report.to.is_integer() {
+        for (row, v) in Option::<f64>::from_column(column)?.into_iter().enumerate() {
+            match v {
+                Some(v) if !v.is_finite() => report.nan += 1,
+                Some(v) if v.fract() != 0.0 => report.fractional += 1,
+                _ => continue,
+            }
+            lost[row] = true;
+        }
+    }
+
+    let cast = column.cast(&report.to)?;
+    let missing_before = column.is_null();
+    let missing_after = cast.is_null();
+    for (row, (before, after)) in missing_before.iter().zip(missing_after.iter()).enumerate() {
+        if lost[row] || before == Some(true) || after != Some(true) {
+            continue;
+        }
+        if column.dtype().is_primitive_numeric() {
+            report.overflow += 1;
+        } else {
+            report.invalid += 1;
+        }
+        lost[row] = true;
+    }
+
+    report.first_lost = lost.iter().position(|l| *l);
+    if report.first_lost.is_none() {
+        return Ok(cast);
+    }
+    let keep = BooleanChunked::from_iter_values("keep".into(), lost.iter().map(|l| !l));
+    let missing = Series::full_null(column.name().clone(), column.len(), &report.to);
+    Ok(cast.zip_with(&keep, &missing)?)
+}
+
+fn value_kind<T>(value: &DataValue<T>) -> ColumnKind {
+    match value {
+        DataValue::Real(_) => ColumnKind::Real,
+        DataValue::Integer(_) => ColumnKind::Integer,
+        DataValue::Boolean(_) => ColumnKind::Boolean,
+        DataValue::Text(_) | DataValue::List(_) => ColumnKind::Text,
+    }
+}
+
+/// The counter of a [`CastReport`] for a lost value.
+type Lost = fn(&mut CastReport) -> &mut usize;
+
+/// Converts a header value to `kind`, or returns why it can't be converted exactly.
+fn coerce_value<T>(value: &DataValue<T>, kind: ColumnKind) -> Result<DataValue<T>, Lost>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    let real = |r: &T| r.to_f64().unwrap_or(f64::NAN);
+    let from_f64 = |r: f64| r.to_string().parse().map_err(|_| overflow as Lost);
+    match (value, kind) {
+        (DataValue::Real(r), ColumnKind::Integer) => match real(r) {
+            r if !r.is_finite() => Err(|report| &mut report.nan),
+            r if r.fract() != 0.0 => Err(|report| &mut report.fractional),
+            r if r.abs() > i64::MAX as f64 => Err(overflow),
+            r => Ok(DataValue::Integer(r as i64)),
+        },
+        (DataValue::Integer(i), ColumnKind::Real) => Ok(DataValue::Real(from_f64(*i as f64)?)),
+        (DataValue::Boolean(b), ColumnKind::Integer) => Ok(DataValue::Integer(*b as i64)),
+        (DataValue::Boolean(b), ColumnKind::Real) => {
+            Ok(DataValue::Real(from_f64(*b as u8 as f64)?))
+        }
+        (DataValue::Integer(i @ (0 | 1)), ColumnKind::Boolean) => Ok(DataValue::Boolean(*i == 1)),
+        (DataValue::Text(t), ColumnKind::Integer) => t
+            .trim()
+            .parse()
+            .map(DataValue::Integer)
+            .map_err(|_| invalid as Lost),
+        (DataValue::Text(t), ColumnKind::Real) => t
+            .trim()
+            .parse()
+            .map(DataValue::Real)
+            .map_err(|_| invalid as Lost),
+        (DataValue::Text(t), ColumnKind::Boolean) => match t.trim() {
+            "true" => Ok(DataValue::Boolean(true)),
+            "false" => Ok(DataValue::Boolean(false)),
+            _ => Err(invalid),
+        },
+        (value, ColumnKind::Text) => Ok(DataValue::Text(value.to_string())),
+        _ => Err(invalid),
+    }
+}
+
+fn overflow(report: &mut CastReport) -> &mut usize {
+    &mut report.overflow
+}
+
+fn invalid(report: &mut CastReport) -> &mut usize {
+    &mut report.invalid
+}
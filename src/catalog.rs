@@ -0,0 +1,128 @@
+//! Indexing of directories full of tfs files.
+//!
+//! [`TfsCatalog::scan`] reads only the headers of all tfs files below a directory and summarises
+//! them in a frame, which can itself be written as a tfs file:
+//!
+//! ```
+//! # use tfs::catalog::TfsCatalog;
+//! let catalog = TfsCatalog::scan("test").unwrap();
+//! let summary = catalog.to_frame();
+//!
+//! assert_eq!(summary.len(), 2);
+//! assert_eq!(*summary.propd("N_FILES"), 2.0);
+//! ```
+use polars::prelude::{DataFrame, NamedFrom};
+use polars::series::Series;
+use std::path::{Path, PathBuf};
+
+use crate::dataframe::DataValue;
+use crate::header::TfsHeader;
+use crate::tfsdataframe::{Properties, TfsDataFrame};
+
+/// A file found by [`TfsCatalog::scan`].
+#[derive(Debug, Clone)]
+pub struct CatalogEntry {
+    pub path: PathBuf,
+    pub header: TfsHeader<f64>,
+}
+
+impl CatalogEntry {
+    fn text(&self, key: &str) -> String {
+        match self.header.properties.get(key) {
+            Some(DataValue::Text(t)) => t.clone(),
+            Some(v) => v.to_string(),
+            None => String::new(),
+        }
+    }
+
+    fn real(&self, key: &str) -> f64 {
+        match self.header.properties.get(key) {
+            Some(DataValue::Real(r)) => *r,
+            Some(DataValue::Integer(i)) => *i as f64,
+            _ => f64::NAN,
+        }
+    }
+}
+
+/// The headers of all tfs files in a directory tree.
+#[derive(Debug, Clone, Default)]
+pub struct TfsCatalog {
+    /// The files that could be read, ordered by path.
+    pub entries: Vec<CatalogEntry>,
+    /// The files with a `.tfs` extension whose header couldn't be read, with the reason.
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl TfsCatalog {
+    /// Reads the headers of all files with the extension `.tfs` in `dir` and its
+    /// subdirectories.
+    pub fn scan<P: AsRef<Path>>(dir: P) -> anyhow::Result<TfsCatalog> {
+        let mut files = Vec::new();
+        collect_tfs_files(dir.as_ref(), &mut files)?;
+        files.sort();
+
+        let mut catalog = TfsCatalog::default();
+        for path in files {
+            match TfsHeader::read(&path) {
+                Ok(header) => catalog.entries.push(CatalogEntry { path, header }),
+                Err(err) => catalog.errors.push((path, err.to_string())),
+            }
+        }
+        Ok(catalog)
+    }
+
+    /// Summarises the catalog in a frame with one row per file and the columns `PATH`, `TYPE`,
+    /// `SEQUENCE`, `DATE`, `TIME`, `Q1`, `Q2` and `N_COLS`. Missing headers are empty strings or
+    /// `NaN`.
+    pub fn to_frame(&self) -> TfsDataFrame<f64> {
+        let text =
+            |key: &str| -> Vec<String> { self.entries.iter().map(|e| e.text(key)).collect() };
+        let real = |key: &str| -> Vec<f64> { self.entries.iter().map(|e| e.real(key)).collect() };
+
+        let paths: Vec<String> = self
+            .entries
+            .iter()
+            .map(|e| e.path.display().to_string())
+            .collect();
+        let n_cols: Vec<f64> = self
+            .entries
+            .iter()
+            .map(|e| e.header.colnames.len() as f64)
+            .collect();
+
+        let df = DataFrame::new(vec![
+            Series::new("PATH".into(), paths).into(),
+            Series::new("TYPE".into(), text("TYPE")).into(),
+            Series::new("SEQUENCE".into(), text("SEQUENCE")).into(),
+            Series::new("DATE".into(), text("DATE")).into(),
+            Series::new("TIME".into(), text("TIME")).into(),
+            Series::new("Q1".into(), real("Q1")).into(),
+            Series::new("Q2".into(), real("Q2")).into(),
+            Series::new("N_COLS".into(), n_cols).into(),
+        ])
+        .expect("all columns have the same length");
+
+        let mut properties = Properties::new();
+        properties.insert("TYPE".to_owned(), DataValue::Text("CATALOG".to_owned()));
+        properties.insert(
+            "N_FILES".to_owned(),
+            DataValue::Real(self.entries.len() as f64),
+        );
+        TfsDataFrame::new(properties, df)
+    }
+}
+
+pub(crate) fn collect_tfs_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_tfs_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("tfs"))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! Checksums of columns and frames, to detect changed inputs without comparing files.
+//!
+//! [`TfsDataFrame::column_hash`] hashes the values of a column, [`TfsDataFrame::frame_fingerprint`]
+//! the header, the column names and all values. Both are xxHash (XXH3, 64 bit) of a canonical
+//! encoding of the values, so they don't depend on how the file was formatted:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let fingerprint = df.frame_fingerprint().unwrap();
+//!
+//! let file = tempfile::NamedTempFile::new().unwrap();
+//! df.write(file.path()).unwrap();
+//! let reloaded = TfsDataFrame::<f64>::open(file.path()).unwrap();
+//! assert_eq!(reloaded.frame_fingerprint().unwrap(), fingerprint);
+//! ```
+//!
+//! All `NaN`s hash the same and `-0.0` like `0.0`. Reals and integers of the same value hash
+//! differently. The order of the header entries doesn't matter, the order of the columns does.
+use polars::prelude::{DataType, NumericNative};
+use polars::series::Series;
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::dataframe::DataValue;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+use crate::types::ColumnKind;
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// The hash of the values of the column `name`, see the [module documentation](self).
+    pub fn column_hash(&self, name: &str) -> anyhow::Result<u64> {
+        let mut hasher = Xxh3::new();
+        hash_series(&mut hasher, self.column(name)?)?;
+        Ok(hasher.digest())
+    }
+
+    /// The hash of the header, the column names and the values of all columns.
+    pub fn frame_fingerprint(&self) -> anyhow::Result<u64> {
+        let mut hasher = Xxh3::new();
+        let mut properties: Vec<_> = self.properties.iter().collect();
+        properties.sort_by_key(|(key, _)| *key);
+        for (key, value) in properties {
+            hash_text(&mut hasher, key);
+            hash_text(&mut hasher, &value_tag(value));
+        }
+        for name in self.column_names() {
+            hash_text(&mut hasher, name);
+            hasher.update(&self.column_hash(name)?.to_le_bytes());
+        }
+        Ok(hasher.digest())
+    }
+}
+
+/// The kind and value of a header entry as text.
+fn value_tag<T: std::fmt::Display>(value: &DataValue<T>) -> String {
+    match value {
+        DataValue::Real(r) => format!("%le {}", r),
+        DataValue::Integer(i) => format!("%d {}", i),
+        DataValue::Boolean(b) => format!("%b {}", b),
+        DataValue::Text(t) => format!("%s {}", t),
+        DataValue::List(_) => format!("%tbl {}", value),
+    }
+}
+
+/// Length-prefixed, so that `("ab", "c")` and `("a", "bc")` hash differently.
+fn hash_text(hasher: &mut Xxh3, text: &str) {
+    hasher.update(&(text.len() as u64).to_le_bytes());
+    hasher.update(text.as_bytes());
+}
+
+/// Hashes the values of `series`, each as a presence flag followed by its bytes.
+fn hash_series(hasher: &mut Xxh3, series: &Series) -> anyhow::Result<()> {
+    let kind = ColumnKind::of_dtype(series.dtype());
+    hasher.update(kind.canonical_code().as_bytes());
+    match kind {
+        ColumnKind::Real => {
+            for v in Option::<f64>::from_column(series)? {
+                hash_value(hasher, v.map(|v| canonical(v).to_le_bytes()));
+            }
+        }
+        ColumnKind::Integer => {
+            for v in Option::<i64>::from_column(series)? {
+                hash_value(hasher, v.map(i64::to_le_bytes));
+            }
+        }
+        ColumnKind::Boolean => {
+            for v in series.cast(&DataType::Boolean)?.bool()? {
+                hash_value(hasher, v.map(|b| [b as u8]));
+            }
+        }
+        ColumnKind::Text => {
+            for v in Option::<String>::from_column(series)? {
+                match v {
+                    Some(text) => {
+                        hasher.update(&[1]);
+                        hash_text(hasher, &text);
+                    }
+                    None => hasher.update(&[0]),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn hash_value<const N: usize>(hasher: &mut Xxh3, value: Option<[u8; N]>) {
+    match value {
+        Some(bytes) => {
+            hasher.update(&[1]);
+            hasher.update(&bytes);
+        }
+        None => hasher.update(&[0]),
+    }
+}
+
+/// A single `NaN` and `0.0` for `-0.0`.
+fn canonical(v: f64) -> f64 {
+    if v.is_nan() {
+        f64::NAN
+    } else if v == 0.0 {
+        0.0
+    } else {
+        v
+    }
+}
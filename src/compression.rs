@@ -0,0 +1,214 @@
+//! In-memory compression of rarely used columns.
+//!
+//! Wide frames often carry many columns that are never looked at. Compressed columns are kept as
+//! lz4 blocks and only decompressed when they are accessed through
+//! [`TfsDataFrame::column`], trading CPU time for memory when many frames are loaded at once.
+//! [`TfsDataFrame::df`] decompresses them into the copy it returns, without keeping them.
+//!
+//! ```
+//! # use tfs::{TfsDataFrame, TfsReadOptions};
+//! let options = TfsReadOptions::new().compressed_columns(&["DPX", "DPY", "K1L"]);
+//! let df = TfsDataFrame::<f64>::open_with("test/test.tfs", &options).unwrap();
+//!
+//! assert_eq!(df.compressed_columns(), vec!["DPX", "DPY", "K1L"]);
+//! assert_eq!(df.column("K1L").unwrap().len(), 5);
+//! ```
+use polars::prelude::{DataType, NamedFrom, NumericNative};
+use polars::series::Series;
+use std::sync::OnceLock;
+
+use crate::tfsdataframe::TfsDataFrame;
+
+/// A column stored as an lz4 block.
+#[derive(Debug)]
+pub(crate) struct CompressedColumn {
+    /// Position of the column in the frame when it was compressed.
+    pub position: usize,
+    dtype: DataType,
+    len: usize,
+    block: Vec<u8>,
+    decoded: OnceLock<Series>,
+}
+
+impl CompressedColumn {
+    /// Values are encoded as a presence flag followed by the little endian value, strings are
+    /// prefixed by their length.
+    pub fn compress(series: &Series, position: usize) -> anyhow::Result<CompressedColumn> {
+        let mut bytes = Vec::with_capacity(series.len() * 9);
+        match series.dtype() {
+            DataType::Float64 => {
+                for v in series.f64()? {
+                    encode(&mut bytes, v.map(f64::to_le_bytes).as_ref().map(|b| &b[..]));
+                }
+            }
+            DataType::Int64 => {
+                for v in series.i64()? {
+                    encode(&mut bytes, v.map(i64::to_le_bytes).as_ref().map(|b| &b[..]));
+                }
+            }
+            DataType::String => {
+                for v in series.str()? {
+                    if let Some(s) = v {
+                        bytes.push(1);
+                        bytes.extend_from_slice(&(s.len() as u32).to_le_bytes());
+                        bytes.extend_from_slice(s.as_bytes());
+                    } else {
+                        bytes.push(0);
+                    }
+                }
+            }
+            dtype => anyhow::bail!(
+                "can't compress column '{}' of type {}",
+                series.name(),
+                dtype
+            ),
+        }
+
+        Ok(CompressedColumn {
+            position,
+            dtype: series.dtype().clone(),
+            len: series.len(),
+            block: lz4_flex::compress_prepend_size(&bytes),
+            decoded: OnceLock::new(),
+        })
+    }
+
+    /// Returns the column, decompressing it on first access. The decompressed copy is kept with
+    /// the block, see [`CompressedColumn::decompress`] for a copy that isn't.
+    pub fn series(&self, name: &str) -> anyhow::Result<&Series> {
+        if let Some(series) = self.decoded.get() {
+            return Ok(series);
+        }
+        let series = self.decompress(name)?;
+        Ok(self.decoded.get_or_init(|| series))
+    }
+
+    /// Size of the compressed block in bytes.
+    pub fn compressed_size(&self) -> usize {
+        self.block.len()
+    }
+
+    /// Decompresses the column into a new series.
+    pub fn decompress(&self, name: &str) -> anyhow::Result<Series> {
+        let bytes = lz4_flex::decompress_size_prepended(&self.block)?;
+        let mut reader = BlockReader { bytes: &bytes };
+
+        Ok(match self.dtype {
+            DataType::Float64 => {
+                let values = (0..self.len)
+                    .map(|_| {
+                        Ok(reader
+                            .value(8)?
+                            .map(|b| f64::from_le_bytes(b.try_into().unwrap())))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Series::new(name.into(), values)
+            }
+            DataType::Int64 => {
+                let values = (0..self.len)
+                    .map(|_| {
+                        Ok(reader
+                            .value(8)?
+                            .map(|b| i64::from_le_bytes(b.try_into().unwrap())))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Series::new(name.into(), values)
+            }
+            _ => {
+                let values = (0..self.len)
+                    .map(|_| reader.string())
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Series::new(name.into(), values)
+            }
+        })
+    }
+}
+
+fn encode(bytes: &mut Vec<u8>, value: Option<&[u8]>) {
+    match value {
+        Some(v) => {
+            bytes.push(1);
+            bytes.extend_from_slice(v);
+        }
+        None => bytes.push(0),
+    }
+}
+
+struct BlockReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> BlockReader<'a> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        if self.bytes.len() < n {
+            anyhow::bail!("compressed column is corrupted");
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn value(&mut self, size: usize) -> anyhow::Result<Option<&'a [u8]>> {
+        match self.take(1)?[0] {
+            0 => Ok(None),
+            _ => Ok(Some(self.take(size)?)),
+        }
+    }
+
+    fn string(&mut self) -> anyhow::Result<Option<String>> {
+        match self.value(4)? {
+            None => Ok(None),
+            Some(len) => {
+                let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+                Ok(Some(String::from_utf8(self.take(len)?.to_vec())?))
+            }
+        }
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Compresses the column `name`. It stays accessible through [`TfsDataFrame::column`] and
+    /// [`TfsDataFrame::df`], which decompress it on access.
+    pub fn compress_column(&mut self, name: &str) -> anyhow::Result<()> {
+        if self.compressed.contains_key(name) {
+            return Ok(());
+        }
+        if self.df.width() == 1 {
+            anyhow::bail!("at least one column has to stay uncompressed");
+        }
+
+        let series = self.column(name)?.clone();
+        let position = self
+            .column_names()
+            .iter()
+            .position(|c| *c == name)
+            .unwrap_or_default();
+
+        let compressed = CompressedColumn::compress(&series, position)?;
+        self.df.drop_in_place(name)?;
+        self.compressed.insert(name.to_owned(), compressed);
+        Ok(())
+    }
+
+    /// Stores all compressed columns uncompressed again, at their original positions.
+    pub fn decompress_columns(&mut self) -> anyhow::Result<()> {
+        self.df = self.df()?.into_owned();
+        self.compressed.clear();
+        Ok(())
+    }
+
+    /// Names of the compressed columns, in their original order.
+    pub fn compressed_columns(&self) -> Vec<&str> {
+        let mut compressed: Vec<_> = self.compressed.iter().collect();
+        compressed.sort_by_key(|(_, c)| c.position);
+        compressed
+            .into_iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Memory used by the compressed columns, in bytes (without decompressed copies).
+    pub fn compressed_size(&self) -> usize {
+        self.compressed.values().map(|c| c.compressed_size()).sum()
+    }
+}
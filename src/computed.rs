@@ -0,0 +1,116 @@
+//! Virtual columns, computed on first access.
+//!
+//! [`TfsDataFrame::register_virtual`] defines a column by a [`Formula`] without computing it.
+//! [`TfsDataFrame::column`] computes it the first time it is asked for and keeps the values
+//! until a column of the frame changes, so derived quantities cost no memory until they are
+//! used:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! df.register_virtual("BETX_HALF", "BETX / 2").unwrap();
+//! assert!(!df.is_computed("BETX_HALF"));
+//!
+//! let half = df.column("BETX_HALF").unwrap().f64().unwrap().get(0).unwrap();
+//! assert!(df.is_computed("BETX_HALF"));
+//! assert_eq!(half, df.column("BETX").unwrap().f64().unwrap().get(0).unwrap() / 2.0);
+//! ```
+//!
+//! Virtual columns are not among the [column names](TfsDataFrame::column_names) and are not
+//! written. Header entries of a formula are read when it is computed, changing them later
+//! doesn't recompute it, see [`TfsDataFrame::clear_virtual`].
+use polars::prelude::NumericNative;
+use polars::series::Series;
+use std::sync::OnceLock;
+
+use crate::formula::Formula;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// The formula of a virtual column and its values, once computed.
+#[derive(Debug)]
+pub(crate) struct VirtualColumn {
+    formula: Formula,
+    values: OnceLock<Series>,
+}
+
+impl VirtualColumn {
+    fn new(formula: Formula) -> VirtualColumn {
+        VirtualColumn {
+            formula,
+            values: OnceLock::new(),
+        }
+    }
+
+    /// Returns the column, computing it on first access.
+    pub fn series<T>(&self, name: &str, df: &TfsDataFrame<T>) -> anyhow::Result<&Series>
+    where
+        T: std::str::FromStr + NumericNative,
+    {
+        if let Some(series) = self.values.get() {
+            return Ok(series);
+        }
+        let series = Option::<f64>::to_column(name, self.formula.evaluate(df)?);
+        Ok(self.values.get_or_init(|| series))
+    }
+}
+
+impl Clone for VirtualColumn {
+    /// Clones the formula only, the clone is computed again.
+    fn clone(&self) -> Self {
+        VirtualColumn::new(self.formula.clone())
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Defines the virtual column `name` by the [`Formula`] `expression`, see the
+    /// [module documentation](self). Fails if the frame already has a column `name` or the
+    /// formula uses a column that doesn't exist yet, real or virtual.
+    pub fn register_virtual(&mut self, name: &str, expression: &str) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !self.column_names().contains(&name) && !self.virtual_columns.contains_key(name),
+            "there already is a column '{}'",
+            name
+        );
+        let formula = expression.parse::<Formula>()?;
+        for column in formula.columns() {
+            anyhow::ensure!(
+                self.column_names().contains(&column) || self.virtual_columns.contains_key(column),
+                "the formula of '{}' uses the unknown column '{}'",
+                name,
+                column
+            );
+        }
+        self.virtual_columns
+            .insert(name.to_owned(), VirtualColumn::new(formula));
+        Ok(())
+    }
+
+    /// Removes the virtual column `name`, returns whether there was one. Virtual columns using it
+    /// fail when they are computed.
+    pub fn remove_virtual(&mut self, name: &str) -> bool {
+        let removed = self.virtual_columns.shift_remove(name).is_some();
+        self.clear_virtual();
+        removed
+    }
+
+    /// Names of the virtual columns, in the order they were registered.
+    pub fn virtual_columns(&self) -> Vec<&str> {
+        self.virtual_columns.keys().map(String::as_str).collect()
+    }
+
+    /// Whether the virtual column `name` has been computed and is kept in memory.
+    pub fn is_computed(&self, name: &str) -> bool {
+        self.virtual_columns
+            .get(name)
+            .is_some_and(|c| c.values.get().is_some())
+    }
+
+    /// Drops the values of all virtual columns, which are computed again on their next access.
+    /// Happens whenever a column of the frame changes.
+    pub fn clear_virtual(&mut self) {
+        for column in self.virtual_columns.values_mut() {
+            column.values.take();
+        }
+    }
+}
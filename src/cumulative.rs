@@ -0,0 +1,124 @@
+//! Running sums, products and differences along the rows, e.g. accumulated lengths or the phase
+//! advance between neighbouring elements.
+//!
+//! The results are new columns, named after the operation in upper case like `CUMSUM(S)`, which
+//! can be stored with [`TfsDataFrame::set_column`]. Differences of quantities growing by a
+//! period every turn, like the phase advance by the tune, can [wrap around](Wrap) the ring, so
+//! that the first row gets the advance from the last element:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::cumulative::Wrap;
+//! # use tfs::formula::prop;
+//! # use tfs::polars::prelude::*;
+//! let df = TfsDataFrame::<f64>::new(
+//!     vec![("Q1".into(), tfs::DataValue::Real(1.25))],
+//!     df!("MUX" => [0.25, 0.5, 1.0]).unwrap(),
+//! );
+//! let advance = df.diff_rows("MUX", Wrap::period(prop("Q1"))).unwrap();
+//! assert_eq!(advance.name().as_str(), "DIFF(MUX)");
+//! assert_eq!(advance.f64().unwrap().to_vec(), [Some(0.5), Some(0.25), Some(0.5)]);
+//!
+//! let open = df.diff_rows("MUX", Wrap::Open).unwrap();
+//! assert_eq!(open.f64().unwrap().get(0), None);
+//! assert_eq!(df.cumsum("MUX").unwrap().f64().unwrap().get(2), Some(1.75));
+//! ```
+//!
+//! Missing values stay missing and are skipped by sums and products, differences with a missing
+//! value are missing.
+use polars::prelude::NumericNative;
+use polars::series::Series;
+
+use crate::formula::{property, Scalar};
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// How [`TfsDataFrame::diff_rows`] treats the first row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Wrap {
+    /// The first row has no predecessor, its difference is missing.
+    Open,
+    /// The frame is one turn of a ring: the first row follows the last one, whose value is one
+    /// period lower.
+    Period(Scalar),
+}
+
+impl Wrap {
+    /// Wraps around with the period `period`, a number or a header entry given with
+    /// [`prop`](crate::formula::prop).
+    pub fn period<S: Into<Scalar>>(period: S) -> Wrap {
+        Wrap::Period(period.into())
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// The running sum of the numeric column `name`, named `CUMSUM(name)`.
+    pub fn cumsum(&self, name: &str) -> anyhow::Result<Series> {
+        self.accumulate(name, "CUMSUM", 0.0, |total, v| total + v)
+    }
+
+    /// The running product of the numeric column `name`, named `CUMPROD(name)`.
+    pub fn cumprod(&self, name: &str) -> anyhow::Result<Series> {
+        self.accumulate(name, "CUMPROD", 1.0, |total, v| total * v)
+    }
+
+    /// The difference of every row of the numeric column `name` to the row before, named
+    /// `DIFF(name)`, see the [module documentation](self).
+    pub fn diff_rows(&self, name: &str, wrap: Wrap) -> anyhow::Result<Series> {
+        let values = numbers(self, name)?;
+        let first = match (wrap, values.first(), values.last()) {
+            (Wrap::Open, _, _) => None,
+            (Wrap::Period(period), Some(first), Some(last)) => {
+                let period = match period {
+                    Scalar::Value(value) => value,
+                    Scalar::Property(key) => property(self, &key)?,
+                };
+                first.zip(*last).map(|(first, last)| first + period - last)
+            }
+            (Wrap::Period(_), _, _) => None,
+        };
+        let mut differences = Vec::with_capacity(values.len());
+        if !values.is_empty() {
+            differences.push(first);
+        }
+        differences.extend(values.windows(2).map(|pair| Some(pair[1]? - pair[0]?)));
+        Ok(Option::<f64>::to_column(
+            &format!("DIFF({})", name),
+            differences,
+        ))
+    }
+
+    fn accumulate<F>(&self, name: &str, operation: &str, start: f64, f: F) -> anyhow::Result<Series>
+    where
+        F: Fn(f64, f64) -> f64,
+    {
+        let mut total = start;
+        let values: Vec<Option<f64>> = numbers(self, name)?
+            .into_iter()
+            .map(|v| {
+                let v = v?;
+                total = f(total, v);
+                Some(total)
+            })
+            .collect();
+        Ok(Option::<f64>::to_column(
+            &format!("{}({})", operation, name),
+            values,
+        ))
+    }
+}
+
+/// The values of the numeric column `name` as reals.
+pub(crate) fn numbers<T>(df: &TfsDataFrame<T>, name: &str) -> anyhow::Result<Vec<Option<f64>>>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    let column = df.column(name)?;
+    anyhow::ensure!(
+        column.dtype().is_primitive_numeric(),
+        "can't compute with the column '{}' of type {}",
+        name,
+        column.dtype()
+    );
+    Option::<f64>::from_column(column)
+}
@@ -1,7 +1,7 @@
-
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
-use std::ops::{Add, Div, Mul, Sub};
+use std::ops::{Add, Sub};
 
 /// Helps deciding if we access by key (a valid String index has to be setup with `set_index`) or
 /// by an integer index
@@ -40,18 +40,67 @@ impl<'a> From<u32> for Indexer<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum DataValue<T> {
     Text(String),
     Real(T),
+    Integer(i64),
+    /// `%b` in MAD-NG and omc3, see [`Dialect`](crate::Dialect).
+    Boolean(bool),
+    /// A table like `{1, 2, "a"}`, `%tbl` in MAD-NG.
+    List(Vec<DataValue<T>>),
     //Complex(c128),
 }
 
+impl<T> DataValue<T> {
+    /// The name of the kind of the value, for error messages.
+    fn kind_name(&self) -> &'static str {
+        match self {
+            DataValue::Text(_) => "text",
+            DataValue::Real(_) => "a real value",
+            DataValue::Integer(_) => "an integer",
+            DataValue::Boolean(_) => "a boolean",
+            DataValue::List(_) => "a list",
+        }
+    }
+
+    /// The text of a `Text` value. Unlike the conversion `String::from`, fails instead of
+    /// panicking for other values.
+    pub fn try_into_text(self) -> anyhow::Result<String> {
+        match self {
+            DataValue::Text(t) => Ok(t),
+            value => anyhow::bail!("the data value is {}, not text", value.kind_name()),
+        }
+    }
+
+    /// The number of a `Real` value. Unlike the conversions `f64::from` and `f32::from`, fails
+    /// instead of panicking for other values.
+    pub fn try_into_real(self) -> anyhow::Result<T> {
+        match self {
+            DataValue::Real(r) => Ok(r),
+            value => anyhow::bail!("the data value is {}, not a real value", value.kind_name()),
+        }
+    }
+}
+
 impl<T: fmt::Display> fmt::Display for DataValue<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             DataValue::Text(s) => write!(f, "'{}'", s),
             DataValue::Real(r) => write!(f, "{}", r),
+            DataValue::Integer(i) => write!(f, "{}", i),
+            DataValue::Boolean(b) => write!(f, "{}", b),
+            DataValue::List(items) => {
+                write!(f, "{{")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "}}")
+            }
         }
     }
 }
@@ -73,9 +122,30 @@ impl<'a, T: Display> Display for DataView<'a, T> {
     }
 }
 
-impl<T> Into<String> for DataValue<T> {
-    fn into(self) -> String {
-        if let DataValue::Text(t) = self {
+impl<'a, T> DataView<'a, T> {
+    /// The text of a `Text` view. Unlike the conversion to `&String`, fails instead of panicking
+    /// for real values.
+    pub fn try_text(&self) -> anyhow::Result<&'a String> {
+        match self {
+            DataView::Text(t) => Ok(t),
+            DataView::Real(_) => anyhow::bail!("the data value is a real value, not text"),
+        }
+    }
+
+    /// The number of a `Real` view. Unlike the conversion to `f64`, fails instead of panicking
+    /// for text.
+    pub fn try_real(&self) -> anyhow::Result<&'a T> {
+        match self {
+            DataView::Real(r) => Ok(r),
+            DataView::Text(_) => anyhow::bail!("the data value is text, not a real value"),
+        }
+    }
+}
+
+/// Panics if the value is not a `Text`, see [`DataValue::try_into_text`].
+impl<T> From<DataValue<T>> for String {
+    fn from(value: DataValue<T>) -> String {
+        if let DataValue::Text(t) = value {
             t
         } else {
             panic!("The data value is not a Text");
@@ -85,9 +155,10 @@ impl<T> Into<String> for DataValue<T> {
 
 macro_rules! impl_data_into {
     ($a:ident) => {
-        impl<T: Into<$a>> Into<$a> for DataValue<T> {
-            fn into(self) -> $a {
-                if let DataValue::Real(r) = self {
+        /// Panics if the value is not `Real`, see [`DataValue::try_into_real`].
+        impl<T: Into<$a>> From<DataValue<T>> for $a {
+            fn from(value: DataValue<T>) -> $a {
+                if let DataValue::Real(r) = value {
                     r.into()
                 } else {
                     panic!("The data value is not a real value")
@@ -110,9 +181,10 @@ impl_data_into!(f32);
 //    }
 //}
 
-impl<'a, T> Into<&'a String> for DataView<'a, T> {
-    fn into(self) -> &'a String {
-        if let DataView::Text(t) = self {
+/// Panics if the view is not `Text`, see [`DataView::try_text`].
+impl<'a, T> From<DataView<'a, T>> for &'a String {
+    fn from(view: DataView<'a, T>) -> &'a String {
+        if let DataView::Text(t) = view {
             t
         } else {
             panic!("The data value is not a Text");
@@ -120,9 +192,10 @@ impl<'a, T> Into<&'a String> for DataView<'a, T> {
     }
 }
 
-impl<'a, T: Copy + Into<f64>> Into<f64> for DataView<'a, T> {
-    fn into(self) -> f64 {
-        if let DataView::Real(r) = self {
+/// Panics if the view is not `Real`, see [`DataView::try_real`].
+impl<'a, T: Copy + Into<f64>> From<DataView<'a, T>> for f64 {
+    fn from(view: DataView<'a, T>) -> f64 {
+        if let DataView::Real(r) = view {
             (*r).into()
         } else {
             panic!("The data value is not a real number");
@@ -150,10 +223,11 @@ pub enum DataVector<T> {
 
 macro_rules! impl_datavec_into {
     ($a:ident) => {
-        impl<'a> Into<&'a Vec<$a>> for &'a DataVector<$a> {
-            fn into(self) -> &'a Vec<$a> {
-                if let DataVector::RealVector(v) = self {
-                    &v
+        /// Panics if the vector is not a `RealVector`, see [`DataVector::try_reals`].
+        impl<'a> From<&'a DataVector<$a>> for &'a Vec<$a> {
+            fn from(vector: &'a DataVector<$a>) -> &'a Vec<$a> {
+                if let DataVector::RealVector(v) = vector {
+                    v
                 } else {
                     panic!("The data value is not a real value")
                 }
@@ -165,10 +239,66 @@ macro_rules! impl_datavec_into {
 impl_datavec_into!(f64);
 impl_datavec_into!(f32);
 
-impl<'a, T> Into<&'a Vec<String>> for &'a DataVector<T> {
-    fn into(self) -> &'a Vec<String> {
-        if let DataVector::TextVector(v) = self {
-            &v
+impl<T> DataVector<T> {
+    /// The values of a `RealVector`. Unlike the conversion to `&Vec<f64>`, fails instead of
+    /// panicking for a `TextVector`.
+    pub fn try_reals(&self) -> anyhow::Result<&Vec<T>> {
+        match self {
+            DataVector::RealVector(v) => Ok(v),
+            DataVector::TextVector(_) => anyhow::bail!("the vector holds text, not real values"),
+        }
+    }
+
+    /// The values of a `TextVector`. Unlike the conversion to `&Vec<String>`, fails instead of
+    /// panicking for a `RealVector`.
+    pub fn try_texts(&self) -> anyhow::Result<&Vec<String>> {
+        match self {
+            DataVector::TextVector(v) => Ok(v),
+            DataVector::RealVector(_) => anyhow::bail!("the vector holds real values, not text"),
+        }
+    }
+
+    /// Element-wise `op` of two `RealVector`s of the same length.
+    fn try_zip(&self, other: &DataVector<T>, op: impl Fn(T, T) -> T) -> anyhow::Result<Self>
+    where
+        T: Copy,
+    {
+        let (a, b) = (self.try_reals()?, other.try_reals()?);
+        anyhow::ensure!(
+            a.len() == b.len(),
+            "the vectors have different lengths, {} and {}",
+            a.len(),
+            b.len()
+        );
+        Ok(DataVector::RealVector(
+            a.iter().zip(b).map(|(x, y)| op(*x, *y)).collect(),
+        ))
+    }
+
+    /// Element-wise sum, like `&a + &b`, but fails instead of panicking if one of the vectors
+    /// holds text or their lengths differ.
+    pub fn try_add(&self, other: &DataVector<T>) -> anyhow::Result<Self>
+    where
+        T: Copy + Add + From<<T as Add>::Output>,
+    {
+        self.try_zip(other, |x, y| T::from(x + y))
+    }
+
+    /// Element-wise difference, like `&a - &b`, but fails instead of panicking if one of the
+    /// vectors holds text or their lengths differ.
+    pub fn try_sub(&self, other: &DataVector<T>) -> anyhow::Result<Self>
+    where
+        T: Copy + Sub + From<<T as Sub>::Output>,
+    {
+        self.try_zip(other, |x, y| T::from(x - y))
+    }
+}
+
+/// Panics if the vector is not a `TextVector`, see [`DataVector::try_texts`].
+impl<'a, T> From<&'a DataVector<T>> for &'a Vec<String> {
+    fn from(vector: &'a DataVector<T>) -> &'a Vec<String> {
+        if let DataVector::TextVector(v) = vector {
+            v
         } else {
             panic!("not a TextVector")
         }
@@ -182,7 +312,8 @@ where
     type Output = DataVector<T>;
 
     /// Implementation for Addition of two `DataVector`s.
-    /// Yields element-wise addition of the two Vectors if they are both `DataVector::RealVector`.
+    /// Yields element-wise addition of the two Vectors if they are both `DataVector::RealVector`,
+    /// panics otherwise or if their lengths differ. See [`DataVector::try_add`].
     /// ```
     /// # use tfs::DataVector;
     ///
@@ -196,20 +327,7 @@ where
     /// assert_eq!(c, test_c);
     /// ```
     fn add(self, other: &'a DataVector<T>) -> DataVector<T> {
-        if let &DataVector::RealVector(ref a) = self {
-            if let &DataVector::RealVector(ref b) = other {
-                DataVector::RealVector(
-                    a.iter()
-                        .zip(b.iter())
-                        .map(|(x, y)| T::from(*x + *y))
-                        .collect::<Vec<T>>(),
-                )
-            } else {
-                panic!("rhs has to be data")
-            }
-        } else {
-            panic!("lhs has to be data")
-        }
+        self.try_add(other).unwrap_or_else(|err| panic!("{}", err))
     }
 }
 
@@ -219,7 +337,8 @@ where
 {
     type Output = DataVector<T>;
 
-    /// Implementation for Subtraction of two `DataVector`s.
+    /// Implementation for Subtraction of two `DataVector`s. Panics if one of them is not a
+    /// `DataVector::RealVector` or their lengths differ, see [`DataVector::try_sub`].
     /// ```
     /// use tfs::DataVector;
     ///
@@ -229,24 +348,7 @@ where
     /// let c = &a - &b;
     /// ```
     fn sub(self, other: &'a DataVector<T>) -> DataVector<T> {
-        if let &DataVector::RealVector(ref a) = self {
-            if let &DataVector::RealVector(ref b) = other {
-                if a.len() == b.len() {
-                    DataVector::RealVector(
-                        a.iter()
-                            .zip(b.iter())
-                            .map(|(x, y)| T::from(*x - *y))
-                            .collect::<Vec<T>>(),
-                    )
-                } else {
-                    panic!("Vectors have to have the same length")
-                }
-            } else {
-                panic!("rhs has to be data")
-            }
-        } else {
-            panic!("lhs has to be data")
-        }
+        self.try_sub(other).unwrap_or_else(|err| panic!("{}", err))
     }
 }
 
@@ -255,15 +357,15 @@ impl<T: Debug> Debug for DataVector<T> {
         match self {
             DataVector::RealVector(v) => {
                 write!(f, "RealVector[{}] {{ ", v.len())?;
-                for i in 0..v.len().min(5) {
-                    write!(f, "{:?}, ", v[i])?;
+                for x in v.iter().take(5) {
+                    write!(f, "{:?}, ", x)?;
                 }
                 write!(f, "}}")?;
             }
             DataVector::TextVector(v) => {
                 write!(f, "TextVector[{}] {{ ", v.len())?;
-                for i in 0..v.len().min(5) {
-                    write!(f, "'{:?}', ", v[i])?;
+                for x in v.iter().take(5) {
+                    write!(f, "'{:?}', ", x)?;
                 }
                 write!(f, "}}")?;
             }
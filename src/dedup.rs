@@ -0,0 +1,82 @@
+//! Finding and removing repeated rows.
+//!
+//! Merged measurement files often contain the same BPM more than once. Rows are duplicates if
+//! they agree in a subset of the columns, by default the `NAME` column that identifies the
+//! elements:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::dedup::Keep;
+//! let mut df = TfsDataFrame::<f64>::new(
+//!     Vec::new(),
+//!     tfs::polars::df!("NAME" => ["BPM1", "BPM2", "BPM1"], "X" => [0.1, 0.2, 0.3]).unwrap(),
+//! );
+//! assert_eq!(df.duplicated(None).unwrap(), [false, false, true]);
+//!
+//! df.drop_duplicates(None, Keep::Last).unwrap();
+//! assert_eq!(df.len(), 2);
+//! ```
+use polars::prelude::NumericNative;
+
+use crate::join::{key_columns, rows_by_key, NullKeys};
+use crate::mask::NAME_COLUMN;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Which of the rows with the same values [`TfsDataFrame::drop_duplicates`] keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Keep {
+    #[default]
+    First,
+    Last,
+    /// Drops all rows that have a duplicate.
+    None,
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Marks the rows that have the same values in the columns `subset` as an earlier row.
+    /// Without a subset the rows are compared by `NAME`, or by all columns if there is none.
+    /// Missing values equal each other.
+    pub fn duplicated(&self, subset: Option<&[&str]>) -> anyhow::Result<Vec<bool>> {
+        self.duplicates(subset, Keep::First)
+    }
+
+    /// Removes repeated rows, compared like in [`TfsDataFrame::duplicated`], keeping the first,
+    /// the last or none of them. Returns the number of removed rows.
+    pub fn drop_duplicates(
+        &mut self,
+        subset: Option<&[&str]>,
+        keep: Keep,
+    ) -> anyhow::Result<usize> {
+        let duplicates = self.duplicates(subset, keep)?;
+        let keep: Vec<bool> = duplicates.iter().map(|d| !d).collect();
+        self.retain_rows(&keep)?;
+        Ok(duplicates.iter().filter(|d| **d).count())
+    }
+
+    /// The rows that aren't kept with `keep`.
+    fn duplicates(&self, subset: Option<&[&str]>, keep: Keep) -> anyhow::Result<Vec<bool>> {
+        let names = self.column_names();
+        let subset = match subset {
+            Some(subset) => subset.to_vec(),
+            None if names.contains(&NAME_COLUMN) => vec![NAME_COLUMN],
+            None => names,
+        };
+        let df = self.df()?;
+        let mut duplicates = vec![false; df.height()];
+        if subset.is_empty() {
+            return Ok(duplicates);
+        }
+        for (_, rows) in rows_by_key(&key_columns(&df, &subset)?, NullKeys::Match)? {
+            let rows = match keep {
+                Keep::First => &rows[1..],
+                Keep::Last => &rows[..rows.len() - 1],
+                Keep::None if rows.len() > 1 => &rows[..],
+                Keep::None => &[],
+            };
+            for row in rows {
+                duplicates[*row as usize] = true;
+            }
+        }
+        Ok(duplicates)
+    }
+}
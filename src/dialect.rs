@@ -0,0 +1,186 @@
+//! The tfs dialects of the programs writing and reading tfs files.
+//!
+//! MAD-X, MAD-NG and omc3 (through tfs-pandas) agree on the `%le`, `%d` and `%s` type codes but
+//! differ beyond them: MAD-NG writes booleans with `%b` as `true` or `false` and tables in the
+//! header with `%tbl` as `{1, 2, "a"}`, omc3 reads and writes booleans but no tables, and MAD-X
+//! knows neither. The [`Dialect`] of a file is detected when it is read, or given with
+//! [`TfsReadOptions::dialect`](crate::TfsReadOptions::dialect), and decides how these values are
+//! written again:
+//!
+//! ```
+//! # use tfs::{DataValue, Dialect, TfsDataFrame};
+//! let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! assert_eq!(df.dialect(), Dialect::MadX);
+//!
+//! // MAD-X has no booleans, this is written as `%d 1`
+//! df.properties.insert("CLOSED".to_owned(), DataValue::Boolean(true));
+//! df.set_dialect(Dialect::MadNg);
+//! ```
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dataframe::DataValue;
+use crate::types::ColumnKind;
+
+/// Type code of booleans in MAD-NG and omc3.
+pub const BOOLEAN_CODE: &str = "%b";
+
+/// Type code of tables in MAD-NG headers.
+pub const TABLE_CODE: &str = "%tbl";
+
+/// Type code of complex numbers in MAD-NG, only used to detect the dialect.
+const COMPLEX_CODE: &str = "%lz";
+
+/// The program a tfs file is written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    #[default]
+    MadX,
+    MadNg,
+    Omc3,
+}
+
+impl FromStr for Dialect {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "madx" | "mad-x" => Ok(Dialect::MadX),
+            "madng" | "mad-ng" => Ok(Dialect::MadNg),
+            "omc3" | "tfs-pandas" => Ok(Dialect::Omc3),
+            _ => anyhow::bail!("unknown dialect '{}', expected madx, madng or omc3", s),
+        }
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dialect::MadX => write!(f, "madx"),
+            Dialect::MadNg => write!(f, "madng"),
+            Dialect::Omc3 => write!(f, "omc3"),
+        }
+    }
+}
+
+impl Dialect {
+    /// Guesses the dialect from the type codes of the header entries and columns and the
+    /// `ORIGIN` header entry. MAD-NG is recognised by its own type codes or its name in
+    /// `ORIGIN`. Anything else is taken for the dialect this crate writes by default, MAD-X, as
+    /// neither MAD-X nor omc3 files can be told apart from it.
+    pub fn detect<'a>(codes: impl IntoIterator<Item = &'a str>, origin: Option<&str>) -> Dialect {
+        let madng = codes
+            .into_iter()
+            .any(|code| [BOOLEAN_CODE, TABLE_CODE, COMPLEX_CODE].contains(&code))
+            || origin.is_some_and(|origin| origin.to_ascii_uppercase().contains("MAD-NG"));
+        if madng {
+            Dialect::MadNg
+        } else {
+            Dialect::default()
+        }
+    }
+
+    /// The kind of columns with the type `code` beyond the codes of the
+    /// [`TypeRegistry`](crate::TypeRegistry).
+    pub(crate) fn column_kind(&self, code: &str) -> Option<ColumnKind> {
+        match self {
+            Dialect::MadNg | Dialect::Omc3 if code == BOOLEAN_CODE => Some(ColumnKind::Boolean),
+            _ => None,
+        }
+    }
+
+    /// Whether booleans are written as such, otherwise they are written as the integers 0 and 1.
+    pub fn has_booleans(&self) -> bool {
+        *self != Dialect::MadX
+    }
+
+    /// Whether tables are written as such in the header, otherwise they are written as text.
+    pub fn has_tables(&self) -> bool {
+        *self == Dialect::MadNg
+    }
+
+    /// Parses the header value `value` with the type `code`, if it is a boolean or table of
+    /// this dialect.
+    pub(crate) fn parse_value<T: FromStr>(&self, code: &str, value: &str) -> Option<DataValue<T>> {
+        match code {
+            BOOLEAN_CODE if self.has_booleans() => parse_boolean(value).map(DataValue::Boolean),
+            TABLE_CODE if self.has_tables() => parse_table(value),
+            _ => None,
+        }
+    }
+
+    /// Writes the header value `value`, given as type code and value.
+    pub(crate) fn format_value<T: fmt::Display>(&self, value: &DataValue<T>) -> (String, String) {
+        match value {
+            DataValue::Text(t) => ("%s".to_owned(), format!("\"{}\"", t)),
+            DataValue::Real(r) => ("%le".to_owned(), r.to_string()),
+            DataValue::Integer(i) => ("%d".to_owned(), i.to_string()),
+            DataValue::Boolean(b) if self.has_booleans() => {
+                (BOOLEAN_CODE.to_owned(), b.to_string())
+            }
+            DataValue::Boolean(b) => ("%d".to_owned(), (*b as i64).to_string()),
+            DataValue::List(_) if self.has_tables() => (TABLE_CODE.to_owned(), format_table(value)),
+            DataValue::List(_) => (
+                "%s".to_owned(),
+                format!("\"{}\"", format_table(value).replace('"', "'")),
+            ),
+        }
+    }
+}
+
+fn parse_boolean(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parses a table like `{1, 2.5, "a", true, {3}}`.
+fn parse_table<T: FromStr>(value: &str) -> Option<DataValue<T>> {
+    let inner = value.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let mut items = Vec::new();
+    let (mut depth, mut quoted, mut start) = (0, false, 0);
+    for (i, c) in inner.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '{' if !quoted => depth += 1,
+            '}' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                items.push(parse_item(&inner[start..i])?);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !inner[start..].trim().is_empty() {
+        items.push(parse_item(&inner[start..])?);
+    }
+    Some(DataValue::List(items))
+}
+
+fn parse_item<T: FromStr>(item: &str) -> Option<DataValue<T>> {
+    let item = item.trim();
+    if let Some(text) = item.strip_prefix('"').and_then(|i| i.strip_suffix('"')) {
+        Some(DataValue::Text(text.to_owned()))
+    } else if item.starts_with('{') {
+        parse_table(item)
+    } else if let Some(boolean) = parse_boolean(item) {
+        Some(DataValue::Boolean(boolean))
+    } else if let Ok(integer) = item.parse() {
+        Some(DataValue::Integer(integer))
+    } else {
+        item.parse().ok().map(DataValue::Real)
+    }
+}
+
+fn format_table<T: fmt::Display>(value: &DataValue<T>) -> String {
+    match value {
+        DataValue::Text(t) => format!("\"{}\"", t),
+        DataValue::List(items) => {
+            let items: Vec<String> = items.iter().map(format_table).collect();
+            format!("{{{}}}", items.join(", "))
+        }
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,235 @@
+//! Differences between two frames of the same shape.
+//!
+//! A [`FrameDiff`] records the changed header entries and data cells. It can be stored as JSON
+//! next to a large reference file and applied later on, instead of keeping modified copies of
+//! the whole file:
+//!
+//! ```
+//! # use tfs::{DataValue, TfsDataFrame};
+//! # use tfs::diff::FrameDiff;
+//! # let path = std::env::temp_dir().join("tfs_diff_doctest.json");
+//! let reference = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let mut updated = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! updated
+//!     .properties
+//!     .insert("ORIGIN".to_owned(), DataValue::Text("corrections".to_owned()));
+//!
+//! let diff = reference.diff(&updated).unwrap();
+//! diff.write(&path).unwrap();
+//!
+//! let mut patched = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! patched.apply_diff(&FrameDiff::open(&path).unwrap()).unwrap();
+//! assert_eq!(patched.props("ORIGIN"), "corrections");
+//! ```
+use polars::prelude::{AnyValue, Column, DataType, NamedFrom, NumericNative};
+use polars::series::Series;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::dataframe::DataValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// A single changed data cell.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CellChange {
+    pub row: usize,
+    pub column: String,
+    /// The new value, `None` stands for a missing value or `NaN`.
+    pub value: Option<DataValue<f64>>,
+}
+
+/// The changes turning one frame into another one with the same columns and number of rows.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameDiff<T> {
+    /// Header entries that were added or changed.
+    pub set_properties: BTreeMap<String, DataValue<T>>,
+    /// Header entries that were removed.
+    pub removed_properties: Vec<String>,
+    /// Changed data cells, ordered by column and row.
+    pub cells: Vec<CellChange>,
+}
+
+impl<T> FrameDiff<T> {
+    /// Returns `true` if the two frames were equal.
+    pub fn is_empty(&self) -> bool {
+        self.set_properties.is_empty()
+            && self.removed_properties.is_empty()
+            && self.cells.is_empty()
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> FrameDiff<T> {
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<FrameDiff<T>> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Writes the diff as a JSON file.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Reads a diff from a JSON file.
+    pub fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<FrameDiff<T>> {
+        FrameDiff::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+fn cell_value(column: &Column, row: usize) -> anyhow::Result<Option<DataValue<f64>>> {
+    Ok(match column.get(row)? {
+        AnyValue::Null => None,
+        AnyValue::Float64(v) if v.is_nan() => None,
+        AnyValue::Float64(v) => Some(DataValue::Real(v)),
+        AnyValue::Float32(v) if v.is_nan() => None,
+        AnyValue::Float32(v) => Some(DataValue::Real(v.into())),
+        AnyValue::String(s) => Some(DataValue::Text(s.to_owned())),
+        AnyValue::StringOwned(s) => Some(DataValue::Text(s.to_string())),
+        v => match v.extract::<i64>() {
+            Some(i) => Some(DataValue::Integer(i)),
+            None => Some(DataValue::Text(v.to_string())),
+        },
+    })
+}
+
+/// Builds a copy of `column` with the values at the given rows replaced.
+fn patch_column(series: &Series, changes: &[&CellChange]) -> anyhow::Result<Series> {
+    let name = series.name().clone();
+
+    let patched = match series.dtype() {
+        DataType::Float64 => {
+            let mut values: Vec<f64> = series
+                .f64()?
+                .iter()
+                .map(|v| v.unwrap_or(f64::NAN))
+                .collect();
+            for change in changes {
+                values[change.row] = match &change.value {
+                    None => f64::NAN,
+                    Some(DataValue::Real(r)) => *r,
+                    Some(DataValue::Integer(i)) => *i as f64,
+                    Some(DataValue::Boolean(b)) => *b as i64 as f64,
+                    Some(DataValue::Text(_) | DataValue::List(_)) => {
+                        anyhow::bail!("can't set text in the real column '{}'", name)
+                    }
+                };
+            }
+            Series::new(name, values)
+        }
+        DataType::String => {
+            let mut values: Vec<Option<String>> =
+                series.str()?.iter().map(|v| v.map(String::from)).collect();
+            for change in changes {
+                values[change.row] = match &change.value {
+                    None => None,
+                    Some(DataValue::Text(t)) => Some(t.clone()),
+                    Some(v) => Some(v.to_string()),
+                };
+            }
+            Series::new(name, values)
+        }
+        dtype if dtype.is_integer() => {
+            let mut values: Vec<Option<i64>> =
+                series.cast(&DataType::Int64)?.i64()?.iter().collect();
+            for change in changes {
+                values[change.row] = match &change.value {
+                    None => None,
+                    Some(DataValue::Integer(i)) => Some(*i),
+                    Some(_) => anyhow::bail!("can only set integers in the column '{}'", name),
+                };
+            }
+            Series::new(name, values).cast(dtype)?
+        }
+        dtype => anyhow::bail!("can't patch column '{}' of type {}", name, dtype),
+    };
+    Ok(patched)
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Computes the changes turning `self` into `other`. Both frames need the same columns and
+    /// number of rows.
+    pub fn diff(&self, other: &TfsDataFrame<T>) -> anyhow::Result<FrameDiff<T>> {
+        if self.len() != other.len() {
+            anyhow::bail!(
+                "can't diff frames of different length ({} and {} rows)",
+                self.len(),
+                other.len()
+            );
+        }
+        let (df, other_df) = (self.df()?, other.df()?);
+        if df.get_column_names() != other_df.get_column_names() {
+            anyhow::bail!("can't diff frames with different columns");
+        }
+
+        let set_properties = other
+            .properties
+            .iter()
+            .filter(|(key, value)| self.properties.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        let mut removed_properties: Vec<String> = self
+            .properties
+            .keys()
+            .filter(|key| !other.properties.contains_key(*key))
+            .cloned()
+            .collect();
+        removed_properties.sort();
+
+        let mut cells = Vec::new();
+        for (old, new) in df.get_columns().iter().zip(other_df.get_columns()) {
+            for row in 0..self.len() {
+                let value = cell_value(new, row)?;
+                if cell_value(old, row)? != value {
+                    cells.push(CellChange {
+                        row,
+                        column: old.name().to_string(),
+                        value,
+                    });
+                }
+            }
+        }
+
+        Ok(FrameDiff {
+            set_properties,
+            removed_properties,
+            cells,
+        })
+    }
+
+    /// Applies the changes in `diff`, see [`TfsDataFrame::diff`].
+    pub fn apply_diff(&mut self, diff: &FrameDiff<T>) -> anyhow::Result<()> {
+        let mut by_column: BTreeMap<&str, Vec<&CellChange>> = BTreeMap::new();
+        for change in &diff.cells {
+            if change.row >= self.len() {
+                anyhow::bail!(
+                    "row {} of the diff is out of range, the frame has {} rows",
+                    change.row,
+                    self.len()
+                );
+            }
+            by_column.entry(&change.column).or_default().push(change);
+        }
+
+        // patch all columns before touching the frame, so a failing diff leaves it unchanged
+        let patched = by_column
+            .into_iter()
+            .map(|(name, changes)| patch_column(self.column(name)?, &changes))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for series in patched {
+            self.set_column(series)?;
+        }
+
+        for key in &diff.removed_properties {
+            self.properties.shift_remove(key);
+        }
+        for (key, value) in &diff.set_properties {
+            self.properties.insert(key.clone(), value.clone());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Twiss files of elegant in the conventions of MAD-X.
+//!
+//! elegant writes the twiss parameters of `&twiss_output` as an SDDS file with its own names,
+//! e.g. `betax`, `psix` (in radians) and `etax`. [`read_twiss`] renames them to the MAD-X names
+//! (`BETX`, `MUX` in units of 2π, `DX`), so that they can be compared with a MAD-X twiss
+//! directly:
+//!
+//! ```no_run
+//! # use tfs::TfsDataFrame;
+//! let elegant = tfs::elegant::read_twiss::<f64, _>("ring.twi").unwrap();
+//! let madx = TfsDataFrame::<f64>::open("twiss.tfs").unwrap();
+//! let elegant_q1 = elegant.properties["Q1"].clone();
+//! assert_eq!(elegant_q1, madx.properties["Q1"]);
+//! ```
+//!
+//! Names without a MAD-X equivalent are upper-cased, other characters than letters, digits and
+//! `_` replaced by `_`.
+use polars::prelude::NumericNative;
+use std::f64::consts::PI;
+use std::path::Path;
+
+use crate::dataframe::DataValue;
+use crate::sdds::Sdds;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// The electron rest mass in GeV, the unit of elegant's momenta.
+const ELECTRON_MASS: f64 = 0.51099895069e-3;
+
+/// Columns of an elegant twiss file and their MAD-X names.
+pub const COLUMN_NAMES: [(&str, &str); 16] = [
+    ("ElementName", "NAME"),
+    ("ElementType", "KEYWORD"),
+    ("s", "S"),
+    ("betax", "BETX"),
+    ("alphax", "ALFX"),
+    ("psix", "MUX"),
+    ("etax", "DX"),
+    ("etaxp", "DPX"),
+    ("betay", "BETY"),
+    ("alphay", "ALFY"),
+    ("psiy", "MUY"),
+    ("etay", "DY"),
+    ("etayp", "DPY"),
+    ("xAperture", "APER_1"),
+    ("yAperture", "APER_2"),
+    ("pCentral0", "PC"),
+];
+
+/// Parameters of an elegant twiss file and their MAD-X header names.
+pub const PARAMETER_NAMES: [(&str, &str); 9] = [
+    ("nux", "Q1"),
+    ("nuy", "Q2"),
+    ("dnux/dp", "DQ1"),
+    ("dnuy/dp", "DQ2"),
+    ("alphac", "ALFA"),
+    ("pCentral", "PC"),
+    ("betaxMax", "BETXMAX"),
+    ("betayMax", "BETYMAX"),
+    ("ex0", "EX"),
+];
+
+/// Columns in radians, MAD-X gives them in units of 2π.
+const PHASES: [&str; 2] = ["psix", "psiy"];
+
+/// Momenta in units of the electron mass, MAD-X gives them in GeV.
+const MOMENTA: [&str; 2] = ["pCentral", "pCentral0"];
+
+/// The MAD-X name of the column or parameter `name` of an elegant twiss file.
+pub fn madx_name(name: &str) -> String {
+    COLUMN_NAMES
+        .iter()
+        .chain(&PARAMETER_NAMES)
+        .find(|(elegant, _)| *elegant == name)
+        .map(|(_, madx)| madx.to_string())
+        .unwrap_or_else(|| {
+            name.chars()
+                .map(|c| match c {
+                    c if c.is_ascii_alphanumeric() => c.to_ascii_uppercase(),
+                    _ => '_',
+                })
+                .collect()
+        })
+}
+
+/// Reads the ascii SDDS twiss file of elegant at `path` with MAD-X names and units.
+pub fn read_twiss<T, P>(path: P) -> anyhow::Result<TfsDataFrame<T>>
+where
+    T: std::str::FromStr + NumericNative,
+    P: AsRef<Path>,
+{
+    parse_twiss(&std::fs::read_to_string(path)?)
+}
+
+/// Parses an elegant twiss file like [`read_twiss`].
+pub fn parse_twiss<T>(text: &str) -> anyhow::Result<TfsDataFrame<T>>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    let mut sdds = Sdds::parse(text)?;
+    for (parameter, value) in &mut sdds.parameters {
+        if MOMENTA.contains(&parameter.name.as_str()) {
+            let momentum: f64 = value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid momentum '{}'", value))?;
+            *value = (momentum * ELECTRON_MASS).to_string();
+        }
+        parameter.name = madx_name(&parameter.name);
+    }
+    for column in &mut sdds.columns {
+        let name = column.name().to_string();
+        if PHASES.contains(&name.as_str()) {
+            *column = &*column / (2.0 * PI);
+        } else if MOMENTA.contains(&name.as_str()) {
+            *column = &*column * ELECTRON_MASS;
+        }
+        column.rename(madx_name(&name).into());
+    }
+
+    let mut df = sdds.into_frame()?;
+    df.properties
+        .shift_insert(0, "TYPE".to_owned(), DataValue::Text("TWISS".to_owned()));
+    Ok(df)
+}
@@ -0,0 +1,182 @@
+//! Random lattice errors as MAD-X error tables.
+//!
+//! [`LatticeErrors`] draws field errors and misalignments for the elements of a twiss frame,
+//! with a distribution per element class (the `KEYWORD` column) and error. The result has the
+//! columns of the `EFIELD` table written by MAD-X's `ESAVE`, so it can be read back with
+//! `READTABLE` and assigned with `SETERR`:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::errors::LatticeErrors;
+//! # use tfs::noise::NoiseDistribution;
+//! let twiss = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//!
+//! let errors = LatticeErrors::new()
+//!     .error("QUADRUPOLE", "K1L", NoiseDistribution::Gaussian, 1e-5)
+//!     .error("QUADRUPOLE", "DX", NoiseDistribution::Uniform, 1e-4)
+//!     .error("MONITOR", "DY", NoiseDistribution::Gaussian, 2e-4)
+//!     .generate(&twiss, 42)
+//!     .unwrap();
+//! assert_eq!(errors.len(), 3);
+//! ```
+use polars::prelude::{Column, DataFrame, NamedFrom, NumericNative};
+use polars::series::Series;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::dataframe::DataValue;
+use crate::noise::NoiseDistribution;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// The column holding the element class.
+pub const KEYWORD_COLUMN: &str = "KEYWORD";
+
+/// Class matching all elements in [`LatticeErrors::error`].
+pub const ALL_CLASSES: &str = "*";
+
+/// Highest multipole order of the `EFIELD` table.
+const MAX_ORDER: usize = 20;
+
+/// Misalignment and other columns of the `EFIELD` table between the field and the phase errors.
+const ALIGNMENT_COLUMNS: [&str; 17] = [
+    "DX",
+    "DY",
+    "DS",
+    "DPHI",
+    "DTHETA",
+    "DPSI",
+    "MREX",
+    "MREY",
+    "MREDX",
+    "MREDY",
+    "AREX",
+    "AREY",
+    "MSCALX",
+    "MSCALY",
+    "RFM_FREQ",
+    "RFM_HARMON",
+    "RFM_LAG",
+];
+
+/// The error columns of the `EFIELD` table in their order: the normal and skew field errors
+/// `K0L`, `K0SL` to `K20L`, `K20SL`, the misalignments `DX` to `DPSI`, further columns up to
+/// `RFM_LAG`, and the phase errors `P0L`, `P0SL` to `P20L`, `P20SL`.
+pub fn efield_columns() -> Vec<String> {
+    let multipoles = |prefix: &str| -> Vec<String> {
+        (0..=MAX_ORDER)
+            .flat_map(|order| {
+                [
+                    format!("{}{}L", prefix, order),
+                    format!("{}{}SL", prefix, order),
+                ]
+            })
+            .collect()
+    };
+    let mut columns = multipoles("K");
+    columns.extend(ALIGNMENT_COLUMNS.iter().map(|c| c.to_string()));
+    columns.extend(multipoles("P"));
+    columns
+}
+
+/// An error drawn for all elements of a class.
+#[derive(Debug, Clone, PartialEq)]
+struct ErrorRule {
+    class: String,
+    column: String,
+    distribution: NoiseDistribution,
+    sigma: f64,
+}
+
+/// Generates error tables, see the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct LatticeErrors {
+    rules: Vec<ErrorRule>,
+}
+
+impl LatticeErrors {
+    pub fn new() -> LatticeErrors {
+        LatticeErrors::default()
+    }
+
+    /// Draws the error `column` of the elements of `class` (their `KEYWORD`, ignoring case, or
+    /// [`ALL_CLASSES`]) from `distribution` with the standard deviation `sigma`. Errors given
+    /// more than once for an element add up.
+    pub fn error(
+        mut self,
+        class: &str,
+        column: &str,
+        distribution: NoiseDistribution,
+        sigma: f64,
+    ) -> Self {
+        self.rules.push(ErrorRule {
+            class: class.to_owned(),
+            column: column.to_ascii_uppercase(),
+            distribution,
+            sigma,
+        });
+        self
+    }
+
+    /// Returns the error table of the elements of `twiss` that have at least one error, in
+    /// the order of `twiss`. The same `seed` gives the same errors.
+    pub fn generate<T: std::str::FromStr + NumericNative>(
+        &self,
+        twiss: &TfsDataFrame<T>,
+        seed: u64,
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let columns = efield_columns();
+        for rule in &self.rules {
+            anyhow::ensure!(
+                columns.contains(&rule.column),
+                "'{}' is not a column of the EFIELD table",
+                rule.column
+            );
+            anyhow::ensure!(
+                rule.sigma >= 0.0,
+                "the error {} of {} has a negative standard deviation",
+                rule.column,
+                rule.class
+            );
+        }
+
+        let names = String::from_column(twiss.column("NAME")?)?;
+        let classes = String::from_column(twiss.column(KEYWORD_COLUMN)?)?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut rows = Vec::new();
+        let mut values = vec![Vec::new(); columns.len()];
+        for (name, class) in names.into_iter().zip(&classes) {
+            let rules: Vec<&ErrorRule> = self
+                .rules
+                .iter()
+                .filter(|rule| rule.class == ALL_CLASSES || rule.class.eq_ignore_ascii_case(class))
+                .collect();
+            if rules.is_empty() {
+                continue;
+            }
+            let mut row = vec![0.0; columns.len()];
+            for rule in rules {
+                let index = columns.iter().position(|c| *c == rule.column).unwrap();
+                row[index] += rule.distribution.sample(&mut rng, rule.sigma);
+            }
+            rows.push(name);
+            for (column, value) in values.iter_mut().zip(row) {
+                column.push(value);
+            }
+        }
+
+        let mut df_columns: Vec<Column> = vec![Series::new("NAME".into(), rows).into()];
+        for (name, values) in columns.iter().zip(values) {
+            df_columns.push(Series::new(name.into(), values).into());
+        }
+        let header = [
+            ("NAME", DataValue::Text("EFIELD".to_owned())),
+            ("TYPE", DataValue::Text("EFIELD".to_owned())),
+            ("SEED", DataValue::Integer(seed as i64)),
+        ];
+        Ok(TfsDataFrame::new(
+            header.map(|(key, value)| (key.to_owned(), value)),
+            DataFrame::new(df_columns)?,
+        ))
+    }
+}
@@ -0,0 +1,694 @@
+//! Expressions on the rows of a frame, the language of row filters and formulas.
+//!
+//! A [`RowFilter`] is a condition on the columns of a row, like
+//! `S > 500 && NAME =~ 'BPM.*B1'`. Conditions compare values and are combined with `&&`, `||`,
+//! `!` and parentheses. Values are columns, numbers, quoted strings and header entries, which
+//! are written with an `@` like `@Q1`, and arithmetic on them. A
+//! [`Formula`](crate::formula::Formula) is such an arithmetic expression on its own:
+//!
+//! | operator | meaning |
+//! |---|---|
+//! | `+`, `-`, `*`, `/` | arithmetic on numbers, `-X` negates |
+//! | `==`, `!=`, `<`, `<=`, `>`, `>=` | numbers by value, strings lexicographically |
+//! | `=~`, `!~` | the string (doesn't) contain a match of the regular expression |
+//!
+//! `|X|` is the absolute value of the numeric column `X`. Arithmetic on missing values gives
+//! missing values. Conditions on missing values are neither true nor false, also when negated
+//! with `!`, and the rows where the whole expression is undecided are dropped. Numbers are
+//! written as literals like `1.5e3`, so columns named `NAN` or `INF` can be used like any other.
+//! Column names can't contain spaces, operators or parentheses.
+//!
+//! ```
+//! # use tfs::{DataValue, TfsDataFrame};
+//! let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! df.properties.insert("S0".to_owned(), DataValue::Real(20.0));
+//! df.filter_expr("S - @S0 > 5 && NAME =~ '^BPM'").unwrap();
+//! assert_eq!(df.len(), 1);
+//! ```
+use polars::prelude::{DataType, NumericNative};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dataframe::DataValue;
+use crate::formula::property;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Match,
+    NotMatch,
+}
+
+const OPERATORS: [(&str, Op); 8] = [
+    ("==", Op::Eq),
+    ("!=", Op::Ne),
+    ("<=", Op::Le),
+    (">=", Op::Ge),
+    ("=~", Op::Match),
+    ("!~", Op::NotMatch),
+    ("<", Op::Lt),
+    (">", Op::Gt),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl BinOp {
+    fn apply(&self, a: f64, b: f64) -> f64 {
+        match self {
+            BinOp::Add => a + b,
+            BinOp::Sub => a - b,
+            BinOp::Mul => a * b,
+            BinOp::Div => a / b,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    Compare(Box<Node>, Op, Box<Node>),
+    Matches(Box<Node>, Regex, bool),
+    Binary(Box<Node>, BinOp, Box<Node>),
+    Neg(Box<Node>),
+    /// The absolute value of a column.
+    Abs(String),
+    Number(f64),
+    Text(String),
+    Column(String),
+    Property(String),
+}
+
+impl Node {
+    /// Whether the node is true or false rather than a value.
+    fn is_condition(&self) -> bool {
+        matches!(
+            self,
+            Node::And(..) | Node::Or(..) | Node::Not(_) | Node::Compare(..) | Node::Matches(..)
+        )
+    }
+
+    /// The columns and header entries of the node, from left to right.
+    fn leaves(&self) -> Vec<&Node> {
+        match self {
+            Node::And(a, b) | Node::Or(a, b) | Node::Compare(a, _, b) | Node::Binary(a, _, b) => {
+                let mut leaves = a.leaves();
+                leaves.extend(b.leaves());
+                leaves
+            }
+            Node::Not(a) | Node::Neg(a) | Node::Matches(a, _, _) => a.leaves(),
+            leaf => vec![leaf],
+        }
+    }
+}
+
+/// A parsed expression, the condition of a [`RowFilter`] or the arithmetic of a
+/// [`Formula`](crate::formula::Formula).
+#[derive(Debug, Clone)]
+pub(crate) struct Expr {
+    source: String,
+    root: Node,
+}
+
+impl Expr {
+    pub(crate) fn parse(source: &str) -> anyhow::Result<Expr> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+        let root = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            anyhow::bail!("unexpected '{}' in '{}'", token, source);
+        }
+        Ok(Expr {
+            source: source.to_owned(),
+            root,
+        })
+    }
+
+    pub(crate) fn is_condition(&self) -> bool {
+        self.root.is_condition()
+    }
+
+    pub(crate) fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The names of the columns the expression uses.
+    pub(crate) fn columns(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        for leaf in self.root.leaves() {
+            if let Node::Column(name) | Node::Abs(name) = leaf {
+                if !names.contains(&name.as_str()) {
+                    names.push(name.as_str());
+                }
+            }
+        }
+        names
+    }
+
+    /// The keys of the header entries the expression uses.
+    pub(crate) fn properties(&self) -> Vec<&str> {
+        let mut keys = Vec::new();
+        for leaf in self.root.leaves() {
+            if let Node::Property(key) = leaf {
+                if !keys.contains(&key.as_str()) {
+                    keys.push(key.as_str());
+                }
+            }
+        }
+        keys
+    }
+
+    /// Evaluates the condition for every row of `df`, `None` where it depends on missing values.
+    pub(crate) fn conditions<T: std::str::FromStr + NumericNative>(
+        &self,
+        df: &TfsDataFrame<T>,
+    ) -> anyhow::Result<Vec<Option<bool>>> {
+        self.rows(df, |value| match value {
+            Value::Bool(b) => Ok(b),
+            _ => anyhow::bail!("'{}' is not a condition", self.source),
+        })
+    }
+
+    /// Evaluates the arithmetic for every row of `df`, `None` where a value is missing.
+    pub(crate) fn numbers<T: std::str::FromStr + NumericNative>(
+        &self,
+        df: &TfsDataFrame<T>,
+    ) -> anyhow::Result<Vec<Option<f64>>> {
+        self.rows(df, |value| match value {
+            Value::Real(r) => Ok(r),
+            _ => anyhow::bail!("'{}' is not a number", self.source),
+        })
+    }
+
+    fn rows<T, R, F>(&self, df: &TfsDataFrame<T>, f: F) -> anyhow::Result<Vec<R>>
+    where
+        T: std::str::FromStr + NumericNative,
+        F: Fn(Value) -> anyhow::Result<R>,
+    {
+        let mut columns = HashMap::new();
+        for name in self.columns() {
+            let column = df.column(name)?;
+            let values = match column.dtype() {
+                DataType::String => Values::Text(Option::<String>::from_column(column)?),
+                dtype if dtype.is_primitive_numeric() => {
+                    Values::Real(Option::<f64>::from_column(column)?)
+                }
+                dtype => anyhow::bail!("can't use the column '{}' of type {}", name, dtype),
+            };
+            columns.insert(name, values);
+        }
+        let mut properties = HashMap::new();
+        for key in self.properties() {
+            let value = match df.properties.get(key) {
+                Some(DataValue::Text(t)) => Property::Text(t.clone()),
+                _ => Property::Real(property(df, key)?),
+            };
+            properties.insert(key, value);
+        }
+
+        let env = Env {
+            columns,
+            properties,
+        };
+        (0..df.len())
+            .map(|row| f(evaluate(&self.root, &env, row)?))
+            .collect()
+    }
+}
+
+/// A condition on the rows of a frame, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct RowFilter {
+    expr: Expr,
+}
+
+impl FromStr for RowFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> anyhow::Result<RowFilter> {
+        let expr = Expr::parse(source)?;
+        anyhow::ensure!(expr.is_condition(), "'{}' is not a condition", source);
+        Ok(RowFilter { expr })
+    }
+}
+
+impl fmt::Display for RowFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expr.source())
+    }
+}
+
+impl RowFilter {
+    /// The names of the columns the filter uses.
+    pub fn columns(&self) -> Vec<&str> {
+        self.expr.columns()
+    }
+
+    /// Evaluates the filter for every row of `df`.
+    pub fn mask<T: std::str::FromStr + NumericNative>(
+        &self,
+        df: &TfsDataFrame<T>,
+    ) -> anyhow::Result<Vec<bool>> {
+        Ok(self
+            .expr
+            .conditions(df)?
+            .into_iter()
+            .map(|keep| keep.unwrap_or(false))
+            .collect())
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Keeps only the rows for which `expression` holds, see [`RowFilter`].
+    pub fn filter_expr(&mut self, expression: &str) -> anyhow::Result<()> {
+        let keep = expression.parse::<RowFilter>()?.mask(self)?;
+        self.retain_rows(&keep)
+    }
+
+    /// Keeps only the rows for which `keep` is `true`, e.g. a [`RowFilter::mask`] or the
+    /// matches of [`TextColumn::contains`](crate::text::TextColumn::contains).
+    pub fn filter_mask(&mut self, keep: &[bool]) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            keep.len() == self.len(),
+            "the mask has {} values but the frame {} rows",
+            keep.len(),
+            self.len()
+        );
+        self.retain_rows(keep)
+    }
+}
+
+/// The values of a column used in an expression.
+enum Values {
+    Real(Vec<Option<f64>>),
+    Text(Vec<Option<String>>),
+}
+
+/// The value of a header entry used in an expression.
+enum Property {
+    Real(f64),
+    Text(String),
+}
+
+/// The columns and header entries an expression is evaluated on.
+struct Env<'a> {
+    columns: HashMap<&'a str, Values>,
+    properties: HashMap<&'a str, Property>,
+}
+
+/// A value of a row, `None` if it is missing.
+enum Value<'a> {
+    Real(Option<f64>),
+    Text(Option<&'a str>),
+    Bool(Option<bool>),
+}
+
+/// The number `node` evaluates to in `row`.
+fn number(node: &Node, env: &Env, row: usize) -> anyhow::Result<Option<f64>> {
+    match evaluate(node, env, row)? {
+        Value::Real(r) => Ok(r),
+        _ => match node {
+            Node::Column(name) => anyhow::bail!("can't compute with the text column '{}'", name),
+            Node::Property(key) => anyhow::bail!("the header entry '{}' is not a number", key),
+            Node::Text(t) => anyhow::bail!("can't compute with the string '{}'", t),
+            _ => anyhow::bail!("can't compute with a condition"),
+        },
+    }
+}
+
+/// Whether `node` holds in `row`, `None` if that depends on missing values.
+fn condition(node: &Node, env: &Env, row: usize) -> anyhow::Result<Option<bool>> {
+    match evaluate(node, env, row)? {
+        Value::Bool(b) => Ok(b),
+        _ => anyhow::bail!("expected a condition instead of a value"),
+    }
+}
+
+fn evaluate<'a>(node: &'a Node, env: &'a Env, row: usize) -> anyhow::Result<Value<'a>> {
+    Ok(match node {
+        Node::Number(n) => Value::Real(Some(*n)),
+        Node::Text(t) => Value::Text(Some(t)),
+        Node::Column(name) => match &env.columns[name.as_str()] {
+            Values::Real(v) => Value::Real(v[row]),
+            Values::Text(v) => Value::Text(v[row].as_deref()),
+        },
+        Node::Abs(name) => match &env.columns[name.as_str()] {
+            Values::Real(v) => Value::Real(v[row].map(f64::abs)),
+            Values::Text(_) => anyhow::bail!("'|{}|' is the absolute value of text", name),
+        },
+        Node::Property(key) => match &env.properties[key.as_str()] {
+            Property::Real(r) => Value::Real(Some(*r)),
+            Property::Text(t) => Value::Text(Some(t)),
+        },
+        Node::Neg(a) => Value::Real(number(a, env, row)?.map(|a| -a)),
+        Node::Binary(a, op, b) => {
+            let (a, b) = (number(a, env, row)?, number(b, env, row)?);
+            Value::Real(a.zip(b).map(|(a, b)| op.apply(a, b)))
+        }
+        Node::And(a, b) => Value::Bool(match condition(a, env, row)? {
+            Some(false) => Some(false),
+            a => match condition(b, env, row)? {
+                Some(false) => Some(false),
+                b => a.and(b),
+            },
+        }),
+        Node::Or(a, b) => Value::Bool(match condition(a, env, row)? {
+            Some(true) => Some(true),
+            a => match condition(b, env, row)? {
+                Some(true) => Some(true),
+                b => a.and(b),
+            },
+        }),
+        Node::Not(a) => Value::Bool(condition(a, env, row)?.map(|a| !a)),
+        Node::Matches(a, regex, negated) => match evaluate(a, env, row)? {
+            Value::Text(Some(text)) => Value::Bool(Some(regex.is_match(text) != *negated)),
+            Value::Text(None) => Value::Bool(None),
+            _ => anyhow::bail!("'{}' is matched against a number", regex),
+        },
+        Node::Compare(a, op, b) => {
+            let ordering = match (evaluate(a, env, row)?, evaluate(b, env, row)?) {
+                (Value::Real(Some(a)), Value::Real(Some(b))) => a.partial_cmp(&b),
+                (Value::Text(Some(a)), Value::Text(Some(b))) => Some(a.cmp(b)),
+                (Value::Real(None), Value::Real(_) | Value::Text(_))
+                | (Value::Real(_) | Value::Text(_), Value::Real(None)) => None,
+                (Value::Text(None), Value::Real(_) | Value::Text(_))
+                | (Value::Real(_) | Value::Text(_), Value::Text(None)) => None,
+                (Value::Bool(_), _) | (_, Value::Bool(_)) => {
+                    anyhow::bail!("conditions are compared like values")
+                }
+                _ => anyhow::bail!("a number is compared with a string"),
+            };
+            let Some(ordering) = ordering else {
+                return Ok(Value::Bool(None));
+            };
+            Value::Bool(Some(match op {
+                Op::Eq => ordering.is_eq(),
+                Op::Ne => ordering.is_ne(),
+                Op::Lt => ordering.is_lt(),
+                Op::Le => ordering.is_le(),
+                Op::Gt => ordering.is_gt(),
+                Op::Ge => ordering.is_ge(),
+                Op::Match | Op::NotMatch => unreachable!("regex matches are parsed as Matches"),
+            }))
+        }
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Op(Op, &'static str),
+    Arith(BinOp, char),
+    Number(f64),
+    Text(String),
+    Column(String),
+    Abs(String),
+    Property(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::And => write!(f, "&&"),
+            Token::Or => write!(f, "||"),
+            Token::Not => write!(f, "!"),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::Op(_, symbol) => write!(f, "{}", symbol),
+            Token::Arith(_, symbol) => write!(f, "{}", symbol),
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Text(t) => write!(f, "'{}'", t),
+            Token::Column(name) => write!(f, "{}", name),
+            Token::Abs(name) => write!(f, "|{}|", name),
+            Token::Property(key) => write!(f, "@{}", key),
+        }
+    }
+}
+
+/// Characters ending a column name or header key.
+const DELIMITERS: &str = "&|!=<>~()'\"+-*/@";
+
+fn tokenize(source: &str) -> anyhow::Result<Vec<Token>> {
+    let word_len = |text: &str| {
+        text.find(|c: char| c.is_whitespace() || DELIMITERS.contains(c))
+            .unwrap_or(text.len())
+    };
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let (token, len) = if rest.starts_with("&&") {
+            (Token::And, 2)
+        } else if rest.starts_with("||") {
+            (Token::Or, 2)
+        } else if let Some((symbol, op)) = OPERATORS.iter().find(|(s, _)| rest.starts_with(s)) {
+            (Token::Op(*op, symbol), symbol.len())
+        } else if let Some(op) = match c {
+            '+' => Some(BinOp::Add),
+            '-' => Some(BinOp::Sub),
+            '*' => Some(BinOp::Mul),
+            '/' => Some(BinOp::Div),
+            _ => None,
+        } {
+            (Token::Arith(op, c), 1)
+        } else if c == '!' {
+            (Token::Not, 1)
+        } else if c == '(' {
+            (Token::Open, 1)
+        } else if c == ')' {
+            (Token::Close, 1)
+        } else if c == '|' {
+            let len = rest[1..]
+                .find('|')
+                .ok_or_else(|| anyhow::anyhow!("unterminated '|' in '{}'", source))?;
+            let name = rest[1..=len].trim();
+            anyhow::ensure!(
+                !name.is_empty() && word_len(name) == name.len(),
+                "'|' needs a column name in '{}'",
+                source
+            );
+            (Token::Abs(name.to_owned()), len + 2)
+        } else if c == '\'' || c == '"' {
+            let len = rest[1..]
+                .find(c)
+                .ok_or_else(|| anyhow::anyhow!("unterminated string in '{}'", source))?;
+            (Token::Text(rest[1..=len].to_owned()), len + 2)
+        } else if c == '@' {
+            match word_len(&rest[1..]) {
+                0 => anyhow::bail!("missing header key after '@' in '{}'", source),
+                len => (Token::Property(rest[1..=len].to_owned()), len + 1),
+            }
+        } else if c.is_ascii_digit() || c == '.' {
+            let len = number_len(rest);
+            let number = rest[..len].parse().map_err(|_| {
+                anyhow::anyhow!("invalid number '{}' in '{}'", &rest[..len], source)
+            })?;
+            (Token::Number(number), len)
+        } else {
+            match word_len(rest) {
+                0 => anyhow::bail!("unexpected '{}' in '{}'", c, source),
+                len => (Token::Column(rest[..len].to_owned()), len),
+            }
+        };
+        tokens.push(token);
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// The length of the number at the start of `text`, with an optional exponent like `e-3`.
+/// Unlike [`f64::from_str`] this doesn't take `nan` or `inf`, which are column names.
+fn number_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let digits = |from: usize| {
+        from + bytes[from..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit() || **b == b'.')
+            .count()
+    };
+    let len = digits(0);
+    if !matches!(bytes.get(len), Some(b'e' | b'E')) {
+        return len;
+    }
+    let sign = matches!(bytes.get(len + 1), Some(b'+' | b'-')) as usize;
+    match digits(len + 1 + sign) {
+        end if end > len + 1 + sign => end,
+        _ => len,
+    }
+}
+
+/// Parses the tokens from the loosest to the tightest binding: `||`, `&&`, `!`, comparisons,
+/// `+` and `-`, `*` and `/`, negation.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_arith(&self, ops: [BinOp; 2]) -> Option<BinOp> {
+        match self.peek() {
+            Some(Token::Arith(op, _)) if ops.contains(op) => Some(*op),
+            _ => None,
+        }
+    }
+
+    fn or(&mut self) -> anyhow::Result<Node> {
+        let mut node = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            let right = self.and()?;
+            node = Node::Or(
+                Box::new(conditions("||", node)?),
+                Box::new(conditions("||", right)?),
+            );
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> anyhow::Result<Node> {
+        let mut node = self.not()?;
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            let right = self.not()?;
+            node = Node::And(
+                Box::new(conditions("&&", node)?),
+                Box::new(conditions("&&", right)?),
+            );
+        }
+        Ok(node)
+    }
+
+    fn not(&mut self) -> anyhow::Result<Node> {
+        if self.peek() == Some(&Token::Not) {
+            self.position += 1;
+            return Ok(Node::Not(Box::new(conditions("!", self.not()?)?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> anyhow::Result<Node> {
+        let left = self.sum()?;
+        let Some(Token::Op(op, symbol)) = self.peek().cloned() else {
+            return Ok(left);
+        };
+        self.position += 1;
+        let left = values(symbol, left)?;
+        match op {
+            Op::Match | Op::NotMatch => match self.next() {
+                Some(Token::Text(pattern)) => Ok(Node::Matches(
+                    Box::new(left),
+                    Regex::new(&pattern)?,
+                    op == Op::NotMatch,
+                )),
+                _ => anyhow::bail!("'{}' needs a quoted regular expression", symbol),
+            },
+            op => {
+                let right = values(symbol, self.sum()?)?;
+                Ok(Node::Compare(Box::new(left), op, Box::new(right)))
+            }
+        }
+    }
+
+    fn sum(&mut self) -> anyhow::Result<Node> {
+        let mut node = self.product()?;
+        while let Some(op) = self.peek_arith([BinOp::Add, BinOp::Sub]) {
+            self.position += 1;
+            let right = self.product()?;
+            node = binary(node, op, right)?;
+        }
+        Ok(node)
+    }
+
+    fn product(&mut self) -> anyhow::Result<Node> {
+        let mut node = self.unary()?;
+        while let Some(op) = self.peek_arith([BinOp::Mul, BinOp::Div]) {
+            self.position += 1;
+            let right = self.unary()?;
+            node = binary(node, op, right)?;
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> anyhow::Result<Node> {
+        match self.next() {
+            Some(Token::Arith(BinOp::Sub, _)) => {
+                Ok(Node::Neg(Box::new(values("-", self.unary()?)?)))
+            }
+            Some(Token::Open) => {
+                let node = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(node),
+                    _ => anyhow::bail!("missing ')'"),
+                }
+            }
+            Some(Token::Number(n)) => Ok(Node::Number(n)),
+            Some(Token::Text(t)) => Ok(Node::Text(t)),
+            Some(Token::Column(name)) => Ok(Node::Column(name)),
+            Some(Token::Abs(name)) => Ok(Node::Abs(name)),
+            Some(Token::Property(key)) => Ok(Node::Property(key)),
+            Some(token) => anyhow::bail!("unexpected '{}'", token),
+            None => anyhow::bail!("unexpected end of the expression"),
+        }
+    }
+}
+
+/// `node` as an operand of `symbol`, which takes conditions.
+fn conditions(symbol: &str, node: Node) -> anyhow::Result<Node> {
+    anyhow::ensure!(node.is_condition(), "'{}' needs conditions", symbol);
+    Ok(node)
+}
+
+/// `node` as an operand of `symbol`, which takes values.
+fn values(symbol: &str, node: Node) -> anyhow::Result<Node> {
+    anyhow::ensure!(
+        !node.is_condition(),
+        "'{}' needs values, not conditions",
+        symbol
+    );
+    Ok(node)
+}
+
+fn binary(left: Node, op: BinOp, right: Node) -> anyhow::Result<Node> {
+    let symbol = match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+    };
+    Ok(Node::Binary(
+        Box::new(values(symbol, left)?),
+        op,
+        Box::new(values(symbol, right)?),
+    ))
+}
@@ -0,0 +1,277 @@
+//! File formats of tables, read into and written from [`TfsDataFrame`]s.
+//!
+//! Every format implements [`TableFormat`]: it recognises its files, by their extension or
+//! their first bytes, reads them and possibly writes them. The [`FormatRegistry`] knows tfs, the
+//! ascii SDDS files of elegant, pyAT summaries and Tao tables, further formats can be
+//! registered. [`TfsDataFrame::open_auto`] opens a file in whichever of these formats it has:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let df = TfsDataFrame::<f64>::open_auto("test/test.tfs").unwrap();
+//! assert_eq!(df.len(), 5);
+//! ```
+use polars::prelude::NumericNative;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Bytes read from the start of a file to detect its format.
+const HEAD_SIZE: usize = 4096;
+
+/// A file format of tables.
+pub trait TableFormat<T: FromStr + NumericNative> {
+    /// The name of the format, e.g. for command line options.
+    fn name(&self) -> &str;
+
+    /// The file extensions of the format, without the dot.
+    fn extensions(&self) -> &[&str];
+
+    /// Whether a file starting with `head` (up to 4 kB, cut at a line end) is in this format.
+    fn detect(&self, head: &str) -> bool;
+
+    fn read(&self, path: &Path) -> anyhow::Result<TfsDataFrame<T>>;
+
+    /// Writes `df` to `path`, by default the format can't be written.
+    fn write(&self, df: &TfsDataFrame<T>, path: &Path) -> anyhow::Result<()> {
+        let _ = (df, path);
+        anyhow::bail!("{} files can't be written", self.name())
+    }
+}
+
+/// Tfs files, see [`TfsDataFrame::open`] and [`TfsDataFrame::write`].
+pub struct TfsFormat;
+
+/// Ascii SDDS files, see [`TfsDataFrame::open_sdds`].
+pub struct SddsFormat;
+
+/// Twiss files of elegant with MAD-X names, see [`crate::elegant`].
+pub struct ElegantFormat;
+
+/// pyAT summaries as CSV or JSON, see [`crate::pyat`].
+pub struct PyatFormat;
+
+/// `show lattice` tables of Tao, see [`crate::tao`].
+pub struct TaoFormat;
+
+impl<T> TableFormat<T> for TfsFormat
+where
+    T: FromStr + NumericNative + fmt::Display,
+    <T as FromStr>::Err: fmt::Debug,
+{
+    fn name(&self) -> &str {
+        "tfs"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tfs", "dat", "out"]
+    }
+
+    fn detect(&self, head: &str) -> bool {
+        first_line(head).is_some_and(|line| line.starts_with('@') || line.starts_with('*'))
+    }
+
+    fn read(&self, path: &Path) -> anyhow::Result<TfsDataFrame<T>> {
+        Ok(TfsDataFrame::open(path)?)
+    }
+
+    fn write(&self, df: &TfsDataFrame<T>, path: &Path) -> anyhow::Result<()> {
+        Ok(df.write(path)?)
+    }
+}
+
+impl<T: FromStr + NumericNative> TableFormat<T> for SddsFormat {
+    fn name(&self) -> &str {
+        "sdds"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sdds"]
+    }
+
+    fn detect(&self, head: &str) -> bool {
+        head.starts_with("SDDS")
+    }
+
+    fn read(&self, path: &Path) -> anyhow::Result<TfsDataFrame<T>> {
+        TfsDataFrame::open_sdds(path)
+    }
+}
+
+impl<T: FromStr + NumericNative> TableFormat<T> for ElegantFormat {
+    fn name(&self) -> &str {
+        "elegant"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["twi"]
+    }
+
+    fn detect(&self, head: &str) -> bool {
+        head.starts_with("SDDS") && head.contains("&column name=betax")
+    }
+
+    fn read(&self, path: &Path) -> anyhow::Result<TfsDataFrame<T>> {
+        crate::elegant::read_twiss(path)
+    }
+}
+
+impl<T: FromStr + NumericNative> TableFormat<T> for PyatFormat {
+    fn name(&self) -> &str {
+        "pyat"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv", "json"]
+    }
+
+    fn detect(&self, head: &str) -> bool {
+        let json = head.trim_start().starts_with(['{', '[']);
+        let csv = first_line(head).is_some_and(|line| line.contains(','));
+        (json || csv) && head.contains("s_pos")
+    }
+
+    fn read(&self, path: &Path) -> anyhow::Result<TfsDataFrame<T>> {
+        crate::pyat::read(path)
+    }
+}
+
+impl<T: FromStr + NumericNative> TableFormat<T> for TaoFormat {
+    fn name(&self) -> &str {
+        "tao"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[]
+    }
+
+    fn detect(&self, head: &str) -> bool {
+        head.lines()
+            .take_while(|line| line.trim().is_empty() || line.trim_start().starts_with('#'))
+            .any(|line| {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                words.contains(&"Index") && words.contains(&"name")
+            })
+    }
+
+    fn read(&self, path: &Path) -> anyhow::Result<TfsDataFrame<T>> {
+        crate::tao::read_lattice(path)
+    }
+}
+
+fn first_line(head: &str) -> Option<&str> {
+    head.lines()
+        .map(str::trim_start)
+        .find(|line| !line.is_empty())
+}
+
+/// The known [`TableFormat`]s.
+pub struct FormatRegistry<T: FromStr + NumericNative> {
+    formats: Vec<Box<dyn TableFormat<T>>>,
+}
+
+impl<T> Default for FormatRegistry<T>
+where
+    T: FromStr + NumericNative + fmt::Display,
+    <T as FromStr>::Err: fmt::Debug,
+{
+    /// The formats of this crate, tfs first.
+    fn default() -> Self {
+        FormatRegistry {
+            formats: vec![
+                Box::new(TfsFormat),
+                Box::new(ElegantFormat),
+                Box::new(SddsFormat),
+                Box::new(PyatFormat),
+                Box::new(TaoFormat),
+            ],
+        }
+    }
+}
+
+impl<T: FromStr + NumericNative> FormatRegistry<T> {
+    /// A registry without any format.
+    pub fn empty() -> Self {
+        FormatRegistry {
+            formats: Vec::new(),
+        }
+    }
+
+    /// Adds `format`, which is tried before the formats registered so far.
+    pub fn register(&mut self, format: Box<dyn TableFormat<T>>) {
+        self.formats.insert(0, format);
+    }
+
+    /// Names of the formats, in the order they are tried.
+    pub fn names(&self) -> Vec<&str> {
+        self.formats.iter().map(|format| format.name()).collect()
+    }
+
+    /// The format called `name`.
+    pub fn get(&self, name: &str) -> anyhow::Result<&dyn TableFormat<T>> {
+        self.formats
+            .iter()
+            .find(|format| format.name() == name)
+            .map(|format| format.as_ref())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "unknown format '{}', expected one of {}",
+                    name,
+                    self.names().join(", ")
+                )
+            })
+    }
+
+    /// The format of the file at `path`: the first format with its extension that detects its
+    /// content, else the first that detects its content, else the first with its extension.
+    pub fn detect(&self, path: &Path) -> anyhow::Result<&dyn TableFormat<T>> {
+        let mut bytes = Vec::with_capacity(HEAD_SIZE);
+        std::fs::File::open(path)?
+            .take(HEAD_SIZE as u64)
+            .read_to_end(&mut bytes)?;
+        let mut head = String::from_utf8_lossy(&bytes).into_owned();
+        if bytes.len() == HEAD_SIZE {
+            head.truncate(head.rfind('\n').unwrap_or(head.len()));
+        }
+
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        let has_extension = |format: &dyn TableFormat<T>| {
+            format
+                .extensions()
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(extension))
+        };
+        self.formats
+            .iter()
+            .find(|format| has_extension(format.as_ref()) && format.detect(&head))
+            .or_else(|| self.formats.iter().find(|format| format.detect(&head)))
+            .or_else(|| {
+                self.formats
+                    .iter()
+                    .find(|format| has_extension(format.as_ref()))
+            })
+            .map(|format| format.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("the format of {} is unknown", path.display()))
+    }
+
+    /// Reads the file at `path` in its format, see [`FormatRegistry::detect`].
+    pub fn open(&self, path: &Path) -> anyhow::Result<TfsDataFrame<T>> {
+        self.detect(path)?.read(path)
+    }
+}
+
+impl<T> TfsDataFrame<T>
+where
+    T: FromStr + NumericNative + fmt::Display,
+    <T as FromStr>::Err: fmt::Debug,
+{
+    /// Opens a file in any format of the default [`FormatRegistry`].
+    pub fn open_auto<P: AsRef<Path>>(path: P) -> anyhow::Result<TfsDataFrame<T>> {
+        FormatRegistry::default().open(path.as_ref())
+    }
+}
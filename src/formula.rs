@@ -0,0 +1,248 @@
+//! Arithmetic on columns and header entries.
+//!
+//! A [`Formula`] computes a real value per row from columns, numbers and header entries, which
+//! are written with an `@` like `@Q1`. It is the arithmetic of the [expressions](crate::expr) of
+//! row filters: `+`, `-`, `*`, `/`, negation and parentheses; missing values stay missing.
+//! [`TfsDataFrame::eval`] evaluates a formula, [`TfsDataFrame::eval_column`] stores the result
+//! as a column with its lineage:
+//!
+//! ```
+//! # use tfs::{DataValue, TfsDataFrame};
+//! # use tfs::formula::prop;
+//! let mut df = TfsDataFrame::<f64>::new(
+//!     vec![
+//!         ("Q1".to_owned(), DataValue::Real(62.31)),
+//!         ("ENERGY".to_owned(), DataValue::Real(6800.0)),
+//!     ],
+//!     tfs::polars::df!("MUX" => [0.0, 0.5], "X" => [1e-3, 2e-3]).unwrap(),
+//! );
+//! let phase = df.eval("MUX * @Q1").unwrap();
+//! assert_eq!(phase.f64().unwrap().get(1), Some(31.155));
+//!
+//! df.eval_column("X_NORM", "X / (2 * @Q1)").unwrap();
+//! df.scale_column("X", prop("ENERGY")).unwrap();
+//! assert_eq!(df.lineage("X_NORM").unwrap().inputs, ["X"]);
+//! ```
+//!
+//! [`TfsDataFrame::set_where`] replaces the values of selected rows only, by a number or by a
+//! formula.
+use polars::prelude::NumericNative;
+use polars::series::Series;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dataframe::DataValue;
+use crate::expr::Expr;
+use crate::lineage::Lineage;
+use crate::record::ColumnValue;
+use crate::selection::RowSelection;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// An arithmetic expression on the rows of a frame, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Formula {
+    expr: Expr,
+}
+
+impl FromStr for Formula {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> anyhow::Result<Formula> {
+        let expr = Expr::parse(source)?;
+        anyhow::ensure!(
+            !expr.is_condition(),
+            "'{}' is a condition, not a formula",
+            source
+        );
+        Ok(Formula { expr })
+    }
+}
+
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expr.source())
+    }
+}
+
+impl Formula {
+    /// The names of the columns the formula uses.
+    pub fn columns(&self) -> Vec<&str> {
+        self.expr.columns()
+    }
+
+    /// The keys of the header entries the formula uses.
+    pub fn properties(&self) -> Vec<&str> {
+        self.expr.properties()
+    }
+
+    /// Evaluates the formula for every row of `df`, `None` where a column value is missing.
+    pub fn evaluate<T: std::str::FromStr + NumericNative>(
+        &self,
+        df: &TfsDataFrame<T>,
+    ) -> anyhow::Result<Vec<Option<f64>>> {
+        self.expr.numbers(df)
+    }
+}
+
+/// The header entry `key` as a number.
+pub(crate) fn property<T>(df: &TfsDataFrame<T>, key: &str) -> anyhow::Result<f64>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    match df.properties.get(key) {
+        Some(DataValue::Real(r)) => Ok(r.to_f64().unwrap_or(f64::NAN)),
+        Some(DataValue::Integer(i)) => Ok(*i as f64),
+        Some(value) => anyhow::bail!("the header entry '{}' is {}, not a number", key, value),
+        None => anyhow::bail!("the header has no entry '{}'", key),
+    }
+}
+
+/// A factor given as a number or as a header entry, see [`TfsDataFrame::scale_column`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scalar {
+    Value(f64),
+    Property(String),
+}
+
+impl From<f64> for Scalar {
+    fn from(value: f64) -> Self {
+        Scalar::Value(value)
+    }
+}
+
+/// The header entry `key` as a [`Scalar`].
+pub fn prop(key: &str) -> Scalar {
+    Scalar::Property(key.to_owned())
+}
+
+/// The values written by [`TfsDataFrame::set_where`]: the same number in every row, or a
+/// formula evaluated on the row.
+#[derive(Debug, Clone)]
+pub enum Replacement {
+    Value(f64),
+    Formula(Formula),
+}
+
+impl From<f64> for Replacement {
+    fn from(value: f64) -> Self {
+        Replacement::Value(value)
+    }
+}
+
+impl From<Formula> for Replacement {
+    fn from(formula: Formula) -> Self {
+        Replacement::Formula(formula)
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Evaluates the formula `expression` for every row, see the
+    /// [module documentation](crate::formula). The result is named after the expression.
+    pub fn eval(&self, expression: &str) -> anyhow::Result<Series> {
+        let values = expression.parse::<Formula>()?.evaluate(self)?;
+        Ok(Option::<f64>::to_column(expression, values))
+    }
+
+    /// Stores the result of the formula `expression` as the column `name` and records its
+    /// lineage, like [`TfsDataFrame::derive_column`].
+    pub fn eval_column(&mut self, name: &str, expression: &str) -> anyhow::Result<()> {
+        let formula = expression.parse::<Formula>()?;
+        let values = formula.evaluate(self)?;
+        self.set_column(Option::<f64>::to_column(name, values))?;
+        self.lineage.insert(
+            name.to_owned(),
+            Lineage {
+                expression: expression.to_owned(),
+                inputs: formula.columns().into_iter().map(String::from).collect(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Multiplies the real column `name` by `factor`, a number or a header entry given with
+    /// [`prop`].
+    pub fn scale_column<S: Into<Scalar>>(&mut self, name: &str, factor: S) -> anyhow::Result<()> {
+        let factor = match factor.into() {
+            Scalar::Value(value) => value,
+            Scalar::Property(key) => property(self, &key)?,
+        };
+        let values: Vec<Option<f64>> = Option::<f64>::from_column(self.column(name)?)?
+            .into_iter()
+            .map(|v| v.map(|v| v * factor))
+            .collect();
+        self.set_column(Option::<f64>::to_column(name, values))
+    }
+
+    /// Sets the real column `column` to `value` in the rows `rows`, a mask, a range or a list of
+    /// rows like for [`TfsDataFrame::get`]. The other rows keep their values:
+    ///
+    /// ```
+    /// # use tfs::TfsDataFrame;
+    /// # use tfs::formula::Formula;
+    /// let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+    /// let outside: Vec<bool> = df.column("S").unwrap().f64().unwrap()
+    ///     .into_iter()
+    ///     .map(|s| s.unwrap() > 25.0)
+    ///     .collect();
+    /// df.set_where(&outside, "K1L", 0.0).unwrap();
+    /// df.set_where(vec![0], "X", "-X".parse::<Formula>().unwrap()).unwrap();
+    /// assert_eq!(df.column("K1L").unwrap().f64().unwrap().get(2), Some(0.0));
+    /// ```
+    pub fn set_where<R, V>(&mut self, rows: R, column: &str, value: V) -> anyhow::Result<()>
+    where
+        R: Into<RowSelection>,
+        V: Into<Replacement>,
+    {
+        let mask = rows.into().to_mask(self.len())?;
+        let current = self.column(column)?;
+        anyhow::ensure!(
+            current.dtype().is_float(),
+            "column '{}' is {} instead of real",
+            column,
+            current.dtype()
+        );
+        let current = Option::<f64>::from_column(current)?;
+        let replacements = match value.into() {
+            Replacement::Value(value) => vec![Some(value); self.len()],
+            Replacement::Formula(formula) => formula.evaluate(self)?,
+        };
+        let values: Vec<Option<f64>> = current
+            .into_iter()
+            .zip(replacements)
+            .zip(mask)
+            .map(|((current, replacement), set)| if set { replacement } else { current })
+            .collect();
+        self.set_column(Option::<f64>::to_column(column, values))
+    }
+
+    /// Replaces every value of every real column by `f(column, value)`, e.g. to scale all
+    /// lengths. Text, integer and boolean columns are left as they are, missing values stay
+    /// missing.
+    ///
+    /// ```
+    /// # use tfs::TfsDataFrame;
+    /// let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+    /// let betx = df.column("BETX").unwrap().f64().unwrap().get(0).unwrap();
+    /// df.map_numeric(|name, v| if name.starts_with("BET") { v * 1e3 } else { v })
+    ///     .unwrap();
+    /// assert_eq!(df.column("BETX").unwrap().f64().unwrap().get(0), Some(betx * 1e3));
+    /// ```
+    pub fn map_numeric<F>(&mut self, mut f: F) -> anyhow::Result<()>
+    where
+        F: FnMut(&str, f64) -> f64,
+    {
+        let names: Vec<String> = self.column_names().into_iter().map(String::from).collect();
+        for name in names {
+            let column = self.column(&name)?;
+            if !column.dtype().is_float() {
+                continue;
+            }
+            let values: Vec<Option<f64>> = Option::<f64>::from_column(column)?
+                .into_iter()
+                .map(|v| v.map(|v| f(&name, v)))
+                .collect();
+            self.set_column(Option::<f64>::to_column(&name, values))?;
+        }
+        Ok(())
+    }
+}
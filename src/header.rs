@@ -0,0 +1,365 @@
+//! Typed access to the header (properties) of a tfs file.
+//!
+//! Structs implementing [`FromHeader`] can be extracted from the properties of a
+//! [`TfsDataFrame`]. With the `derive` feature, `#[derive(TfsHeader)]` implements it for structs
+//! with named fields, every field is looked up under its uppercase name or under the name given
+//! by `#[tfs(rename = "...")]`:
+//!
+//! ```ignore
+//! use tfs::{FromHeader, TfsHeader};
+//!
+//! #[derive(TfsHeader)]
+//! struct TwissHeader {
+//!     q1: f64,
+//!     q2: f64,
+//!     sequence: String,
+//!     #[tfs(rename = "DQ1")]
+//!     chroma_x: Option<f64>,
+//! }
+//!
+//! let df = tfs::TfsDataFrame::<f64>::open("twiss.tfs")?;
+//! let header = TwissHeader::from_frame(&df)?;
+//! ```
+//!
+//! [`TfsHeader::read`] reads only the header of a file, without the data.
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use polars::prelude::{NumericNative, PolarsError};
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::dataframe::DataValue;
+use crate::options::TfsReadOptions;
+use crate::paths::resolve_path;
+use crate::reader::read_header;
+use crate::tfsdataframe::{Properties, TfsDataFrame};
+
+/// The header of a tfs file: its properties and the names and types of the columns.
+///
+/// Reading it stops after the `*` and `$` lines, which makes it cheap to index large numbers of
+/// files by their metadata:
+///
+/// ```
+/// # use tfs::TfsHeader;
+/// let header = TfsHeader::<f64>::read("test/test.tfs").unwrap();
+/// assert_eq!(header.colnames[1], "S");
+/// assert_eq!(header.coltypes[1], "%le");
+/// ```
+#[derive(Debug, Clone)]
+pub struct TfsHeader<T> {
+    pub properties: Properties<T>,
+    pub colnames: Vec<String>,
+    pub coltypes: Vec<String>,
+}
+
+impl<T: std::str::FromStr> TfsHeader<T> {
+    /// Reads the header of a tfs file.
+    pub fn read<P>(path: P) -> Result<TfsHeader<T>, PolarsError>
+    where
+        P: AsRef<Path>,
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        TfsHeader::read_with(path, &TfsReadOptions::default())
+    }
+
+    /// Reads the header of a tfs file, parsing it according to `options`.
+    pub fn read_with<P>(path: P, options: &TfsReadOptions) -> Result<TfsHeader<T>, PolarsError>
+    where
+        P: AsRef<Path>,
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        let path = resolve_path(path.as_ref(), &options.search_path)?;
+        let mut reader = BufReader::new(File::open(path)?);
+        let (header, _) = read_header(&mut reader, options)?;
+        Ok(TfsHeader {
+            properties: header.properties,
+            colnames: header.colnames,
+            coltypes: header.coltypes,
+        })
+    }
+
+    /// Returns the creation time from the `DATE` and `TIME` headers, see
+    /// [`TfsDataFrame::timestamp`].
+    pub fn timestamp(&self) -> Option<NaiveDateTime> {
+        header_timestamp(&self.properties)
+    }
+
+    /// Returns the type code (e.g. `%le`) of the column `name`.
+    pub fn coltype(&self, name: &str) -> Option<&str> {
+        self.colnames
+            .iter()
+            .position(|c| c == name)
+            .and_then(|i| self.coltypes.get(i))
+            .map(String::as_str)
+    }
+}
+
+/// Formats of the `DATE` header, MAD-X writes `dd/mm/yy`.
+const DATE_FORMATS: [&str; 4] = ["%d/%m/%y", "%d/%m/%Y", "%Y-%m-%d", "%d.%m.%Y"];
+
+/// Formats of the `TIME` header, MAD-X writes `hh.mm.ss`.
+const TIME_FORMATS: [&str; 3] = ["%H.%M.%S", "%H:%M:%S", "%H:%M:%S%.f"];
+
+fn text_property<'a, T>(properties: &'a Properties<T>, key: &str) -> Option<&'a str> {
+    match properties.get(key) {
+        Some(DataValue::Text(t)) => Some(t.trim()),
+        _ => None,
+    }
+}
+
+/// Parses the `DATE` and `TIME` headers. A missing `TIME` is taken as midnight.
+pub(crate) fn header_timestamp<T>(properties: &Properties<T>) -> Option<NaiveDateTime> {
+    let date = text_property(properties, "DATE")?;
+    let date = DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(date, format).ok())?;
+
+    let time = match text_property(properties, "TIME") {
+        Some(time) => TIME_FORMATS
+            .iter()
+            .find_map(|format| NaiveTime::parse_from_str(time, format).ok())?,
+        None => NaiveTime::MIN,
+    };
+    Some(date.and_time(time))
+}
+
+/// Error returned if a header can't be converted into a typed struct.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderError {
+    /// The key is not present in the header.
+    Missing(String),
+    /// The key is present but its value has another type.
+    WrongType {
+        key: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderError::Missing(key) => write!(f, "the key '{}' is missing in the header", key),
+            HeaderError::WrongType {
+                key,
+                expected,
+                found,
+            } => write!(
+                f,
+                "the key '{}' should be {} but it is {}",
+                key, expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HeaderError {}
+
+fn type_name<T>(value: &DataValue<T>) -> &'static str {
+    match value {
+        DataValue::Text(_) => "a string",
+        DataValue::Real(_) => "a real value",
+        DataValue::Integer(_) => "an integer",
+        DataValue::Boolean(_) => "a boolean",
+        DataValue::List(_) => "a table",
+    }
+}
+
+/// Conversion of a single header value into a field of a [`FromHeader`] struct.
+pub trait FromProperty: Sized {
+    /// Converts `value`, the entry of `key` in the header or `None` if the key is missing.
+    fn from_property<T: Copy + Into<f64>>(
+        key: &str,
+        value: Option<&DataValue<T>>,
+    ) -> Result<Self, HeaderError>;
+}
+
+impl FromProperty for f64 {
+    fn from_property<T: Copy + Into<f64>>(
+        key: &str,
+        value: Option<&DataValue<T>>,
+    ) -> Result<Self, HeaderError> {
+        match value {
+            Some(DataValue::Real(r)) => Ok((*r).into()),
+            Some(DataValue::Integer(i)) => Ok(*i as f64),
+            Some(v) => Err(HeaderError::WrongType {
+                key: key.to_owned(),
+                expected: "a real value",
+                found: type_name(v),
+            }),
+            None => Err(HeaderError::Missing(key.to_owned())),
+        }
+    }
+}
+
+impl FromProperty for i64 {
+    fn from_property<T: Copy + Into<f64>>(
+        key: &str,
+        value: Option<&DataValue<T>>,
+    ) -> Result<Self, HeaderError> {
+        match value {
+            Some(DataValue::Integer(i)) => Ok(*i),
+            Some(v) => Err(HeaderError::WrongType {
+                key: key.to_owned(),
+                expected: "an integer",
+                found: type_name(v),
+            }),
+            None => Err(HeaderError::Missing(key.to_owned())),
+        }
+    }
+}
+
+impl FromProperty for String {
+    fn from_property<T: Copy + Into<f64>>(
+        key: &str,
+        value: Option<&DataValue<T>>,
+    ) -> Result<Self, HeaderError> {
+        match value {
+            Some(DataValue::Text(t)) => Ok(t.clone()),
+            Some(v) => Err(HeaderError::WrongType {
+                key: key.to_owned(),
+                expected: "a string",
+                found: type_name(v),
+            }),
+            None => Err(HeaderError::Missing(key.to_owned())),
+        }
+    }
+}
+
+/// Optional fields are `None` if the key is missing, a value of the wrong type is still an error.
+impl<P: FromProperty> FromProperty for Option<P> {
+    fn from_property<T: Copy + Into<f64>>(
+        key: &str,
+        value: Option<&DataValue<T>>,
+    ) -> Result<Self, HeaderError> {
+        match value {
+            Some(_) => P::from_property(key, value).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Structs that can be built from the header of a tfs file. Usually implemented with
+/// `#[derive(TfsHeader)]` (feature `derive`).
+pub trait FromHeader: Sized {
+    fn from_properties<T: Copy + Into<f64>>(
+        properties: &Properties<T>,
+    ) -> Result<Self, HeaderError>;
+
+    fn from_frame<T>(df: &TfsDataFrame<T>) -> Result<Self, HeaderError>
+    where
+        T: std::str::FromStr + NumericNative + Into<f64>,
+    {
+        Self::from_properties(&df.properties)
+    }
+}
+
+/// Items of list-valued header entries, see [`TfsDataFrame::prop_list`].
+pub trait ListItem: Sized {
+    /// Parses an item of a separated list, already trimmed.
+    fn parse_item(text: &str) -> Option<Self>;
+
+    /// Converts an item of a table (`%tbl` in MAD-NG).
+    fn from_item<T: NumericNative>(value: &DataValue<T>) -> Option<Self>;
+
+    /// Writes the item into a separated list.
+    fn format_item(&self) -> String;
+}
+
+impl ListItem for String {
+    fn parse_item(text: &str) -> Option<Self> {
+        Some(text.to_owned())
+    }
+
+    fn from_item<T: NumericNative>(value: &DataValue<T>) -> Option<Self> {
+        match value {
+            DataValue::Text(t) => Some(t.clone()),
+            _ => None,
+        }
+    }
+
+    fn format_item(&self) -> String {
+        self.clone()
+    }
+}
+
+impl ListItem for f64 {
+    fn parse_item(text: &str) -> Option<Self> {
+        text.parse().ok()
+    }
+
+    fn from_item<T: NumericNative>(value: &DataValue<T>) -> Option<Self> {
+        match value {
+            DataValue::Real(r) => r.to_f64(),
+            DataValue::Integer(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    fn format_item(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Returns the header entry `key` as a list, e.g. the knobs of `"KQ1.L1, KQ2.L1"` with the
+    /// separator `","`. Items are trimmed, an empty string is an empty list. Tables like
+    /// `{1, 2}` (MAD-NG) are read item by item, regardless of `sep`.
+    ///
+    /// ```
+    /// # use tfs::{DataValue, TfsDataFrame};
+    /// # use tfs::polars::prelude::DataFrame;
+    /// let mut df = TfsDataFrame::<f64>::new(Vec::new(), DataFrame::empty());
+    /// df.properties.insert("KNOBS".to_owned(), DataValue::Text("KQ1.L1, KQ2.L1".to_owned()));
+    /// let knobs: Vec<String> = df.prop_list("KNOBS", ",").unwrap();
+    /// assert_eq!(knobs, ["KQ1.L1", "KQ2.L1"]);
+    ///
+    /// df.set_prop_list("STRENGTHS", &[1.5, -0.25], ";").unwrap();
+    /// assert_eq!(df.prop_list::<f64>("STRENGTHS", ";").unwrap(), [1.5, -0.25]);
+    /// ```
+    pub fn prop_list<V: ListItem>(&self, key: &str, sep: &str) -> anyhow::Result<Vec<V>> {
+        match self.properties.get(key) {
+            Some(DataValue::Text(t)) if t.trim().is_empty() => Ok(Vec::new()),
+            Some(DataValue::Text(t)) => t
+                .split(sep)
+                .map(|item| {
+                    V::parse_item(item.trim()).ok_or_else(|| {
+                        anyhow::anyhow!("invalid item '{}' in the header entry '{}'", item, key)
+                    })
+                })
+                .collect(),
+            Some(DataValue::List(items)) => items
+                .iter()
+                .map(|item| {
+                    V::from_item(item).ok_or_else(|| {
+                        anyhow::anyhow!("invalid item {} in the header entry '{}'", item, key)
+                    })
+                })
+                .collect(),
+            Some(value) => anyhow::bail!("the header entry '{}' is {}, not a list", key, value),
+            None => anyhow::bail!("the header has no entry '{}'", key),
+        }
+    }
+
+    /// Sets the header entry `key` to the string of `values` separated by `sep`, which
+    /// [`TfsDataFrame::prop_list`] reads back. Fails for items containing `sep` or quotes.
+    pub fn set_prop_list<V: ListItem>(
+        &mut self,
+        key: &str,
+        values: &[V],
+        sep: &str,
+    ) -> anyhow::Result<()> {
+        let items: Vec<String> = values.iter().map(ListItem::format_item).collect();
+        if let Some(item) = items.iter().find(|i| i.contains(sep) || i.contains('"')) {
+            anyhow::bail!(
+                "the item '{}' of the header entry '{}' contains the separator or a quote",
+                item,
+                key
+            );
+        }
+        self.properties
+            .insert(key.to_owned(), DataValue::Text(items.join(sep)));
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+//! Standalone HTML reports.
+//!
+//! [`TfsDataFrame::to_html_report`] writes a single HTML page without external resources: the
+//! header, a table that is sorted by clicking on the column names and a line plot of every real
+//! column, over `S` if the frame has such a column and over the row number otherwise.
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//!
+//! let dir = tempfile::tempdir().unwrap();
+//! df.to_html_report(dir.path().join("twiss.html")).unwrap();
+//! ```
+use polars::prelude::{AnyValue, NumericNative};
+use std::fmt::{self, Write as _};
+use std::path::Path;
+
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Column used as horizontal axis of the plots, if present.
+const S_COLUMN: &str = "S";
+
+const PLOT_WIDTH: f64 = 480.0;
+const PLOT_HEIGHT: f64 = 160.0;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; font-size: 0.9em; }
+th, td { border: 1px solid #ccc; padding: 2px 8px; }
+th { background: #eee; cursor: pointer; }
+td.num { text-align: right; font-family: monospace; }
+.plots { display: flex; flex-wrap: wrap; gap: 1em; }
+figure { margin: 0; }
+polyline { fill: none; stroke: #1f77b4; stroke-width: 1.5; }";
+
+/// Sorts the rows of the table by the clicked column, numerically if possible, and toggles the
+/// direction on repeated clicks.
+const SCRIPT: &str = "\
+document.querySelectorAll('#data th').forEach((th, col) => th.addEventListener('click', () => {
+  const body = th.closest('table').tBodies[0];
+  const asc = th.dataset.order !== 'asc';
+  th.dataset.order = asc ? 'asc' : 'desc';
+  const key = row => row.cells[col].textContent;
+  const rows = Array.from(body.rows).sort((a, b) => {
+    const [x, y] = [key(a), key(b)];
+    const d = (isNaN(x) || isNaN(y)) ? x.localeCompare(y) : x - y;
+    return asc ? d : -d;
+  });
+  rows.forEach(row => body.appendChild(row));
+}));";
+
+impl<T: std::str::FromStr + NumericNative + fmt::Display> TfsDataFrame<T> {
+    /// Writes the frame as a standalone HTML page to `path`.
+    pub fn to_html_report<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let title = path
+            .as_ref()
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        std::fs::write(path, self.html_report(&title)?)?;
+        Ok(())
+    }
+
+    fn html_report(&self, title: &str) -> anyhow::Result<String> {
+        let mut html = String::new();
+        writeln!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             <style>\n{}\n</style>\n</head>\n<body>\n<h1>{}</h1>",
+            escape(title),
+            STYLE,
+            escape(title)
+        )?;
+
+        writeln!(html, "<h2>Header</h2>\n<table>")?;
+        for (key, value) in &self.properties {
+            writeln!(
+                html,
+                "<tr><th>{}</th><td>{}</td></tr>",
+                escape(key),
+                escape(&value.to_string())
+            )?;
+        }
+        writeln!(html, "</table>")?;
+
+        let names = self.column_names();
+        writeln!(html, "<h2>Plots</h2>\n<div class=\"plots\">")?;
+        let (x_label, x) = match self.column(S_COLUMN) {
+            Ok(s) if s.dtype().is_float() => (S_COLUMN, f64::from_column(s)?),
+            _ => ("row", (0..self.len()).map(|row| row as f64).collect()),
+        };
+        for name in &names {
+            let column = self.column(name)?;
+            if *name == S_COLUMN || !column.dtype().is_float() {
+                continue;
+            }
+            let y = f64::from_column(column)?;
+            writeln!(html, "{}", plot(name, x_label, &x, &y))?;
+        }
+        writeln!(html, "</div>")?;
+
+        writeln!(
+            html,
+            "<h2>Data [{} rows]</h2>\n<table id=\"data\">\n<thead><tr>",
+            self.len()
+        )?;
+        for name in &names {
+            write!(html, "<th>{}</th>", escape(name))?;
+        }
+        writeln!(html, "</tr></thead>\n<tbody>")?;
+        let columns = names
+            .iter()
+            .map(|name| self.column(name))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for row in 0..self.len() {
+            write!(html, "<tr>")?;
+            for column in &columns {
+                match column.get(row)? {
+                    AnyValue::Null => write!(html, "<td></td>")?,
+                    AnyValue::String(s) => write!(html, "<td>{}</td>", escape(s))?,
+                    v if column.dtype().is_primitive_numeric() => {
+                        write!(html, "<td class=\"num\">{}</td>", v)?
+                    }
+                    v => write!(html, "<td>{}</td>", escape(&v.to_string()))?,
+                }
+            }
+            writeln!(html, "</tr>")?;
+        }
+        writeln!(
+            html,
+            "</tbody>\n</table>\n<script>\n{}\n</script>\n</body>\n</html>",
+            SCRIPT
+        )?;
+        Ok(html)
+    }
+}
+
+/// An SVG line plot of `y` over `x`, leaving out points where either is `NaN`.
+fn plot(name: &str, x_label: &str, x: &[f64], y: &[f64]) -> String {
+    let points: Vec<(f64, f64)> = x
+        .iter()
+        .zip(y)
+        .filter(|(x, y)| x.is_finite() && y.is_finite())
+        .map(|(x, y)| (*x, *y))
+        .collect();
+    let range = |values: &mut dyn Iterator<Item = f64>| {
+        values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+            (min.min(v), max.max(v))
+        })
+    };
+    let (x_min, x_max) = range(&mut points.iter().map(|p| p.0));
+    let (y_min, y_max) = range(&mut points.iter().map(|p| p.1));
+    // constant values are drawn in the middle
+    let scale = |v: f64, min: f64, max: f64| {
+        if max > min {
+            (v - min) / (max - min)
+        } else {
+            0.5
+        }
+    };
+
+    let polyline: Vec<String> = points
+        .iter()
+        .map(|(x, y)| {
+            format!(
+                "{:.1},{:.1}",
+                scale(*x, x_min, x_max) * PLOT_WIDTH,
+                (1.0 - scale(*y, y_min, y_max)) * PLOT_HEIGHT
+            )
+        })
+        .collect();
+
+    format!(
+        "<figure><svg width=\"{w}\" height=\"{h}\" viewBox=\"-4 -4 {w} {h}\">\
+         <polyline points=\"{points}\"/></svg>\
+         <figcaption>{name} over {x_label} [{y_min:.4e}, {y_max:.4e}]</figcaption></figure>",
+        w = PLOT_WIDTH + 8.0,
+        h = PLOT_HEIGHT + 8.0,
+        points = polyline.join(" "),
+        name = escape(name),
+        x_label = x_label,
+        y_min = y_min,
+        y_max = y_max,
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,164 @@
+//! Random access to the rows of large files.
+//!
+//! A [`RowIndex`] holds the byte offsets of every [`ROW_INDEX_STRIDE`]-th data row of a file. It
+//! is written next to the file (`tracking.tfs.idx` for `tracking.tfs`) and lets
+//! [`TfsDataFrame::read_row_range`] start reading close to the requested rows instead of at the
+//! start of the body. The index is built on the first call and rebuilt when the file changed
+//! size:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # let dir = tempfile::tempdir().unwrap();
+//! # let path = dir.path().join("test.tfs");
+//! # std::fs::copy("test/test.tfs", &path).unwrap();
+//! let rows = TfsDataFrame::<f64>::read_row_range(&path, 1, 3).unwrap();
+//!
+//! assert_eq!(rows.len(), 2);
+//! assert_eq!(rows.column("NAME").unwrap().str().unwrap().get(0), Some("DRIFT_5"));
+//! ```
+use polars::prelude::NumericNative;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::stages::TfsHeaderParser;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Number of rows between two offsets of a [`RowIndex`].
+pub const ROW_INDEX_STRIDE: usize = 1024;
+
+const MAGIC: &[u8; 8] = b"TFSIDX01";
+
+/// Byte offsets of the data rows of a tfs file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowIndex {
+    /// Size of the indexed file, to tell if the index is stale.
+    pub file_len: u64,
+    /// Number of data rows of the file.
+    pub rows: usize,
+    /// Offset of the rows `0`, `ROW_INDEX_STRIDE`, `2 * ROW_INDEX_STRIDE`, ...
+    pub offsets: Vec<u64>,
+}
+
+impl RowIndex {
+    /// Reads the whole file and records the row offsets.
+    pub fn scan<P: AsRef<Path>>(path: P) -> anyhow::Result<RowIndex> {
+        let file_len = std::fs::metadata(path.as_ref())?.len();
+        let mut body = TfsHeaderParser::open(path.as_ref())?.parse::<f64>()?;
+
+        let mut rows = 0;
+        let mut offsets = Vec::new();
+        loop {
+            let position = body.position();
+            let skipped = body.skip_rows(ROW_INDEX_STRIDE)?;
+            if skipped == 0 {
+                break;
+            }
+            // the offset of the line after the previous row, possibly an empty or comment line
+            offsets.push(position);
+            rows += skipped;
+        }
+
+        Ok(RowIndex {
+            file_len,
+            rows,
+            offsets,
+        })
+    }
+
+    /// The path the index of `path` is stored at.
+    pub fn path_of<P: AsRef<Path>>(path: P) -> PathBuf {
+        let mut index = path.as_ref().as_os_str().to_owned();
+        index.push(".idx");
+        PathBuf::from(index)
+    }
+
+    /// Reads the index stored next to `path`. Returns `None` if there is none or if it doesn't
+    /// match the file anymore.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Option<RowIndex>> {
+        let index_path = RowIndex::path_of(path.as_ref());
+        if !index_path.exists() {
+            return Ok(None);
+        }
+
+        let mut bytes = Vec::new();
+        BufReader::new(File::open(&index_path)?).read_to_end(&mut bytes)?;
+        let Some(words) = bytes.strip_prefix(MAGIC) else {
+            anyhow::bail!("{} is not a row index", index_path.display());
+        };
+        let mut words = words
+            .chunks_exact(8)
+            .map(|word| u64::from_le_bytes(word.try_into().unwrap()));
+
+        let (Some(file_len), Some(rows)) = (words.next(), words.next()) else {
+            anyhow::bail!("the row index {} is truncated", index_path.display());
+        };
+        if file_len != std::fs::metadata(path.as_ref())?.len() {
+            return Ok(None);
+        }
+
+        Ok(Some(RowIndex {
+            file_len,
+            rows: rows as usize,
+            offsets: words.collect(),
+        }))
+    }
+
+    /// Writes the index next to `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(File::create(RowIndex::path_of(path))?);
+        writer.write_all(MAGIC)?;
+        for word in [self.file_len, self.rows as u64]
+            .into_iter()
+            .chain(self.offsets.iter().copied())
+        {
+            writer.write_all(&word.to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Scans the file at `path` and writes its [`RowIndex`] next to it.
+    pub fn build_row_index<P: AsRef<Path>>(path: P) -> anyhow::Result<RowIndex> {
+        let index = RowIndex::scan(path.as_ref())?;
+        index.save(path)?;
+        Ok(index)
+    }
+
+    /// Reads the rows `start..end` of the file at `path`, using its [`RowIndex`]. The index is
+    /// built if there is none yet or if it is stale; if it can't be written next to the file it
+    /// is only used for this call.
+    ///
+    /// `end` is clamped to the number of rows of the file.
+    pub fn read_row_range<P: AsRef<Path>>(
+        path: P,
+        start: usize,
+        end: usize,
+    ) -> anyhow::Result<TfsDataFrame<T>>
+    where
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        anyhow::ensure!(start <= end, "invalid row range {}..{}", start, end);
+        let path = path.as_ref();
+        let index = match RowIndex::load(path) {
+            Ok(Some(index)) => index,
+            _ => {
+                let index = RowIndex::scan(path)?;
+                let _ = index.save(path);
+                index
+            }
+        };
+
+        let mut body = TfsHeaderParser::open(path)?.parse::<T>()?;
+        let end = end.min(index.rows);
+        let start = start.min(end);
+        if let Some(offset) = index.offsets.get(start / ROW_INDEX_STRIDE) {
+            body.seek(*offset)?;
+            body.skip_rows(start % ROW_INDEX_STRIDE)?;
+        }
+        body.read_rows(end - start)?;
+        Ok(body.into_frame()?)
+    }
+}
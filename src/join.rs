@@ -0,0 +1,857 @@
+//! Joining and stacking frames.
+//!
+//! [`TfsDataFrame::join`] merges the columns of two frames on a key column, e.g. a measurement
+//! and the model it is compared to, both keyed by `NAME`. [`TfsDataFrame::concat`] stacks the
+//! rows of several frames:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::join::JoinType;
+//! let model = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let measurement = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//!
+//! let joined = measurement
+//!     .join(&model, "NAME", JoinType::Inner, ("", "_MDL"))
+//!     .unwrap();
+//! assert!(joined.column("BETX_MDL").is_ok());
+//!
+//! let both = TfsDataFrame::concat(&[model, measurement]).unwrap();
+//! assert_eq!(both.len(), 10);
+//! ```
+//!
+//! A key that appears more than once in both frames joins every row with every match, which is
+//! rarely intended. [`TfsDataFrame::join_with`] can check the keys first, and decides what null
+//! keys do:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::join::{JoinOptions, JoinValidation, NullKeys};
+//! # let model = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! # let measurement = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let options = JoinOptions::new()
+//!     .suffixes("", "_MDL")
+//!     .validate(JoinValidation::ManyToOne)
+//!     .null_keys(NullKeys::Error);
+//! let joined = measurement.join_with(&model, "NAME", &options).unwrap();
+//! ```
+//!
+//! The result has the header of the first frame. Entries that other frames have with a
+//! different value, like the sequences of two beams, are [`HeaderConflict`]s. They are kept on the
+//! result, and a [`HeaderMerge`] decides which value wins:
+//!
+//! ```
+//! # use tfs::{DataValue, TfsDataFrame};
+//! # use tfs::join::HeaderMerge;
+//! let beam1 = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let mut beam2 = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! beam2.properties.insert("SEQUENCE".into(), DataValue::Text("LHCB2".into()));
+//!
+//! let beams = [beam1, beam2];
+//! let both = TfsDataFrame::concat(&beams).unwrap();
+//! assert_eq!(both.header_conflicts()[0].key, "SEQUENCE");
+//! assert!(TfsDataFrame::concat_with(&beams, HeaderMerge::Error).is_err());
+//! ```
+use polars::prelude::{
+    AnyValue, Column, DataFrame, DataType, IdxCa, IdxSize, NamedFrom, NumericNative,
+};
+use polars::series::Series;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dataframe::DataValue;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::{Properties, TfsDataFrame, NROWS_KEY};
+
+/// Which rows [`TfsDataFrame::join`] keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JoinType {
+    /// Rows with a key in both frames.
+    #[default]
+    Inner,
+    /// All rows of the left frame, with nulls where the right frame has no such key.
+    Left,
+    /// All rows of both frames, with nulls where a key is only in one of them.
+    Outer,
+}
+
+impl FromStr for JoinType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "inner" => Ok(JoinType::Inner),
+            "left" => Ok(JoinType::Left),
+            "outer" | "full" => Ok(JoinType::Outer),
+            _ => anyhow::bail!("unknown join type '{}', expected inner, left or outer", s),
+        }
+    }
+}
+
+/// How often keys may appear in the frames of [`TfsDataFrame::join_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JoinValidation {
+    /// Keys may appear any number of times in both frames.
+    #[default]
+    ManyToMany,
+    /// Keys appear at most once in each frame.
+    OneToOne,
+    /// Keys appear at most once in the left frame.
+    OneToMany,
+    /// Keys appear at most once in the right frame, e.g. in a model joined to measurements.
+    ManyToOne,
+}
+
+impl JoinValidation {
+    /// Whether keys have to be unique in the left and in the right frame.
+    fn unique(&self) -> (bool, bool) {
+        match self {
+            JoinValidation::ManyToMany => (false, false),
+            JoinValidation::OneToOne => (true, true),
+            JoinValidation::OneToMany => (true, false),
+            JoinValidation::ManyToOne => (false, true),
+        }
+    }
+}
+
+impl FromStr for JoinValidation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "m:m" | "many_to_many" => Ok(JoinValidation::ManyToMany),
+            "1:1" | "one_to_one" => Ok(JoinValidation::OneToOne),
+            "1:m" | "one_to_many" => Ok(JoinValidation::OneToMany),
+            "m:1" | "many_to_one" => Ok(JoinValidation::ManyToOne),
+            _ => anyhow::bail!(
+                "unknown join validation '{}', expected 1:1, 1:m, m:1 or m:m",
+                s
+            ),
+        }
+    }
+}
+
+/// What [`TfsDataFrame::join_with`] does with rows whose key is null.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullKeys {
+    /// Null keys match nothing, not even each other. Left and outer joins keep their rows.
+    #[default]
+    Unmatched,
+    /// Null keys match each other, like any other key.
+    Match,
+    /// Null keys are an error.
+    Error,
+}
+
+impl FromStr for NullKeys {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "unmatched" => Ok(NullKeys::Unmatched),
+            "match" => Ok(NullKeys::Match),
+            "error" => Ok(NullKeys::Error),
+            _ => anyhow::bail!(
+                "unknown null key handling '{}', expected unmatched, match or error",
+                s
+            ),
+        }
+    }
+}
+
+/// Which value of a [`HeaderConflict`] the result of a join or concatenation gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeaderMerge {
+    /// The value of the first frame, or the left frame of a join.
+    #[default]
+    KeepFirst,
+    /// The value of the last frame having the entry.
+    KeepLast,
+    /// None, the entry is removed.
+    Drop,
+    /// The join or concatenation fails, listing the conflicts.
+    Error,
+}
+
+impl FromStr for HeaderMerge {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "first" | "keep_first" => Ok(HeaderMerge::KeepFirst),
+            "last" | "keep_last" => Ok(HeaderMerge::KeepLast),
+            "drop" => Ok(HeaderMerge::Drop),
+            "error" => Ok(HeaderMerge::Error),
+            _ => anyhow::bail!(
+                "unknown header merge '{}', expected first, last, drop or error",
+                s
+            ),
+        }
+    }
+}
+
+/// A header entry with different values in the frames of a join or concatenation, see the
+/// [module documentation](self). Entries only some frames have are no conflict, neither is
+/// `NROWS`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderConflict<T> {
+    pub key: String,
+    /// The value in each frame, in the order of the frames, `None` where a frame doesn't have
+    /// the entry.
+    pub values: Vec<Option<DataValue<T>>>,
+}
+
+impl<T: fmt::Display> fmt::Display for HeaderConflict<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values: Vec<String> = self
+            .values
+            .iter()
+            .map(|v| v.as_ref().map_or("missing".to_owned(), |v| v.to_string()))
+            .collect();
+        write!(
+            f,
+            "header entry '{}' differs: {}",
+            self.key,
+            values.join(", ")
+        )
+    }
+}
+
+/// The header of the result of joining or stacking `frames`, which starts with the header of
+/// the first frame, and the conflicts resolved by `merge`.
+fn merge_headers<T>(
+    frames: &[&TfsDataFrame<T>],
+    merge: HeaderMerge,
+) -> anyhow::Result<(Properties<T>, Vec<HeaderConflict<T>>)>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    let Some(first) = frames.first() else {
+        return Ok((Properties::new(), Vec::new()));
+    };
+    let mut properties = first.properties.clone();
+    let mut conflicts = Vec::new();
+    for key in first.properties.keys() {
+        let values: Vec<Option<&DataValue<T>>> =
+            frames.iter().map(|f| f.properties.get(key)).collect();
+        let mut present = values.iter().flatten();
+        let value = present.next();
+        if key == NROWS_KEY || present.all(|v| Some(v) == value) {
+            continue;
+        }
+        match merge {
+            HeaderMerge::KeepFirst | HeaderMerge::Error => {}
+            HeaderMerge::KeepLast => {
+                let last = values.iter().rev().flatten().next();
+                properties.insert(key.clone(), (*last.unwrap()).clone());
+            }
+            HeaderMerge::Drop => {
+                properties.shift_remove(key);
+            }
+        }
+        conflicts.push(HeaderConflict {
+            key: key.clone(),
+            values: values.into_iter().map(|v| v.cloned()).collect(),
+        });
+    }
+    if merge == HeaderMerge::Error && !conflicts.is_empty() {
+        let conflicts: Vec<String> = conflicts.iter().map(|c| c.to_string()).collect();
+        anyhow::bail!("the headers conflict: {}", conflicts.join("; "));
+    }
+    Ok((properties, conflicts))
+}
+
+/// Configures [`TfsDataFrame::join_with`]. The default is an inner join without suffixes that
+/// doesn't check the keys. Header conflicts keep the value of the left frame.
+#[derive(Debug, Clone, Default)]
+pub struct JoinOptions {
+    how: JoinType,
+    suffixes: (String, String),
+    validate: JoinValidation,
+    null_keys: NullKeys,
+    headers: HeaderMerge,
+}
+
+impl JoinOptions {
+    pub fn new() -> JoinOptions {
+        JoinOptions::default()
+    }
+
+    pub fn how(mut self, how: JoinType) -> Self {
+        self.how = how;
+        self
+    }
+
+    /// The suffixes of the columns of the left and right frame that are in both frames.
+    pub fn suffixes(mut self, left: &str, right: &str) -> Self {
+        self.suffixes = (left.to_owned(), right.to_owned());
+        self
+    }
+
+    /// Fails the join if a key appears more often than `validate` allows.
+    pub fn validate(mut self, validate: JoinValidation) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    pub fn null_keys(mut self, null_keys: NullKeys) -> Self {
+        self.null_keys = null_keys;
+        self
+    }
+
+    /// How header entries with different values in both frames are resolved.
+    pub fn headers(mut self, headers: HeaderMerge) -> Self {
+        self.headers = headers;
+        self
+    }
+}
+
+/// The key columns of a join: a single name, or several like `&["NAME", "SLICE"]` for sliced
+/// lattices where names repeat.
+pub trait JoinKeys {
+    fn names(&self) -> Vec<&str>;
+}
+
+impl JoinKeys for str {
+    fn names(&self) -> Vec<&str> {
+        vec![self]
+    }
+}
+
+impl JoinKeys for String {
+    fn names(&self) -> Vec<&str> {
+        vec![self]
+    }
+}
+
+impl JoinKeys for [&str] {
+    fn names(&self) -> Vec<&str> {
+        self.to_vec()
+    }
+}
+
+impl<const N: usize> JoinKeys for [&str; N] {
+    fn names(&self) -> Vec<&str> {
+        self.to_vec()
+    }
+}
+
+impl JoinKeys for Vec<&str> {
+    fn names(&self) -> Vec<&str> {
+        self.clone()
+    }
+}
+
+/// The key of a row, the values of its key columns.
+type Key = Vec<Option<String>>;
+
+/// Whether any value of `key` is null, which makes it a null key.
+fn is_null(key: &Key) -> bool {
+    key.iter().any(Option::is_none)
+}
+
+fn key(keys: &[&Column], row: usize) -> anyhow::Result<Key> {
+    keys.iter()
+        .map(|column| {
+            Ok(match column.get(row)? {
+                AnyValue::Null => None,
+                AnyValue::String(s) => Some(s.to_owned()),
+                v => Some(v.to_string()),
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn key_columns<'a>(df: &'a DataFrame, on: &[&str]) -> anyhow::Result<Vec<&'a Column>> {
+    Ok(on
+        .iter()
+        .map(|name| df.column(name))
+        .collect::<Result<_, _>>()?)
+}
+
+/// The rows of each key of `keys`, in the order of their first row.
+pub(crate) fn rows_by_key(
+    keys: &[&Column],
+    nulls: NullKeys,
+) -> anyhow::Result<Vec<(Key, Vec<IdxSize>)>> {
+    let mut rows: Vec<(Key, Vec<IdxSize>)> = Vec::new();
+    let mut index: HashMap<Key, usize> = HashMap::new();
+    let height = keys.first().map_or(0, |column| column.len());
+    for row in 0..height {
+        let key = key(keys, row)?;
+        if is_null(&key) && nulls == NullKeys::Error {
+            let column = keys.iter().find(|c| c.get(row).is_ok_and(|v| v.is_null()));
+            anyhow::bail!(
+                "the key '{}' is null in row {}",
+                column.map_or("", |c| c.name().as_str()),
+                row
+            );
+        }
+        let i = *index.entry(key.clone()).or_insert_with(|| {
+            rows.push((key, Vec::new()));
+            rows.len() - 1
+        });
+        rows[i].1.push(row as IdxSize);
+    }
+    Ok(rows)
+}
+
+/// Fails if a key of `rows` appears more than once, null keys only if they `match`.
+fn ensure_unique(
+    rows: &[(Key, Vec<IdxSize>)],
+    side: &str,
+    nulls: NullKeys,
+    validate: JoinValidation,
+) -> anyhow::Result<()> {
+    for (key, rows) in rows {
+        if rows.len() < 2 || (is_null(key) && nulls != NullKeys::Match) {
+            continue;
+        }
+        anyhow::bail!(
+            "the key {} appears {} times in the {} frame, the first in rows {} and {}, which a \
+             {:?} join doesn't allow",
+            key.iter()
+                .map(|v| v.as_ref().map_or("null".to_owned(), |v| format!("'{}'", v)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            rows.len(),
+            side,
+            rows[0],
+            rows[1],
+            validate
+        );
+    }
+    Ok(())
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Joins the columns of `other` on the key column `on`, or several key columns, see
+    /// [`JoinKeys`]. The result has the header of `self`, the key columns followed by the other
+    /// columns of `self` and then of `other`. Column names in both frames get the left and right
+    /// suffix of `suffixes`, respectively. Keys that appear more than once are joined with every
+    /// match, in the order of `self`, null keys match nothing. See [`TfsDataFrame::join_with`]
+    /// to check the keys.
+    pub fn join<K: JoinKeys + ?Sized>(
+        &self,
+        other: &TfsDataFrame<T>,
+        on: &K,
+        how: JoinType,
+        suffixes: (&str, &str),
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let options = JoinOptions::new().how(how).suffixes(suffixes.0, suffixes.1);
+        self.join_with(other, on, &options)
+    }
+
+    /// Joins the columns of `other` on the key columns `on` like [`TfsDataFrame::join`], with
+    /// the checks and null key handling of `options`. A key with a null in any of its columns
+    /// is a null key.
+    pub fn join_with<K: JoinKeys + ?Sized>(
+        &self,
+        other: &TfsDataFrame<T>,
+        on: &K,
+        options: &JoinOptions,
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let on = on.names();
+        anyhow::ensure!(!on.is_empty(), "a join needs at least one key column");
+        let how = options.how;
+        let suffixes = (options.suffixes.0.as_str(), options.suffixes.1.as_str());
+        let left = self.df()?;
+        let right = other.df()?;
+        let (left_keys, right_keys) = (key_columns(&left, &on)?, key_columns(&right, &on)?);
+
+        let left_rows = rows_by_key(&left_keys, options.null_keys)?;
+        let right_rows = rows_by_key(&right_keys, options.null_keys)?;
+        let (left_unique, right_unique) = options.validate.unique();
+        if left_unique {
+            ensure_unique(&left_rows, "left", options.null_keys, options.validate)?;
+        }
+        if right_unique {
+            ensure_unique(&right_rows, "right", options.null_keys, options.validate)?;
+        }
+        let right_rows: HashMap<Key, Vec<IdxSize>> = right_rows
+            .into_iter()
+            .filter(|(key, _)| !is_null(key) || options.null_keys == NullKeys::Match)
+            .collect();
+
+        let mut left_take = Vec::new();
+        let mut right_take = Vec::new();
+        let mut matched = vec![false; right.height()];
+        for row in 0..left.height() {
+            let matches = right_rows.get(&key(&left_keys, row)?);
+            match matches {
+                Some(matches) => {
+                    for right_row in matches {
+                        left_take.push(Some(row as IdxSize));
+                        right_take.push(Some(*right_row));
+                        matched[*right_row as usize] = true;
+                    }
+                }
+                None if how != JoinType::Inner => {
+                    left_take.push(Some(row as IdxSize));
+                    right_take.push(None);
+                }
+                None => {}
+            }
+        }
+        if how == JoinType::Outer {
+            for (row, _) in matched.iter().enumerate().filter(|(_, m)| !**m) {
+                left_take.push(None);
+                right_take.push(Some(row as IdxSize));
+            }
+        }
+
+        let joined = self.combine(&left, &right, left_take, right_take, &on, suffixes)?;
+        joined.merged_with(&[self, other], options.headers)
+    }
+
+    /// Joins the columns of `other` to the rows of `self` with the nearest `S`, if it is at most
+    /// `tolerance` away. Meant for tables of codes that name elements differently. Both `S`
+    /// columns are kept, the one of `other` with the suffix `_right` like other columns in both
+    /// frames. See [`TfsDataFrame::join_nearest`] for more options.
+    pub fn join_asof_on_s(
+        &self,
+        other: &TfsDataFrame<T>,
+        tolerance: f64,
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let options = JoinOptions::new()
+            .how(JoinType::Left)
+            .suffixes("", "_right");
+        self.join_nearest(other, "S", tolerance, &options)
+    }
+
+    /// Joins the columns of `other` on the nearest value of the numeric column `on`, if it is at
+    /// most `tolerance` away. Of two rows equally far away the one with the smaller value
+    /// matches. Both `on` columns are kept and suffixed like other columns in both frames.
+    ///
+    /// Every row of `self` matches at most one row of `other`. A [`JoinValidation`] with a
+    /// unique left side fails if a row of `other` is the nearest to several rows of `self`. Null
+    /// and `NaN` positions match nothing, or fail with [`NullKeys::Error`].
+    pub fn join_nearest(
+        &self,
+        other: &TfsDataFrame<T>,
+        on: &str,
+        tolerance: f64,
+        options: &JoinOptions,
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let left = self.df()?;
+        let right = other.df()?;
+        let positions = |df: &DataFrame| -> anyhow::Result<Vec<Option<f64>>> {
+            let positions = Option::<f64>::from_column(df.column(on)?.as_materialized_series())?;
+            let positions: Vec<_> = positions
+                .into_iter()
+                .map(|p| p.filter(|p| !p.is_nan()))
+                .collect();
+            if let Some(row) = positions.iter().position(Option::is_none) {
+                anyhow::ensure!(
+                    options.null_keys != NullKeys::Error,
+                    "the position '{}' is missing in row {}",
+                    on,
+                    row
+                );
+            }
+            Ok(positions)
+        };
+        let left_positions = positions(&left)?;
+        let mut sorted: Vec<(f64, IdxSize)> = positions(&right)?
+            .into_iter()
+            .enumerate()
+            .filter_map(|(row, p)| Some((p?, row as IdxSize)))
+            .collect();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut left_take = Vec::new();
+        let mut right_take = Vec::new();
+        let mut matched_by: Vec<Option<usize>> = vec![None; right.height()];
+        for (row, position) in left_positions.into_iter().enumerate() {
+            let nearest = position.and_then(|p| {
+                let i = sorted.partition_point(|(s, _)| *s < p);
+                [i.checked_sub(1), Some(i)]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|i| sorted.get(i))
+                    .filter(|(s, _)| (s - p).abs() <= tolerance)
+                    .min_by(|a, b| (a.0 - p).abs().total_cmp(&(b.0 - p).abs()))
+            });
+            match nearest {
+                Some((_, right_row)) => {
+                    let first = matched_by[*right_row as usize].replace(row);
+                    if let (Some(first), true) = (first, options.validate.unique().0) {
+                        anyhow::bail!(
+                            "row {} of the right frame is the nearest to rows {} and {} of the \
+                             left frame, which a {:?} join doesn't allow",
+                            right_row,
+                            first,
+                            row,
+                            options.validate
+                        );
+                    }
+                    left_take.push(Some(row as IdxSize));
+                    right_take.push(Some(*right_row));
+                }
+                None if options.how != JoinType::Inner => {
+                    left_take.push(Some(row as IdxSize));
+                    right_take.push(None);
+                }
+                None => {}
+            }
+        }
+        if options.how == JoinType::Outer {
+            for (row, _) in matched_by.iter().enumerate().filter(|(_, m)| m.is_none()) {
+                left_take.push(None);
+                right_take.push(Some(row as IdxSize));
+            }
+        }
+
+        let suffixes = (options.suffixes.0.as_str(), options.suffixes.1.as_str());
+        let joined = self.combine(&left, &right, left_take, right_take, &[], suffixes)?;
+        joined.merged_with(&[self, other], options.headers)
+    }
+
+    /// Joins every row of `self` with every row of `other`, in the order of `self`. Columns of
+    /// `other` that are in both frames get the suffix `_right`.
+    pub fn cross_join(&self, other: &TfsDataFrame<T>) -> anyhow::Result<TfsDataFrame<T>> {
+        let left = self.df()?;
+        let right = other.df()?;
+        let (n_left, n_right) = (left.height() as IdxSize, right.height() as IdxSize);
+        let left_take = (0..n_left)
+            .flat_map(|row| std::iter::repeat_n(Some(row), n_right as usize))
+            .collect();
+        let right_take = (0..n_left).flat_map(|_| (0..n_right).map(Some)).collect();
+        let joined = self.combine(&left, &right, left_take, right_take, &[], ("", "_right"))?;
+        joined.merged_with(&[self, other], HeaderMerge::default())
+    }
+
+    /// A frame with a row for every combination of the values of `columns`, e.g. the settings
+    /// of a parameter scan. The values of the last column change fastest.
+    ///
+    /// ```
+    /// # use tfs::TfsDataFrame;
+    /// # use tfs::polars::prelude::{NamedFrom, Series};
+    /// let scan = TfsDataFrame::<f64>::cartesian(vec![
+    ///     Series::new("KQF".into(), [0.01, 0.02]),
+    ///     Series::new("SEED".into(), [1, 2, 3]),
+    /// ])
+    /// .unwrap();
+    /// assert_eq!(scan.len(), 6);
+    /// ```
+    pub fn cartesian(columns: Vec<Series>) -> anyhow::Result<TfsDataFrame<T>> {
+        let mut grid = TfsDataFrame::new(Vec::new(), DataFrame::new(vec![])?);
+        for (i, column) in columns.into_iter().enumerate() {
+            let frame = TfsDataFrame::new(Vec::new(), DataFrame::new(vec![column.into()])?);
+            grid = match i {
+                0 => frame,
+                _ => grid.cross_join(&frame)?,
+            };
+        }
+        Ok(grid)
+    }
+
+    /// Puts the rows `left_take` of `left` and `right_take` of `right` side by side, see
+    /// [`TfsDataFrame::join`]. The key columns `on` are taken from either frame.
+    fn combine(
+        &self,
+        left: &DataFrame,
+        right: &DataFrame,
+        left_take: Vec<Option<IdxSize>>,
+        right_take: Vec<Option<IdxSize>>,
+        on: &[&str],
+        suffixes: (&str, &str),
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let left = left.take(&IdxCa::new("idx".into(), left_take))?;
+        let right = right.take(&IdxCa::new("idx".into(), right_take))?;
+
+        let mut columns = Vec::new();
+        for on in on {
+            // keys of rows only in the right frame come from there
+            let keys = left.column(on)?.as_materialized_series().zip_with(
+                &left.column(on)?.is_not_null(),
+                right.column(on)?.as_materialized_series(),
+            )?;
+            columns.push(keys.into());
+        }
+        for (df, suffix, partner) in [(&left, suffixes.0, &right), (&right, suffixes.1, &left)] {
+            for column in df.get_columns() {
+                let name = column.name().as_str();
+                if on.contains(&name) {
+                    continue;
+                }
+                let mut column = column.clone();
+                if partner.get_column_index(name).is_some() {
+                    column.rename(format!("{}{}", name, suffix).into());
+                }
+                columns.push(column);
+            }
+        }
+
+        let mut joined = self.with_rows(DataFrame::new(columns)?);
+        joined.lineage.clear();
+        Ok(joined)
+    }
+
+    /// Stacks the rows of `frames`. The result has the header of the first frame, see
+    /// [`TfsDataFrame::header_conflicts`]. All frames need the same columns, their order may
+    /// differ.
+    pub fn concat(frames: &[TfsDataFrame<T>]) -> anyhow::Result<TfsDataFrame<T>> {
+        TfsDataFrame::concat_with(frames, HeaderMerge::default())
+    }
+
+    /// Stacks the rows of `frames` like [`TfsDataFrame::concat`], resolving header conflicts
+    /// with `merge`.
+    pub fn concat_with(
+        frames: &[TfsDataFrame<T>],
+        merge: HeaderMerge,
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let Some(first) = frames.first() else {
+            anyhow::bail!("nothing to concatenate");
+        };
+        let names = first.column_names();
+        let mut expected = names.clone();
+        expected.sort_unstable();
+
+        let mut stacked = first.df()?.into_owned();
+        for (i, frame) in frames.iter().enumerate().skip(1) {
+            let mut other_names = frame.column_names();
+            other_names.sort_unstable();
+            anyhow::ensure!(
+                other_names == expected,
+                "frame {} has the columns {:?}, expected {:?}",
+                i,
+                frame.column_names(),
+                names
+            );
+            stacked.vstack_mut(&frame.df()?.select(names.iter().copied())?)?;
+        }
+        first
+            .with_rows(stacked)
+            .merged_with(&frames.iter().collect::<Vec<_>>(), merge)
+    }
+
+    /// Stacks the rows of `frames` like [`TfsDataFrame::concat`], but the frames may have
+    /// different columns: columns missing in some frames are null there.
+    pub fn concat_diagonal(frames: &[TfsDataFrame<T>]) -> anyhow::Result<TfsDataFrame<T>> {
+        let Some(first) = frames.first() else {
+            anyhow::bail!("nothing to concatenate");
+        };
+        let dfs = frames
+            .iter()
+            .map(|frame| Ok(frame.df()?.into_owned()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        first
+            .with_rows(stack_diagonal(dfs)?)
+            .merged_with(&frames.iter().collect::<Vec<_>>(), HeaderMerge::default())
+    }
+
+    /// The header entries that differed between the frames this frame was joined or stacked
+    /// from, see the [module documentation](self).
+    pub fn header_conflicts(&self) -> &[HeaderConflict<T>] {
+        &self.header_conflicts
+    }
+
+    /// Sets the header merged from `frames`, which this frame was joined or stacked from.
+    fn merged_with(
+        mut self,
+        frames: &[&TfsDataFrame<T>],
+        merge: HeaderMerge,
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let (properties, conflicts) = merge_headers(frames, merge)?;
+        self.properties = properties;
+        self.header_conflicts = conflicts;
+        Ok(self)
+    }
+
+    /// Stacks the rows of `frames` like [`TfsDataFrame::concat`] and tags the rows of each frame
+    /// with its label in `labels`, e.g. the beam or the seed, in a new first column
+    /// `label_column`.
+    ///
+    /// ```
+    /// # use tfs::TfsDataFrame;
+    /// let b1 = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+    /// let b2 = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+    /// let both = TfsDataFrame::concat_labeled(&[b1, b2], "BEAM", &[1i64, 2]).unwrap();
+    /// assert_eq!(both.column_names()[0], "BEAM");
+    /// ```
+    pub fn concat_labeled<L: ColumnValue>(
+        frames: &[TfsDataFrame<T>],
+        label_column: &str,
+        labels: &[L],
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        anyhow::ensure!(
+            frames.len() == labels.len(),
+            "{} frames but {} labels",
+            frames.len(),
+            labels.len()
+        );
+        anyhow::ensure!(
+            frames
+                .iter()
+                .all(|frame| !frame.column_names().contains(&label_column)),
+            "the frames already have a column '{}'",
+            label_column
+        );
+        let mut stacked = TfsDataFrame::concat(frames)?;
+        let tags = frames
+            .iter()
+            .zip(labels)
+            .flat_map(|(frame, label)| std::iter::repeat_n(label.clone(), frame.len()))
+            .collect();
+        stacked
+            .df
+            .insert_column(0, L::to_column(label_column, tags))?;
+        Ok(stacked)
+    }
+
+    /// Splits the frame into a frame per value of the column `by`, e.g. per `KEYWORD`, in the
+    /// order the values first appear. The frames keep the header and all columns. Rows with a
+    /// missing value form a group named by the empty string.
+    ///
+    /// ```
+    /// # use tfs::TfsDataFrame;
+    /// let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+    /// for (keyword, elements) in df.partition_by("KEYWORD").unwrap() {
+    ///     println!("{} {}", elements.len(), keyword);
+    /// }
+    /// ```
+    pub fn partition_by(&self, by: &str) -> anyhow::Result<Vec<(String, TfsDataFrame<T>)>> {
+        let df = self.df()?;
+        rows_by_key(&[df.column(by)?], NullKeys::Match)?
+            .into_iter()
+            .map(|(key, rows)| {
+                let rows = df.take(&IdxCa::new("idx".into(), rows))?;
+                let name = key.into_iter().flatten().next().unwrap_or_default();
+                Ok((name, self.with_rows(rows)))
+            })
+            .collect()
+    }
+}
+
+/// Stacks `frames`, adding null columns for the columns some of them miss.
+pub(crate) fn stack_diagonal(frames: Vec<DataFrame>) -> anyhow::Result<DataFrame> {
+    let mut columns: Vec<(String, DataType)> = Vec::new();
+    for df in &frames {
+        for column in df.get_columns() {
+            if !columns
+                .iter()
+                .any(|(name, _)| name == column.name().as_str())
+            {
+                columns.push((column.name().to_string(), column.dtype().clone()));
+            }
+        }
+    }
+
+    let mut stacked: Option<DataFrame> = None;
+    for mut df in frames {
+        for (name, dtype) in &columns {
+            if df.get_column_index(name).is_none() {
+                df.with_column(Series::full_null(name.into(), df.height(), dtype))?;
+            }
+        }
+        let df = df.select(columns.iter().map(|(name, _)| name.as_str()))?;
+        match stacked.as_mut() {
+            Some(stacked) => {
+                stacked.vstack_mut(&df)?;
+            }
+            None => stacked = Some(df),
+        }
+    }
+    Ok(stacked.unwrap_or_default())
+}
@@ -5,15 +5,116 @@
 //! # Starting Points
 //!
 //! - The documentation of [`TfsDataFrame`](tfsdataframe/struct.TfsDataFrame.html) provides examples and API reference
-//! for the main struct.
+//!   for the main struct.
 //!
 //! - The dataframe namespace (see below) contains a very general trait `DataFrame` that has to be implemented
-//! by all dataframe-like objects.
+//!   by all dataframe-like objects.
+//!
+//! # Panics
+//!
+//! A few conveniences panic on values of the wrong kind: [`TfsDataFrame::propd`] and
+//! [`TfsDataFrame::props`], the conversions of [`DataValue`], [`DataView`]
+//! and [`DataVector`] and the operators of [`DataVector`]. Each has a `try_*` counterpart that
+//! returns an error instead, for files that are not under the control of the program.
+pub mod aggregate;
+pub mod aperture;
+pub mod arrow;
+pub mod beams;
+pub mod calculus;
+pub mod cast;
+pub mod catalog;
+pub mod checksum;
+mod compression;
+pub mod computed;
+pub mod cumulative;
 pub mod dataframe;
+pub mod dedup;
+pub mod dialect;
+pub mod diff;
+pub mod elegant;
+pub mod errors;
+pub mod expr;
+pub mod format;
+pub mod formula;
+pub mod header;
+pub mod html;
+pub mod index;
+pub mod join;
+#[cfg(feature = "linalg")]
+mod linalg;
+pub mod lineage;
+pub mod madx;
+pub mod mask;
+pub mod matrix;
+pub mod naming;
+pub mod noise;
+pub mod options;
+mod parse;
+pub mod paths;
+pub mod peaks;
+pub mod pipeline;
+pub mod precision;
+pub mod pyat;
+mod reader;
+pub mod record;
+pub mod remote;
+pub mod render;
+pub mod report;
+pub mod reshape;
+pub mod resonance;
+pub mod sampling;
+pub mod schema;
+pub mod sdds;
+pub mod selection;
+pub mod sequence;
+pub mod smoothing;
+pub mod spill;
+#[cfg(feature = "sql")]
+pub mod sql;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stages;
+pub mod stats;
+pub mod tables;
+pub mod tao;
+pub mod text;
 pub mod tfsdataframe;
+pub mod timeseries;
+pub mod types;
+pub mod uncertainty;
+pub mod workbook;
+mod zip;
 
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+pub use cast::CastReport;
 pub use dataframe::*;
+pub use dialect::Dialect;
+pub use format::{FormatRegistry, TableFormat};
+pub use header::*;
+pub use index::RowIndex;
+pub use lineage::Lineage;
+pub use options::{MissingFields, TfsReadOptions};
+pub use parse::ParseWarning;
+pub use precision::{Precision, PrecisionLoss, RealFormat};
+pub use record::*;
+pub use report::LoadReport;
+pub use sampling::BootstrapEstimate;
+pub use spill::SpilledFrame;
+pub use stages::{TfsBodyParser, TfsHeaderParser};
+pub use stats::ColumnStats;
 pub use tfsdataframe::*;
+pub use types::{ColumnKind, TypeRegistry};
+pub use uncertainty::{ErrorPrefix, ValueErrorPair};
+pub use workbook::TfsWorkbook;
+
+pub use anyhow;
+pub use chrono;
+pub use indexmap;
+pub use polars;
+#[cfg(feature = "derive")]
+pub use tfs_derive::{TfsHeader, TfsRecord};
 
 // The following is tests
 
@@ -31,4 +132,4233 @@ mod tests {
     fn load_all_data() {
         assert_eq!(TfsDataFrame::<f32>::open_expect("test/test.tfs").len(), 5);
     }
+
+    #[test]
+    fn write_and_reload_nrows() {
+        let path = std::env::temp_dir().join("tfs_write_and_reload_nrows.tfs");
+
+        let mut df = TfsDataFrame::<f64>::open_expect("test/test.tfs");
+        df.set_nrows();
+        assert!(df.is_consistent());
+        df.write(&path).unwrap();
+
+        let reloaded = TfsDataFrame::<f64>::open_expect(&path);
+        assert_eq!(reloaded.len(), 5);
+        assert_eq!(reloaded.column_count(), df.column_count());
+        assert_eq!(reloaded.props("SEQUENCE"), "LHCB1");
+        assert!(reloaded.is_consistent());
+        assert_eq!(reloaded.column("BETX").unwrap(), df.column("BETX").unwrap());
+    }
+
+    #[test]
+    fn synthetic_frame_roundtrip() {
+        use testing::{make_frame, roundtrip, FrameSpec};
+
+        let spec = FrameSpec {
+            n_elements: 50,
+            noise: 1e-3,
+            seed: 42,
+            ..Default::default()
+        };
+        let df = make_frame(&spec);
+        assert_eq!(df.len(), 50);
+        assert_eq!(*df.propd("Q1"), spec.q1);
+
+        let reloaded = roundtrip(&df).unwrap();
+        assert_eq!(reloaded.column("MUX").unwrap(), df.column("MUX").unwrap());
+        assert_eq!(reloaded.column("NAME").unwrap(), df.column("NAME").unwrap());
+
+        // same seed, same frame
+        assert_eq!(make_frame(&spec).df().unwrap(), df.df().unwrap());
+    }
+
+    #[test]
+    fn diff_and_patch() {
+        use polars::prelude::NamedFrom;
+        use polars::series::Series;
+
+        let reference = TfsDataFrame::<f64>::open_expect("test/test.tfs");
+        let mut updated = TfsDataFrame::<f64>::open_expect("test/test.tfs");
+        assert!(reference.diff(&updated).unwrap().is_empty());
+
+        let mut k1l: Vec<f64> = updated
+            .column("K1L")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        k1l[2] = 1.5e-2;
+        updated.set_column(Series::new("K1L".into(), k1l)).unwrap();
+        updated.properties.shift_remove("TYPE");
+
+        let diff = reference.diff(&updated).unwrap();
+        assert_eq!(diff.cells.len(), 1);
+        assert_eq!(diff.removed_properties, vec!["TYPE".to_owned()]);
+
+        let diff = diff::FrameDiff::from_json(&diff.to_json().unwrap()).unwrap();
+        let mut patched = TfsDataFrame::<f64>::open_expect("test/test.tfs");
+        patched.apply_diff(&diff).unwrap();
+        assert!(patched.diff(&updated).unwrap().is_empty());
+    }
+
+    #[test]
+    fn lineage_written_to_header() {
+        let path = std::env::temp_dir().join("tfs_lineage_written_to_header.tfs");
+
+        let mut df = TfsDataFrame::<f64>::open_expect("test/test.tfs");
+        df.derive_column("SQRT_BETX", &["BETX"], "sqrt(BETX)", |v| v[0].sqrt())
+            .unwrap();
+        df.write(&path).unwrap();
+
+        let reloaded = TfsDataFrame::<f64>::open_expect(&path);
+        let lineage = reloaded.lineage("SQRT_BETX").unwrap();
+        assert_eq!(lineage.expression, "sqrt(BETX)");
+        assert_eq!(lineage.inputs, vec!["BETX".to_owned()]);
+        assert!(reloaded.lineage("BETX").is_none());
+    }
+
+    #[test]
+    fn real_number_formats() {
+        use parse::parse_real;
+
+        let options = TfsReadOptions::default();
+        assert_eq!(parse_real::<f64>("1.234D-05", &options), Some(1.234e-5));
+        assert_eq!(parse_real::<f64>("-2.5d+03", &options), Some(-2.5e3));
+        assert_eq!(parse_real::<f64>("+1.5", &options), Some(1.5));
+        assert_eq!(parse_real::<f64>("-Inf", &options), Some(f64::NEG_INFINITY));
+        assert!(parse_real::<f64>("NaN", &options).unwrap().is_nan());
+        assert!(parse_real::<f64>("-nan", &options).unwrap().is_nan());
+        assert_eq!(parse_real::<f64>("1.0DD3", &options), None);
+
+        let strict = TfsReadOptions::new().fortran_exponents(false);
+        assert_eq!(parse_real::<f64>("1.234D-05", &strict), None);
+    }
+
+    #[test]
+    fn decimal_comma() {
+        let path = std::env::temp_dir().join("tfs_decimal_comma.tfs");
+        let data = "* NAME X\n$ %s %le\n \"A\" 1.5\n \"B\" 2,5\n \"C\" 1,000.5\n";
+        std::fs::write(&path, data).unwrap();
+
+        let strict = TfsDataFrame::<f64>::open_expect(&path);
+        let x = strict.column("X").unwrap().f64().unwrap();
+        assert!(x.get(1).unwrap().is_nan());
+        assert_eq!(strict.load_report().decimal_commas().count(), 0);
+        assert_eq!(strict.load_report().nan_coercions().count(), 2);
+
+        std::fs::write(&path, format!("@ Q1 %le 62,31\n{}", data)).unwrap();
+        let options = TfsReadOptions::new().decimal_comma(true);
+        let lenient = TfsDataFrame::<f64>::open_with(&path, &options).unwrap();
+        let x = lenient.column("X").unwrap().f64().unwrap();
+        assert_eq!(x.get(1), Some(2.5));
+        assert!(x.get(2).unwrap().is_nan());
+        assert_eq!(*lenient.propd("Q1"), 62.31);
+        assert_eq!(lenient.load_report().decimal_commas().count(), 2);
+        assert_eq!(lenient.parse_warnings().len(), 3);
+    }
+
+    #[test]
+    fn cached_stats_invalidated() {
+        use polars::prelude::NamedFrom;
+        use polars::series::Series;
+
+        let mut df = TfsDataFrame::<f64>::open_expect("test/test.tfs");
+        let stats = df.stats("BETX").unwrap();
+        assert_eq!(stats.count, 5);
+        assert_eq!(df.stats("BETX").unwrap(), stats);
+        assert!(df.stats("NAME").is_err());
+
+        df.set_column(Series::new("BETX".into(), [1.0, 2.0, f64::NAN, 4.0, 8.0]))
+            .unwrap();
+        let stats = df.stats("BETX").unwrap();
+        assert_eq!((stats.min, stats.max, stats.mean), (1.0, 8.0, 3.75));
+        assert_eq!(stats.count, 4);
+
+        // every change drops the statistics of all columns, virtual ones depend on others
+        df.register_virtual("BETX2", "BETX * 2").unwrap();
+        assert_eq!(df.stats("BETX2").unwrap().max, 16.0);
+        df.set_where(vec![4], "BETX", 2.0).unwrap();
+        assert_eq!(df.stats("BETX").unwrap().max, 4.0);
+        assert_eq!(df.stats("BETX2").unwrap().max, 8.0);
+        let bpm1 = df.mask_from_condition("BETX", |b| b == 1.0).unwrap();
+        df.apply_mask(&bpm1, mask::MaskAction::NaN).unwrap();
+        assert_eq!(df.stats("BETX").unwrap().min, 2.0);
+    }
+
+    #[test]
+    fn preview() {
+        let df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 10000,
+            ..Default::default()
+        });
+        let file = testing::write_temp(&df).unwrap();
+
+        let preview = TfsDataFrame::<f64>::open_preview(file.path(), 3).unwrap();
+        assert_eq!(preview.len(), 6);
+        assert_eq!(preview.props("SEQUENCE"), "SYNTH");
+        let names = preview.column("NAME").unwrap().str().unwrap();
+        assert_eq!(names.get(0), Some("MQ.F1"));
+        assert_eq!(names.get(2), Some("MQ.F2"));
+        assert_eq!(names.get(3), Some("BPM.4999"));
+        assert_eq!(names.get(5), Some("BPM.5000"));
+
+        let small = TfsDataFrame::<f64>::open_preview("test/test.tfs", 3).unwrap();
+        assert_eq!(small.len(), 5);
+    }
+
+    #[test]
+    fn header_only() {
+        let header = TfsHeader::<f64>::read("test/test.tfs").unwrap();
+        let df = TfsDataFrame::<f64>::open_expect("test/test.tfs");
+
+        assert_eq!(header.properties, df.properties);
+        assert_eq!(header.colnames.len(), df.column_count());
+        assert_eq!(header.coltype("KEYWORD"), Some("%s"));
+        assert_eq!(header.coltype("NOT_THERE"), None);
+    }
+
+    #[test]
+    fn catalog_of_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("b1");
+        std::fs::create_dir(&nested).unwrap();
+
+        let df = testing::make_frame(&testing::FrameSpec::default());
+        df.write(dir.path().join("twiss.tfs")).unwrap();
+        df.write(nested.join("twiss_b1.tfs")).unwrap();
+        std::fs::write(nested.join("notes.txt"), "not a tfs file").unwrap();
+        std::fs::write(nested.join("broken.tfs"), "@ TYPE %s \"nothing else\"").unwrap();
+
+        let catalog = catalog::TfsCatalog::scan(dir.path()).unwrap();
+        assert_eq!(catalog.entries.len(), 2);
+        assert_eq!(catalog.errors.len(), 1);
+
+        let summary = testing::roundtrip(&catalog.to_frame()).unwrap();
+        assert_eq!(summary.len(), 2);
+        assert_eq!(
+            summary.column("Q1").unwrap().f64().unwrap().get(0),
+            Some(62.31)
+        );
+        assert_eq!(
+            summary.column("SEQUENCE").unwrap().str().unwrap().get(1),
+            Some("SYNTH")
+        );
+    }
+
+    #[test]
+    fn compressed_columns() {
+        let original = testing::make_frame(&testing::FrameSpec::default());
+        let mut df = testing::roundtrip(&original).unwrap();
+        df.compress_column("BETX").unwrap();
+        df.compress_column("NAME").unwrap();
+
+        assert_eq!(df.compressed_columns(), vec!["NAME", "BETX"]);
+        assert_eq!(df.column_count(), original.column_count());
+        assert_eq!(
+            df.df()
+                .unwrap()
+                .column("BETX")
+                .unwrap()
+                .as_materialized_series(),
+            original.column("BETX").unwrap()
+        );
+        assert_eq!(df.column("BETX").unwrap(), original.column("BETX").unwrap());
+        assert_eq!(df.column("NAME").unwrap(), original.column("NAME").unwrap());
+
+        let reloaded = testing::roundtrip(&df).unwrap();
+        assert_eq!(
+            reloaded.df().unwrap().get_column_names(),
+            original.df().unwrap().get_column_names()
+        );
+        assert!(reloaded.diff(&original).unwrap().is_empty());
+
+        df.decompress_columns().unwrap();
+        assert!(df.compressed_columns().is_empty());
+        assert_eq!(
+            df.df().unwrap().get_column_names(),
+            original.df().unwrap().get_column_names()
+        );
+    }
+
+    #[test]
+    fn pipeline_over_directory() {
+        let input = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+
+        let df = testing::make_frame(&testing::FrameSpec::default());
+        df.write(input.path().join("a.tfs")).unwrap();
+        df.write(input.path().join("b.tfs")).unwrap();
+        std::fs::write(input.path().join("c.tfs"), "@ TYPE %s \"nothing else\"").unwrap();
+
+        let pipeline = pipeline::TfsPipeline::new()
+            .filter("S", |s| s < 10000.0)
+            .derive("BETX_BETY", &["BETX", "BETY"], "BETX / BETY", |v| {
+                v[0] / v[1]
+            })
+            .write_to(output.path());
+        let report = pipeline.run_dir(input.path()).unwrap();
+
+        assert!(!report.is_success());
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.processed.len(), 2);
+        assert_eq!(report.processed[0].path, input.path().join("a.tfs"));
+        assert_eq!(report.processed[0].rows, 3);
+
+        let processed = TfsDataFrame::<f64>::open(output.path().join("b.tfs")).unwrap();
+        assert_eq!(processed.len(), 3);
+        assert_eq!(
+            processed.lineage("BETX_BETY").unwrap().inputs,
+            vec!["BETX", "BETY"]
+        );
+    }
+
+    #[test]
+    fn header_order() {
+        let df = testing::make_frame(&testing::FrameSpec::default());
+        let mut reloaded = testing::roundtrip(&df).unwrap();
+        let keys: Vec<_> = reloaded.properties.keys().collect();
+        assert_eq!(keys, vec!["TYPE", "SEQUENCE", "LENGTH", "Q1", "Q2"]);
+
+        let sorted: Vec<_> = reloaded
+            .sorted_properties()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(sorted, vec!["LENGTH", "Q1", "Q2", "SEQUENCE", "TYPE"]);
+
+        let printed = reloaded.to_string();
+        assert!(printed.find("TYPE").unwrap() < printed.find("LENGTH").unwrap());
+        reloaded.set_header_order(HeaderOrder::Alphabetical);
+        let printed = reloaded.to_string();
+        assert!(printed.find("TYPE").unwrap() > printed.find("LENGTH").unwrap());
+    }
+
+    #[test]
+    fn display_statistics() {
+        let df = testing::make_frame(&testing::FrameSpec::default());
+
+        assert!(!df.to_string().contains("Statistics"));
+        let printed = format!("{:#}", df);
+        let footer = &printed[printed.find("Statistics:").unwrap()..];
+        assert!(footer.contains("BETX"));
+        assert!(footer.contains("max 2.665888e4"));
+        assert!(!footer.contains("NAME"));
+    }
+
+    #[test]
+    fn error_columns() {
+        let mut df = testing::make_frame(&testing::FrameSpec::default());
+        let betx = df.column("BETX").unwrap().clone();
+        df.set_column(betx.clone().with_name("ERRBETX".into()))
+            .unwrap();
+        df.set_column(betx.clone().with_name("STDBETX".into()))
+            .unwrap();
+        df.set_column(betx.clone().with_name("ERRBETY".into()))
+            .unwrap();
+        df.set_column(betx.clone().with_name("DELTABETY".into()))
+            .unwrap();
+        df.set_column(betx.with_name("ERRDELTABETY".into()))
+            .unwrap();
+
+        let pairs = df.value_error_pairs();
+        assert_eq!(pairs.len(), 5);
+        assert_eq!(
+            (pairs[0].value_name, pairs[0].error_name),
+            ("BETX", "ERRBETX")
+        );
+        assert_eq!(pairs[1].prefix, ErrorPrefix::Std);
+        assert_eq!(pairs[2].value_name, "BETY");
+        assert_eq!(
+            (pairs[3].value_name, pairs[3].prefix),
+            ("BETY", ErrorPrefix::Delta)
+        );
+        assert_eq!(
+            (pairs[4].value_name, pairs[4].error_name),
+            ("DELTABETY", "ERRDELTABETY")
+        );
+        assert!(df.validate_error_columns().is_ok());
+
+        df.set_column(f64::to_column("ERRBETA", vec![0.0; df.len()]))
+            .unwrap();
+        df.set_column(f64::to_column("DELTABETA", vec![0.0; df.len()]))
+            .unwrap();
+        assert_eq!(df.orphaned_error_columns(), vec!["ERRBETA", "DELTABETA"]);
+        assert!(df.validate_error_columns().is_err());
+    }
+
+    #[test]
+    fn element_mask() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut df = testing::make_frame(&testing::FrameSpec::default());
+        df.derive_column("BETX_BETY", &["BETX", "BETY"], "BETX / BETY", |v| {
+            v[0] / v[1]
+        })
+        .unwrap();
+
+        let mask = df.mask_from_condition("ALFX", |a| a.abs() > 1e-3).unwrap();
+        assert!(mask.is_empty());
+        let mask = df.mask_from_condition("BETX", |b| b > 100.0).unwrap();
+        assert_eq!(mask.len(), 5);
+        assert!(mask.contains("MQ.F1"));
+
+        let text = dir.path().join("bad_bpms.txt");
+        mask.write(&text).unwrap();
+        assert_eq!(mask::ElementMask::open(&text).unwrap(), mask);
+        let listed = dir.path().join("bad_bpms.tfs");
+        df.write(&listed).unwrap();
+        assert_eq!(mask::ElementMask::open(&listed).unwrap().len(), 10);
+
+        let mut dropped = testing::roundtrip(&df).unwrap();
+        dropped.apply_mask(&mask, mask::MaskAction::Drop).unwrap();
+        assert_eq!(dropped.len(), 5);
+
+        df.apply_mask(&mask, mask::MaskAction::NaN).unwrap();
+        assert_eq!(df.len(), 10);
+        let betx = df.column("BETX").unwrap().f64().unwrap();
+        assert!(betx.get(0).unwrap().is_nan());
+        assert!(!betx.get(1).unwrap().is_nan());
+        assert!(df.lineage("BETX_BETY").is_some());
+    }
+
+    #[test]
+    fn sampling_and_bootstrap() {
+        let df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 1000,
+            ..Default::default()
+        });
+
+        let sample = df.sample(100, false, 7).unwrap();
+        assert_eq!(sample.len(), 100);
+        assert_eq!(sample.props("TYPE"), "TWISS");
+        let mut names = String::from_column(sample.column("NAME").unwrap()).unwrap();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), 100);
+        assert_eq!(
+            sample.column("S").unwrap(),
+            df.sample(100, false, 7).unwrap().column("S").unwrap()
+        );
+        assert_eq!(df.sample(2000, true, 7).unwrap().len(), 2000);
+        assert!(df.sample(2000, false, 7).is_err());
+
+        // BETX alternates between 1.5 and 0.5 times its mean, the standard error of the mean
+        // is about 0.5 * mean / sqrt(n)
+        let mean = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
+        let estimate = df.bootstrap_stat("BETX", mean, 500).unwrap();
+        let expected = 0.5 * estimate.value / (1000.0f64).sqrt();
+        assert!((estimate.std_err / expected - 1.0).abs() < 0.2);
+        assert!((estimate.mean - estimate.value).abs() < 3.0 * estimate.std_err);
+    }
+
+    #[test]
+    fn time_series() {
+        use chrono::NaiveDate;
+        use polars::prelude::DataType;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("orbit_history.tfs");
+        std::fs::write(
+            &path,
+            "@ TYPE %s \"ORBIT\"\n\
+             * TIME X EPOCH\n\
+             $ %s %le %le\n\
+             \"2024-05-01T10:00:00\" 1.0 1714557600\n\
+             \"2024-05-01T12:00:30.5+02:00\" 2.0 1714557630.5\n\
+             \"2024-05-01T10:01:10.250\" 4.0 1714557670.25\n\
+             \"1714557720\" 8.0 1714557720\n",
+        )
+        .unwrap();
+
+        let mut df = TfsDataFrame::<f64>::open(&path).unwrap();
+        df.parse_timestamps("TIME").unwrap();
+        df.parse_timestamps("EPOCH").unwrap();
+        let micros = |df: &TfsDataFrame<f64>, name: &str| {
+            i64::from_column(&df.column(name).unwrap().cast(&DataType::Int64).unwrap()).unwrap()
+        };
+        assert_eq!(micros(&df, "TIME"), micros(&df, "EPOCH"));
+
+        let at = |h, m| {
+            NaiveDate::from_ymd_opt(2024, 5, 1)
+                .unwrap()
+                .and_hms_opt(h, m, 0)
+                .unwrap()
+        };
+        let first_minute = df.between_times("TIME", at(10, 0), at(10, 1)).unwrap();
+        assert_eq!(first_minute.len(), 2);
+        assert_eq!(first_minute.props("TYPE"), "ORBIT");
+
+        let per_minute = df.resample("TIME", chrono::Duration::minutes(1)).unwrap();
+        assert_eq!(
+            f64::from_column(per_minute.column("X").unwrap()).unwrap(),
+            vec![1.5, 4.0, 8.0]
+        );
+
+        let mut reloaded = testing::roundtrip(&df).unwrap();
+        reloaded.parse_timestamps("TIME").unwrap();
+        assert_eq!(micros(&reloaded, "TIME"), micros(&df, "TIME"));
+    }
+
+    #[test]
+    fn header_timestamp() {
+        let stamp = |date: &str, time: Option<&str>| {
+            let mut properties = vec![
+                (
+                    "ORIGIN".to_owned(),
+                    DataValue::Text("5.07.00 Linux 64".to_owned()),
+                ),
+                ("DATE".to_owned(), DataValue::Text(date.to_owned())),
+            ];
+            properties.extend(time.map(|t| ("TIME".to_owned(), DataValue::Text(t.to_owned()))));
+            let df = TfsDataFrame::<f64>::new(properties, polars::prelude::DataFrame::empty());
+            assert_eq!(df.origin(), Some("5.07.00 Linux 64"));
+            df.timestamp().map(|t| t.to_string())
+        };
+
+        let expected = Some("2024-05-01 10:30:15".to_owned());
+        assert_eq!(stamp("01/05/24", Some("10.30.15")), expected);
+        assert_eq!(stamp("01/05/2024", Some("10:30:15")), expected);
+        assert_eq!(stamp("2024-05-01", Some("10:30:15")), expected);
+        assert_eq!(stamp("01.05.2024", Some("10.30.15")), expected);
+        assert_eq!(
+            stamp("01/05/24", None),
+            Some("2024-05-01 00:00:00".to_owned())
+        );
+        assert_eq!(stamp("yesterday", Some("10.30.15")), None);
+        assert_eq!(stamp("01/05/24", Some("noon")), None);
+
+        let df = TfsDataFrame::<f64>::open_expect("test/test.tfs");
+        assert_eq!(df.timestamp(), None);
+    }
+
+    #[test]
+    fn missing_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("short_rows.tfs");
+        std::fs::write(
+            &path,
+            "* NAME S COMMENT\n\
+             $ %s %le %s\n\
+             \"BPM1\" 1.0 \"ok\"\n\
+             \"BPM2\" 2.0\n\
+             \"BPM3\"\n",
+        )
+        .unwrap();
+
+        let options = TfsReadOptions::new().missing_fields(MissingFields::Error);
+        assert!(TfsDataFrame::<f64>::open_with(&path, &options).is_err());
+
+        let df = TfsDataFrame::<f64>::open(&path).unwrap();
+        assert_eq!(df.len(), 3);
+        assert_eq!(df.column("S").unwrap().null_count(), 1);
+        assert_eq!(df.column("COMMENT").unwrap().null_count(), 2);
+        assert_eq!(
+            df.column("COMMENT").unwrap().str().unwrap().get(0),
+            Some("ok")
+        );
+
+        let options = TfsReadOptions::new().missing_fields(MissingFields::Fill {
+            real: -1.0,
+            text: "n/a".to_owned(),
+        });
+        let df = TfsDataFrame::<f64>::open_with(&path, &options).unwrap();
+        assert_eq!(df.column("S").unwrap().f64().unwrap().get(2), Some(-1.0));
+        assert_eq!(
+            df.column("COMMENT").unwrap().str().unwrap().get(1),
+            Some("n/a")
+        );
+    }
+
+    #[test]
+    fn column_count_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mismatch.tfs");
+
+        std::fs::write(
+            &path,
+            "* NAME S BETX BETY\n$ %s %le\n\"BPM1\" 1.0 2.0 3.0\n",
+        )
+        .unwrap();
+        let err = TfsDataFrame::<f64>::open(&path).unwrap_err().to_string();
+        assert!(
+            err.contains("the columns BETX, BETY have no type"),
+            "{}",
+            err
+        );
+
+        std::fs::write(&path, "* NAME S\n$ %s %le %le\n\"BPM1\" 1.0 2.0\n").unwrap();
+        let err = TfsHeader::<f64>::read(&path).unwrap_err().to_string();
+        assert!(err.contains("the types %le have no column name"), "{}", err);
+    }
+
+    #[test]
+    fn type_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("custom_types.tfs");
+        std::fs::write(
+            &path,
+            "* NAME TURN X Y FLAG\n\
+             $ %s %08d %lf %f %b\n\
+             \"BPM1\" 00000012 1.5 2.5 true\n\
+             \"BPM2\" 12.5 1.5 2.5 yes\n",
+        )
+        .unwrap();
+
+        let df = TfsDataFrame::<f64>::open(&path).unwrap();
+        assert_eq!(df.column("TURN").unwrap().i64().unwrap().get(0), Some(12));
+        assert!(df.column("X").unwrap().str().is_ok());
+        let warnings: Vec<String> = df.parse_warnings().iter().map(|w| w.to_string()).collect();
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings[0].contains("'%lf' of column 'X'"));
+        assert!(warnings[2].contains("'12.5' in column 'TURN', row 1"));
+        assert!(warnings[3].contains("'yes' in column 'FLAG', row 1"));
+        assert_eq!(df.load_report().null_coercions().count(), 2);
+        // %b is a MAD-NG boolean
+        assert!(df.column("FLAG").unwrap().bool().is_ok());
+
+        let options = TfsReadOptions::new()
+            .register_type("%lf", ColumnKind::Real)
+            .register_type("%f", ColumnKind::Real)
+            .register_type("%b", ColumnKind::Text);
+        let df = TfsDataFrame::<f64>::open_with(&path, &options).unwrap();
+        assert_eq!(
+            df.parse_warnings(),
+            [ParseWarning::NullCoercion {
+                column: "TURN".to_owned(),
+                row: 1,
+                token: "12.5".to_owned(),
+            }]
+        );
+        assert_eq!(df.column("TURN").unwrap().null_count(), 1);
+        assert_eq!(df.column("Y").unwrap().f64().unwrap().get(0), Some(2.5));
+
+        let written = dir.path().join("written.tfs");
+        df.write(&written).unwrap();
+        let header = TfsHeader::<f64>::read(&written).unwrap();
+        assert_eq!(header.coltypes, vec!["%s", "%08d", "%lf", "%f", "%b"]);
+    }
+
+    #[test]
+    fn load_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dirty.tfs");
+        std::fs::write(
+            &path,
+            "@ TYPE %s \"TWISS\"\n\
+             @ TYPE %s \"OPTICS\"\n\
+             stray text\n\
+             * NAME S BETX\n\
+             $ %s %le %lf\n\
+             \"BPM1\" 1.0 2.0\n\
+             # commented out row\n\
+             \"BPM2\" n/a 3.0\n",
+        )
+        .unwrap();
+
+        let df = TfsDataFrame::<f64>::open(&path).unwrap();
+        assert_eq!(df.len(), 2);
+        assert_eq!(df.props("TYPE"), "OPTICS");
+
+        let report = df.load_report();
+        assert!(!report.is_clean());
+        assert_eq!(report.duplicate_headers().count(), 1);
+        assert_eq!(report.skipped_lines().count(), 2);
+        assert_eq!(report.unknown_type_codes().count(), 1);
+        assert_eq!(
+            report.nan_coercions().next(),
+            Some(&ParseWarning::NanCoercion {
+                column: "S".to_owned(),
+                row: 1,
+                token: "n/a".to_owned(),
+            })
+        );
+        assert!(report
+            .to_string()
+            .starts_with("1 NaN coercions, 0 null coercions, 2 skipped lines"));
+
+        let df = TfsDataFrame::<f64>::open_expect("test/test.tfs");
+        assert!(df.load_report().is_clean());
+    }
+
+    #[test]
+    fn nan_sentinels() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sentinels.tfs");
+        std::fs::write(
+            &path,
+            "* NAME X Y\n\
+             $ %s %le %le\n\
+             \"BPM1\" -999999 1e99\n\
+             \"BPM2\" 1.0 -999999\n",
+        )
+        .unwrap();
+
+        let options = TfsReadOptions::new()
+            .nan_sentinel(-999999.0)
+            .column_nan_sentinel("Y", 1e99);
+        let df = TfsDataFrame::<f64>::open_with(&path, &options).unwrap();
+        let x = df.column("X").unwrap().f64().unwrap();
+        let y = df.column("Y").unwrap().f64().unwrap();
+        assert!(x.get(0).unwrap().is_nan());
+        assert_eq!(x.get(1), Some(1.0));
+        assert!(y.get(0).unwrap().is_nan());
+        assert_eq!(y.get(1), Some(-999999.0));
+
+        let written = dir.path().join("written.tfs");
+        df.write(&written).unwrap();
+        let plain = TfsDataFrame::<f64>::open(&written).unwrap();
+        assert_eq!(
+            plain.column("X").unwrap().f64().unwrap().get(0),
+            Some(-999999.0)
+        );
+        assert_eq!(plain.column("Y").unwrap().f64().unwrap().get(0), Some(1e99));
+
+        let mut df = df;
+        df.set_nan_sentinel("X", None);
+        df.write(&written).unwrap();
+        let plain = TfsDataFrame::<f64>::open(&written).unwrap();
+        assert!(plain
+            .column("X")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .is_nan());
+    }
+
+    #[test]
+    fn staged_reading() {
+        let df = testing::make_frame(&testing::FrameSpec::default());
+        let file = testing::write_temp(&df).unwrap();
+
+        // decide on the header, then continue with the same reader
+        let mut body = TfsHeaderParser::open(file.path())
+            .unwrap()
+            .parse::<f64>()
+            .unwrap();
+        assert_eq!(body.header().coltype("NAME"), Some("%s"));
+        assert_eq!(body.rows_read(), 0);
+        let after_header = body.position();
+
+        assert_eq!(body.read_rows(4).unwrap(), 4);
+        assert!(body.position() > after_header);
+        *body.options_mut() = TfsReadOptions::new().decimal_comma(true);
+        let partial = body.finish().unwrap();
+        assert_eq!(partial.len(), 10);
+        assert!(partial.diff(&df).unwrap().is_empty());
+
+        let reader = std::io::BufReader::new(std::fs::File::open(file.path()).unwrap());
+        let mut body = TfsHeaderParser::new(reader).parse::<f64>().unwrap();
+        body.read_rows(3).unwrap();
+        assert_eq!(body.into_frame().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn row_index() {
+        let df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 3000,
+            ..Default::default()
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tracking.tfs");
+        df.write(&path).unwrap();
+
+        // built on the first read
+        let rows = TfsDataFrame::<f64>::read_row_range(&path, 2047, 2050).unwrap();
+        let index = RowIndex::load(&path).unwrap().unwrap();
+        assert_eq!(index.rows, 3000);
+        assert_eq!(index.offsets.len(), 3);
+        assert!(rows
+            .diff(&df.with_rows(df.df.slice(2047, 3)))
+            .unwrap()
+            .is_empty());
+
+        let tail = TfsDataFrame::<f64>::read_row_range(&path, 2990, 5000).unwrap();
+        assert!(tail
+            .diff(&df.with_rows(df.df.slice(2990, 10)))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            TfsDataFrame::<f64>::read_row_range(&path, 3000, 3001)
+                .unwrap()
+                .len(),
+            0
+        );
+        assert!(TfsDataFrame::<f64>::read_row_range(&path, 5, 4).is_err());
+
+        // a changed file invalidates the index
+        df.with_rows(df.df.slice(0, 100)).write(&path).unwrap();
+        assert_eq!(RowIndex::load(&path).unwrap(), None);
+        let rows = TfsDataFrame::<f64>::read_row_range(&path, 90, 200).unwrap();
+        assert_eq!(rows.len(), 10);
+        assert_eq!(
+            TfsDataFrame::<f64>::build_row_index(&path).unwrap(),
+            RowIndex::load(&path).unwrap().unwrap()
+        );
+    }
+
+    #[test]
+    fn workbook() {
+        let beam1 = testing::make_frame(&testing::FrameSpec::default());
+        let beam2 = testing::make_frame(&testing::FrameSpec {
+            seed: 2,
+            n_elements: 4,
+            ..Default::default()
+        });
+
+        let mut workbook = TfsWorkbook::new();
+        assert!(workbook.insert("twiss_b2", beam2).unwrap().is_none());
+        workbook
+            .insert("twiss_b1", beam1.with_rows(beam1.df.clone()))
+            .unwrap();
+        assert!(workbook
+            .insert("../twiss", beam1.with_rows(beam1.df.clone()))
+            .is_err());
+        assert!(workbook
+            .insert("", beam1.with_rows(beam1.df.clone()))
+            .is_err());
+        assert_eq!(workbook.len(), 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        workbook.save_dir(dir.path().join("results")).unwrap();
+        let loaded = TfsWorkbook::<f64>::load_dir(dir.path().join("results")).unwrap();
+        assert_eq!(
+            loaded.names().collect::<Vec<_>>(),
+            vec!["twiss_b1", "twiss_b2"]
+        );
+        assert!(loaded
+            .get("twiss_b1")
+            .unwrap()
+            .diff(&beam1)
+            .unwrap()
+            .is_empty());
+
+        let archive = dir.path().join("results.tfs.zip");
+        workbook.save_zip(&archive).unwrap();
+        let mut loaded = TfsWorkbook::<f64>::open_zip(&archive).unwrap();
+        assert_eq!(
+            loaded.names().collect::<Vec<_>>(),
+            vec!["twiss_b2", "twiss_b1"]
+        );
+        assert_eq!(loaded.get("twiss_b2").unwrap().len(), 4);
+        assert!(loaded
+            .remove("twiss_b1")
+            .unwrap()
+            .diff(&beam1)
+            .unwrap()
+            .is_empty());
+        assert_eq!(loaded.len(), 1);
+
+        std::fs::write(&archive, b"not an archive").unwrap();
+        assert!(TfsWorkbook::<f64>::open_zip(&archive).is_err());
+    }
+
+    #[test]
+    fn bundle_manifest() {
+        let mut workbook = TfsWorkbook::new();
+        workbook
+            .insert("twiss", TfsDataFrame::<f64>::open("test/test.tfs").unwrap())
+            .unwrap();
+        let manifest = workbook.manifest();
+        assert_eq!(manifest.version, workbook::BundleManifest::VERSION);
+        assert_eq!(manifest.frames[0].file, "twiss.tfs");
+        assert_eq!(manifest.frames[0].rows, 5);
+
+        let write_bundle = |manifest: &workbook::BundleManifest, path: &std::path::Path| {
+            let mut archive = zip::ZipWriter::default();
+            archive
+                .add(
+                    workbook::MANIFEST,
+                    serde_json::to_string(manifest).unwrap().as_bytes(),
+                )
+                .unwrap();
+            archive
+                .add("twiss.tfs", &std::fs::read("test/test.tfs").unwrap())
+                .unwrap();
+            std::fs::write(path, archive.finish().unwrap()).unwrap();
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.tfs.zip");
+
+        let mut renamed = manifest.clone();
+        renamed.frames[0].name = "optics".to_owned();
+        write_bundle(&renamed, &path);
+        let loaded = TfsWorkbook::<f64>::open_zip(&path).unwrap();
+        assert_eq!(loaded.names().collect::<Vec<_>>(), vec!["optics"]);
+
+        let mut wrong_rows = manifest.clone();
+        wrong_rows.frames[0].rows = 6;
+        write_bundle(&wrong_rows, &path);
+        assert!(TfsWorkbook::<f64>::open_zip(&path).is_err());
+
+        let mut missing = manifest.clone();
+        missing.frames[0].file = "other.tfs".to_owned();
+        write_bundle(&missing, &path);
+        assert!(TfsWorkbook::<f64>::open_zip(&path).is_err());
+
+        let mut future = manifest;
+        future.version += 1;
+        write_bundle(&future, &path);
+        assert!(TfsWorkbook::<f64>::open_zip(&path).is_err());
+    }
+
+    #[test]
+    fn arrow_conversion() {
+        let mut df = testing::make_frame(&testing::FrameSpec::default());
+        df.compress_column("BETY").unwrap();
+
+        let batch = df.to_arrow().unwrap();
+        assert_eq!(batch.num_rows(), 10);
+        assert_eq!(
+            batch
+                .schema()
+                .fields()
+                .iter()
+                .map(|f| f.name().as_str())
+                .collect::<Vec<_>>(),
+            df.column_names()
+        );
+
+        let back = TfsDataFrame::from_arrow(&batch, df.properties.clone()).unwrap();
+        df.decompress_columns().unwrap();
+        assert!(back.diff(&df).unwrap().is_empty());
+        assert_eq!(back.properties, df.properties);
+    }
+
+    #[cfg(feature = "sql")]
+    #[test]
+    fn sql_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let beam1 = testing::make_frame(&testing::FrameSpec::default());
+        let mut beam2 = testing::make_frame(&testing::FrameSpec {
+            n_elements: 4,
+            ..Default::default()
+        });
+        beam2.df.drop_in_place("BETY").unwrap();
+        beam1.write(dir.path().join("b1.tfs")).unwrap();
+        beam2.write(dir.path().join("b2.tfs")).unwrap();
+
+        let mut context = sql::TfsSqlContext::new();
+        assert_eq!(
+            context.register_file(dir.path().join("b1.tfs")).unwrap(),
+            "b1"
+        );
+        context.register_dir("optics", dir.path()).unwrap();
+        assert_eq!(context.tables(), vec!["b1", "optics"]);
+
+        let short = context
+            .query("SELECT NAME, S FROM b1 WHERE S < 10000")
+            .unwrap();
+        assert_eq!(short.column_names(), vec!["NAME", "S"]);
+        assert_eq!(short.len(), 3);
+
+        let per_file = context
+            .query("SELECT FILE, COUNT(BETY) AS N FROM optics GROUP BY FILE ORDER BY FILE")
+            .unwrap();
+        assert_eq!(
+            per_file.column("N").unwrap().u32().unwrap().to_vec(),
+            vec![Some(10), Some(0)]
+        );
+
+        let path = dir.path().join("b2.tfs").display().to_string();
+        let query = format!("SELECT NAME FROM '{}' WHERE NAME <> 'x'", path);
+        assert_eq!(context.query(&query).unwrap().len(), 4);
+        assert!(context.query("SELECT * FROM 'missing.tfs'").is_err());
+
+        // only table names are registered, not string literals naming a file
+        let query = format!("SELECT NAME FROM b1 WHERE NAME <> '{}'", path);
+        let mut fresh = sql::TfsSqlContext::new();
+        fresh.register_file(dir.path().join("b1.tfs")).unwrap();
+        assert_eq!(fresh.query(&query).unwrap().len(), 10);
+        assert_eq!(fresh.tables(), vec!["b1"]);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_archive() {
+        use polars::prelude::NamedFrom;
+        use polars::series::Series;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("campaign.sqlite");
+        let df = TfsDataFrame::<f64>::new(
+            vec![
+                ("TITLE".to_owned(), DataValue::Text("run \"3\"".to_owned())),
+                ("Q1".to_owned(), DataValue::Real(62.31)),
+                ("TURNS".to_owned(), DataValue::Integer(1024)),
+                ("COUPLED".to_owned(), DataValue::Boolean(true)),
+                (
+                    "KNOBS".to_owned(),
+                    DataValue::List(vec![DataValue::Real(1.5), DataValue::Integer(2)]),
+                ),
+            ],
+            polars::df!(
+                "NAME" => ["BPM1", "BPM2", "BPM3"],
+                "S" => [0.0, 1.5, 3.0],
+                "TURN" => [1i64, 2, 3],
+                "OK" => [true, false, true],
+            )
+            .unwrap(),
+        );
+        df.to_sqlite(&path, "beam 1").unwrap();
+        let beam2 = testing::make_frame(&testing::FrameSpec::default());
+        beam2.to_sqlite(&path, "beam2").unwrap();
+        // writing again replaces the table and its header
+        df.to_sqlite(&path, "beam 1").unwrap();
+
+        let back = TfsDataFrame::<f64>::open_sqlite(&path, "beam 1").unwrap();
+        assert!(back.diff(&df).unwrap().is_empty());
+        assert_eq!(back.properties, df.properties);
+        assert_eq!(back.df.dtypes(), df.df.dtypes());
+        let back2 = TfsDataFrame::<f64>::open_sqlite(&path, "beam2").unwrap();
+        assert_eq!(back2.properties, beam2.properties);
+        assert_eq!(back2.len(), beam2.len());
+
+        assert!(TfsDataFrame::<f64>::open_sqlite(&path, "beam3").is_err());
+
+        // NaN, type codes, NaN sentinels and the dialect survive the database
+        let mut read = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+        read.properties
+            .insert("DPP".to_owned(), DataValue::Real(f64::NAN));
+        read.set_column(Series::new("X".into(), [f64::NAN, 1.0, 2.0, 3.0, 4.0]))
+            .unwrap();
+        read.set_nan_sentinel("X", Some(-999.0));
+        read.set_dialect(Dialect::MadNg);
+        read.to_sqlite(&path, "read").unwrap();
+        let back = TfsDataFrame::<f64>::open_sqlite(&path, "read").unwrap();
+        assert!(back
+            .column("X")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .is_nan());
+        assert!(matches!(back.properties["DPP"], DataValue::Real(r) if r.is_nan()));
+        assert_eq!(back.type_codes, read.type_codes);
+        assert_eq!(back.nan_sentinels, read.nan_sentinels);
+        assert_eq!(back.dialect(), Dialect::MadNg);
+
+        let empty = TfsDataFrame::<f64>::new(vec![], polars::prelude::DataFrame::empty());
+        assert!(empty.to_sqlite(&path, "empty").is_err());
+        let missing = dir.path().join("missing.sqlite");
+        assert!(TfsDataFrame::<f64>::open_sqlite(missing, "beam2").is_err());
+    }
+
+    #[test]
+    fn markdown_and_latex() {
+        let mut df = TfsDataFrame::<f64>::new(
+            vec![
+                ("Q1".to_owned(), DataValue::Real(62.31)),
+                ("SEQUENCE".to_owned(), DataValue::Text("LHCB1".to_owned())),
+            ],
+            polars::df!(
+                "NAME" => ["BPM_1", "MQ|2", "BPM_3"],
+                "BETX" => [1.5, 1.2e-5, 100.0],
+                "N" => [1i64, 2, 3],
+            )
+            .unwrap(),
+        );
+        df.compress_column("N").unwrap();
+
+        let markdown = df
+            .to_markdown(2)
+            .unwrap()
+            .footnotes(&["Q1", "MISSING"])
+            .to_string();
+        assert_eq!(
+            markdown,
+            "| NAME | BETX | N |\n\
+             |---|---:|---:|\n\
+             | BPM_1 | 1.5 | 1 |\n\
+             | MQ\\|2 | 0.000012 | 2 |\n\
+             | ... | ... | ... |\n\
+             \n\
+             _2 of 3 rows_\n\
+             \n\
+             - Q1 = 62.31\n"
+        );
+
+        let latex = df
+            .to_latex(&["NAME", "BETX"], render::FloatFormat::Scientific(1))
+            .unwrap()
+            .footnotes(&["SEQUENCE"])
+            .to_string();
+        assert!(latex.starts_with("\\begin{tabular}{lr}\n\\toprule\nNAME & BETX \\\\\n"));
+        assert!(latex.contains("BPM\\_1 & $1.5 \\times 10^{0}$ \\\\\n"));
+        assert!(latex.contains("MQ|2 & $1.2 \\times 10^{-5}$"));
+        assert!(latex.contains("\\multicolumn{2}{l}{\\footnotesize SEQUENCE = "));
+        assert!(latex.ends_with("\\end{tabular}\n"));
+        assert!(df
+            .to_latex(&["MISSING"], render::FloatFormat::Auto)
+            .is_err());
+    }
+
+    #[test]
+    fn html_report() {
+        let mut df = testing::make_frame(&testing::FrameSpec::default());
+        df.properties.insert(
+            "TITLE".to_owned(),
+            DataValue::Text("<b>Q1 & Q2</b>".to_owned()),
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("twiss.html");
+        df.to_html_report(&path).unwrap();
+
+        let html = std::fs::read_to_string(&path).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>twiss</title>"));
+        assert!(html.contains("&lt;b&gt;Q1 &amp; Q2&lt;/b&gt;"));
+        assert!(!html.contains("<b>"));
+        assert!(html.contains("<figcaption>BETX over S"));
+        assert!(!html.contains("<figcaption>S over"));
+        assert!(!html.contains("<figcaption>NAME"));
+        assert_eq!(html.matches("<tr><td>").count(), 10);
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn join_and_concat() {
+        use join::JoinType;
+
+        let measurement = TfsDataFrame::<f64>::new(
+            vec![("TYPE".to_owned(), DataValue::Text("MEASUREMENT".to_owned()))],
+            polars::df!(
+                "NAME" => ["BPM1", "BPM2", "BPM3", "BPM2"],
+                "BETX" => [1.0, 2.0, 3.0, 2.5],
+            )
+            .unwrap(),
+        );
+        let model = TfsDataFrame::<f64>::new(
+            vec![("TYPE".to_owned(), DataValue::Text("MODEL".to_owned()))],
+            polars::df!(
+                "BETX" => [10.0, 20.0, 40.0],
+                "NAME" => ["BPM1", "BPM2", "BPM4"],
+                "MUX" => [0.1, 0.2, 0.4],
+            )
+            .unwrap(),
+        );
+
+        let inner = measurement
+            .join(&model, "NAME", JoinType::Inner, ("", "_MDL"))
+            .unwrap();
+        assert_eq!(
+            inner.column_names(),
+            vec!["NAME", "BETX", "BETX_MDL", "MUX"]
+        );
+        assert_eq!(inner.properties, measurement.properties);
+        assert_eq!(
+            String::from_column(inner.column("NAME").unwrap()).unwrap(),
+            vec!["BPM1", "BPM2", "BPM2"]
+        );
+        assert_eq!(
+            f64::from_column(inner.column("BETX_MDL").unwrap()).unwrap(),
+            vec![10.0, 20.0, 20.0]
+        );
+
+        let left = measurement
+            .join(&model, "NAME", JoinType::Left, ("_MEAS", "_MDL"))
+            .unwrap();
+        assert_eq!(left.len(), 4);
+        assert_eq!(left.column("BETX_MDL").unwrap().null_count(), 1);
+        assert!(left.column("BETX_MEAS").is_ok());
+
+        let outer = measurement
+            .join(&model, "NAME", JoinType::Outer, ("", "_MDL"))
+            .unwrap();
+        assert_eq!(outer.len(), 5);
+        assert_eq!(
+            String::from_column(outer.column("NAME").unwrap()).unwrap()[4],
+            "BPM4"
+        );
+        assert!(measurement
+            .join(&model, "S", JoinType::Inner, ("", "_MDL"))
+            .is_err());
+        assert_eq!("full".parse::<JoinType>().unwrap(), JoinType::Outer);
+
+        let renamed = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!("BETX" => [5.0], "NAME" => ["BPM5"]).unwrap(),
+        );
+        let stacked =
+            TfsDataFrame::concat(&[measurement.with_rows(measurement.df.clone()), renamed])
+                .unwrap();
+        assert_eq!(stacked.len(), 5);
+        assert_eq!(stacked.column_names(), vec!["NAME", "BETX"]);
+        assert_eq!(stacked.properties, measurement.properties);
+        assert!(TfsDataFrame::concat(&[
+            measurement.with_rows(measurement.df.clone()),
+            model.with_rows(model.df.clone())
+        ])
+        .is_err());
+
+        let diagonal = TfsDataFrame::concat_diagonal(&[measurement, model]).unwrap();
+        assert_eq!(diagonal.column_names(), vec!["NAME", "BETX", "MUX"]);
+        assert_eq!(diagonal.column("MUX").unwrap().null_count(), 4);
+    }
+
+    #[test]
+    fn filter_expression() {
+        use crate::expr::RowFilter;
+
+        let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+        let filter: RowFilter = "(L > 1 || NAME == 'BPM1') && !(NAME =~ \"^DRIFT\")"
+            .parse()
+            .unwrap();
+        assert_eq!(filter.columns(), ["L", "NAME"]);
+        assert_eq!(filter.mask(&df).unwrap(), [true, false, true, false, false]);
+
+        df.filter_expr("NAME !~ 'B1$' && S >= 0").unwrap();
+        assert_eq!(df.len(), 3);
+
+        for invalid in [
+            "S >",
+            "S > 1 &&",
+            "(S > 1",
+            "NAME =~ 5",
+            "S 1",
+            "NAME =~ '('",
+            "S + 1",
+            "S > (L > 1)",
+        ] {
+            assert!(invalid.parse::<RowFilter>().is_err(), "{}", invalid);
+        }
+        assert!(df.filter_expr("NOPE > 1").is_err());
+        assert!(df.filter_expr("NAME > 1").is_err());
+
+        // `nan` and `inf` are columns, missing values stay undecided under `!`
+        let df = TfsDataFrame::<f64>::new(
+            vec![
+                ("HALF".to_owned(), DataValue::Real(0.5)),
+                ("LABEL".to_owned(), DataValue::Text("INF".to_owned())),
+            ],
+            polars::df!(
+                "NAN" => [1.0, -1.0, 2.0],
+                "INF" => [Some(1.0), None, Some(3.0)],
+            )
+            .unwrap(),
+        );
+        let mask = |expression: &str| expression.parse::<RowFilter>().unwrap().mask(&df).unwrap();
+        assert_eq!(mask("NAN > 0"), [true, false, true]);
+        assert_eq!(mask("!(INF > 2)"), [true, false, false]);
+        assert_eq!(mask("INF > 2 || NAN < 0"), [false, true, true]);
+        assert_eq!(mask("-1.5e0 < NAN"), [true, true, true]);
+        assert_eq!(mask("|NAN| * @HALF >= INF - 2"), [true, false, true]);
+        assert_eq!(mask("@LABEL == 'INF' && -NAN < 0"), [true, false, true]);
+    }
+
+    #[test]
+    fn schema_validation() {
+        use crate::schema::{Schema, Target};
+
+        let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+        df.properties
+            .insert("ENERGY".to_owned(), DataValue::Real(6800.0));
+        let schema = Schema::from_json(
+            r#"{
+                "headers": [
+                    { "name": "PARTICLE", "kind": "text", "pattern": "^PROTON$" },
+                    { "name": "TYPE", "kind": "real" },
+                    { "name": "SEQUENCE", "min": 0 },
+                    { "name": "Q1" },
+                    { "name": "ENERGY", "kind": "real", "max": 7000, "min": 7000 },
+                    { "name": "COMMENT", "required": false }
+                ],
+                "columns": [
+                    { "name": "NAME", "pattern": "B1$" },
+                    { "name": "L", "kind": "real", "min": 0, "max": 3 },
+                    { "name": "S", "pattern": "." },
+                    { "name": "DISP" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let violations = df.validate(&schema).unwrap();
+        let messages: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "header entry 'TYPE' is %s, expected %le",
+                "header entry 'SEQUENCE' has text, a minimum or maximum needs numbers",
+                "header entry 'Q1' is missing",
+                "header entry 'ENERGY' is below the minimum 7000",
+                "column 'NAME' has 3 values not matching 'B1$', the first in row 0",
+                "column 'L' has 2 values above the maximum 3, the first in row 0",
+                "column 'S' has numbers, a pattern needs text",
+                "column 'DISP' is missing",
+            ]
+        );
+        assert_eq!(violations[3].target, Target::Header);
+        assert_eq!(violations[4].target, Target::Column);
+
+        assert!(df.validate(&Schema::default()).unwrap().is_empty());
+        assert!(Schema::from_json(r#"{ "columns": [{ "name": "S", "unit": "m" }] }"#).is_err());
+        let invalid = Schema::from_json(r#"{ "columns": [{ "name": "NAME", "pattern": "(" }] }"#);
+        assert!(df.validate(&invalid.unwrap()).is_err());
+    }
+
+    #[test]
+    fn correlation_matrix() {
+        use crate::matrix::Correlation;
+
+        let df = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "A" => [1.0, 2.0, 3.0, 4.0, f64::NAN],
+                "B" => [2.0, 4.0, 6.0, 8.0, 0.0],
+                "C" => [1.0, 10.0, 100.0, 1000.0, 0.0],
+                "D" => [-1.0, -2.0, -3.0, -5.0, 0.0],
+                "E" => [1.0, 1.0, 1.0, 1.0, 1.0],
+            )
+            .unwrap(),
+        );
+        let names = ["A", "B", "C", "D", "E"];
+
+        let pearson = df.correlation(&names, Correlation::Pearson).unwrap();
+        assert_eq!(pearson.props("METHOD"), "PEARSON");
+        let names_column = String::from_column(pearson.column("NAME").unwrap()).unwrap();
+        assert_eq!(names_column, names);
+        let b = f64::from_column(pearson.column("B").unwrap()).unwrap();
+        assert!((b[0] - 1.0).abs() < 1e-12);
+        assert!(b[2] > 0.7 && b[2] < 0.99);
+        assert!(b[4].is_nan());
+
+        let spearman = df.correlation(&names, Correlation::Spearman).unwrap();
+        let c = f64::from_column(spearman.column("C").unwrap()).unwrap();
+        assert_eq!(&c[..4], [1.0, 1.0, 1.0, -1.0]);
+
+        let path = std::env::temp_dir().join("tfs_correlation.tfs");
+        spearman.write(&path).unwrap();
+        assert_eq!(TfsDataFrame::<f64>::open(&path).unwrap().len(), 5);
+
+        assert!(df.correlation(&["A", "Z"], Correlation::Pearson).is_err());
+        assert_eq!(
+            "Spearman".parse::<Correlation>().unwrap(),
+            Correlation::Spearman
+        );
+    }
+
+    #[test]
+    fn covariance_matrix() {
+        let df = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "A" => [1.0, 2.0, 3.0, f64::NAN],
+                "B" => [2.0, 4.0, 6.0, 1.0],
+            )
+            .unwrap(),
+        );
+        let covariance = df.covariance(&["A", "B"]).unwrap();
+        assert_eq!(covariance.props("TYPE"), "COVARIANCE");
+        assert_eq!(
+            f64::from_column(covariance.column("A").unwrap()).unwrap(),
+            [1.0, 2.0]
+        );
+        assert_eq!(
+            f64::from_column(covariance.column("B").unwrap()).unwrap(),
+            [2.0, 4.0]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "linalg")]
+    fn singular_value_decomposition() {
+        let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+        let columns = ["BETX", "BETY", "DX", "MUX", "L"];
+        let svd = df.svd(&columns).unwrap();
+        assert_eq!(
+            svd.u.column_names(),
+            ["NAME", "MODE1", "MODE2", "MODE3", "MODE4", "MODE5"]
+        );
+
+        let s = f64::from_column(svd.s.column("S").unwrap()).unwrap();
+        assert!(s.windows(2).all(|w| w[0] >= w[1]));
+        // U S Vt reproduces the matrix
+        for (j, name) in columns.iter().enumerate() {
+            let original = f64::from_column(df.column(name).unwrap()).unwrap();
+            let vt = f64::from_column(svd.vt.column(name).unwrap()).unwrap();
+            for (row, value) in original.iter().enumerate() {
+                let reconstructed: f64 = (0..s.len())
+                    .map(|k| {
+                        let u = svd.u.column(&format!("MODE{}", k + 1)).unwrap();
+                        u.f64().unwrap().get(row).unwrap() * s[k] * vt[k]
+                    })
+                    .sum();
+                assert!((reconstructed - value).abs() < 1e-9 * s[0], "{} {}", j, row);
+            }
+        }
+
+        // more columns than rows
+        let wide: Vec<&str> = df.column_names().into_iter().skip(1).take(8).collect();
+        let svd = df.svd(&wide).unwrap();
+        assert_eq!(svd.s.len(), 5);
+        assert_eq!(svd.vt.column_names().len(), 9);
+    }
+
+    #[test]
+    #[cfg(feature = "linalg")]
+    fn principal_components() {
+        use polars::prelude::{Column, DataFrame, NamedFrom, Series};
+
+        // turn-by-turn data of 6 BPMs with an oscillation of phase advance 0.3 between them,
+        // offset by a closed orbit
+        let turns = 40;
+        let tune = 0.27;
+        let mut columns: Vec<Column> = vec![Series::new(
+            "NAME".into(),
+            (1..=6).map(|i| format!("BPM{}", i)).collect::<Vec<_>>(),
+        )
+        .into()];
+        let mut turn_names = Vec::new();
+        for turn in 0..turns {
+            let values: Vec<f64> = (0..6)
+                .map(|bpm| {
+                    let phase =
+                        2.0 * std::f64::consts::PI * (tune * turn as f64 + 0.3 * bpm as f64);
+                    1e-3 * bpm as f64 + 2.0 * phase.cos()
+                })
+                .collect();
+            turn_names.push(format!("TURN{}", turn));
+            columns.push(Series::new(turn_names[turn].as_str().into(), values).into());
+        }
+        let df = TfsDataFrame::<f64>::new(vec![], DataFrame::new(columns).unwrap());
+        let turn_names: Vec<&str> = turn_names.iter().map(String::as_str).collect();
+
+        let pca = df.pca(&turn_names, 3).unwrap();
+        assert_eq!(
+            pca.spatial.column_names(),
+            ["NAME", "MODE1", "MODE2", "MODE3"]
+        );
+        assert_eq!(pca.spatial.len(), 6);
+        assert_eq!(pca.amplitudes.len(), turns);
+
+        // the oscillation is a pair of modes holding all the variance
+        let ratios = f64::from_column(pca.modes.column("VARIANCE_RATIO").unwrap()).unwrap();
+        assert!((ratios[0] + ratios[1] - 1.0).abs() < 1e-9);
+        assert!(ratios[2] < 1e-9);
+
+        assert_eq!(df.pca(&turn_names, 100).unwrap().modes.len(), 6);
+        assert!(df.pca(&[], 2).is_err());
+    }
+
+    #[test]
+    fn noise_injection() {
+        use crate::noise::{NoiseDistribution, Sigma};
+        use polars::prelude::{NamedFrom, Series};
+
+        let df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 2000,
+            seed: 3,
+            ..Default::default()
+        });
+        let mut measured = df.with_rows(df.df.clone());
+        let errors: Vec<f64> = (0..df.len())
+            .map(|i| if i % 2 == 0 { 0.0 } else { 2.0 })
+            .collect();
+        measured
+            .set_column(Series::new("ERRBETX".into(), errors))
+            .unwrap();
+
+        let columns = [
+            ("BETX", Sigma::ErrorColumn(ErrorPrefix::Err)),
+            ("BETY", Sigma::Fixed(0.5)),
+        ];
+        for distribution in [NoiseDistribution::Gaussian, NoiseDistribution::Uniform] {
+            let noisy = measured.add_noise(&columns, distribution, 7).unwrap();
+            let differences = |name: &str| -> Vec<f64> {
+                let before = f64::from_column(df.column(name).unwrap()).unwrap();
+                let after = f64::from_column(noisy.column(name).unwrap()).unwrap();
+                before.iter().zip(&after).map(|(b, a)| a - b).collect()
+            };
+            let std = |values: &[f64]| {
+                (values.iter().map(|v| v * v).sum::<f64>() / values.len() as f64).sqrt()
+            };
+
+            let betx = differences("BETX");
+            assert!(betx.iter().step_by(2).all(|d| *d == 0.0));
+            let odd: Vec<f64> = betx.iter().skip(1).step_by(2).copied().collect();
+            assert!((std(&odd) - 2.0).abs() < 0.2, "{}", std(&odd));
+            assert!((std(&differences("BETY")) - 0.5).abs() < 0.05);
+            assert_eq!(noisy.column("ALFX").unwrap(), df.column("ALFX").unwrap());
+
+            let again = measured.add_noise(&columns, distribution, 7).unwrap();
+            assert_eq!(again.column("BETY").unwrap(), noisy.column("BETY").unwrap());
+        }
+
+        assert!(df
+            .add_noise(
+                &[("NAME", Sigma::Fixed(1.0))],
+                NoiseDistribution::Gaussian,
+                0
+            )
+            .is_err());
+        assert!(df
+            .add_noise(
+                &[("BETX", Sigma::ErrorColumn(ErrorPrefix::Std))],
+                NoiseDistribution::Gaussian,
+                0
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn lattice_error_table() {
+        use crate::errors::{efield_columns, LatticeErrors, ALL_CLASSES};
+        use crate::noise::NoiseDistribution;
+
+        let twiss = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+        let generator = LatticeErrors::new()
+            .error("quadrupole", "k1l", NoiseDistribution::Gaussian, 1e-4)
+            .error(ALL_CLASSES, "DPSI", NoiseDistribution::Uniform, 1e-3)
+            .error("QUADRUPOLE", "DPSI", NoiseDistribution::Uniform, 0.0);
+        let errors = generator.generate(&twiss, 1).unwrap();
+
+        assert_eq!(errors.props("TYPE"), "EFIELD");
+        assert_eq!(errors.len(), 5);
+        let columns = efield_columns();
+        assert_eq!(columns.len(), 101);
+        assert_eq!(&columns[40..44], ["K20L", "K20SL", "DX", "DY"]);
+        assert_eq!(
+            errors.column_names()[1..],
+            columns.iter().map(String::as_str).collect::<Vec<_>>()
+        );
+
+        let k1l = f64::from_column(errors.column("K1L").unwrap()).unwrap();
+        let keyword = String::from_column(twiss.column("KEYWORD").unwrap()).unwrap();
+        for (k1l, keyword) in k1l.iter().zip(&keyword) {
+            assert_eq!(*k1l != 0.0, keyword == "QUADRUPOLE");
+        }
+        let dpsi = f64::from_column(errors.column("DPSI").unwrap()).unwrap();
+        assert!(dpsi
+            .iter()
+            .all(|d| *d != 0.0 && d.abs() <= 3f64.sqrt() * 1e-3));
+        assert!(f64::from_column(errors.column("DX").unwrap())
+            .unwrap()
+            .iter()
+            .all(|d| *d == 0.0));
+
+        // readable as tfs, reproducible by the seed
+        let file = testing::write_temp(&errors).unwrap();
+        let reread = TfsDataFrame::<f64>::open(file.path()).unwrap();
+        assert_eq!(reread.column("K1L").unwrap(), errors.column("K1L").unwrap());
+        let again = generator.generate(&twiss, 1).unwrap();
+        assert_eq!(
+            again.column("DPSI").unwrap(),
+            errors.column("DPSI").unwrap()
+        );
+
+        let only_monitors = LatticeErrors::new()
+            .error("MONITOR", "DX", NoiseDistribution::Gaussian, 1e-4)
+            .generate(&twiss, 1)
+            .unwrap();
+        assert_eq!(only_monitors.len(), 1);
+        assert!(LatticeErrors::new()
+            .error("QUADRUPOLE", "K1", NoiseDistribution::Gaussian, 1e-4)
+            .generate(&twiss, 1)
+            .is_err());
+    }
+
+    #[test]
+    fn madx_compliance() {
+        use crate::madx::Compliance;
+
+        // a twiss table written by MAD-X itself passes unchanged
+        let fixture = TfsDataFrame::<f64>::open("test/madx.tfs").unwrap();
+        assert!(fixture.check_madx().is_empty());
+        let unchanged = fixture.to_madx().unwrap();
+        assert!(unchanged.diff(&fixture).unwrap().is_empty());
+        assert_eq!(unchanged.properties, fixture.properties);
+
+        let mut df = fixture.with_rows(
+            polars::df!(
+                "NAME" => ["BPM\"A\"", "B", "C"],
+                "S" => [Some(1.0), None, Some(3.0)],
+                "TURN" => [Some(1i64), None, Some(3)],
+                "VALID" => [true, false, true],
+                "my col" => [1i64, 2, 3],
+            )
+            .unwrap(),
+        );
+        df.properties.insert(
+            "ORIGIN KEY".to_owned(),
+            DataValue::Text("say\n\"hi\"".to_owned()),
+        );
+        df.derive_column("S2", &["S"], "2 * S", |v| 2.0 * v[0])
+            .unwrap();
+
+        let messages: Vec<String> = df.check_madx().iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "header entry 'ORIGIN KEY' is not a single word",
+                "header entry 'ORIGIN KEY' has a double quote or line break",
+                "column 'NAME' has a double quote or line break",
+                "column 'S' has missing values",
+                "column 'TURN' has missing values",
+                "column 'VALID' is neither real, integer nor text",
+                "column 'my col' is not a single word",
+                "column 'S2' has a lineage comment",
+            ]
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("madx.tfs");
+        assert!(df.write_madx(&path, Compliance::Check).is_err());
+        assert!(!path.exists());
+
+        df.write_madx(&path, Compliance::Enforce).unwrap();
+        let written = TfsDataFrame::<f64>::open(&path).unwrap();
+        assert!(written.check_madx().is_empty());
+        assert_eq!(
+            written.properties["ORIGIN_KEY"],
+            DataValue::Text("say 'hi'".to_owned())
+        );
+        assert_eq!(
+            String::from_column(written.column("NAME").unwrap()).unwrap(),
+            ["BPM'A'", "B", "C"]
+        );
+        assert!(f64::from_column(written.column("TURN").unwrap()).unwrap()[1].is_nan());
+        assert_eq!(
+            i64::from_column(written.column("VALID").unwrap()).unwrap(),
+            [1, 0, 1]
+        );
+        assert!(written.column("my_col").is_ok());
+
+        let twice = fixture.with_rows(polars::df!("x" => [1.0], "X" => [2.0]).unwrap());
+        assert!(twice.to_madx().is_err());
+        let mut keys = fixture.with_rows(fixture.df.clone());
+        for key in ["MY KEY", "MY_KEY"] {
+            keys.properties
+                .insert(key.to_owned(), DataValue::Integer(1));
+        }
+        assert!(keys.to_madx().is_err());
+    }
+
+    #[test]
+    fn dialects() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("madng.tfs");
+        std::fs::write(
+            &path,
+            "@ TYPE             %s \"TRACK\"\n\
+             @ CLOSED           %b true\n\
+             @ RANGE            %tbl {1, 2.5, \"a\", {false}}\n\
+             * NAME S ALIVE\n\
+             $ %s %le %b\n\
+             \"BPM1\" 1.0 true\n\
+             \"BPM2\" 2.0 false\n",
+        )
+        .unwrap();
+
+        let mut df = TfsDataFrame::<f64>::open(&path).unwrap();
+        assert_eq!(df.dialect(), Dialect::MadNg);
+        assert_eq!(df.properties["CLOSED"], DataValue::Boolean(true));
+        assert_eq!(
+            df.properties["RANGE"],
+            DataValue::List(vec![
+                DataValue::Integer(1),
+                DataValue::Real(2.5),
+                DataValue::Text("a".to_owned()),
+                DataValue::List(vec![DataValue::Boolean(false)]),
+            ])
+        );
+        let alive: Vec<Option<bool>> = df
+            .column("ALIVE")
+            .unwrap()
+            .bool()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(alive, [Some(true), Some(false)]);
+
+        let copy = dir.path().join("copy.tfs");
+        df.write(&copy).unwrap();
+        let written = std::fs::read_to_string(&copy).unwrap();
+        assert!(written.contains("@ RANGE            %tbl {1, 2.5, \"a\", {false}}"));
+        let read_again = TfsDataFrame::<f64>::open(&copy).unwrap();
+        assert_eq!(read_again.properties, df.properties);
+        assert_eq!(
+            read_again.column("ALIVE").unwrap(),
+            df.column("ALIVE").unwrap()
+        );
+
+        // MAD-X has neither booleans nor tables
+        df.set_dialect(Dialect::MadX);
+        df.write(&copy).unwrap();
+        let written = std::fs::read_to_string(&copy).unwrap();
+        assert!(written.contains("@ CLOSED           %d 1"));
+        assert!(written.contains("@ RANGE            %s \"{1, 2.5, 'a', {false}}\""));
+        let madx = TfsDataFrame::<f64>::open(&copy).unwrap();
+        assert_eq!(madx.dialect(), Dialect::MadX);
+        assert_eq!(
+            i64::from_column(madx.column("ALIVE").unwrap()).unwrap(),
+            [1, 0]
+        );
+
+        let options = TfsReadOptions::new().dialect(Dialect::MadX);
+        let df = TfsDataFrame::<f64>::open_with(&path, &options).unwrap();
+        assert_eq!(df.properties["CLOSED"], DataValue::Text("true".to_owned()));
+        assert!(df.column("ALIVE").unwrap().str().is_ok());
+        assert_eq!(
+            TfsDataFrame::<f64>::open("test/test.tfs")
+                .unwrap()
+                .dialect(),
+            Dialect::MadX
+        );
+    }
+
+    #[test]
+    fn elegant_twiss() {
+        let text = "SDDS1\n\
+            &description text=\"Twiss parameters--input: ring.ele\", contents=\"Twiss parameters\", &end\n\
+            &parameter name=Step, type=long, &end\n\
+            &parameter name=nux, symbol=\"$gn$r$bx$n\", type=double, &end\n\
+            &parameter name=dnux/dp, type=double, &end\n\
+            &parameter name=pCentral, units=\"m$be$nc\", type=double, &end\n\
+            &parameter name=Stage, type=string, &end\n\
+            &column name=s, units=m, type=double, &end\n\
+            &column name=betax, units=m, type=double, &end\n\
+            &column name=psix, units=rad, type=double, &end\n\
+            &column name=ElementName, type=string, &end\n\
+            &column name=ElementOccurence, type=long, &end\n\
+            &data mode=ascii, &end\n\
+            ! page number 1\n\
+            1\n\
+            6.25\n\
+            -1.5\n\
+            1.0e4\n\
+            \"tunes uncorrected\"\n\
+            \t\t3\n\
+            0.0 10.0 0.0 _BEG_ 1\n\
+            1.5 12.0 3.14159265358979 \"Q 1\" 1\n\
+            3.0 10.0 6.28318530717959 Q2 1\n";
+
+        let raw = TfsDataFrame::<f64>::from_sdds_str(text).unwrap();
+        assert_eq!(raw.properties["nux"], DataValue::Real(6.25));
+        assert_eq!(raw.properties["Step"], DataValue::Integer(1));
+        assert_eq!(
+            raw.properties["Stage"],
+            DataValue::Text("tunes uncorrected".to_owned())
+        );
+        assert_eq!(
+            raw.column_names(),
+            ["s", "betax", "psix", "ElementName", "ElementOccurence"]
+        );
+
+        let twiss = crate::elegant::parse_twiss::<f64>(text).unwrap();
+        assert_eq!(twiss.props("TYPE"), "TWISS");
+        assert_eq!(*twiss.propd("Q1"), 6.25);
+        assert_eq!(*twiss.propd("DQ1"), -1.5);
+        assert!((twiss.propd("PC") - 5.1099895069).abs() < 1e-9);
+        assert_eq!(twiss.props("STAGE"), "tunes uncorrected");
+        assert_eq!(
+            twiss.column_names(),
+            ["S", "BETX", "MUX", "NAME", "ELEMENTOCCURENCE"]
+        );
+        let mux = f64::from_column(twiss.column("MUX").unwrap()).unwrap();
+        assert!((mux[1] - 0.5).abs() < 1e-12 && (mux[2] - 1.0).abs() < 1e-12);
+        assert_eq!(
+            String::from_column(twiss.column("NAME").unwrap()).unwrap(),
+            ["_BEG_", "Q 1", "Q2"]
+        );
+
+        let binary = text.replace("mode=ascii", "mode=binary");
+        let err = TfsDataFrame::<f64>::from_sdds_str(&binary).unwrap_err();
+        assert!(err.to_string().contains("sddsconvert"));
+        let short = text.replace("\t\t3", "4");
+        assert!(TfsDataFrame::<f64>::from_sdds_str(&short).is_err());
+    }
+
+    #[test]
+    fn pyat_summary() {
+        let csv = ",name,s_pos,beta_x,beta_y,mu_x,mu_y,dispersion_x,comment\n\
+                   0,START,0.0,10.0,20.0,0.0,0.0,1.5,\"start, of ring\"\n\
+                   1,QF,12.5,15.0,8.0,3.141592653589793,6.283185307179586,,\n";
+        let df = crate::pyat::parse_csv::<f64>(csv).unwrap();
+        assert_eq!(
+            df.column_names(),
+            ["NAME", "S", "BETX", "BETY", "MUX", "MUY", "DX", "COMMENT"]
+        );
+        assert_eq!(df.props("TYPE"), "TWISS");
+        assert_eq!(df.props("ORIGIN"), "pyAT");
+        assert!((df.propd("Q1") - 0.5).abs() < 1e-12);
+        assert!((df.propd("Q2") - 1.0).abs() < 1e-12);
+        assert_eq!(*df.propd("LENGTH"), 12.5);
+        assert!(f64::from_column(df.column("DX").unwrap()).unwrap()[1].is_nan());
+        assert_eq!(
+            String::from_column(df.column("COMMENT").unwrap()).unwrap(),
+            ["start, of ring", ""]
+        );
+
+        let json = r#"{
+            "ringdata": { "tune": [0.31, 0.32, 0.002], "chromaticity": [1.0, 2.0], "harmonic_number": 400 },
+            "elemdata": {
+                "name": ["START", "QF"],
+                "s_pos": [0.0, 12.5],
+                "closed_orbit": [[0, 0, 0, 0, 0, 0], [1e-3, 0, 2e-3, 0, 0, 0]]
+            }
+        }"#;
+        let df = crate::pyat::parse_json::<f64>(json).unwrap();
+        assert_eq!(
+            df.column_names(),
+            [
+                "NAME",
+                "S",
+                "X",
+                "PX",
+                "Y",
+                "PY",
+                "CLOSED_ORBIT_DP",
+                "CLOSED_ORBIT_CT"
+            ]
+        );
+        assert_eq!(*df.propd("Q1"), 0.31);
+        assert_eq!(*df.propd("DQ2"), 2.0);
+        assert_eq!(df.properties["HARMON"], DataValue::Integer(400));
+        assert_eq!(
+            f64::from_column(df.column("Y").unwrap()).unwrap(),
+            [0.0, 2e-3]
+        );
+
+        let rows = r#"[{ "name": "A", "beta": [1.0, 2.0] }, { "name": "B" }]"#;
+        let df = crate::pyat::parse_json::<f64>(rows).unwrap();
+        assert!(f64::from_column(df.column("BETY").unwrap()).unwrap()[1].is_nan());
+        assert!(crate::pyat::parse_json::<f64>(r#"{ "tune": 1 }"#).is_err());
+    }
+
+    #[test]
+    fn tao_lattice() {
+        let text = "\
+# Values shown are for the Exit End of each Element:
+# Index  name                  key                    s       l    beta   alpha     phi     eta   orbit    beta     phi   orbit  Track
+#                                                                      a       a       a       a       x       b       b       y  State
+#                                                   [m]     [m]     [m]     [-]   [rad]     [m]    [mm]     [m]   [rad]    [mm]
+      0  BEGINNING             Beginning_Ele      0.000      --   12.00   0.000   0.000   0.000   0.000   12.00   0.000   0.000  Alive
+      1  D1                    Drift              1.000   1.000   12.08  -0.083   0.082   0.000   0.500   12.08   0.082  -0.250  Alive
+      2  END                   Marker             1.000   0.000   12.08  -0.083   0.082   0.000   0.500   12.08   0.082  -0.250  Alive
+# Index  name                  key                    s       l    beta   alpha     phi     eta   orbit    beta     phi   orbit  Track
+";
+        let df = crate::tao::parse_lattice::<f64>(text).unwrap();
+        assert_eq!(
+            df.column_names(),
+            [
+                "INDEX",
+                "NAME",
+                "KEYWORD",
+                "S",
+                "L",
+                "BETX",
+                "ALFX",
+                "MUX",
+                "DX",
+                "X",
+                "BETY",
+                "MUY",
+                "Y",
+                "TRACK_STATE"
+            ]
+        );
+        assert_eq!(df.props("ORIGIN"), "Tao");
+        assert_eq!(
+            df.props("COMMENT"),
+            "Values shown are for the Exit End of each Element:"
+        );
+        assert_eq!(
+            i64::from_column(df.column("INDEX").unwrap()).unwrap(),
+            [0, 1, 2]
+        );
+        let l = f64::from_column(df.column("L").unwrap()).unwrap();
+        assert!(l[0].is_nan() && l[1] == 1.0);
+        assert_eq!(f64::from_column(df.column("X").unwrap()).unwrap()[1], 5e-4);
+        let mux = f64::from_column(df.column("MUX").unwrap()).unwrap();
+        assert!((mux[1] - 0.082 / (2.0 * std::f64::consts::PI)).abs() < 1e-15);
+        assert_eq!(
+            String::from_column(df.column("KEYWORD").unwrap()).unwrap(),
+            ["Beginning_Ele", "Drift", "Marker"]
+        );
+
+        let broken = text.replace("  Alive\n      2", "\n      2");
+        assert!(crate::tao::parse_lattice::<f64>(&broken).is_err());
+    }
+
+    #[test]
+    fn format_registry() {
+        use crate::format::{FormatRegistry, TableFormat};
+        use std::path::Path;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = |name: &str, content: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+        let registry = FormatRegistry::<f64>::default();
+        let tfs = file("twiss", &std::fs::read_to_string("test/test.tfs").unwrap());
+        let sdds = file(
+            "ring.twi",
+            "SDDS1\n&column name=s, type=double, &end\n&column name=betax, type=double, &end\n\
+             &data mode=ascii, &end\n1\n0.0 12.0\n",
+        );
+        let plain_sdds = file(
+            "beam.txt",
+            "SDDS1\n&column name=x, type=double, &end\n&data mode=ascii, &end\n1\n0.5\n",
+        );
+        let pyat = file(
+            "optics.txt",
+            "{\"elemdata\": [{\"name\": \"A\", \"s_pos\": 0.0}]}",
+        );
+        let tao = file(
+            "lattice.txt",
+            "# Index  name   s\n#               [m]\n  0  BEGIN   0.0\n",
+        );
+        for (path, format, column) in [
+            (&tfs, "tfs", "BETX"),
+            (&sdds, "elegant", "BETX"),
+            (&plain_sdds, "sdds", "x"),
+            (&pyat, "pyat", "S"),
+            (&tao, "tao", "S"),
+        ] {
+            assert_eq!(registry.detect(path).unwrap().name(), format);
+            let df = TfsDataFrame::<f64>::open_auto(path).unwrap();
+            assert!(df.column(column).is_ok(), "{} has no {}", format, column);
+        }
+        assert!(registry.detect(&file("notes.txt", "some notes\n")).is_err());
+        assert!(registry.get("parquet").is_err());
+        let copy = dir.path().join("copy.tfs");
+        let df = registry.get("tfs").unwrap().read(&tfs).unwrap();
+        registry.get("tfs").unwrap().write(&df, &copy).unwrap();
+        assert!(registry.get("tao").unwrap().write(&df, &copy).is_err());
+
+        struct Notes;
+        impl TableFormat<f64> for Notes {
+            fn name(&self) -> &str {
+                "notes"
+            }
+            fn extensions(&self) -> &[&str] {
+                &["txt"]
+            }
+            fn detect(&self, head: &str) -> bool {
+                head.starts_with("some notes")
+            }
+            fn read(&self, _path: &Path) -> anyhow::Result<TfsDataFrame<f64>> {
+                Ok(TfsDataFrame::new(
+                    Vec::new(),
+                    polars::df!("NOTE" => ["some notes"])?,
+                ))
+            }
+        }
+        let mut registry = registry;
+        registry.register(Box::new(Notes));
+        assert_eq!(registry.names()[0], "notes");
+        let notes = registry.open(&dir.path().join("notes.txt")).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(registry.detect(&tao).unwrap().name(), "tao");
+    }
+
+    #[test]
+    fn column_casts() {
+        use crate::cast::CastTarget;
+        use crate::schema::Schema;
+        use polars::prelude::DataType;
+
+        let mut df = TfsDataFrame::<f64>::new(
+            vec![
+                ("NTURNS".to_owned(), DataValue::Real(1000.0)),
+                ("Q1".to_owned(), DataValue::Text("0.31".to_owned())),
+                ("Q2".to_owned(), DataValue::Real(0.32)),
+            ],
+            polars::df!(
+                "TURN" => [1.0, 2.0, 3.0],
+                "BIG" => [1.0, 1e30, -1e30],
+                "COUNT" => ["7", "x", "9"],
+                "ID" => [1i64, 5_000_000_000, 3],
+            )
+            .unwrap(),
+        );
+
+        let report = df.cast_column("TURN", DataType::Int64).unwrap();
+        assert!(report.is_lossless());
+        assert_eq!(df.column("TURN").unwrap().dtype(), &DataType::Int64);
+        let report = df.cast_column("BIG", DataType::Int64).unwrap();
+        assert_eq!((report.overflow, report.first_lost), (2, Some(1)));
+        assert_eq!(
+            report.to_string(),
+            "column 'BIG' f64 -> i64: 2 overflowing, the first in row 1"
+        );
+        let report = df.cast_column("ID", DataType::Int32).unwrap();
+        assert_eq!((report.overflow, report.first_lost), (1, Some(1)));
+        assert_eq!(
+            i64::from_column(&df.column("ID").unwrap().drop_nulls()).unwrap(),
+            [1, 3]
+        );
+        assert!(df.cast_column("MISSING", DataType::Int64).is_err());
+
+        let schema = Schema::from_json(
+            r#"{
+                "headers": [
+                    { "name": "NTURNS", "kind": "integer" },
+                    { "name": "Q1", "kind": "real" },
+                    { "name": "Q2", "kind": "integer" },
+                    { "name": "Q3", "kind": "real" }
+                ],
+                "columns": [
+                    { "name": "COUNT", "kind": "integer" },
+                    { "name": "TURN", "kind": "integer" },
+                    { "name": "S", "kind": "real" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let reports = df.coerce_schema(&schema).unwrap();
+        let names: Vec<_> = reports
+            .iter()
+            .map(|r| (r.target, r.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (CastTarget::Header, "NTURNS"),
+                (CastTarget::Header, "Q1"),
+                (CastTarget::Header, "Q2"),
+                (CastTarget::Column, "COUNT"),
+            ]
+        );
+        assert_eq!(df.properties["NTURNS"], DataValue::Integer(1000));
+        assert_eq!(df.properties["Q1"], DataValue::Real(0.31));
+        assert_eq!(df.properties["Q2"], DataValue::Real(0.32));
+        assert_eq!(reports[2].fractional, 1);
+        assert_eq!((reports[3].invalid, reports[3].first_lost), (1, Some(1)));
+        let violations: Vec<_> = df
+            .validate(&schema)
+            .unwrap()
+            .into_iter()
+            .map(|v| v.name)
+            .collect();
+        assert_eq!(violations, ["Q2", "Q3", "S"]);
+
+        let mut buffer = Vec::new();
+        df.write_to(&mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text
+            .lines()
+            .any(|l| l.starts_with("@ NTURNS") && l.ends_with("%d 1000")));
+        assert!(text.lines().any(|l| l.starts_with('$') && l.contains("%d")));
+    }
+
+    #[test]
+    fn join_validation() {
+        use join::{JoinOptions, JoinType, JoinValidation, NullKeys};
+
+        let measurement = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "NAME" => [Some("BPM1"), Some("BPM2"), None, Some("BPM2")],
+                "BETX" => [1.0, 2.0, 3.0, 2.5],
+            )
+            .unwrap(),
+        );
+        let model = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "NAME" => [Some("BPM1"), Some("BPM2"), None],
+                "BETX" => [10.0, 20.0, 30.0],
+            )
+            .unwrap(),
+        );
+        let options = JoinOptions::new().suffixes("", "_MDL");
+
+        let joined = measurement
+            .join_with(
+                &model,
+                "NAME",
+                &options.clone().validate(JoinValidation::ManyToOne),
+            )
+            .unwrap();
+        assert_eq!(joined.len(), 3);
+        let error = measurement
+            .join_with(
+                &model,
+                "NAME",
+                &options.clone().validate(JoinValidation::OneToOne),
+            )
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("'BPM2' appears 2 times in the left frame"),
+            "{}",
+            error
+        );
+        assert!(model
+            .join_with(
+                &measurement,
+                "NAME",
+                &options.clone().validate(JoinValidation::ManyToOne)
+            )
+            .is_err());
+        assert!(model
+            .join_with(
+                &measurement,
+                "NAME",
+                &options.clone().validate(JoinValidation::OneToMany)
+            )
+            .is_ok());
+
+        // null keys match nothing by default, but are kept by left joins
+        let left = measurement
+            .join_with(&model, "NAME", &options.clone().how(JoinType::Left))
+            .unwrap();
+        assert_eq!(left.len(), 4);
+        assert_eq!(left.column("BETX_MDL").unwrap().null_count(), 1);
+        let matched = measurement
+            .join_with(&model, "NAME", &options.clone().null_keys(NullKeys::Match))
+            .unwrap();
+        assert_eq!(
+            f64::from_column(matched.column("BETX_MDL").unwrap()).unwrap(),
+            [10.0, 20.0, 30.0, 20.0]
+        );
+        assert!(measurement
+            .join_with(&model, "NAME", &options.clone().null_keys(NullKeys::Error))
+            .is_err());
+
+        // two null keys on the right only collide if nulls match
+        let nulls = model
+            .with_rows(polars::df!("NAME" => [None::<&str>, None], "BETX" => [1.0, 2.0]).unwrap());
+        let one_to_one = options.clone().validate(JoinValidation::OneToOne);
+        assert!(model.join_with(&nulls, "NAME", &one_to_one).is_ok());
+        assert!(model
+            .join_with(&nulls, "NAME", &one_to_one.null_keys(NullKeys::Match))
+            .is_err());
+        assert_eq!(
+            "m:1".parse::<JoinValidation>().unwrap(),
+            JoinValidation::ManyToOne
+        );
+        assert!("n:m".parse::<JoinValidation>().is_err());
+    }
+
+    #[test]
+    fn nearest_join() {
+        use join::{JoinOptions, JoinType, JoinValidation, NullKeys};
+
+        let madx = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "NAME" => ["MQ.1", "MQ.2", "MQ.3", "MB.1"],
+                "S" => [0.5, 10.0, 20.0, f64::NAN],
+                "BETX" => [1.0, 2.0, 3.0, 4.0],
+            )
+            .unwrap(),
+        );
+        let elegant = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "NAME" => ["Q3", "Q1", "Q2", "D1"],
+                "S" => [20.001, 0.5, 9.9, 15.0],
+                "BETX" => [3.1, 1.1, 2.1, 0.0],
+            )
+            .unwrap(),
+        );
+
+        let joined = madx.join_asof_on_s(&elegant, 0.01).unwrap();
+        assert_eq!(
+            joined.column_names(),
+            ["NAME", "S", "BETX", "NAME_right", "S_right", "BETX_right"]
+        );
+        assert_eq!(
+            Option::<String>::from_column(joined.column("NAME_right").unwrap()).unwrap(),
+            [Some("Q1".to_owned()), None, Some("Q3".to_owned()), None]
+        );
+
+        let options = JoinOptions::new().suffixes("", "_ELE");
+        let wide = madx.join_nearest(&elegant, "S", 0.5, &options).unwrap();
+        assert_eq!(
+            String::from_column(wide.column("NAME_ELE").unwrap()).unwrap(),
+            ["Q1", "Q2", "Q3"]
+        );
+        let outer = madx
+            .join_nearest(&elegant, "S", 0.5, &options.clone().how(JoinType::Outer))
+            .unwrap();
+        assert_eq!(outer.len(), 5);
+        assert!(madx
+            .join_nearest(
+                &elegant,
+                "S",
+                0.5,
+                &options.clone().null_keys(NullKeys::Error)
+            )
+            .is_err());
+
+        // with a large tolerance Q3 is the nearest to MQ.3 and to MB.1 at 25 m
+        let shifted =
+            madx.with_rows(polars::df!("NAME" => ["MQ.3", "MB.1"], "S" => [20.0, 25.0]).unwrap());
+        let one_to_one = options.validate(JoinValidation::OneToOne);
+        assert!(shifted
+            .join_nearest(&elegant, "S", 1.0, &one_to_one)
+            .is_ok());
+        assert!(shifted
+            .join_nearest(&elegant, "S", 10.0, &one_to_one)
+            .is_err());
+    }
+
+    #[test]
+    fn cross_join_grid() {
+        use polars::prelude::{NamedFrom, Series};
+
+        let knobs = TfsDataFrame::<f64>::new(
+            vec![("TYPE".to_owned(), DataValue::Text("SCAN".to_owned()))],
+            polars::df!("KNOB" => ["KQF", "KQD"], "VALUE" => [0.1, 0.2]).unwrap(),
+        );
+        let seeds = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!("SEED" => [1i64, 2, 3], "VALUE" => [7.0, 8.0, 9.0]).unwrap(),
+        );
+        let grid = knobs.cross_join(&seeds).unwrap();
+        assert_eq!(grid.len(), 6);
+        assert_eq!(grid.properties, knobs.properties);
+        assert_eq!(
+            grid.column_names(),
+            ["KNOB", "VALUE", "SEED", "VALUE_right"]
+        );
+        assert_eq!(
+            String::from_column(grid.column("KNOB").unwrap()).unwrap(),
+            ["KQF", "KQF", "KQF", "KQD", "KQD", "KQD"]
+        );
+        assert_eq!(
+            i64::from_column(grid.column("SEED").unwrap()).unwrap(),
+            [1, 2, 3, 1, 2, 3]
+        );
+        let empty = seeds.with_rows(seeds.df.clear());
+        assert_eq!(knobs.cross_join(&empty).unwrap().len(), 0);
+
+        let scan = TfsDataFrame::<f64>::cartesian(vec![
+            Series::new("KQF".into(), [0.1, 0.2]),
+            Series::new("KQD".into(), [-0.1, -0.2]),
+            Series::new("SEED".into(), [1i64, 2, 3]),
+        ])
+        .unwrap();
+        assert_eq!(scan.len(), 12);
+        assert_eq!(
+            f64::from_column(scan.column("KQD").unwrap()).unwrap()[..4],
+            [-0.1, -0.1, -0.1, -0.2]
+        );
+        assert!(TfsDataFrame::<f64>::cartesian(vec![]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn multi_key_join() {
+        use join::{JoinOptions, JoinValidation};
+
+        let sliced = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "NAME" => ["MQ.1", "MQ.1", "MQ.2", "MQ.2"],
+                "SLICE" => [Some(0i64), Some(1), Some(0), None],
+                "BETX" => [1.0, 1.5, 2.0, 2.5],
+            )
+            .unwrap(),
+        );
+        let model = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "SLICE" => [1i64, 0, 0],
+                "NAME" => ["MQ.1", "MQ.1", "MQ.2"],
+                "BETX" => [15.0, 10.0, 20.0],
+            )
+            .unwrap(),
+        );
+
+        let options = JoinOptions::new()
+            .suffixes("", "_MDL")
+            .validate(JoinValidation::OneToOne);
+        let joined = sliced
+            .join_with(&model, &["NAME", "SLICE"], &options)
+            .unwrap();
+        assert_eq!(joined.column_names(), ["NAME", "SLICE", "BETX", "BETX_MDL"]);
+        assert_eq!(
+            f64::from_column(joined.column("BETX_MDL").unwrap()).unwrap(),
+            [10.0, 15.0, 20.0]
+        );
+        let error = sliced.join_with(&model, "NAME", &options).unwrap_err();
+        assert!(
+            error.to_string().contains("'MQ.1' appears 2 times"),
+            "{}",
+            error
+        );
+
+        let left = sliced
+            .join(
+                &model,
+                &vec!["NAME", "SLICE"],
+                join::JoinType::Left,
+                ("", "_MDL"),
+            )
+            .unwrap();
+        assert_eq!(left.len(), 4);
+        assert_eq!(left.column("SLICE").unwrap().null_count(), 1);
+        let empty: [&str; 0] = [];
+        assert!(sliced.join_with(&model, &empty, &options).is_err());
+
+        // a null in one key column keeps the other values of the key apart
+        let unsliced = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "NAME" => ["MQ.1", "MQ.2"],
+                "SLICE" => [Option::<i64>::None, None],
+                "BETX" => [10.0, 20.0],
+            )
+            .unwrap(),
+        );
+        let options = JoinOptions::new()
+            .suffixes("", "_MDL")
+            .null_keys(join::NullKeys::Match);
+        let matched = sliced
+            .join_with(&unsliced, &["NAME", "SLICE"], &options)
+            .unwrap();
+        assert_eq!(
+            f64::from_column(matched.column("BETX_MDL").unwrap()).unwrap(),
+            [20.0]
+        );
+    }
+
+    #[test]
+    fn labeled_concat() {
+        let beam = |betx: [f64; 2]| {
+            TfsDataFrame::<f64>::new(
+                vec![("SEQUENCE".to_owned(), DataValue::Text("LHCB1".to_owned()))],
+                polars::df!("NAME" => ["BPM1", "BPM2"], "BETX" => betx).unwrap(),
+            )
+        };
+        let frames = [beam([1.0, 2.0]), beam([3.0, 4.0]), beam([5.0, 6.0])];
+
+        let seeds = TfsDataFrame::concat_labeled(&frames, "SEED", &[1i64, 2, 3]).unwrap();
+        assert_eq!(seeds.column_names(), ["SEED", "NAME", "BETX"]);
+        assert_eq!(
+            i64::from_column(seeds.column("SEED").unwrap()).unwrap(),
+            [1, 1, 2, 2, 3, 3]
+        );
+        assert_eq!(seeds.properties, frames[0].properties);
+
+        let labels = ["B1".to_owned(), "B2".to_owned(), "B2".to_owned()];
+        let beams = TfsDataFrame::concat_labeled(&frames, "BEAM", &labels).unwrap();
+        assert_eq!(
+            String::from_column(beams.column("BEAM").unwrap()).unwrap()[2],
+            "B2"
+        );
+        assert!(TfsDataFrame::concat_labeled(&frames, "SEED", &[1i64, 2]).is_err());
+        assert!(TfsDataFrame::concat_labeled(&frames, "BETX", &[1i64, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn partitions() {
+        let df = TfsDataFrame::<f64>::new(
+            vec![("TYPE".to_owned(), DataValue::Text("TWISS".to_owned()))],
+            polars::df!(
+                "NAME" => ["MQ.1", "MB.1", "BPM.1", "MQ.2", "MARKER"],
+                "KEYWORD" => [Some("QUADRUPOLE"), Some("SBEND"), Some("MONITOR"), Some("QUADRUPOLE"), None],
+                "S" => [1.0, 2.0, 3.0, 4.0, 5.0],
+            )
+            .unwrap(),
+        );
+        let parts = df.partition_by("KEYWORD").unwrap();
+        let names: Vec<_> = parts
+            .iter()
+            .map(|(name, part)| (name.as_str(), part.len()))
+            .collect();
+        assert_eq!(
+            names,
+            [("QUADRUPOLE", 2), ("SBEND", 1), ("MONITOR", 1), ("", 1)]
+        );
+        assert_eq!(parts[0].1.properties, df.properties);
+        assert_eq!(
+            f64::from_column(parts[0].1.column("S").unwrap()).unwrap(),
+            [1.0, 4.0]
+        );
+
+        let labels: Vec<String> = parts.iter().map(|(name, _)| name.clone()).collect();
+        let frames: Vec<_> = parts.into_iter().map(|(_, part)| part).collect();
+        let stacked = TfsDataFrame::concat_labeled(&frames, "GROUP", &labels).unwrap();
+        assert_eq!(stacked.len(), df.len());
+        assert!(df.partition_by("MISSING").is_err());
+        let by_s = df.partition_by("S").unwrap();
+        assert_eq!(by_s[0].0, "1.0");
+    }
+
+    #[test]
+    fn duplicate_rows() {
+        use dedup::Keep;
+
+        let merged = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "NAME" => [Some("BPM1"), Some("BPM2"), Some("BPM1"), None, None, Some("BPM1")],
+                "X" => [0.1, 0.2, 0.1, 0.4, 0.5, 0.3],
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            merged.duplicated(None).unwrap(),
+            [false, false, true, false, true, true]
+        );
+        assert_eq!(
+            merged.duplicated(Some(&["NAME", "X"])).unwrap(),
+            [false, false, true, false, false, false]
+        );
+        assert_eq!(merged.duplicated(Some(&[])).unwrap(), [false; 6]);
+        assert!(merged.duplicated(Some(&["Y"])).is_err());
+
+        let mut last = merged.with_rows(merged.df.clone());
+        assert_eq!(last.drop_duplicates(None, Keep::Last).unwrap(), 3);
+        assert_eq!(
+            f64::from_column(last.column("X").unwrap()).unwrap(),
+            [0.2, 0.5, 0.3]
+        );
+        let mut none = merged.with_rows(merged.df.clone());
+        assert_eq!(none.drop_duplicates(None, Keep::None).unwrap(), 5);
+        assert_eq!(
+            String::from_column(none.column("NAME").unwrap()).unwrap(),
+            ["BPM2"]
+        );
+
+        // without a NAME column all columns are compared
+        let mut values = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!("X" => [1.0, 1.0, f64::NAN, f64::NAN], "Y" => [1, 2, 3, 3]).unwrap(),
+        );
+        assert_eq!(values.drop_duplicates(None, Keep::First).unwrap(), 1);
+        assert_eq!(values.len(), 3);
+    }
+
+    #[test]
+    fn value_mapping() {
+        use std::collections::HashMap;
+
+        let mut df = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "NAME" => [Some("BPM.12L1.B1"), Some("MQ.12L1.B1"), None, Some("IP1")],
+                "KEYWORD" => ["MONITOR", "QUADRUPOLE", "DRIFT", "MARKER"],
+                "S" => [1.0, 2.0, 3.0, 4.0],
+            )
+            .unwrap(),
+        );
+        let mapping = HashMap::from([
+            ("MONITOR".to_owned(), "BPM".to_owned()),
+            ("QUADRUPOLE".to_owned(), "QUAD".to_owned()),
+        ]);
+        assert_eq!(df.map_values("KEYWORD", &mapping).unwrap(), 2);
+        assert_eq!(
+            String::from_column(df.column("KEYWORD").unwrap()).unwrap(),
+            ["BPM", "QUAD", "DRIFT", "MARKER"]
+        );
+
+        assert_eq!(
+            df.replace_in_column("NAME", r"^(\w+)\.(\w+)\.B1$", "$1.$2.B2")
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            Option::<String>::from_column(df.column("NAME").unwrap()).unwrap(),
+            [
+                Some("BPM.12L1.B2".to_owned()),
+                Some("MQ.12L1.B2".to_owned()),
+                None,
+                Some("IP1".to_owned())
+            ]
+        );
+        assert!(df.replace_in_column("NAME", "(", "").is_err());
+        assert!(df.map_values("S", &mapping).is_err());
+        assert!(df.map_values("MISSING", &mapping).is_err());
+    }
+
+    #[test]
+    fn string_operations() {
+        let mut df = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "NAME" => [Some("bpm.12l1.b1"), Some("mq.12l1.b1"), None, Some("ip1")],
+                "S" => [1.0, 2.0, 3.0, 4.0],
+            )
+            .unwrap(),
+        );
+        let names = df.text("NAME").unwrap();
+        let upper = names.to_uppercase();
+        assert_eq!(upper.name().as_str(), "NAME");
+        assert_eq!(
+            Option::<String>::from_column(&upper).unwrap()[0].as_deref(),
+            Some("BPM.12L1.B1")
+        );
+        assert_eq!(
+            Option::<String>::from_column(&names.strip_suffix(".b1")).unwrap(),
+            [
+                Some("bpm.12l1".to_owned()),
+                Some("mq.12l1".to_owned()),
+                None,
+                Some("ip1".to_owned())
+            ]
+        );
+        assert_eq!(
+            Option::<String>::from_column(&names.strip_prefix("bpm.")).unwrap()[0].as_deref(),
+            Some("12l1.b1")
+        );
+        let arcs = names.extract(r"\.(\d+)l(\d)", 2).unwrap();
+        assert_eq!(
+            Option::<String>::from_column(&arcs).unwrap(),
+            [Some("1".to_owned()), Some("1".to_owned()), None, None]
+        );
+        assert!(names.extract(r"\.(\d+)", 2).is_err());
+        assert!(names.contains("(").is_err());
+        assert!(df.text("S").is_err());
+
+        let mask = names.contains(r"^(bpm|mq)\.").unwrap();
+        assert_eq!(mask, [true, true, false, false]);
+        df.set_column(upper).unwrap();
+        df.filter_mask(&mask).unwrap();
+        assert_eq!(
+            String::from_column(df.column("NAME").unwrap()).unwrap(),
+            ["BPM.12L1.B1", "MQ.12L1.B1"]
+        );
+        assert!(df.filter_mask(&[true]).is_err());
+    }
+
+    #[test]
+    fn name_translation() {
+        use naming::{NameChain, NameRule, NameTable, NameTranslator};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("names.tfs");
+        let table = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "NAME" => ["BPM.12L1.B1", "MQ.12L1.B1"],
+                "LSA_NAME" => ["LHC.BPM.12L1.B1", "RQ.12L1.B1"],
+            )
+            .unwrap(),
+        );
+        table.write(&path).unwrap();
+        let lsa = NameTable::open(&path, "NAME", "LSA_NAME").unwrap();
+        assert_eq!(lsa.translate("MQ.12L1.B1").as_deref(), Some("RQ.12L1.B1"));
+        assert_eq!(
+            lsa.inverse().translate("RQ.12L1.B1").as_deref(),
+            Some("MQ.12L1.B1")
+        );
+
+        let mut df = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "NAME" => [Some("BPM.12L1.B1"), Some("MQ.12L1.B1"), Some("MCB.12L1.B1"), None, Some("IP1"), Some("IP1")],
+                "S" => [1.0, 2.0, 3.0, 4.0, 5.0, 6.0],
+            )
+            .unwrap(),
+        );
+        let translator = NameChain::new()
+            .with(lsa)
+            .with(NameRule::new(r"^MCB\.(\w+)\.B1$", "RCB.$1.B1").unwrap());
+        let report = df.translate_names(&translator).unwrap();
+        assert_eq!(report.translated, 3);
+        assert_eq!(report.unmatched, ["IP1"]);
+        assert!(!report.is_complete());
+        assert_eq!(report.to_string(), "3 names translated, 1 unknown: IP1");
+        assert_eq!(
+            Option::<String>::from_column(df.column("NAME").unwrap()).unwrap()[..3],
+            [
+                Some("LHC.BPM.12L1.B1".to_owned()),
+                Some("RQ.12L1.B1".to_owned()),
+                Some("RCB.12L1.B1".to_owned())
+            ]
+        );
+
+        let lower = |name: &str| Some(name.to_lowercase());
+        assert!(df.translate_names(&lower).unwrap().is_complete());
+        assert!(df.translate_column("S", &lower).is_err());
+        assert!(NameRule::new("(", "").is_err());
+    }
+
+    #[test]
+    fn snapshots() {
+        use testing::{compare_snapshot, make_frame, FrameSpec, UPDATE_SNAPSHOTS};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshots").join("synthetic.tfs");
+        let df = make_frame(&FrameSpec::default());
+        let error = compare_snapshot(&df, &path, 1e-9).unwrap_err();
+        assert!(error.to_string().contains(UPDATE_SNAPSHOTS));
+
+        std::env::set_var(UPDATE_SNAPSHOTS, "1");
+        compare_snapshot(&df, &path, 1e-9).unwrap();
+        std::env::remove_var(UPDATE_SNAPSHOTS);
+        assert!(path.exists());
+        crate::assert_tfs_eq!(df, &path);
+
+        let mut close = make_frame(&FrameSpec::default());
+        let betx: Vec<f64> = f64::from_column(close.column("BETX").unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|b| b * (1.0 + 1e-12))
+            .collect();
+        close.set_column(f64::to_column("BETX", betx)).unwrap();
+        crate::assert_tfs_eq!(close, &path, rtol = 1e-9);
+        assert!(compare_snapshot(&close, &path, 0.0).is_err());
+
+        let mut changed = make_frame(&FrameSpec::default());
+        changed
+            .properties
+            .insert("Q1".to_owned(), DataValue::Real(62.28));
+        changed.properties.shift_remove("TYPE");
+        changed
+            .properties
+            .insert("NEW".to_owned(), DataValue::Integer(1));
+        let message = compare_snapshot(&changed, &path, 1e-9)
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("in 3 places"), "{}", message);
+        assert!(
+            message.contains("header entry Q1 is 62.28, expected 62.31"),
+            "{}",
+            message
+        );
+
+        let noisy = make_frame(&FrameSpec {
+            noise: 1e-3,
+            ..Default::default()
+        });
+        let message = compare_snapshot(&noisy, &path, 1e-9)
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("BETX[0]"), "{}", message);
+        assert!(message.contains("... and 10 more"), "{}", message);
+    }
+
+    #[test]
+    fn fuzz_parser() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        use testing::{arbitrary_frame, write_temp};
+
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..200 {
+            let df = arbitrary_frame(&mut rng, 8, 6);
+            let file = write_temp(&df).unwrap();
+            testing::compare_snapshot(&df, file.path(), 0.0).unwrap();
+        }
+
+        if let Some(input) = testing::fuzz_parser(0, 2000) {
+            panic!(
+                "the parser panicked on\n{}",
+                String::from_utf8_lossy(&input)
+            );
+        }
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn arbitrary_frames(df in proptest::arbitrary::any::<TfsDataFrame<f64>>(), seed: u64) {
+            use rand::SeedableRng;
+
+            let file = testing::write_temp(&df).unwrap();
+            testing::compare_snapshot(&df, file.path(), 0.0).unwrap();
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            let corrupted = testing::corrupt(&mut rng, &std::fs::read(file.path()).unwrap());
+            let _ = testing::parse_bytes(&corrupted);
+        }
+    }
+
+    #[test]
+    fn fallible_accessors() {
+        let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+        assert_eq!(df.try_props("SEQUENCE").unwrap(), df.props("SEQUENCE"));
+        assert!(df
+            .try_propd("SEQUENCE")
+            .unwrap_err()
+            .to_string()
+            .contains("not a real value"));
+        assert!(df
+            .try_propd("NO_SUCH_KEY")
+            .unwrap_err()
+            .to_string()
+            .contains("no entry"));
+
+        assert_eq!(DataValue::<f64>::Real(1.5).try_into_real().unwrap(), 1.5);
+        assert!(DataValue::<f64>::Integer(1).try_into_text().is_err());
+        let text = String::from("QF");
+        assert!(DataView::<f64>::Text(&text).try_real().is_err());
+        assert_eq!(DataView::<f64>::Text(&text).try_text().unwrap(), "QF");
+
+        let a = DataVector::RealVector(vec![1.0, 2.0]);
+        let b = DataVector::RealVector(vec![0.5, 0.5]);
+        assert_eq!(a.try_sub(&b).unwrap(), &a - &b);
+        assert_eq!(a.try_add(&b).unwrap().try_reals().unwrap(), &vec![1.5, 2.5]);
+        let short = DataVector::RealVector(vec![1.0]);
+        assert!(a.try_add(&short).is_err());
+        let names = DataVector::<f64>::TextVector(vec!["QF".to_owned(), "QD".to_owned()]);
+        assert!(a.try_sub(&names).is_err());
+        assert_eq!(names.try_texts().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn resource_limits() {
+        let df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 1000,
+            ..Default::default()
+        });
+        let file = testing::write_temp(&df).unwrap();
+
+        let options = TfsReadOptions::new().max_memory(10_000);
+        let error = TfsDataFrame::<f64>::open_with(file.path(), &options).unwrap_err();
+        assert!(error.to_string().contains("limit of 10000 bytes"));
+        let options = TfsReadOptions::new().max_memory(1 << 20);
+        assert_eq!(
+            TfsDataFrame::<f64>::open_with(file.path(), &options)
+                .unwrap()
+                .len(),
+            1000
+        );
+
+        let paths = vec![file.path().to_owned(); 4];
+        let report = pipeline::TfsPipeline::new()
+            .options(TfsReadOptions::new().max_threads(1))
+            .run_all(&paths);
+        assert_eq!(report.processed.len(), 4);
+    }
+
+    #[test]
+    fn spilled_frames() {
+        let df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 1000,
+            ..Default::default()
+        });
+        let file = testing::write_temp(&df).unwrap();
+
+        let spilled = SpilledFrame::<f64>::open(file.path(), 128).unwrap();
+        assert_eq!((spilled.len(), spilled.n_chunks()), (1000, 8));
+        assert_eq!(spilled.column_names(), df.column_names());
+        assert_eq!(spilled.properties, df.properties);
+        assert!(spilled
+            .column("BETX")
+            .unwrap()
+            .equals_missing(df.column("BETX").unwrap()));
+        assert_eq!(spilled.chunk(7).unwrap().len(), 1000 - 7 * 128);
+        assert_eq!(spilled.select(&["NAME", "S"]).unwrap().len(), 1000);
+        assert!(spilled.column("NOPE").is_err());
+        assert!(spilled.into_frame().unwrap().diff(&df).unwrap().is_empty());
+
+        let spilled = SpilledFrame::from_frame(testing::roundtrip(&df).unwrap(), 300).unwrap();
+        let rows: Vec<usize> = spilled.chunks().map(|c| c.unwrap().len()).collect();
+        assert_eq!(rows, vec![300, 300, 300, 100]);
+        assert!(spilled.into_frame().unwrap().diff(&df).unwrap().is_empty());
+    }
+
+    #[test]
+    fn streaming_summary() {
+        use aggregate::{Count, CountWhere, Max, Mean, Min, Sum, Summary};
+
+        let df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 20000,
+            ..Default::default()
+        });
+        let file = testing::write_temp(&df).unwrap();
+        let summary = || {
+            Summary::new()
+                .with(Count::rows())
+                .with(Sum::of("S"))
+                .with(Mean::of("BETX"))
+                .with(Min::of("BETY"))
+                .with(Max::of("BETY"))
+                .with(CountWhere::of("S", |s| s >= 0.0))
+                .with(CountWhere::of("BETX", |b| b > 100.0))
+        };
+
+        let streamed = summary().read::<f64, _>(file.path()).unwrap();
+        let names = String::from_column(streamed.column("NAME").unwrap()).unwrap();
+        assert_eq!(names[..3], ["COUNT", "SUM(S)", "MEAN(BETX)"]);
+        let values = f64::from_column(streamed.column("VALUE").unwrap()).unwrap();
+
+        let (betx, bety) = (df.stats("BETX").unwrap(), df.stats("BETY").unwrap());
+        assert_eq!(values[0], 20000.0);
+        assert!((values[2] - betx.mean).abs() < 1e-9 * betx.mean);
+        assert_eq!((values[3], values[4]), (bety.min, bety.max));
+        assert_eq!(values[5], 20000.0);
+        let large = f64::from_column(df.column("BETX").unwrap())
+            .unwrap()
+            .into_iter()
+            .filter(|b| *b > 100.0)
+            .count();
+        assert_eq!(values[6], large as f64);
+
+        let in_memory = summary().of_frame(&df).unwrap();
+        assert_eq!(in_memory.column_names(), streamed.column_names());
+        assert!(summary().with(Mean::of("NOPE")).of_frame(&df).is_err());
+    }
+
+    #[test]
+    fn checksums() {
+        let df = testing::make_frame(&testing::FrameSpec::default());
+        let mut reloaded = testing::roundtrip(&df).unwrap();
+        assert_eq!(
+            reloaded.frame_fingerprint().unwrap(),
+            df.frame_fingerprint().unwrap()
+        );
+        let betx = df.column_hash("BETX").unwrap();
+        reloaded.compress_column("BETX").unwrap();
+        assert_eq!(reloaded.column_hash("BETX").unwrap(), betx);
+
+        let mut changed = testing::roundtrip(&df).unwrap();
+        let mut values = f64::from_column(changed.column("BETX").unwrap()).unwrap();
+        values[3] += 1e-12;
+        changed.set_column(f64::to_column("BETX", values)).unwrap();
+        assert_ne!(changed.column_hash("BETX").unwrap(), betx);
+        assert_eq!(
+            changed.column_hash("BETY").unwrap(),
+            df.column_hash("BETY").unwrap()
+        );
+        assert_ne!(
+            changed.frame_fingerprint().unwrap(),
+            df.frame_fingerprint().unwrap()
+        );
+
+        let mut header = testing::roundtrip(&df).unwrap();
+        header
+            .properties
+            .insert("Q1".to_owned(), DataValue::Real(0.31));
+        assert_ne!(
+            header.frame_fingerprint().unwrap(),
+            df.frame_fingerprint().unwrap()
+        );
+
+        let hash = |values: Vec<f64>| {
+            TfsDataFrame::<f64>::new(Vec::new(), polars::df!("X" => values).unwrap())
+                .column_hash("X")
+                .unwrap()
+        };
+        assert_eq!(hash(vec![0.0, f64::NAN]), hash(vec![-0.0, -f64::NAN]));
+        assert_ne!(hash(vec![0.0, 1.0]), hash(vec![1.0, 0.0]));
+        assert!(df.column_hash("NOPE").is_err());
+    }
+
+    #[test]
+    fn precision_loss() {
+        let mut df = TfsDataFrame::<f64>::new(
+            Vec::new(),
+            polars::df!("S" => [1.0, 23.5129, 1.5e-7], "X" => [0.25, 0.5, 0.75]).unwrap(),
+        );
+        df.set_precision("S", Some(Precision::new(3, 1e-9)));
+        df.set_precision("X", Some(Precision::new(2, 1e-9)));
+        let losses = df.precision_losses().unwrap();
+        assert_eq!(losses.len(), 1);
+        assert_eq!((losses[0].values, losses[0].first_row), (1, 1));
+        assert_eq!(losses[0].needed_digits, 6);
+        let dir = tempfile::tempdir().unwrap();
+        let written = df.write_checked(dir.path().join("lossy.tfs")).unwrap();
+        assert_eq!(written, losses);
+
+        // rows count missing values
+        let mut gaps = TfsDataFrame::<f64>::new(
+            Vec::new(),
+            polars::df!("S" => [None, Some(1.0), Some(23.5129)]).unwrap(),
+        );
+        gaps.set_precision("S", Some(Precision::new(3, 1e-9)));
+        assert_eq!(gaps.precision_losses().unwrap()[0].first_row, 2);
+
+        let mut written = Vec::new();
+        df.write_to(&mut written).unwrap();
+        let text = String::from_utf8(written).unwrap();
+        assert!(text.contains(" 2.35e1 ") && text.contains(" 2.5e-1\n"));
+        let lossy = testing::parse_bytes(text.as_bytes()).unwrap();
+        assert_eq!(
+            f64::from_column(lossy.column("S").unwrap()).unwrap()[1],
+            23.5
+        );
+
+        df.set_precision("S", Some(Precision::new(3, 1e-9).escalating()));
+        let mut written = Vec::new();
+        df.write_to(&mut written).unwrap();
+        let escalated = testing::parse_bytes(&written).unwrap();
+        let s = f64::from_column(escalated.column("S").unwrap()).unwrap();
+        assert_eq!(s, vec![1.0, 23.5129, 1.5e-7]);
+        assert!(String::from_utf8(written).unwrap().contains(" 1.00000e0 "));
+
+        df.set_precision("S", None);
+        assert!(df.precision_losses().unwrap().is_empty());
+    }
+
+    #[test]
+    fn header_lists() {
+        let mut df = testing::make_frame(&testing::FrameSpec::default());
+        df.set_prop_list("KNOBS", &["KQ1.L1".to_owned(), "KQ2.L1".to_owned()], ", ")
+            .unwrap();
+        df.set_prop_list("TUNES", &[0.31, 0.32, 1e-17], ",")
+            .unwrap();
+        df.set_prop_list::<f64>("EMPTY", &[], ",").unwrap();
+        assert!(df.set_prop_list("BAD", &["A,B".to_owned()], ",").is_err());
+
+        let reloaded = testing::roundtrip(&df).unwrap();
+        let knobs: Vec<String> = reloaded.prop_list("KNOBS", ",").unwrap();
+        assert_eq!(knobs, ["KQ1.L1", "KQ2.L1"]);
+        assert_eq!(
+            reloaded.prop_list::<f64>("TUNES", ",").unwrap(),
+            [0.31, 0.32, 1e-17]
+        );
+        assert!(reloaded.prop_list::<f64>("EMPTY", ",").unwrap().is_empty());
+        assert!(reloaded.prop_list::<f64>("KNOBS", ",").is_err());
+        assert!(reloaded.prop_list::<f64>("MISSING", ",").is_err());
+
+        df.properties.insert(
+            "TABLE".to_owned(),
+            DataValue::List(vec![DataValue::Real(1.5), DataValue::Integer(2)]),
+        );
+        assert_eq!(df.prop_list::<f64>("TABLE", ",").unwrap(), [1.5, 2.0]);
+        assert!(df.prop_list::<String>("TABLE", ",").is_err());
+    }
+
+    #[test]
+    fn header_formulas() {
+        let mut df = TfsDataFrame::<f64>::new(
+            vec![
+                ("ENERGY".to_owned(), DataValue::Real(450.0)),
+                ("TURNS".to_owned(), DataValue::Integer(4)),
+                ("SEQUENCE".to_owned(), DataValue::Text("LHCB1".to_owned())),
+            ],
+            polars::df!(
+                "X" => [Some(1.0), None, Some(-2.0)],
+                "Y" => [2.0, 4.0, 8.0],
+                "NAME" => ["A", "B", "C"]
+            )
+            .unwrap(),
+        );
+        let formula: formula::Formula = "-(X + Y) / @TURNS * 2.5e-1 - @TURNS".parse().unwrap();
+        assert_eq!(formula.columns(), ["X", "Y"]);
+        assert_eq!(formula.properties(), ["TURNS"]);
+        let values = formula.evaluate(&df).unwrap();
+        assert_eq!(values, [Some(-4.1875), None, Some(-4.375)]);
+
+        df.eval_column("Y_GEV", "Y * @ENERGY").unwrap();
+        assert_eq!(
+            f64::from_column(df.column("Y_GEV").unwrap()).unwrap(),
+            [900.0, 1800.0, 3600.0]
+        );
+        assert_eq!(df.lineage("Y_GEV").unwrap().inputs, ["Y"]);
+        df.scale_column("Y", formula::prop("TURNS")).unwrap();
+        df.scale_column("Y", 0.5).unwrap();
+        assert_eq!(
+            f64::from_column(df.column("Y").unwrap()).unwrap(),
+            [4.0, 8.0, 16.0]
+        );
+
+        assert!(df.eval("X * @SEQUENCE").is_err());
+        assert!(df.eval("X * @MISSING").is_err());
+        assert!(df.eval("NAME + 1").is_err());
+        assert!(df.eval("(X + 1").is_err());
+        assert!(df.eval("X Y").is_err());
+        assert!(df.eval("X * @").is_err());
+    }
+
+    #[test]
+    fn resonances() {
+        let lines = resonance::resonance_lines(0.28, 0.31, 0..=3);
+        assert_eq!(lines.len(), 2 + 4 + 6);
+        let names: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(
+            names,
+            [
+                "Qy", "Qx", "2Qy", "Qx+Qy", "Qx-Qy", "2Qx", "3Qy", "Qx+2Qy", "Qx-2Qy", "2Qx+Qy",
+                "2Qx-Qy", "3Qx"
+            ]
+        );
+        assert!(lines.iter().all(|l| (0.0..=0.5).contains(&l.frequency)));
+        assert_eq!(lines[11].order(), 3);
+        assert!((lines[11].frequency - 0.16).abs() < 1e-12);
+
+        let mut df = testing::make_frame(&testing::FrameSpec::default());
+        let lines = df.resonance_lines(1..=3).unwrap();
+        assert!((lines[1].frequency - 0.31).abs() < 1e-12);
+        df.set_column(f64::to_column("FREQ", vec![0.69; df.len()]))
+            .unwrap();
+        assert_eq!(df.flag_resonances("FREQ", &lines, 1e-6).unwrap(), df.len());
+        let flags = Option::<String>::from_column(df.column("RESONANCE").unwrap()).unwrap();
+        assert_eq!(flags[0].as_deref(), Some("Qx"));
+
+        df.properties.shift_remove("Q2");
+        assert!(df.resonance_lines(1..=3).is_err());
+    }
+
+    #[test]
+    fn beam_stay_clear() {
+        let mut twiss = testing::make_frame(&testing::FrameSpec::default());
+        let names = String::from_column(twiss.column("NAME").unwrap()).unwrap();
+        twiss
+            .set_column(f64::to_column("X", vec![1e-3; twiss.len()]))
+            .unwrap();
+        let apertypes: Vec<&str> = (0..names.len())
+            .map(|i| ["rectellipse", "CIRCLE", "NONE"][i % 3])
+            .collect();
+        let aperture = TfsDataFrame::<f64>::new(
+            Vec::new(),
+            polars::df!(
+                "NAME" => &names,
+                "APERTYPE" => apertypes,
+                "APER_1" => vec![0.03; names.len()],
+                "APER_2" => vec![0.02; names.len()],
+                "APER_3" => vec![0.025; names.len()],
+                "APER_4" => vec![0.025; names.len()]
+            )
+            .unwrap(),
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("aperture.tfs");
+        aperture.write(&path).unwrap();
+        let aperture = aperture::read_aperture::<f64, _>(&path).unwrap();
+
+        let beam = aperture::BeamParameters::new(2e-9, 1e-9)
+            .momentum_spread(1e-3)
+            .closed_orbit(2e-3)
+            .beta_beating(0.1);
+        let clearance = aperture::beam_stay_clear(&twiss, &aperture, &beam).unwrap();
+        assert_eq!(clearance.len(), twiss.len());
+        assert_eq!(clearance.properties["Q1"], twiss.properties["Q1"]);
+        let ax = Option::<f64>::from_column(clearance.column("AX").unwrap()).unwrap();
+        let ay = Option::<f64>::from_column(clearance.column("AY").unwrap()).unwrap();
+        assert_eq!((ax[0], ay[0]), (Some(0.025), Some(0.02)));
+        assert_eq!((ax[1], ay[1]), (Some(0.03), Some(0.03)));
+        assert_eq!((ax[2], ay[2]), (None, None));
+
+        let betx = f64::from_column(twiss.column("BETX").unwrap()).unwrap();
+        let dx = f64::from_column(twiss.column("DX").unwrap()).unwrap();
+        let n1x = Option::<f64>::from_column(clearance.column("N1X").unwrap()).unwrap();
+        let expected = (0.025 - 1e-3 - 2e-3 - dx[0].abs() * 1e-3) / (1.1 * betx[0] * 2e-9).sqrt();
+        assert!((n1x[0].unwrap() - expected).abs() < 1e-9);
+        let n1 = Option::<f64>::from_column(clearance.column("N1").unwrap()).unwrap();
+        let n1y = Option::<f64>::from_column(clearance.column("N1Y").unwrap()).unwrap();
+        assert_eq!(n1[0], Some(n1x[0].unwrap().min(n1y[0].unwrap())));
+        assert_eq!(n1[2], None);
+
+        let incomplete = aperture.df().unwrap().drop("APER_4").unwrap();
+        let incomplete = TfsDataFrame::<f64>::new(Vec::new(), incomplete);
+        assert!(aperture::beam_stay_clear(&twiss, &incomplete, &beam).is_err());
+    }
+
+    #[test]
+    fn element_neighbours() {
+        let df = TfsDataFrame::<f64>::new(
+            Vec::new(),
+            polars::df!(
+                "NAME" => ["BPM.3", "MQ.1", "BPM.1", "MB.1", "MQ.2", "BPM.2"],
+                "KEYWORD" => ["MONITOR", "QUADRUPOLE", "MONITOR", "SBEND", "QUADRUPOLE", "MONITOR"],
+                "S" => [30.0, 5.0, 0.0, 10.0, 20.0, 20.0]
+            )
+            .unwrap(),
+        );
+        let sequence = df.sequence().unwrap();
+        let names: Vec<&str> = sequence
+            .elements()
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(names, ["BPM.1", "MQ.1", "MB.1", "MQ.2", "BPM.2", "BPM.3"]);
+
+        let next = df.next_element("BPM.1", "Quadrupole").unwrap().unwrap();
+        assert_eq!((next.name.as_str(), next.row, next.s), ("MQ.1", 1, 5.0));
+        let previous = df.previous_element("BPM.2", "QUADRUPOLE").unwrap().unwrap();
+        assert_eq!(previous.name, "MQ.2");
+        assert_eq!(df.previous_element("BPM.1", "*").unwrap(), None);
+        assert_eq!(df.next_element("BPM.3", "MONITOR").unwrap(), None);
+        assert!(df.next_element("BPM.4", "MONITOR").is_err());
+
+        let between = df
+            .elements_between("BPM.1", "BPM.3", sequence::ALL_CLASSES)
+            .unwrap();
+        assert_eq!(between.len(), 4);
+        assert!(df.elements_between("MQ.2", "MQ.1", "*").is_err());
+
+        let ring = sequence.cyclic(true);
+        assert_eq!(
+            ring.next("BPM.3", "MONITOR").unwrap().unwrap().name,
+            "BPM.1"
+        );
+        assert_eq!(
+            ring.previous("MQ.1", "MONITOR").unwrap().unwrap().name,
+            "BPM.1"
+        );
+        assert_eq!(
+            ring.previous("BPM.1", "MONITOR").unwrap().unwrap().name,
+            "BPM.3"
+        );
+        let wrapped = ring.between("BPM.2", "MQ.1", "*").unwrap();
+        let wrapped: Vec<&str> = wrapped.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(wrapped, ["BPM.3", "BPM.1"]);
+    }
+
+    #[test]
+    fn madx_tables() {
+        let twiss = testing::make_frame(&testing::FrameSpec::default());
+        let len = twiss.len();
+        let twiss = tables::TwissTable::new(twiss).unwrap();
+        assert_eq!(twiss.mux().unwrap().len(), len);
+        assert_eq!(twiss.name().unwrap().len(), len);
+        assert!(twiss.column("KEYWORD").is_ok());
+        let twiss = twiss.into_inner();
+
+        let mut mistyped = testing::roundtrip(&twiss).unwrap();
+        let names = String::from_column(mistyped.column("NAME").unwrap()).unwrap();
+        mistyped
+            .set_column(String::to_column("BETX", names))
+            .unwrap();
+        let error = tables::TwissTable::new(mistyped).unwrap_err().to_string();
+        assert!(error.starts_with("not a twiss table: column 'BETX' is %s, expected %le"));
+
+        let mut survey = TfsDataFrame::<f64>::new(
+            Vec::new(),
+            polars::df!("NAME" => ["IP1", "IP5"], "S" => [0.0, 13329.0]).unwrap(),
+        );
+        for column in ["X", "Y", "Z", "THETA", "PHI", "PSI"] {
+            survey
+                .set_column(f64::to_column(column, vec![0.5, -0.5]))
+                .unwrap();
+        }
+        survey
+            .cast_column("PSI", polars::prelude::DataType::Float32)
+            .unwrap();
+        let survey = tables::SurveyTable::new(survey).unwrap();
+        assert_eq!(survey.psi().unwrap().get(0), Some(0.5));
+        assert_eq!(survey.theta().unwrap().get(1), Some(-0.5));
+        assert_eq!(survey.s().unwrap().get(1), Some(13329.0));
+
+        let summ = polars::df!(
+            "LENGTH" => [26658.88],
+            "ALFA" => [3.2e-4],
+            "GAMMATR" => [55.7],
+            "Q1" => [62.31],
+            "DQ1" => [2.0],
+            "Q2" => [60.32],
+            "DQ2" => [Option::<f64>::None],
+            "SYNCH_2" => [1e-3]
+        )
+        .unwrap();
+        let two_rows = summ.vstack(&summ).unwrap();
+        let summ = TfsDataFrame::<f64>::new(Vec::new(), summ);
+        let dir = tempfile::tempdir().unwrap();
+        summ.write(dir.path().join("summ.tfs")).unwrap();
+        let summ = tables::SummTable::<f64>::open(dir.path().join("summ.tfs")).unwrap();
+        assert_eq!((summ.q1, summ.length), (62.31, Some(26658.88)));
+        assert_eq!((summ.dq2, summ.dqmin), (None, None));
+        assert_eq!(summ.synch, [None, Some(1e-3), None, None, None]);
+        assert_eq!(summ.frame().len(), 1);
+        assert!(tables::SummTable::<f64>::new(TfsDataFrame::new(Vec::new(), two_rows)).is_err());
+        let no_tunes = polars::df!("LENGTH" => [1.0]).unwrap();
+        let error = tables::SummTable::<f64>::new(TfsDataFrame::new(Vec::new(), no_tunes));
+        assert!(error.unwrap_err().to_string().contains("'Q1' is missing"));
+    }
+
+    #[test]
+    fn beam_pairs() {
+        use beams::{Beam, BeamPair};
+        let translator = beams::beam_translator(Beam::B1);
+        assert_eq!(
+            naming::NameTranslator::translate(&translator, "MQ.12R1.b2").unwrap(),
+            "MQ.12R1.b1"
+        );
+        assert_eq!(naming::NameTranslator::translate(&translator, "IP1"), None);
+
+        let frame = |beam: Beam, betx: [f64; 3]| {
+            let names = ["IP1", "BPM.7R1", "MQ.8R1"].map(|n| match n {
+                "IP1" => n.to_owned(),
+                n => format!("{}.{}", n, beam),
+            });
+            TfsDataFrame::<f64>::new(
+                vec![("BEAM".to_owned(), DataValue::Text(beam.to_string()))],
+                polars::df!(
+                    "NAME" => names,
+                    "KEYWORD" => ["MARKER", "MONITOR", "QUADRUPOLE"],
+                    "BETX" => betx,
+                    "TURN" => [1i64, 2, 3]
+                )
+                .unwrap(),
+            )
+        };
+        let dir = tempfile::tempdir().unwrap();
+        frame(Beam::B1, [0.5, 100.0, 150.0])
+            .write(dir.path().join("b1.tfs"))
+            .unwrap();
+        frame(Beam::B2, [0.5, 90.0, 170.0])
+            .write(dir.path().join("b2.tfs"))
+            .unwrap();
+        let pair =
+            BeamPair::<f64>::open(dir.path().join("b1.tfs"), dir.path().join("b2.tfs")).unwrap();
+        assert_eq!(pair.get(Beam::B2).props("BEAM"), "B2");
+
+        let diff = pair.diff_beams("NAME").unwrap();
+        assert_eq!(diff.column_names(), ["NAME", "BETX"]);
+        assert_eq!(diff.props("BEAM"), "B1");
+        assert_eq!(
+            String::from_column(diff.column("NAME").unwrap()).unwrap(),
+            ["IP1", "BPM.7R1.B1", "MQ.8R1.B1"]
+        );
+        assert_eq!(
+            f64::from_column(diff.column("BETX").unwrap()).unwrap(),
+            [0.0, 10.0, -20.0]
+        );
+
+        let monitors = pair
+            .map_both(|beam, df| {
+                let mut monitors = testing::roundtrip(df)?;
+                monitors.filter_expr("KEYWORD == 'MONITOR'")?;
+                monitors
+                    .properties
+                    .insert("FILTERED".to_owned(), DataValue::Text(beam.to_string()));
+                Ok(monitors)
+            })
+            .unwrap();
+        assert_eq!((monitors.b1.len(), monitors.b2.len()), (1, 1));
+        assert_eq!(monitors.get(Beam::B2).props("FILTERED"), "B2");
+        assert_eq!(Beam::B2.other(), Beam::B1);
+    }
+
+    #[test]
+    fn exact_real_roundtrip() {
+        let values = [
+            0.1 + 0.2,
+            -0.0,
+            5e-324,
+            1e-310,
+            f64::MIN_POSITIVE,
+            f64::MAX,
+            1.0 / 3.0,
+            1e16,
+            f64::NAN,
+            f64::NEG_INFINITY,
+        ];
+        let mut df = TfsDataFrame::<f64>::new(
+            vec![("DELTA".to_owned(), DataValue::Real(0.1 + 0.2))],
+            polars::df!("X" => values, "N" => [1i64; 10]).unwrap(),
+        );
+        assert_eq!(df.real_format(), RealFormat::Scientific);
+        assert_exact_roundtrip!(df);
+
+        df.set_real_format(RealFormat::Shortest);
+        assert_exact_roundtrip!(df);
+        let mut written = Vec::new();
+        df.write_to(&mut written).unwrap();
+        let text = String::from_utf8(written.clone()).unwrap();
+        assert!(text.contains(" 5e-324 ") && text.contains(" -0.0 ") && text.contains(" 1e16 "));
+
+        let mut read = testing::parse_bytes(&written).unwrap();
+        read.set_real_format(RealFormat::Shortest);
+        let mut rewritten = Vec::new();
+        read.write_to(&mut rewritten).unwrap();
+        assert_eq!(String::from_utf8(rewritten).unwrap(), text);
+
+        df.set_precision("X", Some(Precision::new(3, 1.0)));
+        let error = testing::check_exact_roundtrip(&df).unwrap_err();
+        assert!(error.to_string().contains("column 'X' row 0"));
+    }
+
+    #[test]
+    fn pipeline_thread_count() {
+        let files: Vec<_> = (0..6)
+            .map(|seed| {
+                let mut df = testing::make_frame(&testing::FrameSpec {
+                    n_elements: 10 + 10 * seed as usize,
+                    seed,
+                    ..Default::default()
+                });
+                df.filter_rows("BETX", |b| b > 400.0 + seed as f64).unwrap();
+                testing::write_temp(&df).unwrap()
+            })
+            .collect();
+        let mut paths: Vec<_> = files.iter().map(|f| f.path().to_owned()).collect();
+        paths.push("not_there.tfs".into());
+
+        let run = |threads| {
+            let report = pipeline::TfsPipeline::new()
+                .options(TfsReadOptions::new().max_threads(threads))
+                .filter("S", |s| s > 5000.0)
+                .run_all(&paths);
+            let processed: Vec<_> = report
+                .processed
+                .iter()
+                .map(|p| (p.path.clone(), p.rows))
+                .collect();
+            (processed, report.errors)
+        };
+        let sequential = run(1);
+        assert_eq!(sequential.0.len(), 6);
+        assert_eq!(sequential.1.len(), 1);
+        for threads in [2, 3, 8] {
+            assert_eq!(run(threads), sequential);
+        }
+
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let stage_seen = seen.clone();
+        let report = pipeline::TfsPipeline::new()
+            .options(TfsReadOptions::new().max_threads(8).deterministic(true))
+            .stage(move |df| {
+                stage_seen.lock().unwrap().push(df.len());
+                Ok(())
+            })
+            .run_all(&paths);
+        let rows: Vec<_> = report.processed.iter().map(|p| p.rows).collect();
+        assert_eq!(*seen.lock().unwrap(), rows);
+        assert_eq!(report.errors, sequential.1);
+    }
+
+    #[test]
+    fn virtual_columns() {
+        use polars::prelude::NamedFrom;
+        use polars::series::Series;
+
+        let mut df = testing::make_frame(&testing::FrameSpec::default());
+        df.register_virtual("BETX_REL", "BETX / @LENGTH").unwrap();
+        df.register_virtual("BEAT_X", "BETX_REL * 2 - BETX / 1000")
+            .unwrap();
+        assert!(df.register_virtual("BETX", "BETY").is_err());
+        assert!(df.register_virtual("ANY", "NOPE + 1").is_err());
+        assert_eq!(df.virtual_columns(), ["BETX_REL", "BEAT_X"]);
+        assert!(!df.column_names().contains(&"BEAT_X"));
+
+        let expected = |df: &TfsDataFrame<f64>| -> Vec<f64> {
+            f64::from_column(df.column("BETX").unwrap())
+                .unwrap()
+                .iter()
+                .map(|b| b / 26658.8832 * 2.0 - b / 1000.0)
+                .collect()
+        };
+        let beat = f64::from_column(df.column("BEAT_X").unwrap()).unwrap();
+        assert_eq!(beat, expected(&df));
+        assert!(df.is_computed("BEAT_X") && df.is_computed("BETX_REL"));
+
+        df.scale_column("BETX", 2.0).unwrap();
+        assert!(!df.is_computed("BEAT_X"));
+        let beat = f64::from_column(df.column("BEAT_X").unwrap()).unwrap();
+        assert_eq!(beat, expected(&df));
+
+        df.filter_rows("S", |s| s > 10000.0).unwrap();
+        assert_eq!(df.column("BEAT_X").unwrap().len(), df.len());
+        let written = testing::roundtrip(&df).unwrap();
+        assert!(!written.column_names().contains(&"BEAT_X"));
+
+        assert!(df.remove_virtual("BETX_REL"));
+        assert!(df.column("BEAT_X").is_err());
+        df.set_column(Series::new("BEAT_X".into(), vec![0.0; df.len()]))
+            .unwrap();
+        assert!(df.virtual_columns().is_empty());
+    }
+
+    #[test]
+    fn frame_views() {
+        let mut df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 100,
+            ..Default::default()
+        });
+        df.eval_column("BETX_BETY", "BETX / BETY").unwrap();
+        df.register_virtual("BEAT_X", "BETX / 100").unwrap();
+
+        let view = df
+            .view(20..30, &["NAME", "BETX", "BETX_BETY", "BEAT_X"])
+            .unwrap();
+        assert_eq!(view.len(), 10);
+        assert_eq!(view.column_names(), ["NAME", "BETX", "BETX_BETY", "BEAT_X"]);
+        assert!(view.virtual_columns().is_empty());
+        assert!(view.lineage("BETX_BETY").is_some());
+
+        let values = |df: &TfsDataFrame<f64>, name| {
+            df.column(name)
+                .unwrap()
+                .f64()
+                .unwrap()
+                .cont_slice()
+                .unwrap()
+                .as_ptr()
+        };
+        assert_eq!(values(&view, "BETX"), values(&df, "BETX").wrapping_add(20));
+        assert_eq!(view.mean("BETX").unwrap(), {
+            let betx = f64::from_column(df.column("BETX").unwrap()).unwrap();
+            betx[20..30].iter().sum::<f64>() / 10.0
+        });
+
+        assert_eq!(df.view(100..100, &["S"]).unwrap().len(), 0);
+        assert!(df.view(90..101, &["S"]).is_err());
+        assert!(df.view(0..10, &["NOPE"]).is_err());
+    }
+
+    #[test]
+    fn fancy_indexing() {
+        let df = testing::make_frame(&testing::FrameSpec::default());
+        let names = |df: &TfsDataFrame<f64>| String::from_column(&df["NAME"]).unwrap();
+
+        let quads: Vec<bool> = names(&df).iter().map(|n| n.starts_with("MQ")).collect();
+        let selected = df.get(&quads, &["NAME", "BETX"]).unwrap();
+        assert_eq!(selected.column_names(), ["NAME", "BETX"]);
+        assert_eq!(
+            names(&selected),
+            ["MQ.F1", "MQ.F2", "MQ.F3", "MQ.F4", "MQ.F5"]
+        );
+        assert_eq!(selected.properties, df.properties);
+
+        assert_eq!(
+            names(&df.get(vec![9, 0, 0], "NAME").unwrap()),
+            ["BPM.5", "MQ.F1", "MQ.F1"]
+        );
+        assert_eq!(names(&df.get(8.., ..).unwrap()), ["MQ.F5", "BPM.5"]);
+        assert_eq!(df.get(..=2, ..).unwrap().column_count(), df.column_count());
+        assert_eq!(
+            df.get(3, ["S"]).unwrap()["S"],
+            df.get(3..4, "S").unwrap()["S"]
+        );
+
+        assert!(df.get(vec![10], ..).is_err());
+        assert!(df.get(&[true, false], ..).is_err());
+        assert!(df.get(.., &["NOPE"]).is_err());
+        assert!(df.get(..=usize::MAX, ..).is_err());
+        let after_last = selection::RowSelection::Range(
+            std::ops::Bound::Excluded(usize::MAX),
+            std::ops::Bound::Unbounded,
+        );
+        assert!(df.get(after_last, ..).is_err());
+        let df = std::panic::AssertUnwindSafe(df);
+        assert!(std::panic::catch_unwind(|| df["NOPE"].len()).is_err());
+    }
+
+    #[test]
+    fn column_positions() {
+        let mut df = testing::make_frame(&testing::FrameSpec::default());
+        df.compress_column("S").unwrap();
+        df.move_column("S", 0).unwrap();
+        df.move_column("NAME", 1).unwrap();
+        df.move_column("S", 1).unwrap();
+        assert_eq!(df.column_names()[..3], ["NAME", "S", "KEYWORD"]);
+        assert!(df.compressed_columns().is_empty());
+
+        df.insert_column_at(2, "PARENT", vec!["ARC".to_owned(); 10])
+            .unwrap();
+        let end = df.column_count();
+        df.insert_column_at(end, "N", (0..10).collect::<Vec<i64>>())
+            .unwrap();
+        let names = df.column_names();
+        assert_eq!((names[2], names[end]), ("PARENT", "N"));
+
+        assert!(df.insert_column_at(0, "S", vec![0.0; 10]).is_err());
+        assert!(df.insert_column_at(0, "SHORT", vec![0.0; 3]).is_err());
+        assert!(df.insert_column_at(end + 2, "FAR", vec![0.0; 10]).is_err());
+        assert!(df.move_column("NOPE", 0).is_err());
+        assert!(df.move_column("S", end + 1).is_err());
+
+        let read = testing::roundtrip(&df).unwrap();
+        assert_eq!(read.column_names(), df.column_names());
+    }
+
+    #[test]
+    fn map_real_columns() {
+        let mut df = testing::make_frame(&testing::FrameSpec::default());
+        df.insert_column_at(0, "TURNS", vec![Some(1i64); 10])
+            .unwrap();
+        df.set_column(Option::<f64>::to_column("X", [None, Some(2.0)].repeat(5)))
+            .unwrap();
+        df.compress_column("DX").unwrap();
+        let before = testing::roundtrip(&df).unwrap();
+
+        let mut visited = Vec::new();
+        df.map_numeric(|name, v| {
+            if !visited.contains(&name.to_owned()) {
+                visited.push(name.to_owned());
+            }
+            v * 0.5
+        })
+        .unwrap();
+        assert_eq!(
+            visited,
+            ["S", "BETX", "BETY", "ALFX", "ALFY", "MUX", "MUY", "DX", "X"]
+        );
+
+        let halved = |name| f64::from_column(df.column(name).unwrap()).unwrap();
+        let original = |name| f64::from_column(before.column(name).unwrap()).unwrap();
+        assert!(halved("DX")
+            .iter()
+            .zip(original("DX"))
+            .all(|(h, o)| *h == o * 0.5));
+        let x = Option::<f64>::from_column(df.column("X").unwrap()).unwrap();
+        assert_eq!(x[..2], [None, Some(1.0)]);
+        assert_eq!(
+            i64::from_column(df.column("TURNS").unwrap()).unwrap(),
+            vec![1; 10]
+        );
+        assert_eq!(df.column("NAME").unwrap(), before.column("NAME").unwrap());
+    }
+
+    #[test]
+    fn conditional_updates() {
+        use formula::Formula;
+
+        let mut df = testing::make_frame(&testing::FrameSpec::default());
+        let betx = f64::from_column(df.column("BETX").unwrap()).unwrap();
+        let bpms: Vec<bool> = String::from_column(df.column("NAME").unwrap())
+            .unwrap()
+            .iter()
+            .map(|name| name.starts_with("BPM"))
+            .collect();
+
+        df.set_where(&bpms, "BETX", f64::NAN).unwrap();
+        df.set_where(2..4, "DX", "DX * @Q1".parse::<Formula>().unwrap())
+            .unwrap();
+        df.set_where(vec![9], "BETY", 0.0).unwrap();
+
+        let updated = f64::from_column(df.column("BETX").unwrap()).unwrap();
+        for (row, bpm) in bpms.iter().enumerate() {
+            assert_eq!(updated[row].is_nan(), *bpm);
+            if !bpm {
+                assert_eq!(updated[row], betx[row]);
+            }
+        }
+        let dx = f64::from_column(df.column("DX").unwrap()).unwrap();
+        assert_eq!((dx[1], dx[2]), (1.0, 2.0 * 62.31));
+        assert_eq!(
+            f64::from_column(df.column("BETY").unwrap()).unwrap()[9],
+            0.0
+        );
+
+        assert!(df.set_where(.., "NAME", 0.0).is_err());
+        assert!(df.set_where(vec![10], "S", 0.0).is_err());
+        assert!(df.set_where(&[true; 3], "S", 0.0).is_err());
+    }
+
+    #[test]
+    fn polars_conversion() {
+        let mut df = testing::make_frame(&testing::FrameSpec::default());
+        df.compress_column("MUX").unwrap();
+        let buffer =
+            |series: &polars::series::Series| series.f64().unwrap().cont_slice().unwrap().as_ptr();
+        let betx = buffer(df.column("BETX").unwrap());
+        let header = df.properties.clone();
+
+        let (polars, properties) = df.into_polars().unwrap();
+        assert_eq!(properties, header);
+        assert_eq!(polars.width(), 10);
+        assert_eq!(polars.get_column_names_str()[7], "MUX");
+        assert_eq!(
+            buffer(polars.column("BETX").unwrap().as_materialized_series()),
+            betx
+        );
+
+        let df = TfsDataFrame::from_polars(polars, properties);
+        assert_eq!(buffer(df.column("BETX").unwrap()), betx);
+        assert!(df
+            .diff(&testing::make_frame(&testing::FrameSpec::default()))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn wide_missing_fields() {
+        let columns = 2000;
+        let mut file = format!(
+            "* NAME {}\n$ %s{}\n",
+            (0..columns)
+                .map(|c| format!("K{}", c))
+                .collect::<Vec<_>>()
+                .join(" "),
+            " %le".repeat(columns)
+        );
+        for row in 0..20 {
+            let fields: Vec<String> = (0..row * 100).map(|c| c.to_string()).collect();
+            file.push_str(&format!("\"BPM{}\" {}\n", row, fields.join(" ")));
+        }
+
+        let options = TfsReadOptions::new().missing_fields(MissingFields::Null);
+        let df = TfsHeaderParser::with_options(std::io::Cursor::new(file), options)
+            .parse::<f64>()
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!((df.len(), df.column_count()), (20, columns + 1));
+        assert_eq!(df.column("K1999").unwrap().null_count(), 20);
+        for column in [0, 99, 100, 1234, 1899] {
+            let values = df.column(&format!("K{}", column)).unwrap();
+            assert_eq!(values.null_count(), column / 100 + 1);
+            assert_eq!(values.f64().unwrap().get(19), Some(column as f64));
+        }
+    }
+
+    #[test]
+    fn transposition() {
+        let mut df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 4,
+            ..Default::default()
+        });
+        df.insert_column_at(2, "TURN", vec![1i64, 2, 3, 4]).unwrap();
+        df.set_where(vec![1], "DX", f64::NAN).unwrap();
+        df.set_column(Option::<f64>::to_column(
+            "X",
+            vec![None, Some(1e-3), None, None],
+        ))
+        .unwrap();
+
+        let transposed = df.transpose("COLUMN", "NAME").unwrap();
+        assert_eq!(
+            transposed.column_names(),
+            ["COLUMN", "MQ.F1", "BPM.1", "MQ.F2", "BPM.2"]
+        );
+        let rows = String::from_column(transposed.column("COLUMN").unwrap()).unwrap();
+        assert_eq!(rows[..2], ["TURN", "S"]);
+        assert!(!rows.contains(&"KEYWORD".to_owned()));
+        assert_eq!(transposed.len(), 10);
+        assert_eq!(transposed.properties, df.properties);
+        let bpm = Option::<f64>::from_column(transposed.column("BPM.1").unwrap()).unwrap();
+        assert_eq!(
+            (bpm[0], bpm[8].map(f64::is_nan), bpm[9]),
+            (Some(2.0), Some(true), Some(1e-3))
+        );
+        assert_eq!(transposed.column("MQ.F1").unwrap().null_count(), 1);
+
+        let back = transposed.transpose("NAME", "COLUMN").unwrap();
+        assert_eq!(back.len(), 4);
+        assert_eq!(back.column("BETX").unwrap(), df.column("BETX").unwrap());
+
+        assert!(df.transpose("MQ.F1", "NAME").is_err());
+        assert!(df.transpose("COLUMN", "S").is_err());
+        df.set_column(Option::<String>::to_column(
+            "NAME",
+            vec![Some("A".to_owned()); 4],
+        ))
+        .unwrap();
+        assert!(df.transpose("COLUMN", "NAME").is_err());
+    }
+
+    #[test]
+    fn melting() {
+        let df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 4,
+            ..Default::default()
+        });
+        let melted = df.melt(&["NAME"], &["BETX", "BETY", "DX"]).unwrap();
+        assert_eq!(melted.len(), 12);
+        assert_eq!(melted.properties, df.properties);
+        let names = String::from_column(melted.column("NAME").unwrap()).unwrap();
+        assert_eq!(names[4..8], ["MQ.F1", "BPM.1", "MQ.F2", "BPM.2"]);
+        let variables = String::from_column(melted.column("VARIABLE").unwrap()).unwrap();
+        assert_eq!(
+            (variables[3].as_str(), variables[4].as_str()),
+            ("BETX", "BETY")
+        );
+        let values = f64::from_column(melted.column("VALUE").unwrap()).unwrap();
+        assert_eq!(
+            values[8..],
+            f64::from_column(df.column("DX").unwrap()).unwrap()
+        );
+
+        let all = df.melt(&["NAME", "KEYWORD"], &[]).unwrap();
+        assert_eq!(all.len(), 4 * 8);
+        let texts = df.melt(&[], &["NAME", "KEYWORD"]).unwrap();
+        assert_eq!(
+            texts.column("VALUE").unwrap().str().unwrap().get(4),
+            Some("QUADRUPOLE")
+        );
+
+        assert!(df.melt(&["NAME"], &["KEYWORD", "S"]).is_err());
+        assert!(df.melt(&["NAME"], &["NOPE"]).is_err());
+        assert!(df.melt(&["VARIABLE"], &["S"]).is_err());
+    }
+
+    #[test]
+    fn running_operations() {
+        use crate::cumulative::Wrap;
+        use crate::formula::prop;
+
+        let mut df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 5,
+            ..Default::default()
+        });
+        let mux = f64::from_column(df.column("MUX").unwrap()).unwrap();
+        let q1 = *df.propd("Q1");
+
+        let advance = df.diff_rows("MUX", Wrap::period(prop("Q1"))).unwrap();
+        let advance = f64::from_column(&advance).unwrap();
+        assert!((advance[0] - (mux[0] + q1 - mux[4])).abs() < 1e-12);
+        assert!((advance.iter().sum::<f64>() - q1).abs() < 1e-9);
+        let open = df.diff_rows("MUX", Wrap::Open).unwrap();
+        assert_eq!(open.null_count(), 1);
+        let turn = f64::from_column(&df.diff_rows("MUX", Wrap::period(2.0)).unwrap()).unwrap();
+        assert!((turn[0] - (mux[0] + 2.0 - mux[4])).abs() < 1e-12);
+
+        let total = df.cumsum("MUX").unwrap();
+        assert_eq!(total.name().as_str(), "CUMSUM(MUX)");
+        assert!((f64::from_column(&total).unwrap()[4] - mux.iter().sum::<f64>()).abs() < 1e-12);
+        df.set_column(total.with_name("MUX_SUM".into())).unwrap();
+        assert!(df.column_names().contains(&"MUX_SUM"));
+
+        df.set_column(Option::<f64>::to_column(
+            "X",
+            vec![Some(2.0), None, Some(3.0), Some(0.5), Some(1.0)],
+        ))
+        .unwrap();
+        let products = Option::<f64>::from_column(&df.cumprod("X").unwrap()).unwrap();
+        assert_eq!(products, [Some(2.0), None, Some(6.0), Some(3.0), Some(3.0)]);
+        let steps =
+            Option::<f64>::from_column(&df.diff_rows("X", Wrap::period(1.0)).unwrap()).unwrap();
+        assert_eq!(steps, [Some(2.0), None, None, Some(-2.5), Some(0.5)]);
+
+        assert!(df.cumsum("NAME").is_err());
+        assert!(df.diff_rows("MUX", Wrap::period(prop("NOPE"))).is_err());
+    }
+
+    #[test]
+    fn derivatives_along_s() {
+        use crate::calculus::S_COLUMN;
+
+        let s = [0.0, 1.0, 1.0, 2.5, 4.0, 7.0];
+        let mut df = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!(
+                "S" => s,
+                "X" => s.map(|s| s * s),
+                "DX" => [Some(1.0), Some(2.0), None, Some(2.0), Some(3.0), Some(1.0)],
+            )
+            .unwrap(),
+        );
+
+        let slope = f64::from_column(&df.gradient("X", S_COLUMN).unwrap()).unwrap();
+        for row in 1..5 {
+            assert!((slope[row] - 2.0 * s[row]).abs() < 1e-12, "row {}", row);
+        }
+        assert_eq!((slope[0], slope[5]), (1.0, 11.0));
+
+        let dx = Option::<f64>::from_column(&df.gradient("DX", S_COLUMN).unwrap()).unwrap();
+        assert_eq!(dx[2], None);
+        assert_eq!(dx[5], Some(-2.0 / 3.0));
+
+        let area = df.integrate("DX", S_COLUMN).unwrap();
+        assert_eq!(area.name().as_str(), "INTEGRAL(DX)");
+        let area = Option::<f64>::from_column(&area).unwrap();
+        assert_eq!(
+            area,
+            [
+                Some(0.0),
+                Some(1.5),
+                None,
+                Some(4.5),
+                Some(8.25),
+                Some(14.25)
+            ]
+        );
+
+        df.set_column(Option::<f64>::to_column("DOWN", vec![Some(1.0); 6]))
+            .unwrap();
+        assert!(df.integrate("X", "DOWN").is_ok());
+        df.scale_column("S", -1.0).unwrap();
+        assert!(df.gradient("X", S_COLUMN).is_err());
+        assert!(df.integrate("X", "NAME").is_err());
+    }
+
+    #[test]
+    fn smoothing_filters() {
+        use crate::smoothing::Kernel;
+
+        let x: Vec<f64> = (0..9).map(|i| i as f64).collect();
+        let cubic: Vec<f64> = x.iter().map(|x| x.powi(3) - 4.0 * x + 1.0).collect();
+        let noisy = [1.0, 0.0, 4.0, 0.0, 2.0, 1.0, 0.0, 1.0, 3.0];
+        let mut df = TfsDataFrame::<f64>::new(
+            vec![],
+            polars::df!("CUBIC" => &cubic, "NOISY" => noisy).unwrap(),
+        );
+
+        let fitted = f64::from_column(
+            &df.smooth(
+                "CUBIC",
+                Kernel::SavitzkyGolay {
+                    window: 5,
+                    order: 3,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        for (fitted, cubic) in fitted.iter().zip(&cubic) {
+            assert!((fitted - cubic).abs() < 1e-9);
+        }
+        let quadratic = f64::from_column(
+            &df.smooth(
+                "NOISY",
+                Kernel::SavitzkyGolay {
+                    window: 5,
+                    order: 2,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let classic = (-3.0 * 1.0 + 12.0 * 0.0 + 17.0 * 4.0 + 12.0 * 0.0 - 3.0 * 2.0) / 35.0;
+        assert!((quadratic[2] - classic).abs() < 1e-12);
+
+        let mean =
+            f64::from_column(&df.smooth("NOISY", Kernel::MovingAverage(3)).unwrap()).unwrap();
+        assert_eq!((mean[0], mean[4]), (0.5, 1.0));
+        let gauss = f64::from_column(&df.smooth("NOISY", Kernel::Gaussian(1.5)).unwrap()).unwrap();
+        assert!(gauss.iter().all(|v| (0.0..4.0).contains(v)));
+        let flat = df.smooth("CUBIC", Kernel::Gaussian(0.01)).unwrap();
+        assert_eq!(flat.name().as_str(), "SMOOTH(CUBIC)");
+        assert!((f64::from_column(&flat).unwrap()[4] - cubic[4]).abs() < 1e-12);
+
+        df.set_column(Option::<f64>::to_column(
+            "GAPS",
+            vec![
+                Some(1.0),
+                None,
+                Some(3.0),
+                Some(5.0),
+                None,
+                None,
+                None,
+                None,
+                Some(2.0),
+            ],
+        ))
+        .unwrap();
+        let gaps =
+            Option::<f64>::from_column(&df.smooth("GAPS", Kernel::MovingAverage(3)).unwrap())
+                .unwrap();
+        assert_eq!(&gaps[..4], [Some(1.0), None, Some(4.0), Some(4.0)]);
+        assert_eq!(gaps[8], Some(2.0));
+        assert!(df
+            .smooth(
+                "GAPS",
+                Kernel::SavitzkyGolay {
+                    window: 5,
+                    order: 2
+                }
+            )
+            .is_ok());
+
+        assert!(df.smooth("NOISY", Kernel::MovingAverage(4)).is_err());
+        assert!(df.smooth("NOISY", Kernel::Gaussian(0.0)).is_err());
+        assert!(df
+            .smooth(
+                "NOISY",
+                Kernel::SavitzkyGolay {
+                    window: 3,
+                    order: 3
+                }
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn peak_finding() {
+        let spectrum = [
+            Some(5.0),
+            Some(1.0),
+            Some(3.0),
+            Some(1.0),
+            Some(2.0),
+            Some(2.0),
+            Some(2.0),
+            Some(0.0),
+            Some(4.0),
+            None,
+            Some(6.0),
+            Some(1.0),
+            Some(1.5),
+            Some(1.0),
+            Some(9.0),
+        ];
+        let mut df = TfsDataFrame::<f64>::new(
+            vec![("TYPE".into(), DataValue::Text("SPECTRUM".into()))],
+            polars::df!("AMPLITUDE" => spectrum).unwrap(),
+        );
+        df.insert_column_at(0, "NAME", (0..15).map(|i| format!("BIN{}", i)).collect())
+            .unwrap();
+
+        let peaks = df.find_peaks("AMPLITUDE", 0.0, 1).unwrap();
+        assert_eq!(peaks.column_names(), ["ROW", "NAME", "AMPLITUDE"]);
+        assert_eq!(peaks.properties, df.properties);
+        assert_eq!(
+            i64::from_column(peaks.column("ROW").unwrap()).unwrap(),
+            [2, 5, 12]
+        );
+        assert_eq!(
+            String::from_column(peaks.column("NAME").unwrap()).unwrap(),
+            ["BIN2", "BIN5", "BIN12"]
+        );
+
+        let high = df.find_peaks("AMPLITUDE", 2.5, 1).unwrap();
+        assert_eq!(
+            f64::from_column(high.column("AMPLITUDE").unwrap()).unwrap(),
+            [3.0]
+        );
+        let apart = df.find_peaks("AMPLITUDE", 0.0, 4).unwrap();
+        assert_eq!(
+            i64::from_column(apart.column("ROW").unwrap()).unwrap(),
+            [2, 12]
+        );
+
+        let twiss = testing::make_frame(&testing::FrameSpec::default());
+        let maxima = twiss.find_peaks("BETX", f64::NEG_INFINITY, 0).unwrap();
+        assert_eq!(maxima.column_names(), ["ROW", "NAME", "S", "BETX"]);
+        assert!(df.find_peaks("NAME", 0.0, 1).is_err());
+    }
+
+    #[test]
+    fn schema_checks() {
+        use crate::pipeline::TfsPipeline;
+        use crate::schema::{Schema, Target};
+
+        let mut df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 6,
+            ..Default::default()
+        });
+        df.set_column(Option::<f64>::to_column(
+            "X",
+            vec![
+                Some(0.01),
+                Some(-0.2),
+                None,
+                Some(f64::NAN),
+                Some(0.3),
+                Some(-0.05),
+            ],
+        ))
+        .unwrap();
+        let schema = Schema::from_json(
+            r#"{ "checks": ["BETX > 0", "|X| < 0.1", "S >= 0 && |DX| < 1e9", "MISSING > 0"] }"#,
+        )
+        .unwrap();
+
+        let violations = df.validate(&schema).unwrap();
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].target, Target::Check);
+        assert_eq!(violations[0].name, "|X| < 0.1");
+        assert_eq!(violations[0].elements, ["BPM.1", "MQ.F3"]);
+        assert_eq!(
+            violations[0].to_string(),
+            "check '|X| < 0.1' fails for 2 rows, the first in row 1"
+        );
+        assert_eq!(violations[1].message, "uses the missing column 'MISSING'");
+
+        let json = serde_json::to_string(&violations[1]).unwrap();
+        assert!(!json.contains("elements"));
+        assert!(Schema::from_json(r#"{ "checks": ["|NAME| > 0"] }"#)
+            .and_then(|schema| df.validate(&schema))
+            .is_err());
+        assert!(df
+            .validate(&Schema::from_json(r#"{ "checks": ["|X"] }"#).unwrap())
+            .is_err());
+
+        let file = testing::write_temp(&df).unwrap();
+        let pipeline =
+            TfsPipeline::new().validate(Schema::from_json(r#"{ "checks": ["|X| < 1"] }"#).unwrap());
+        assert!(pipeline.run(file.path()).is_ok());
+        let strict = TfsPipeline::new().validate(schema);
+        let err = strict.run(file.path()).unwrap_err().to_string();
+        assert!(
+            err.contains("check '|X| < 0.1' fails for 2 rows"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn mask_reasons() {
+        use crate::mask::{ElementMask, MaskAction, MaskReason};
+
+        let mut df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 6,
+            ..Default::default()
+        });
+        assert!(df.masked().is_empty());
+        assert_eq!(df.masked_frame().unwrap().len(), 0);
+
+        df.apply_mask(&ElementMask::from_names(["BPM.1"]), MaskAction::Drop)
+            .unwrap();
+        let broken = df.mask_from_condition("S", |s| s > 0.0 && s < 1e9).unwrap();
+        let cleaning = MaskReason::Cleaning("svd".into());
+        df.mask_with_reason(&broken, MaskAction::NaN, cleaning.clone())
+            .unwrap();
+        assert_eq!(df.len(), 5);
+        assert_eq!(df.masked().len(), 1 + broken.len());
+        assert_eq!(df.masked()[0].reason, MaskReason::User);
+        assert_eq!(df.masked()[1].reason, cleaning);
+
+        let masked = df.masked_frame().unwrap();
+        assert_eq!(masked.properties, df.properties);
+        assert_eq!(
+            String::from_column(masked.column("REASON").unwrap()).unwrap()[..2],
+            ["USER", "CLEANING"]
+        );
+        assert_eq!(
+            String::from_column(masked.column("ACTION").unwrap()).unwrap()[..2],
+            ["DROP", "NAN"]
+        );
+        assert_eq!(
+            masked.column("DETAIL").unwrap().str().unwrap().get(1),
+            Some("svd")
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let companion = dir.path().join("masked.tfs");
+        df.write_masked(&companion).unwrap();
+        let read = TfsDataFrame::<f64>::open(&companion).unwrap();
+        assert_eq!(
+            String::from_column(read.column("NAME").unwrap()).unwrap(),
+            String::from_column(masked.column("NAME").unwrap()).unwrap()
+        );
+        assert_eq!(
+            df.view(0..2, &["NAME"]).unwrap().masked().len(),
+            df.masked().len()
+        );
+    }
+
+    #[test]
+    fn header_conflicts() {
+        use crate::join::{HeaderMerge, JoinOptions};
+
+        let spec = testing::FrameSpec {
+            n_elements: 3,
+            ..Default::default()
+        };
+        let nominal = testing::make_frame(&spec);
+        let shifted = || {
+            let mut df = testing::make_frame(&spec);
+            df.properties.insert("Q1".into(), DataValue::Real(62.28));
+            df.properties.insert("NROWS".into(), DataValue::Integer(3));
+            df.properties.shift_remove("Q2");
+            df.properties.insert("CHROMA".into(), DataValue::Real(2.0));
+            df
+        };
+        let frames = || [testing::make_frame(&spec), shifted()];
+
+        let stacked = TfsDataFrame::concat(&frames()).unwrap();
+        assert_eq!(stacked.properties, nominal.properties);
+        assert_eq!(stacked.header_conflicts().len(), 1);
+        let conflict = &stacked.header_conflicts()[0];
+        assert_eq!(conflict.key, "Q1");
+        assert_eq!(
+            conflict.values,
+            [Some(DataValue::Real(62.31)), Some(DataValue::Real(62.28))]
+        );
+        assert_eq!(
+            conflict.to_string(),
+            "header entry 'Q1' differs: 62.31, 62.28"
+        );
+        assert_eq!(
+            stacked.get(0..2, "NAME").unwrap().header_conflicts(),
+            stacked.header_conflicts()
+        );
+
+        let last = TfsDataFrame::concat_with(&frames(), HeaderMerge::KeepLast).unwrap();
+        assert_eq!(last.properties["Q1"], DataValue::Real(62.28));
+        assert!(last.properties.contains_key("Q2"));
+        assert!(!last.properties.contains_key("CHROMA"));
+        let dropped = TfsDataFrame::concat_with(&frames(), HeaderMerge::Drop).unwrap();
+        assert!(!dropped.properties.contains_key("Q1"));
+        let err = TfsDataFrame::concat_with(&frames(), HeaderMerge::Error).unwrap_err();
+        assert!(err.to_string().contains("'Q1' differs"), "{}", err);
+        let same =
+            TfsDataFrame::concat_with(&[nominal, testing::make_frame(&spec)], HeaderMerge::Error)
+                .unwrap();
+        assert!(same.header_conflicts().is_empty());
+
+        let model = shifted();
+        let measurement = testing::make_frame(&spec);
+        let joined = measurement
+            .join(&model, "NAME", join::JoinType::Inner, ("", "_MDL"))
+            .unwrap();
+        assert_eq!(joined.header_conflicts()[0].key, "Q1");
+        let options = JoinOptions::new()
+            .suffixes("", "_MDL")
+            .headers("error".parse().unwrap());
+        assert!(measurement.join_with(&model, "NAME", &options).is_err());
+        let options = JoinOptions::new()
+            .suffixes("", "_MDL")
+            .headers(HeaderMerge::KeepLast);
+        let joined = measurement.join_with(&model, "NAME", &options).unwrap();
+        assert_eq!(joined.properties["Q1"], DataValue::Real(62.28));
+    }
+
+    #[test]
+    fn path_resolution() {
+        use crate::paths::{expand_path, resolve_path, SEARCH_PATH_VAR};
+        use std::path::Path;
+
+        let dir = tempfile::tempdir().unwrap();
+        let df = testing::make_frame(&testing::FrameSpec::default());
+        df.write(dir.path().join("optics_738.tfs")).unwrap();
+        std::env::set_var("TFS_TEST_DIR_738", dir.path());
+
+        let expanded = expand_path(Path::new("$TFS_TEST_DIR_738/a/${TFS_TEST_DIR_738}")).unwrap();
+        let dir_text = dir.path().display().to_string();
+        assert_eq!(expanded, Path::new(&format!("{}/a/{}", dir_text, dir_text)));
+        assert_eq!(expand_path(Path::new("a$/b")).unwrap(), Path::new("a$/b"));
+        let home = std::env::var("HOME").unwrap();
+        assert_eq!(
+            expand_path(Path::new("~/x.tfs")).unwrap(),
+            Path::new(&home).join("x.tfs")
+        );
+        assert!(expand_path(Path::new("$TFS_UNSET_738/x.tfs")).is_err());
+        assert!(expand_path(Path::new("${TFS_TEST_DIR_738")).is_err());
+
+        let literal = dir.path().join("$TFS_UNSET_738.tfs");
+        df.write(&literal).unwrap();
+        assert_eq!(resolve_path(&literal, &[]).unwrap(), literal);
+
+        let read = TfsDataFrame::<f64>::open("$TFS_TEST_DIR_738/optics_738.tfs").unwrap();
+        assert_eq!(read.len(), df.len());
+        let options = TfsReadOptions::new().search_path("$TFS_TEST_DIR_738");
+        assert_eq!(
+            TfsDataFrame::<f64>::open_with("optics_738.tfs", &options)
+                .unwrap()
+                .len(),
+            10
+        );
+        assert!(TfsHeader::<f64>::read_with("optics_738.tfs", &options).is_ok());
+
+        let err = TfsDataFrame::<f64>::open_with("missing_738.tfs", &options)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("couldn't find 'missing_738.tfs'"), "{}", err);
+        assert!(
+            err.contains(&dir.path().join("missing_738.tfs").display().to_string()),
+            "{}",
+            err
+        );
+
+        std::env::set_var(SEARCH_PATH_VAR, dir.path());
+        assert!(TfsDataFrame::<f64>::open("optics_738.tfs").is_ok());
+        assert!(resolve_path(Path::new("/optics_738.tfs"), &[]).is_err());
+        std::env::remove_var(SEARCH_PATH_VAR);
+    }
+
+    #[test]
+    fn remote_fetchers() {
+        use crate::remote::{Fetcher, LocalFetcher};
+        use std::io::BufRead;
+
+        /// Serves a single file from memory, like a remote store would.
+        struct MemoryFetcher(Vec<u8>);
+
+        impl Fetcher for MemoryFetcher {
+            fn schemes(&self) -> &[&str] {
+                &["mem"]
+            }
+
+            fn fetch(&self, url: &str) -> anyhow::Result<Box<dyn BufRead>> {
+                let path = url.split('?').next().unwrap();
+                anyhow::ensure!(path == "mem://optics.tfs", "no such file '{}'", url);
+                Ok(Box::new(std::io::Cursor::new(self.0.clone())))
+            }
+        }
+
+        let df = testing::make_frame(&testing::FrameSpec::default());
+        let mut bytes = Vec::new();
+        df.write_to(&mut bytes).unwrap();
+        let fetcher = MemoryFetcher(bytes);
+
+        let read = TfsDataFrame::<f64>::open_url("mem://optics.tfs", &fetcher).unwrap();
+        assert!(read.diff(&df).unwrap().is_empty());
+        assert!(TfsDataFrame::<f64>::open_url("mem://other.tfs", &fetcher).is_err());
+        let versioned = TfsDataFrame::<f64>::open_url("mem://optics.tfs?tag=v1.gz", &fetcher);
+        assert_eq!(versioned.unwrap().len(), df.len());
+        let err = TfsDataFrame::<f64>::open_url("root://eos/optics.tfs", &fetcher).unwrap_err();
+        assert!(
+            err.to_string().contains("the fetcher reads mem://"),
+            "{}",
+            err
+        );
+        assert!(TfsDataFrame::<f64>::open_url("optics.tfs", &fetcher).is_err());
+
+        let options = TfsReadOptions::new().compressed_columns(&["BETX"]);
+        let local =
+            TfsDataFrame::<f64>::open_url_with("FILE://test/test.tfs", &LocalFetcher, &options)
+                .unwrap();
+        assert_eq!(local.len(), 5);
+        assert!(TfsDataFrame::<f64>::open_url("file://test/missing.tfs", &LocalFetcher).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let gzipped = dir.path().join("test.tfs.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&gzipped).unwrap(),
+            flate2::Compression::default(),
+        );
+        std::io::copy(
+            &mut std::fs::File::open("test/test.tfs").unwrap(),
+            &mut encoder,
+        )
+        .unwrap();
+        encoder.finish().unwrap();
+        let url = format!("file://{}", gzipped.display());
+        let unpacked = TfsDataFrame::<f64>::open_url(&url, &LocalFetcher).unwrap();
+        assert!(unpacked
+            .diff(&TfsDataFrame::open("test/test.tfs").unwrap())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[cfg(feature = "object-store")]
+    #[test]
+    fn object_store_fetcher() {
+        use crate::remote::ObjectStoreFetcher;
+
+        let df = testing::make_frame(&testing::FrameSpec {
+            n_elements: 5000,
+            ..Default::default()
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("optics.tfs.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&path).unwrap(),
+            flate2::Compression::fast(),
+        );
+        df.write_to(&mut encoder).unwrap();
+        encoder.finish().unwrap();
+
+        let url = format!("file://{}", path.display());
+        let read = TfsDataFrame::<f64>::open_object_store(&url).unwrap();
+        assert!(read.diff(&df).unwrap().is_empty());
+
+        let missing = format!("file://{}", dir.path().join("missing.tfs").display());
+        let err = TfsDataFrame::<f64>::open_object_store(&missing).unwrap_err();
+        assert!(err.to_string().contains("missing.tfs"), "{}", err);
+        let fetcher = ObjectStoreFetcher::new().option("AWS_REGION", "eu-west-1");
+        assert!(TfsDataFrame::<f64>::open_url("root://eos//f.tfs", &fetcher).is_err());
+        let fetcher = ObjectStoreFetcher::new().option("AWS_NO_SUCH_OPTION", "1");
+        let err = TfsDataFrame::<f64>::open_url("s3://bucket/f.tfs", &fetcher).unwrap_err();
+        assert!(err.to_string().contains("invalid store option"), "{}", err);
+    }
+
+    #[cfg(all(feature = "xrootd", unix))]
+    #[test]
+    fn xrdcp_fetcher() {
+        use crate::remote::XrdcpFetcher;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let fake = dir.path().join("xrdcp");
+        std::fs::write(
+            &fake,
+            "#!/bin/sh\ncase \"$2\" in root://*/test.tfs) cat test/test.tfs ;; \
+             *) echo \"[ERROR] no such file\" >&2; exit 54 ;; esac\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let fetcher = XrdcpFetcher::new().program(&fake);
+        let df = TfsDataFrame::<f64>::open_url("root://eosuser.cern.ch//eos/test.tfs", &fetcher)
+            .unwrap();
+        assert_eq!(df.len(), 5);
+        let err = TfsDataFrame::<f64>::open_url("root://eosuser.cern.ch//eos/x.tfs", &fetcher)
+            .unwrap_err();
+        assert!(err.to_string().contains("no such file"), "{}", err);
+        let missing = XrdcpFetcher::new().program(dir.path().join("nothing"));
+        assert!(TfsDataFrame::<f64>::open_url("root://host//f.tfs", &missing).is_err());
+    }
+
+    #[test]
+    fn truncated_file() {
+        let path = std::env::temp_dir().join("tfs_truncated_file.tfs");
+        let content = std::fs::read_to_string("test/test.tfs").unwrap();
+        let mut lines: Vec<&str> = content.lines().collect();
+        lines.insert(0, "@ NROWS            %d 5");
+        lines.pop();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        assert!(TfsDataFrame::<f64>::open(&path).is_err());
+    }
 }
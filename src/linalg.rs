@@ -0,0 +1,93 @@
+//! Dense linear algebra on column-major matrices, `Vec`s of columns.
+
+/// Sweeps of the Jacobi rotations before giving up on convergence.
+const MAX_SWEEPS: usize = 100;
+
+/// The thin singular value decomposition `A = U S Vᵀ` of the `m × n` matrix `a`, given by its
+/// `n` columns of length `m`. With `k = min(m, n)`, `U` has `k` columns of length `m`, `V` has
+/// `k` columns of length `n` and the `k` singular values are in descending order. Columns of
+/// `U` belonging to zero singular values are zero.
+pub(crate) fn svd(a: &[Vec<f64>]) -> (Vec<Vec<f64>>, Vec<f64>, Vec<Vec<f64>>) {
+    let m = a.first().map_or(0, Vec::len);
+    if m < a.len() {
+        // A = U S Vᵀ for Aᵀ = V S Uᵀ
+        let transposed: Vec<Vec<f64>> = (0..m)
+            .map(|row| a.iter().map(|column| column[row]).collect())
+            .collect();
+        let (u, s, v) = svd(&transposed);
+        return (v, s, u);
+    }
+
+    let (mut a, mut v) = (a.to_vec(), identity(a.len()));
+    one_sided_jacobi(&mut a, &mut v);
+
+    let s: Vec<f64> = a.iter().map(|column| norm(column)).collect();
+    let mut order: Vec<usize> = (0..s.len()).collect();
+    order.sort_by(|i, j| s[*j].total_cmp(&s[*i]));
+
+    let largest = order.first().map_or(0.0, |i| s[*i]);
+    let u = order
+        .iter()
+        .map(|i| {
+            if s[*i] > largest * f64::EPSILON * m as f64 {
+                a[*i].iter().map(|x| x / s[*i]).collect()
+            } else {
+                vec![0.0; m]
+            }
+        })
+        .collect();
+    let v = order.iter().map(|i| v[*i].clone()).collect();
+    (u, order.iter().map(|i| s[*i]).collect(), v)
+}
+
+/// Rotates pairs of columns of `a` until they are orthogonal, applying the same rotations to
+/// `v` (Hestenes' method). Afterwards the columns of `a` are the left singular vectors scaled
+/// by the singular values and `v` holds the right singular vectors.
+fn one_sided_jacobi(a: &mut [Vec<f64>], v: &mut [Vec<f64>]) {
+    let n = a.len();
+    for _ in 0..MAX_SWEEPS {
+        let mut rotated = false;
+        for p in 0..n {
+            for q in p + 1..n {
+                let alpha = dot(&a[p], &a[p]);
+                let beta = dot(&a[q], &a[q]);
+                let gamma = dot(&a[p], &a[q]);
+                if gamma.abs() <= f64::EPSILON * (alpha * beta).sqrt() || gamma == 0.0 {
+                    continue;
+                }
+                rotated = true;
+
+                let zeta = (beta - alpha) / (2.0 * gamma);
+                let t = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+                let c = 1.0 / (1.0 + t * t).sqrt();
+                let s = c * t;
+                rotate(a, p, q, c, s);
+                rotate(v, p, q, c, s);
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+}
+
+fn rotate(matrix: &mut [Vec<f64>], p: usize, q: usize, c: f64, s: f64) {
+    let (left, right) = matrix.split_at_mut(q);
+    for (x, y) in left[p].iter_mut().zip(right[0].iter_mut()) {
+        (*x, *y) = (c * *x - s * *y, s * *x + c * *y);
+    }
+}
+
+fn identity(n: usize) -> Vec<Vec<f64>> {
+    (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect()
+}
+
+fn dot(x: &[f64], y: &[f64]) -> f64 {
+    x.iter().zip(y).map(|(x, y)| x * y).sum()
+}
+
+fn norm(x: &[f64]) -> f64 {
+    dot(x, x).sqrt()
+}
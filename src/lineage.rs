@@ -0,0 +1,117 @@
+//! Provenance of derived columns.
+//!
+//! Columns created with [`TfsDataFrame::derive_column`] remember the expression and the input
+//! columns they were computed from. The lineage is written as comment lines in the header,
+//!
+//! ```text
+//! # LINEAGE BEAT_X [BETX,BETX_MDL] (BETX - BETX_MDL) / BETX_MDL
+//! ```
+//!
+//! and read back when the file is opened, so generated files describe themselves.
+use polars::prelude::NumericNative;
+use std::fmt;
+
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Marker of lineage comments in the header.
+pub(crate) const LINEAGE_TAG: &str = "LINEAGE";
+
+/// How a derived column was computed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lineage {
+    /// Human readable description of the computation.
+    pub expression: String,
+    /// The columns the derived column was computed from.
+    pub inputs: Vec<String>,
+}
+
+impl Lineage {
+    /// Parses a header comment line, returns the column name and its lineage if it is a lineage
+    /// comment.
+    pub(crate) fn parse_comment(line: &str) -> Option<(String, Lineage)> {
+        let mut line_it = line.split_whitespace();
+        if line_it.next() != Some("#") || line_it.next() != Some(LINEAGE_TAG) {
+            return None;
+        }
+        let column = line_it.next()?.to_owned();
+        let lineage = Lineage::parse(&line_it.collect::<Vec<_>>().join(" "))?;
+        Some((column, lineage))
+    }
+
+    /// Parses the part of a lineage comment following the column name, i.e.
+    /// `[INPUT1,INPUT2] expression`.
+    fn parse(text: &str) -> Option<Lineage> {
+        let text = text.trim().strip_prefix('[')?;
+        let (inputs, expression) = text.split_once(']')?;
+        Some(Lineage {
+            expression: expression.trim().to_owned(),
+            inputs: inputs
+                .split(',')
+                .filter(|i| !i.is_empty())
+                .map(String::from)
+                .collect(),
+        })
+    }
+}
+
+impl fmt::Display for Lineage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.inputs.join(","), self.expression)
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Computes the real column `name` row by row from the `inputs` columns and records its
+    /// lineage. `f` gets the values of the inputs in the given order, `expression` describes the
+    /// computation.
+    ///
+    /// ```
+    /// # use tfs::TfsDataFrame;
+    /// let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+    /// df.derive_column("BETX_BETY", &["BETX", "BETY"], "BETX / BETY", |v| v[0] / v[1])
+    ///     .unwrap();
+    ///
+    /// assert_eq!(df.lineage("BETX_BETY").unwrap().inputs, vec!["BETX", "BETY"]);
+    /// ```
+    pub fn derive_column<F>(
+        &mut self,
+        name: &str,
+        inputs: &[&str],
+        expression: &str,
+        f: F,
+    ) -> anyhow::Result<()>
+    where
+        F: Fn(&[f64]) -> f64,
+    {
+        let columns = inputs
+            .iter()
+            .map(|input| f64::from_column(self.column(input)?))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut row_values = vec![0.0; inputs.len()];
+        let values: Vec<f64> = (0..self.len())
+            .map(|row| {
+                for (value, column) in row_values.iter_mut().zip(&columns) {
+                    *value = column[row];
+                }
+                f(&row_values)
+            })
+            .collect();
+
+        self.set_column(f64::to_column(name, values))?;
+        self.lineage.insert(
+            name.to_owned(),
+            Lineage {
+                expression: expression.to_owned(),
+                inputs: inputs.iter().map(|i| String::from(*i)).collect(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Returns how the column `name` was derived, if it is known.
+    pub fn lineage(&self, name: &str) -> Option<&Lineage> {
+        self.lineage.get(name)
+    }
+}
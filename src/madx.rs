@@ -0,0 +1,230 @@
+//! Files for MAD-X's `READTABLE`.
+//!
+//! MAD-X reads a narrower dialect of tfs than this crate writes: only the type codes `%le`,
+//! `%d` and `%s`, strings in double quotes without a way to escape quotes, no comment lines and
+//! no missing values. Column names are compared ignoring case. [`TfsDataFrame::check_madx`]
+//! lists what keeps a frame from being read back by MAD-X, [`TfsDataFrame::to_madx`] fixes it
+//! where possible and [`TfsDataFrame::write_madx`] writes a file in either [`Compliance`] mode:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::madx::Compliance;
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! assert!(df.check_madx().is_empty());
+//!
+//! let dir = tempfile::tempdir().unwrap();
+//! df.write_madx(dir.path().join("twiss.tfs"), Compliance::Check).unwrap();
+//! ```
+use polars::prelude::{AnyValue, DataType, NamedFrom, NumericNative};
+use polars::series::Series;
+use std::path::Path;
+
+use crate::dataframe::DataValue;
+use crate::record::ColumnValue;
+use crate::schema::{Target, Violation};
+use crate::tfsdataframe::TfsDataFrame;
+use crate::types::ColumnKind;
+
+/// How [`TfsDataFrame::write_madx`] deals with a frame MAD-X can't read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compliance {
+    /// Fails, listing the problems.
+    #[default]
+    Check,
+    /// Writes the frame fixed by [`TfsDataFrame::to_madx`].
+    Enforce,
+}
+
+/// Replaces the characters MAD-X can't read in a quoted string.
+fn quotable(text: &str) -> String {
+    text.replace('"', "'").replace(['\n', '\r'], " ")
+}
+
+fn is_quotable(text: &str) -> bool {
+    !text.contains(['"', '\n', '\r'])
+}
+
+/// Replaces whitespace in a header key or column name.
+fn identifier(name: &str) -> String {
+    if name.is_empty() {
+        return "_".to_owned();
+    }
+    name.replace(char::is_whitespace, "_")
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && !name.contains(char::is_whitespace)
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Returns the reasons why MAD-X couldn't read the frame as written by
+    /// [`TfsDataFrame::write`], empty if it can.
+    pub fn check_madx(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let mut fail = |target: Target, name: &str, message: &str| {
+            violations.push(Violation {
+                target,
+                name: name.to_owned(),
+                message: message.to_owned(),
+                elements: Vec::new(),
+            })
+        };
+
+        for (key, value) in &self.properties {
+            if !is_identifier(key) {
+                fail(Target::Header, key, "is not a single word");
+            }
+            if let DataValue::Text(text) = value {
+                if !is_quotable(text) {
+                    fail(Target::Header, key, "has a double quote or line break");
+                }
+            }
+        }
+
+        let names = self.column_names();
+        for (i, name) in names.iter().enumerate() {
+            if !is_identifier(name) {
+                fail(Target::Column, name, "is not a single word");
+            }
+            if names[..i]
+                .iter()
+                .any(|other| other.eq_ignore_ascii_case(name))
+            {
+                fail(Target::Column, name, "is there twice, ignoring case");
+            }
+            if self.lineage.contains_key(*name) {
+                fail(Target::Column, name, "has a lineage comment");
+            }
+            if let Some((code, _)) = self.type_codes.get(*name) {
+                let kind = self.column(name).map(|c| ColumnKind::of_dtype(c.dtype()));
+                if kind.is_ok_and(|kind| code != kind.canonical_code() && !is_string_code(code)) {
+                    fail(
+                        Target::Column,
+                        name,
+                        "has a type code other than %le, %d and %s",
+                    );
+                }
+            }
+
+            let Ok(column) = self.column(name) else {
+                continue;
+            };
+            match column.dtype() {
+                DataType::Float64 | DataType::Float32 => {}
+                DataType::String => {
+                    let quotable = column
+                        .str()
+                        .is_ok_and(|values| values.into_iter().flatten().all(is_quotable));
+                    if !quotable {
+                        fail(Target::Column, name, "has a double quote or line break");
+                    }
+                }
+                dtype if dtype.is_integer() => {}
+                _ => fail(Target::Column, name, "is neither real, integer nor text"),
+            }
+            if column.null_count() > 0 {
+                fail(Target::Column, name, "has missing values");
+            }
+        }
+        violations
+    }
+
+    /// Returns a copy of the frame that MAD-X can read: whitespace in names is replaced by `_`
+    /// and double quotes in strings by single quotes, missing reals become `NaN`, missing
+    /// integers turn the column real and missing strings become empty. Boolean columns become
+    /// integers, other types text. Lineage comments and non-standard type codes are dropped.
+    /// Fails if column names only differ in case, or header keys are the same after replacing
+    /// whitespace.
+    pub fn to_madx(&self) -> anyhow::Result<TfsDataFrame<T>> {
+        let mut madx = self.with_rows(self.df()?.into_owned());
+        madx.lineage.clear();
+        madx.type_codes.clear();
+        madx.properties.clear();
+        for (key, value) in &self.properties {
+            let value = match value {
+                DataValue::Text(text) => DataValue::Text(quotable(text)),
+                value => value.clone(),
+            };
+            let new_key = identifier(key);
+            anyhow::ensure!(
+                !madx.properties.contains_key(&new_key),
+                "the header entry '{}' is there twice",
+                new_key
+            );
+            madx.properties.insert(new_key, value);
+        }
+
+        let mut columns = Vec::new();
+        for name in self.column_names() {
+            let column = self.column(name)?;
+            let new_name = identifier(name);
+            anyhow::ensure!(
+                !columns
+                    .iter()
+                    .any(|c: &Series| c.name().eq_ignore_ascii_case(&new_name)),
+                "the column '{}' is there twice, ignoring case",
+                new_name
+            );
+
+            let column = match column.dtype() {
+                DataType::Float64 | DataType::Float32 => {
+                    Series::new(new_name.into(), f64::from_column(column)?)
+                }
+                dtype if dtype.is_integer() && column.null_count() == 0 => {
+                    column.cast(&DataType::Int64)?.with_name(new_name.into())
+                }
+                dtype if dtype.is_integer() => {
+                    Series::new(new_name.into(), f64::from_column(column)?)
+                }
+                DataType::Boolean => column.cast(&DataType::Int64)?.with_name(new_name.into()),
+                _ => {
+                    let text = match column.dtype() {
+                        DataType::String => column.clone(),
+                        _ => column.cast(&DataType::String)?,
+                    };
+                    let values: Vec<String> = text
+                        .iter()
+                        .map(|value| match value {
+                            AnyValue::String(s) => quotable(s),
+                            AnyValue::StringOwned(s) => quotable(&s),
+                            _ => String::new(),
+                        })
+                        .collect();
+                    Series::new(new_name.into(), values)
+                }
+            };
+            columns.push(column);
+        }
+        madx.df = polars::prelude::DataFrame::new(columns.into_iter().map(Into::into).collect())?;
+        Ok(madx)
+    }
+
+    /// Writes the frame for MAD-X to `path`. With [`Compliance::Check`] it fails if MAD-X
+    /// couldn't read the frame, with [`Compliance::Enforce`] it writes
+    /// [`TfsDataFrame::to_madx`].
+    pub fn write_madx<P: AsRef<Path>>(
+        &self,
+        path: P,
+        compliance: Compliance,
+    ) -> anyhow::Result<()> {
+        match compliance {
+            Compliance::Check => {
+                let violations = self.check_madx();
+                if !violations.is_empty() {
+                    let reasons: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                    anyhow::bail!("MAD-X can't read the frame: {}", reasons.join("; "));
+                }
+                self.write(path)?;
+            }
+            Compliance::Enforce => self.to_madx()?.write(path)?,
+        }
+        Ok(())
+    }
+}
+
+/// `%s` with a width, like the `%05s` MAD-X writes itself.
+fn is_string_code(code: &str) -> bool {
+    code.strip_prefix('%')
+        .and_then(|code| code.strip_suffix('s'))
+        .is_some_and(|width| width.chars().all(|c| c.is_ascii_digit()))
+}
@@ -0,0 +1,272 @@
+//! Lists of bad elements (e.g. broken BPMs) and their removal from frames.
+//!
+//! An [`ElementMask`] is a set of element names, stored either as a plain text file with one name
+//! per line or as a tfs file with a `NAME` column. Applying it to a frame drops the rows of those
+//! elements or sets their real values to `NaN`:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::mask::{ElementMask, MaskAction};
+//! let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let mask = ElementMask::from_names(["BPM1", "BPMYB.5L2.B1"]);
+//!
+//! df.apply_mask(&mask, MaskAction::Drop).unwrap();
+//! assert_eq!(df.len(), 3);
+//! ```
+//!
+//! The frame remembers every masked element and why, e.g. which cleaning stage removed it. The
+//! [masked elements](TfsDataFrame::masked_frame) are a frame of their own, which can be written
+//! next to the cleaned file:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::mask::{ElementMask, MaskAction, MaskReason};
+//! let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let outliers = df.mask_from_condition("BETX", |b| b > 100.0).unwrap();
+//! df.mask_with_reason(&outliers, MaskAction::NaN, MaskReason::Range("BETX > 100".into()))
+//!     .unwrap();
+//!
+//! let masked = df.masked_frame().unwrap();
+//! assert_eq!(masked.column_names(), ["NAME", "REASON", "DETAIL", "ACTION"]);
+//! assert_eq!(masked.column("REASON").unwrap().str().unwrap().get(0), Some("RANGE"));
+//! ```
+use polars::prelude::{Column, DataFrame, DataType, NamedFrom, NumericNative};
+use polars::series::Series;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Column identifying the elements.
+pub const NAME_COLUMN: &str = "NAME";
+
+/// A set of element names.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ElementMask {
+    pub names: BTreeSet<String>,
+}
+
+/// What [`TfsDataFrame::apply_mask`] does with the masked rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaskAction {
+    /// Remove the rows.
+    Drop,
+    /// Keep the rows but set all real values to `NaN`.
+    NaN,
+}
+
+impl MaskAction {
+    /// The code in the `ACTION` column of [`TfsDataFrame::masked_frame`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            MaskAction::Drop => "DROP",
+            MaskAction::NaN => "NAN",
+        }
+    }
+}
+
+/// Why elements were masked, recorded by [`TfsDataFrame::mask_with_reason`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MaskReason {
+    /// A mask given by the user, e.g. a list of known broken BPMs.
+    #[default]
+    User,
+    /// A cleaning stage of an analysis, by name.
+    Cleaning(String),
+    /// A value out of its physical range, by the violated condition.
+    Range(String),
+}
+
+impl MaskReason {
+    /// The code in the `REASON` column of [`TfsDataFrame::masked_frame`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            MaskReason::User => "USER",
+            MaskReason::Cleaning(_) => "CLEANING",
+            MaskReason::Range(_) => "RANGE",
+        }
+    }
+
+    /// The name of the cleaning stage or the condition, empty for user masks.
+    pub fn detail(&self) -> &str {
+        match self {
+            MaskReason::User => "",
+            MaskReason::Cleaning(detail) | MaskReason::Range(detail) => detail,
+        }
+    }
+}
+
+/// An element masked in a frame, see [`TfsDataFrame::masked`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaskedElement {
+    pub name: String,
+    pub reason: MaskReason,
+    pub action: MaskAction,
+}
+
+impl ElementMask {
+    pub fn from_names<I, S>(names: I) -> ElementMask
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        ElementMask {
+            names: names.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Reads a mask. Files with the extension `.tfs` are read as tfs files and the `NAME` column
+    /// is used, other files are read as text with one name per line. Empty lines and lines
+    /// starting with `#` are ignored.
+    pub fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<ElementMask> {
+        let path = path.as_ref();
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("tfs"))
+        {
+            let df = TfsDataFrame::<f64>::open(path)?;
+            return Ok(ElementMask::from_names(String::from_column(
+                df.column(NAME_COLUMN)?,
+            )?));
+        }
+
+        let text = std::fs::read_to_string(path)?;
+        Ok(ElementMask::from_names(
+            text.lines()
+                .map(|line| line.trim().trim_matches('"'))
+                .filter(|line| !line.is_empty() && !line.starts_with('#')),
+        ))
+    }
+
+    /// Writes the mask as text, one name per line.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(File::create(path.as_ref())?);
+        for name in &self.names {
+            writeln!(writer, "{}", name)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Applies `mask` to the rows whose `NAME` is in the mask. Names in the mask that are not in
+    /// the frame are ignored. The elements are recorded as masked by the user.
+    pub fn apply_mask(&mut self, mask: &ElementMask, action: MaskAction) -> anyhow::Result<()> {
+        self.mask_with_reason(mask, action, MaskReason::User)
+    }
+
+    /// Applies `mask` like [`TfsDataFrame::apply_mask`] and records the masked elements with
+    /// `reason`, see the [module documentation](self).
+    pub fn mask_with_reason(
+        &mut self,
+        mask: &ElementMask,
+        action: MaskAction,
+        reason: MaskReason,
+    ) -> anyhow::Result<()> {
+        let names = String::from_column(self.column(NAME_COLUMN)?)?;
+        let masked: Vec<bool> = names.iter().map(|name| mask.contains(name)).collect();
+        self.masked.extend(
+            names
+                .into_iter()
+                .zip(&masked)
+                .filter(|(_, m)| **m)
+                .map(|(name, _)| MaskedElement {
+                    name,
+                    reason: reason.clone(),
+                    action,
+                }),
+        );
+
+        match action {
+            MaskAction::Drop => {
+                let keep: Vec<bool> = masked.iter().map(|m| !m).collect();
+                self.retain_rows(&keep)
+            }
+            MaskAction::NaN => {
+                let real_columns: Vec<String> = self
+                    .column_names()
+                    .into_iter()
+                    .filter(|name| {
+                        self.column(name)
+                            .is_ok_and(|c| c.dtype() == &DataType::Float64)
+                    })
+                    .map(String::from)
+                    .collect();
+
+                for name in real_columns {
+                    let values: Vec<f64> = f64::from_column(self.column(&name)?)?
+                        .into_iter()
+                        .zip(&masked)
+                        .map(|(v, m)| if *m { f64::NAN } else { v })
+                        .collect();
+                    // masking rows doesn't change how a derived column was computed
+                    let lineage = self.lineage.remove(&name);
+                    self.set_column(f64::to_column(&name, values))?;
+                    self.lineage.extend(lineage.map(|l| (name, l)));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// The elements masked so far, in the order they were masked. An element masked twice is
+    /// listed twice.
+    pub fn masked(&self) -> &[MaskedElement] {
+        &self.masked
+    }
+
+    /// The [masked elements](TfsDataFrame::masked) as a frame with the columns `NAME`,
+    /// `REASON`, `DETAIL` and `ACTION` and the header of this frame.
+    pub fn masked_frame(&self) -> anyhow::Result<TfsDataFrame<T>> {
+        let column = |name: &str, f: &dyn Fn(&MaskedElement) -> &str| -> Column {
+            let values: Vec<&str> = self.masked.iter().map(f).collect();
+            Series::new(name.into(), values).into()
+        };
+        let df = DataFrame::new(vec![
+            column(NAME_COLUMN, &|m| &m.name),
+            column("REASON", &|m| m.reason.code()),
+            column("DETAIL", &|m| m.reason.detail()),
+            column("ACTION", &|m| m.action.code()),
+        ])?;
+        Ok(TfsDataFrame::new(self.properties.clone(), df))
+    }
+
+    /// Writes the [masked elements](TfsDataFrame::masked_frame) as a tfs file, e.g. next to the
+    /// cleaned file.
+    pub fn write_masked<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        Ok(self.masked_frame()?.write(path)?)
+    }
+
+    /// Returns a mask of the elements for which `predicate` holds on the real column `column`,
+    /// e.g. BPMs with an unphysical beta function.
+    pub fn mask_from_condition<F>(&self, column: &str, predicate: F) -> anyhow::Result<ElementMask>
+    where
+        F: Fn(f64) -> bool,
+    {
+        let names = String::from_column(self.column(NAME_COLUMN)?)?;
+        let values = f64::from_column(self.column(column)?)?;
+        Ok(ElementMask::from_names(
+            names
+                .into_iter()
+                .zip(values)
+                .filter(|(_, v)| predicate(*v))
+                .map(|(name, _)| name),
+        ))
+    }
+}
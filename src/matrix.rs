@@ -0,0 +1,414 @@
+//! Relations between real columns.
+//!
+//! [`TfsDataFrame::correlation`] computes the correlation of every pair of the given columns.
+//! The result is a frame itself, with a `NAME` column naming the rows, so it can be written as
+//! tfs like any other table:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::matrix::Correlation;
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//!
+//! let correlation = df.correlation(&["BETX", "DX", "X"], Correlation::Pearson).unwrap();
+//! assert_eq!(correlation.column("BETX").unwrap().f64().unwrap().get(0), Some(1.0));
+//! ```
+//!
+//! [`TfsDataFrame::transpose`] swaps the rows and columns of such matrices.
+//!
+//! With the `linalg` feature, [`TfsDataFrame::svd`] decomposes the matrix of the columns, the
+//! rows being e.g. BPMs and the columns turns, and [`TfsDataFrame::pca`] finds its principal
+//! components, the spatial and temporal patterns of e.g. betatron oscillations.
+use polars::prelude::{Column, DataFrame, NamedFrom, NumericNative};
+use polars::series::Series;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use crate::dataframe::DataValue;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Column of the matrix frames naming the rows.
+pub const NAME_COLUMN: &str = "NAME";
+
+/// The singular value decomposition of [`TfsDataFrame::svd`].
+#[cfg(feature = "linalg")]
+pub struct Svd<T: std::str::FromStr + NumericNative> {
+    /// The left singular vectors, a column `MODE1`, `MODE2`, ... per mode and a row per row of
+    /// the decomposed frame. It has their `NAME` column, if there is one.
+    pub u: TfsDataFrame<T>,
+    /// The singular values in descending order, in the column `S`, and the `MODE` numbers.
+    pub s: TfsDataFrame<T>,
+    /// The right singular vectors, a row per mode, named `MODE1`, `MODE2`, ... in the `NAME`
+    /// column, and a column per decomposed column.
+    pub vt: TfsDataFrame<T>,
+}
+
+/// The principal components of [`TfsDataFrame::pca`].
+#[cfg(feature = "linalg")]
+pub struct Pca<T: std::str::FromStr + NumericNative> {
+    /// The spatial vectors, a column `MODE1`, `MODE2`, ... per mode and a row per row of the
+    /// decomposed frame. It has their `NAME` column, if there is one.
+    pub spatial: TfsDataFrame<T>,
+    /// The amplitudes of the modes, the temporal vectors scaled by the singular values: a row
+    /// per decomposed column, named in the `NAME` column, and a column per mode.
+    pub amplitudes: TfsDataFrame<T>,
+    /// The singular value `S` and the fraction of the variance, `VARIANCE_RATIO`, of the modes,
+    /// named in the `NAME` column.
+    pub modes: TfsDataFrame<T>,
+}
+
+/// How [`TfsDataFrame::correlation`] measures the relation of two columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Correlation {
+    /// The linear correlation of the values.
+    #[default]
+    Pearson,
+    /// The linear correlation of the ranks of the values, i.e. how monotonic the relation is.
+    /// Equal values get the mean of their ranks.
+    Spearman,
+}
+
+impl FromStr for Correlation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pearson" => Ok(Correlation::Pearson),
+            "spearman" => Ok(Correlation::Spearman),
+            _ => anyhow::bail!("unknown correlation '{}', expected pearson or spearman", s),
+        }
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Returns the correlation matrix of the real `columns`: a frame with a `NAME` column and a
+    /// column per entry of `columns`. Rows with `NaN` or missing values in any of the columns
+    /// are left out, columns with constant values have a correlation of `NaN`. The header has a
+    /// `TYPE` of `CORRELATION` and the `METHOD`.
+    pub fn correlation(
+        &self,
+        columns: &[&str],
+        method: Correlation,
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let (mut values, _) = self.complete_rows(columns)?;
+        if method == Correlation::Spearman {
+            values = values.iter().map(|v| ranks(v)).collect();
+        }
+
+        let matrix: Vec<Vec<f64>> = values
+            .iter()
+            .map(|x| values.iter().map(|y| pearson(x, y)).collect())
+            .collect();
+        let method = match method {
+            Correlation::Pearson => "PEARSON",
+            Correlation::Spearman => "SPEARMAN",
+        };
+        matrix_frame(
+            columns,
+            &matrix,
+            [
+                ("TYPE", DataValue::Text("CORRELATION".to_owned())),
+                ("METHOD", DataValue::Text(method.to_owned())),
+            ],
+        )
+    }
+
+    /// Returns the covariance matrix of the real `columns`, in the same form as
+    /// [`TfsDataFrame::correlation`]. It is the sample covariance, normalized by the number of
+    /// rows minus one. The header has a `TYPE` of `COVARIANCE`.
+    pub fn covariance(&self, columns: &[&str]) -> anyhow::Result<TfsDataFrame<T>> {
+        let (values, _) = self.complete_rows(columns)?;
+        let matrix: Vec<Vec<f64>> = values
+            .iter()
+            .map(|x| values.iter().map(|y| covariance(x, y)).collect())
+            .collect();
+        matrix_frame(
+            columns,
+            &matrix,
+            [("TYPE", DataValue::Text("COVARIANCE".to_owned()))],
+        )
+    }
+
+    /// Decomposes the matrix of the real `columns` into `U S Vᵀ`. Rows with `NaN` or missing
+    /// values in any of the columns are left out. With `m` rows and `n` columns there are
+    /// `min(m, n)` modes.
+    #[cfg(feature = "linalg")]
+    pub fn svd(&self, columns: &[&str]) -> anyhow::Result<Svd<T>> {
+        let (values, complete) = self.complete_rows(columns)?;
+        anyhow::ensure!(
+            !columns.is_empty() && complete.contains(&true),
+            "the decomposed matrix is empty"
+        );
+        let (u, s, v) = crate::linalg::svd(&values);
+        let modes = mode_names(s.len());
+
+        let mut u_columns = self.row_names(&complete)?;
+        for (mode, vector) in modes.iter().zip(u) {
+            u_columns.push(Series::new(mode.into(), vector).into());
+        }
+
+        let mode_numbers: Vec<i64> = (1..=s.len() as i64).collect();
+        let s = DataFrame::new(vec![
+            Series::new("MODE".into(), mode_numbers).into(),
+            Series::new("S".into(), s).into(),
+        ])?;
+
+        let mut vt_columns: Vec<Column> = vec![Series::new(NAME_COLUMN.into(), &modes).into()];
+        for (j, name) in columns.iter().enumerate() {
+            let values: Vec<f64> = v.iter().map(|vector| vector[j]).collect();
+            vt_columns.push(Series::new((*name).into(), values).into());
+        }
+
+        Ok(Svd {
+            u: TfsDataFrame::new(type_header("SVD_U"), DataFrame::new(u_columns)?),
+            s: TfsDataFrame::new(type_header("SVD_S"), s),
+            vt: TfsDataFrame::new(type_header("SVD_VT"), DataFrame::new(vt_columns)?),
+        })
+    }
+
+    /// Returns the first `n_modes` principal components of the matrix of the real `columns`,
+    /// e.g. the turns of turn-by-turn data with a row per BPM. The mean of every row is
+    /// subtracted before the decomposition. Rows with `NaN` or missing values in any of the
+    /// columns are left out.
+    #[cfg(feature = "linalg")]
+    pub fn pca(&self, columns: &[&str], n_modes: usize) -> anyhow::Result<Pca<T>> {
+        let (mut values, complete) = self.complete_rows(columns)?;
+        anyhow::ensure!(
+            !columns.is_empty() && complete.contains(&true),
+            "the decomposed matrix is empty"
+        );
+        let rows = values[0].len();
+        for row in 0..rows {
+            let mean = values.iter().map(|column| column[row]).sum::<f64>() / columns.len() as f64;
+            for column in values.iter_mut() {
+                column[row] -= mean;
+            }
+        }
+
+        let (u, s, v) = crate::linalg::svd(&values);
+        let total: f64 = s.iter().map(|s| s * s).sum();
+        let n_modes = n_modes.min(s.len());
+        let modes = mode_names(n_modes);
+
+        let mut spatial = self.row_names(&complete)?;
+        for (mode, vector) in modes.iter().zip(u) {
+            spatial.push(Series::new(mode.into(), vector).into());
+        }
+
+        let mut amplitudes: Vec<Column> = vec![Series::new(NAME_COLUMN.into(), columns).into()];
+        for (mode, (s, vector)) in modes.iter().zip(s.iter().zip(&v)) {
+            let values: Vec<f64> = vector.iter().map(|v| s * v).collect();
+            amplitudes.push(Series::new(mode.into(), values).into());
+        }
+
+        let s = &s[..n_modes];
+        let ratios: Vec<f64> = s.iter().map(|s| s * s / total).collect();
+        let mode_frame = DataFrame::new(vec![
+            Series::new(NAME_COLUMN.into(), &modes).into(),
+            Series::new("S".into(), s).into(),
+            Series::new("VARIANCE_RATIO".into(), ratios).into(),
+        ])?;
+
+        Ok(Pca {
+            spatial: TfsDataFrame::new(type_header("PCA_SPATIAL"), DataFrame::new(spatial)?),
+            amplitudes: TfsDataFrame::new(
+                type_header("PCA_AMPLITUDES"),
+                DataFrame::new(amplitudes)?,
+            ),
+            modes: TfsDataFrame::new(type_header("PCA_MODES"), mode_frame),
+        })
+    }
+
+    /// The `NAME` column of the `complete` rows, if there is one.
+    #[cfg(feature = "linalg")]
+    fn row_names(&self, complete: &[bool]) -> anyhow::Result<Vec<Column>> {
+        use polars::prelude::{BooleanChunked, NewChunkedArray};
+
+        match self.column(NAME_COLUMN) {
+            Ok(names) => {
+                let mask = BooleanChunked::from_slice("complete".into(), complete);
+                Ok(vec![names.filter(&mask)?.into()])
+            }
+            Err(_) => Ok(Vec::new()),
+        }
+    }
+
+    /// Swaps rows and columns, e.g. turns a response matrix with a row per knob into one with a
+    /// row per BPM. The text column `names_from` names the columns of the result, the numeric
+    /// columns become its rows, named in the new text column `index`:
+    ///
+    /// ```
+    /// # use tfs::TfsDataFrame;
+    /// let response = TfsDataFrame::<f64>::new(
+    ///     Vec::new(),
+    ///     tfs::polars::df!("KNOB" => ["KQ1", "KQ2"], "BPM1" => [0.1, 0.3], "BPM2" => [0.2, 0.4])
+    ///         .unwrap(),
+    /// );
+    /// let per_bpm = response.transpose("NAME", "KNOB").unwrap();
+    /// assert_eq!(per_bpm.column_names(), ["NAME", "KQ1", "KQ2"]);
+    /// assert_eq!(per_bpm.column("KQ2").unwrap().f64().unwrap().get(0), Some(0.3));
+    /// ```
+    ///
+    /// Integer and boolean columns are transposed as reals, other text columns are left out.
+    /// The names in `names_from` have to be present, unique and different from `index`. The
+    /// result has the header of this frame, missing values stay missing.
+    pub fn transpose(&self, index: &str, names_from: &str) -> anyhow::Result<TfsDataFrame<T>> {
+        let names = self.column(names_from)?;
+        anyhow::ensure!(
+            names.dtype().is_string(),
+            "column '{}' is not text and can't name columns",
+            names_from
+        );
+        let names = Option::<String>::from_column(names)?
+            .into_iter()
+            .enumerate()
+            .map(|(row, name)| {
+                name.ok_or_else(|| anyhow::anyhow!("the name in row {} is missing", row))
+            })
+            .collect::<anyhow::Result<Vec<String>>>()?;
+        let mut seen = HashSet::from([index]);
+        if let Some(name) = names.iter().find(|name| !seen.insert(name.as_str())) {
+            anyhow::bail!("the name '{}' is used for more than one column", name);
+        }
+
+        let mut transposed = Vec::new();
+        let mut values = Vec::new();
+        for name in self.column_names() {
+            let column = self.column(name)?;
+            if name == names_from
+                || !(column.dtype().is_primitive_numeric() || column.dtype().is_bool())
+            {
+                continue;
+            }
+            transposed.push(name);
+            values.push(Option::<f64>::from_column(column)?);
+        }
+
+        let mut columns: Vec<Column> = vec![Series::new(index.into(), &transposed).into()];
+        for (row, name) in names.iter().enumerate() {
+            let row_values = values.iter().map(|column| column[row]).collect();
+            columns.push(Option::<f64>::to_column(name, row_values).into());
+        }
+        Ok(TfsDataFrame::new(
+            self.properties.clone(),
+            DataFrame::new(columns)?,
+        ))
+    }
+
+    /// The values of the real `columns`, leaving out rows with `NaN` or missing values in any
+    /// of them, and which rows were kept.
+    fn complete_rows(&self, columns: &[&str]) -> anyhow::Result<(Vec<Vec<f64>>, Vec<bool>)> {
+        let mut values = Vec::with_capacity(columns.len());
+        for name in columns {
+            let column = self.column(name)?;
+            anyhow::ensure!(
+                column.dtype().is_primitive_numeric(),
+                "column '{}' is not numeric",
+                name
+            );
+            values.push(f64::from_column(column)?);
+        }
+
+        let complete: Vec<bool> = (0..self.len())
+            .map(|row| values.iter().all(|v| !v[row].is_nan()))
+            .collect();
+        let values = values
+            .into_iter()
+            .map(|v| {
+                v.into_iter()
+                    .zip(&complete)
+                    .filter(|(_, complete)| **complete)
+                    .map(|(v, _)| v)
+                    .collect()
+            })
+            .collect();
+        Ok((values, complete))
+    }
+}
+
+#[cfg(feature = "linalg")]
+fn mode_names(n: usize) -> Vec<String> {
+    (1..=n).map(|i| format!("MODE{}", i)).collect()
+}
+
+#[cfg(feature = "linalg")]
+fn type_header<T>(kind: &str) -> [(String, DataValue<T>); 1] {
+    [("TYPE".to_owned(), DataValue::Text(kind.to_owned()))]
+}
+
+/// A frame holding the square `matrix` with rows and columns named by `names`.
+fn matrix_frame<T, const N: usize>(
+    names: &[&str],
+    matrix: &[Vec<f64>],
+    header: [(&str, DataValue<T>); N],
+) -> anyhow::Result<TfsDataFrame<T>>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    let mut columns: Vec<Column> = vec![Series::new(NAME_COLUMN.into(), names).into()];
+    for (i, name) in names.iter().enumerate() {
+        let values: Vec<f64> = matrix.iter().map(|row| row[i]).collect();
+        columns.push(Series::new((*name).into(), values).into());
+    }
+    Ok(TfsDataFrame::new(
+        header.map(|(key, value)| (key.to_owned(), value)),
+        DataFrame::new(columns)?,
+    ))
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// The sample covariance of `x` and `y`, `NaN` for less than two values.
+fn covariance(x: &[f64], y: &[f64]) -> f64 {
+    if x.len() < 2 {
+        return f64::NAN;
+    }
+    let (mean_x, mean_y) = (mean(x), mean(y));
+    let sum: f64 = x
+        .iter()
+        .zip(y)
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    sum / (x.len() - 1) as f64
+}
+
+/// The Pearson correlation of `x` and `y`, `NaN` if either is constant.
+fn pearson(x: &[f64], y: &[f64]) -> f64 {
+    let (mean_x, mean_y) = (mean(x), mean(y));
+    let mut covariance = 0.0;
+    let mut variance_x = 0.0;
+    let mut variance_y = 0.0;
+    for (x, y) in x.iter().zip(y) {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return f64::NAN;
+    }
+    covariance / (variance_x * variance_y).sqrt()
+}
+
+/// The ranks of `values`, starting at 1. Equal values get the mean of their ranks.
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|a, b| values[*a].total_cmp(&values[*b]));
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        // ranks start + 1 to end, inclusive
+        let rank = (start + 1 + end) as f64 / 2.0;
+        for i in &order[start..end] {
+            ranks[*i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
@@ -0,0 +1,219 @@
+//! Translating element names between naming conventions.
+//!
+//! The same element has different names in MAD-X, in the control system (LSA) and in the
+//! measurement devices. A [`NameTranslator`] maps a name of one convention to another, or tells
+//! that it doesn't know it. The translators of this module are a fixed [`NameTable`], e.g. read
+//! from a file with a column per convention, and a [`NameRule`] rewriting names with a regular
+//! expression; a [`NameChain`] tries several in turn:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::naming::{NameChain, NameRule, NameTable};
+//! let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let translator = NameChain::new()
+//!     .with(NameTable::from_pairs([("BPM1", "BPM.1L1.B1")]))
+//!     .with(NameRule::new(r"^BPM(\w+)\.(\w+)\.B1$", "BPM$1.$2.B2").unwrap());
+//!
+//! let report = df.translate_names(&translator).unwrap();
+//! println!("{}", report);
+//! ```
+//!
+//! Names without a translation are kept and listed in the [`NamingReport`].
+use polars::prelude::{NamedFrom, NumericNative};
+use polars::series::Series;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::mask::NAME_COLUMN;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Translates element names from one naming convention to another.
+pub trait NameTranslator {
+    /// The name of the element `name` in the target convention, `None` if it is unknown.
+    fn translate(&self, name: &str) -> Option<String>;
+}
+
+impl<F: Fn(&str) -> Option<String>> NameTranslator for F {
+    fn translate(&self, name: &str) -> Option<String> {
+        self(name)
+    }
+}
+
+/// A fixed table of names and their translations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NameTable {
+    pub names: HashMap<String, String>,
+}
+
+impl NameTable {
+    pub fn from_pairs<I, S, U>(pairs: I) -> NameTable
+    where
+        I: IntoIterator<Item = (S, U)>,
+        S: Into<String>,
+        U: Into<String>,
+    {
+        NameTable {
+            names: pairs
+                .into_iter()
+                .map(|(from, to)| (from.into(), to.into()))
+                .collect(),
+        }
+    }
+
+    /// Reads the table from the text columns `from` and `to` of a tfs file, e.g. `NAME` and
+    /// `LSA_NAME`. Rows with a missing value are skipped.
+    pub fn open<P: AsRef<Path>>(path: P, from: &str, to: &str) -> anyhow::Result<NameTable> {
+        let df = TfsDataFrame::<f64>::open(path)?;
+        let froms = Option::<String>::from_column(df.column(from)?)?;
+        let tos = Option::<String>::from_column(df.column(to)?)?;
+        Ok(NameTable::from_pairs(
+            froms
+                .into_iter()
+                .zip(tos)
+                .filter_map(|(from, to)| Some((from?, to?))),
+        ))
+    }
+
+    /// The table translating the other way.
+    pub fn inverse(&self) -> NameTable {
+        NameTable::from_pairs(
+            self.names
+                .iter()
+                .map(|(from, to)| (to.clone(), from.clone())),
+        )
+    }
+}
+
+impl NameTranslator for NameTable {
+    fn translate(&self, name: &str) -> Option<String> {
+        self.names.get(name).cloned()
+    }
+}
+
+/// Translates the names matching a regular expression by replacing the match, e.g.
+/// `^(.*)\.B1$` by `$1.B2`. Other names are unknown.
+#[derive(Debug, Clone)]
+pub struct NameRule {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl NameRule {
+    pub fn new(pattern: &str, replacement: &str) -> anyhow::Result<NameRule> {
+        Ok(NameRule {
+            pattern: Regex::new(pattern)?,
+            replacement: replacement.to_owned(),
+        })
+    }
+}
+
+impl NameTranslator for NameRule {
+    fn translate(&self, name: &str) -> Option<String> {
+        self.pattern
+            .is_match(name)
+            .then(|| self.pattern.replace(name, &self.replacement).into_owned())
+    }
+}
+
+/// Translators tried in turn, the first that knows a name translates it.
+#[derive(Default)]
+pub struct NameChain {
+    translators: Vec<Box<dyn NameTranslator>>,
+}
+
+impl NameChain {
+    pub fn new() -> NameChain {
+        NameChain::default()
+    }
+
+    pub fn with<N: NameTranslator + 'static>(mut self, translator: N) -> Self {
+        self.translators.push(Box::new(translator));
+        self
+    }
+}
+
+impl NameTranslator for NameChain {
+    fn translate(&self, name: &str) -> Option<String> {
+        self.translators.iter().find_map(|t| t.translate(name))
+    }
+}
+
+/// The outcome of [`TfsDataFrame::translate_names`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NamingReport {
+    /// The number of translated names.
+    pub translated: usize,
+    /// The names without a translation, in the order of their first row, each once.
+    pub unmatched: Vec<String>,
+}
+
+impl NamingReport {
+    /// `true` if every name was translated.
+    pub fn is_complete(&self) -> bool {
+        self.unmatched.is_empty()
+    }
+}
+
+impl fmt::Display for NamingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} names translated", self.translated)?;
+        if !self.unmatched.is_empty() {
+            write!(
+                f,
+                ", {} unknown: {}",
+                self.unmatched.len(),
+                self.unmatched.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Translates the element names of the `NAME` column with `translator`, see the
+    /// [module documentation](crate::naming).
+    pub fn translate_names<N>(&mut self, translator: &N) -> anyhow::Result<NamingReport>
+    where
+        N: NameTranslator + ?Sized,
+    {
+        self.translate_column(NAME_COLUMN, translator)
+    }
+
+    /// Translates the names in the text column `column` with `translator`. Names without a
+    /// translation and missing values are kept.
+    pub fn translate_column<N>(
+        &mut self,
+        column: &str,
+        translator: &N,
+    ) -> anyhow::Result<NamingReport>
+    where
+        N: NameTranslator + ?Sized,
+    {
+        let names = Option::<String>::from_column(self.column(column)?)
+            .map_err(|_| anyhow::anyhow!("'{}' is not a text column", column))?;
+        let mut report = NamingReport::default();
+        let names: Vec<Option<String>> = names
+            .into_iter()
+            .map(|name| {
+                let name = name?;
+                match translator.translate(&name) {
+                    Some(translated) => {
+                        report.translated += 1;
+                        Some(translated)
+                    }
+                    None => {
+                        if !report.unmatched.contains(&name) {
+                            report.unmatched.push(name.clone());
+                        }
+                        Some(name)
+                    }
+                }
+            })
+            .collect();
+        self.set_column(Series::new(column.into(), names))?;
+        Ok(report)
+    }
+}
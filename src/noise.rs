@@ -0,0 +1,116 @@
+//! Random noise on columns, for Monte-Carlo studies.
+//!
+//! [`TfsDataFrame::add_noise`] returns a copy of a frame, e.g. a model twiss, with random
+//! numbers added to some columns. The size of the noise is given per column, either as a fixed
+//! standard deviation or by the error column of each row:
+//!
+//! ```
+//! # use tfs::{ErrorPrefix, TfsDataFrame};
+//! # use tfs::noise::{NoiseDistribution, Sigma};
+//! let model = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//!
+//! let noisy = model
+//!     .add_noise(
+//!         &[("BETX", Sigma::Fixed(0.5)), ("X", Sigma::Fixed(1e-4))],
+//!         NoiseDistribution::Gaussian,
+//!         42,
+//!     )
+//!     .unwrap();
+//! assert_ne!(noisy.column("BETX").unwrap(), model.column("BETX").unwrap());
+//! ```
+use polars::prelude::{NamedFrom, NumericNative};
+use polars::series::Series;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, StandardNormal};
+use std::str::FromStr;
+
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+use crate::uncertainty::ErrorPrefix;
+
+/// The distribution of the noise, both with a mean of zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoiseDistribution {
+    #[default]
+    Gaussian,
+    /// Uniform in `[-√3 σ, √3 σ]`, so that the standard deviation is `σ`.
+    Uniform,
+}
+
+impl FromStr for NoiseDistribution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "gaussian" | "normal" => Ok(NoiseDistribution::Gaussian),
+            "uniform" => Ok(NoiseDistribution::Uniform),
+            _ => anyhow::bail!("unknown distribution '{}', expected gaussian or uniform", s),
+        }
+    }
+}
+
+/// The standard deviation of the noise on a column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sigma {
+    /// The same for all rows.
+    Fixed(f64),
+    /// The value of the error column of each row, i.e. `ERRBETX` for `BETX` with
+    /// [`ErrorPrefix::Err`]. Rows with a missing error get no noise.
+    ErrorColumn(ErrorPrefix),
+}
+
+impl NoiseDistribution {
+    pub(crate) fn sample(&self, rng: &mut StdRng, sigma: f64) -> f64 {
+        match self {
+            NoiseDistribution::Gaussian => {
+                sigma * <StandardNormal as Distribution<f64>>::sample(&StandardNormal, rng)
+            }
+            NoiseDistribution::Uniform => sigma * 3f64.sqrt() * rng.random_range(-1.0..=1.0),
+        }
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Returns a copy of the frame with noise of `distribution` added to the real `columns`.
+    /// The same `seed` gives the same noise. Missing values stay missing.
+    pub fn add_noise(
+        &self,
+        columns: &[(&str, Sigma)],
+        distribution: NoiseDistribution,
+        seed: u64,
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut noisy = self.with_rows(self.df()?.into_owned());
+
+        for (name, sigma) in columns {
+            let column = self.column(name)?;
+            anyhow::ensure!(column.dtype().is_float(), "column '{}' is not real", name);
+            let sigmas = match sigma {
+                Sigma::Fixed(sigma) => {
+                    anyhow::ensure!(
+                        *sigma >= 0.0,
+                        "the noise on '{}' has a negative standard deviation",
+                        name
+                    );
+                    vec![*sigma; self.len()]
+                }
+                Sigma::ErrorColumn(prefix) => {
+                    let error_name = format!("{}{}", prefix.prefix(), name);
+                    f64::from_column(self.column(&error_name)?)?
+                        .into_iter()
+                        .map(|error| if error.is_nan() { 0.0 } else { error.abs() })
+                        .collect()
+                }
+            };
+
+            let values: Vec<Option<f64>> = Option::<f64>::from_column(column)?
+                .into_iter()
+                .zip(sigmas)
+                .map(|(value, sigma)| value.map(|v| v + distribution.sample(&mut rng, sigma)))
+                .collect();
+            noisy.set_column(Series::new((*name).into(), values))?;
+        }
+        Ok(noisy)
+    }
+}
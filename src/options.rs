@@ -0,0 +1,169 @@
+//! Options for reading tfs files, see [`TfsDataFrame::open_with`](crate::TfsDataFrame::open_with).
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::dialect::Dialect;
+use crate::types::{ColumnKind, TypeRegistry};
+
+/// What happens with rows that have less fields than there are columns. The default is
+/// [`MissingFields::Null`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissingFields {
+    /// Reading the file fails.
+    Error,
+    /// The missing fields are null.
+    Null,
+    /// Missing real fields are `real`, missing text fields are `text`.
+    Fill { real: f64, text: String },
+}
+
+/// Configures how tfs files are parsed. The default is what [`TfsDataFrame::open`] uses.
+///
+/// ```
+/// # use tfs::{TfsDataFrame, TfsReadOptions};
+/// let options = TfsReadOptions::new().fortran_exponents(false);
+/// let df = TfsDataFrame::<f64>::open_with("test/test.tfs", &options).unwrap();
+/// ```
+///
+/// [`TfsDataFrame::open`]: crate::TfsDataFrame::open
+#[derive(Debug, Clone)]
+pub struct TfsReadOptions {
+    pub(crate) fortran_exponents: bool,
+    pub(crate) decimal_comma: bool,
+    pub(crate) missing_fields: MissingFields,
+    pub(crate) types: TypeRegistry,
+    pub(crate) nan_sentinels: Vec<f64>,
+    pub(crate) column_nan_sentinels: HashMap<String, Vec<f64>>,
+    pub(crate) compressed_columns: Vec<String>,
+    pub(crate) dialect: Option<Dialect>,
+    pub(crate) max_threads: Option<usize>,
+    pub(crate) deterministic: bool,
+    pub(crate) max_memory: Option<usize>,
+    pub(crate) search_path: Vec<PathBuf>,
+}
+
+impl Default for TfsReadOptions {
+    fn default() -> Self {
+        TfsReadOptions {
+            fortran_exponents: true,
+            decimal_comma: false,
+            missing_fields: MissingFields::Null,
+            types: TypeRegistry::default(),
+            nan_sentinels: Vec::new(),
+            column_nan_sentinels: HashMap::new(),
+            compressed_columns: Vec::new(),
+            dialect: None,
+            max_threads: None,
+            deterministic: false,
+            max_memory: None,
+            search_path: Vec::new(),
+        }
+    }
+}
+
+impl TfsReadOptions {
+    pub fn new() -> TfsReadOptions {
+        TfsReadOptions::default()
+    }
+
+    /// Accept Fortran style exponents (`1.234D-05`) in real numbers, enabled by default.
+    pub fn fortran_exponents(mut self, enabled: bool) -> Self {
+        self.fortran_exponents = enabled;
+        self
+    }
+
+    /// Accept real numbers with a decimal comma (`1,23`), as found in some hand-edited files.
+    /// Disabled by default. Every value read this way is listed in
+    /// [`TfsDataFrame::parse_warnings`](crate::TfsDataFrame::parse_warnings).
+    pub fn decimal_comma(mut self, enabled: bool) -> Self {
+        self.decimal_comma = enabled;
+        self
+    }
+
+    /// How to read rows with missing fields at the end, by default the missing fields are null.
+    /// [`MissingFields::Error`] rejects such files.
+    pub fn missing_fields(mut self, missing_fields: MissingFields) -> Self {
+        self.missing_fields = missing_fields;
+        self
+    }
+
+    /// Reads columns with the type code `code` as `kind`, see [`TypeRegistry`].
+    pub fn register_type(mut self, code: &str, kind: ColumnKind) -> Self {
+        self.types.register(code, kind);
+        self
+    }
+
+    /// Reads `value` as `NaN` in all real columns, for writers that encode missing data as e.g.
+    /// `-999999` or `1e99`. Can be given several times. When the frame is written, `NaN`s are
+    /// written as the first sentinel again.
+    pub fn nan_sentinel(mut self, value: f64) -> Self {
+        self.nan_sentinels.push(value);
+        self
+    }
+
+    /// Like [`TfsReadOptions::nan_sentinel`] for the column `column` only. Sentinels of a column
+    /// replace the global ones.
+    pub fn column_nan_sentinel(mut self, column: &str, value: f64) -> Self {
+        self.column_nan_sentinels
+            .entry(column.to_owned())
+            .or_default()
+            .push(value);
+        self
+    }
+
+    /// The `NaN` sentinels of the column `column`.
+    pub(crate) fn sentinels_of(&self, column: &str) -> &[f64] {
+        self.column_nan_sentinels
+            .get(column)
+            .map_or(&self.nan_sentinels, |sentinels| sentinels)
+    }
+
+    /// Keep the given columns lz4-compressed in memory until they are first accessed, see
+    /// [`TfsDataFrame::compress_column`](crate::TfsDataFrame::compress_column). Columns missing in
+    /// the file are ignored.
+    pub fn compressed_columns(mut self, names: &[&str]) -> Self {
+        self.compressed_columns = names.iter().map(|n| String::from(*n)).collect();
+        self
+    }
+
+    /// Reads the file in `dialect` instead of detecting it, see [`Dialect::detect`]. Booleans
+    /// and tables are then only read in dialects that have them, otherwise they are text.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = Some(dialect);
+        self
+    }
+
+    /// Uses at most `n` threads (at least one) to read several files in parallel, e.g. in
+    /// [`TfsPipeline::run_all`](crate::pipeline::TfsPipeline::run_all). By default one thread per
+    /// core is used. A single file is always read by one thread, so its values and
+    /// [parse warnings](crate::TfsDataFrame::parse_warnings), in the order of the lines, don't
+    /// depend on the number of threads.
+    pub fn max_threads(mut self, n: usize) -> Self {
+        self.max_threads = Some(n.max(1));
+        self
+    }
+
+    /// Reads and processes files one after the other on the calling thread, in the order they
+    /// are given, whatever [`TfsReadOptions::max_threads`] says. Custom
+    /// [pipeline stages](crate::pipeline::TfsPipeline::stage) then see the files in a fixed
+    /// order too, for reproducible runs. Disabled by default.
+    pub fn deterministic(mut self, enabled: bool) -> Self {
+        self.deterministic = enabled;
+        self
+    }
+
+    /// Fails reading a file once its columns would take more than about `bytes` of memory,
+    /// instead of exhausting the memory of the process. The limit applies to every file read
+    /// with these options, there is none by default.
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// Looks for relative paths that don't exist in the working directory in `dir`, before the
+    /// directories of `TFS_PATH`, see [`crate::paths`]. Can be given several times.
+    pub fn search_path<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.search_path.push(dir.as_ref().to_owned());
+        self
+    }
+}
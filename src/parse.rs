@@ -0,0 +1,106 @@
+//! Parsing of single values of a tfs file.
+use std::fmt;
+use std::str::FromStr;
+
+use crate::options::TfsReadOptions;
+
+/// Parses a real number.
+///
+/// Besides everything `str::parse` accepts (leading `+`, `inf`, `infinity` and `nan` in any
+/// case, with or without sign), Fortran style exponents (`1.234D-05`, `1.234d-05`) are accepted
+/// if enabled in the options.
+pub(crate) fn parse_real<N: FromStr>(token: &str, options: &TfsReadOptions) -> Option<N> {
+    if let Ok(value) = token.parse() {
+        return Some(value);
+    }
+
+    if options.fortran_exponents && token.contains(['D', 'd']) {
+        return token.replace(['D', 'd'], "e").parse().ok();
+    }
+
+    None
+}
+
+/// Parses a real number like [`parse_real`], additionally accepting a decimal comma (`1,23`) if
+/// enabled in the options. The flag tells if the value was only accepted because of the comma.
+pub(crate) fn parse_real_lenient<N: FromStr>(
+    token: &str,
+    options: &TfsReadOptions,
+) -> Option<(N, bool)> {
+    if let Some(value) = parse_real(token, options) {
+        return Some((value, false));
+    }
+
+    // a single comma and no dot, otherwise it might be a thousands separator
+    if options.decimal_comma && token.matches(',').count() == 1 && !token.contains('.') {
+        return parse_real(&token.replace(',', "."), options).map(|value| (value, true));
+    }
+
+    None
+}
+
+/// Data-quality issues found while reading a file: values that could only be read by being
+/// lenient (see [`TfsReadOptions`]), values that couldn't be read at all and ignored lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// A real number in a column was written with a decimal comma.
+    DecimalComma {
+        column: String,
+        row: usize,
+        token: String,
+    },
+    /// A real header value was written with a decimal comma.
+    DecimalCommaProperty { key: String, token: String },
+    /// The type code of a column is not in the type registry, the column was read as text.
+    UnknownTypeCode { column: String, code: String },
+    /// A field of a real column isn't a number and was read as `NaN`.
+    NanCoercion {
+        column: String,
+        row: usize,
+        token: String,
+    },
+    /// A field of an integer or boolean column isn't a valid value and was read as missing.
+    NullCoercion {
+        column: String,
+        row: usize,
+        token: String,
+    },
+    /// A line that is neither a header line nor a data row was ignored.
+    SkippedLine { line: String },
+    /// A header key appears more than once, the last value is kept.
+    DuplicateHeader { key: String },
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseWarning::DecimalComma { column, row, token } => write!(
+                f,
+                "decimal comma in column '{}', row {}: '{}'",
+                column, row, token
+            ),
+            ParseWarning::DecimalCommaProperty { key, token } => {
+                write!(f, "decimal comma in header '{}': '{}'", key, token)
+            }
+            ParseWarning::UnknownTypeCode { column, code } => write!(
+                f,
+                "unknown type code '{}' of column '{}', read as text",
+                code, column
+            ),
+            ParseWarning::NanCoercion { column, row, token } => write!(
+                f,
+                "'{}' in column '{}', row {} is not a number, read as NaN",
+                token, column, row
+            ),
+            ParseWarning::NullCoercion { column, row, token } => write!(
+                f,
+                "'{}' in column '{}', row {} is not a valid value, read as missing",
+                token, column, row
+            ),
+            ParseWarning::SkippedLine { line } => write!(f, "skipped line '{}'", line),
+            ParseWarning::DuplicateHeader { key } => {
+                write!(f, "the header '{}' appears more than once", key)
+            }
+        }
+    }
+}
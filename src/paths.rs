@@ -0,0 +1,115 @@
+//! Finding the files given to [`TfsDataFrame::open`](crate::TfsDataFrame::open).
+//!
+//! Paths may start with `~` for the home directory and contain environment variables as `$VAR`
+//! or `${VAR}`. Relative paths that don't exist in the working directory are looked up in the
+//! [search path of the read options](crate::TfsReadOptions::search_path) and then in the
+//! directories of the environment variable [`SEARCH_PATH_VAR`], separated like in `PATH`:
+//!
+//! ```
+//! # use tfs::paths::resolve_path;
+//! # use std::path::{Path, PathBuf};
+//! let found = resolve_path(Path::new("test.tfs"), &[PathBuf::from("test")]).unwrap();
+//! assert_eq!(found, Path::new("test/test.tfs"));
+//!
+//! let err = resolve_path(Path::new("missing.tfs"), &[PathBuf::from("test")]).unwrap_err();
+//! assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+//! assert!(err.to_string().contains("test/missing.tfs"));
+//! ```
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The environment variable with the directories searched for relative paths.
+pub const SEARCH_PATH_VAR: &str = "TFS_PATH";
+
+/// Expands a leading `~` and the environment variables in `path`. Fails if a variable isn't set.
+/// Paths that aren't valid UTF-8 are returned unchanged.
+pub fn expand_path(path: &Path) -> io::Result<PathBuf> {
+    let Some(text) = path.to_str() else {
+        return Ok(path.to_owned());
+    };
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+        let home = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .map_err(|_| invalid(format!("no home directory to expand '~' in '{}'", text)))?;
+        expanded.push_str(&home);
+        rest = &rest[1..];
+    }
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced
+                    .find('}')
+                    .ok_or_else(|| invalid(format!("unterminated '${{' in '{}'", text)))?;
+                (&braced[..end], end + 2)
+            }
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        if name.is_empty() {
+            expanded.push('$');
+        } else {
+            let value = std::env::var(name).map_err(|_| {
+                invalid(format!(
+                    "the environment variable '{}' in '{}' is not set",
+                    name, text
+                ))
+            })?;
+            expanded.push_str(&value);
+        }
+        rest = &after[len..];
+    }
+    expanded.push_str(rest);
+    Ok(PathBuf::from(expanded))
+}
+
+/// The file `path` refers to, see the [module documentation](self). A path that exists as it is
+/// isn't expanded, so that names with a literal `$` or `~` are found. The directories of
+/// `search_path` are searched before those of [`SEARCH_PATH_VAR`]. Fails with
+/// [`io::ErrorKind::NotFound`] listing every location tried.
+pub fn resolve_path(path: &Path, search_path: &[PathBuf]) -> io::Result<PathBuf> {
+    if path.exists() {
+        return Ok(path.to_owned());
+    }
+    let expanded = expand_path(path)?;
+    let mut tried = vec![expanded.clone()];
+    if expanded.exists() {
+        return Ok(expanded);
+    }
+
+    if expanded.is_relative() {
+        let mut dirs = search_path.to_vec();
+        if let Some(var) = std::env::var_os(SEARCH_PATH_VAR) {
+            dirs.extend(std::env::split_paths(&var).filter(|dir| !dir.as_os_str().is_empty()));
+        }
+        for dir in dirs {
+            let candidate = expand_path(&dir)?.join(&expanded);
+            if candidate.exists() {
+                return Ok(candidate);
+            }
+            tried.push(candidate);
+        }
+    }
+
+    let tried: Vec<String> = tried.iter().map(|p| p.display().to_string()).collect();
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!(
+            "couldn't find '{}', tried {}",
+            path.display(),
+            tried.join(", ")
+        ),
+    ))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
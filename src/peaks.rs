@@ -0,0 +1,74 @@
+//! Local maxima of columns, e.g. lines of a spectrum or the maxima of the beta functions.
+//!
+//! [`TfsDataFrame::find_peaks`] returns the rows of the peaks as a frame with their position in
+//! `ROW`, the names and positions of the elements if the frame has `NAME` and `S`, and the
+//! values:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let peaks = df.find_peaks("BETX", 0.0, 1).unwrap();
+//! assert_eq!(peaks.column_names(), ["ROW", "NAME", "S", "BETX"]);
+//! ```
+//!
+//! Minima are the peaks of the negated column, see [`TfsDataFrame::eval_column`].
+use polars::prelude::NumericNative;
+use std::cmp::Ordering;
+
+use crate::calculus::S_COLUMN;
+use crate::cumulative::numbers;
+use crate::mask::NAME_COLUMN;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// The column of the peaks frame holding the rows of the peaks.
+pub const ROW_COLUMN: &str = "ROW";
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// The peaks of the numeric column `name` of at least `min_height`, see the
+    /// [module documentation](self). A peak is higher than the rows on both sides, a plateau
+    /// counts once at its middle, the first and last rows are no peaks. Of peaks closer than
+    /// `min_distance` rows only the highest is kept. The header is kept.
+    pub fn find_peaks(
+        &self,
+        name: &str,
+        min_height: f64,
+        min_distance: usize,
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let values = numbers(self, name)?;
+        let mut peaks = Vec::new();
+        let mut row = 1;
+        while row + 1 < values.len() {
+            let (Some(before), Some(value)) = (values[row - 1], values[row]) else {
+                row += 1;
+                continue;
+            };
+            let end = (row..values.len())
+                .find(|j| values[*j] != Some(value))
+                .unwrap_or(values.len());
+            let after = values.get(end).copied().flatten();
+            if value > before && after.is_some_and(|after| value > after) && value >= min_height {
+                peaks.push(((row + end - 1) / 2, value));
+            }
+            row = end;
+        }
+
+        peaks.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        let mut kept: Vec<usize> = Vec::new();
+        for (row, _) in peaks {
+            if kept.iter().all(|k| k.abs_diff(row) >= min_distance) {
+                kept.push(row);
+            }
+        }
+        kept.sort_unstable();
+
+        let columns: Vec<&str> = [NAME_COLUMN, S_COLUMN]
+            .into_iter()
+            .filter(|c| *c != name && self.column_names().contains(c))
+            .chain([name])
+            .collect();
+        let mut frame = self.get(&kept, columns)?;
+        let rows: Vec<i64> = kept.iter().map(|row| *row as i64).collect();
+        frame.insert_column_at(0, ROW_COLUMN, rows)?;
+        Ok(frame)
+    }
+}
@@ -0,0 +1,252 @@
+//! Batch processing of many tfs files.
+//!
+//! A [`TfsPipeline`] opens a file, runs it through a list of stages (row filters, derived columns,
+//! custom transformations) and writes the result. The same pipeline can be run over a whole
+//! directory in parallel, failures of single files are collected in the [`PipelineReport`]:
+//!
+//! ```no_run
+//! # use tfs::pipeline::TfsPipeline;
+//! let pipeline = TfsPipeline::new()
+//!     .filter("S", |s| s < 1000.0)
+//!     .derive("BETX_BETY", &["BETX", "BETY"], "BETX / BETY", |v| v[0] / v[1])
+//!     .write_to("processed");
+//!
+//! let report = pipeline.run_dir("twiss").unwrap();
+//! println!("{}", report);
+//! ```
+use polars::prelude::NumericNative;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::catalog::collect_tfs_files;
+use crate::options::TfsReadOptions;
+use crate::record::ColumnValue;
+use crate::schema::Schema;
+use crate::tfsdataframe::TfsDataFrame;
+
+type Stage = Box<dyn Fn(&mut TfsDataFrame<f64>) -> anyhow::Result<()> + Send + Sync>;
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Keeps only the rows where `predicate` holds for the real column `column`.
+    ///
+    /// ```
+    /// # use tfs::TfsDataFrame;
+    /// let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+    /// df.filter_rows("L", |l| l > 1.0).unwrap();
+    /// assert_eq!(df.len(), 2);
+    /// ```
+    pub fn filter_rows<F>(&mut self, column: &str, predicate: F) -> anyhow::Result<()>
+    where
+        F: Fn(f64) -> bool,
+    {
+        let keep: Vec<bool> = f64::from_column(self.column(column)?)?
+            .into_iter()
+            .map(predicate)
+            .collect();
+        self.retain_rows(&keep)
+    }
+}
+
+/// A sequence of stages applied to tfs files, see the [module documentation](self).
+#[derive(Default)]
+pub struct TfsPipeline {
+    options: TfsReadOptions,
+    stages: Vec<Stage>,
+    output_dir: Option<PathBuf>,
+}
+
+impl TfsPipeline {
+    pub fn new() -> TfsPipeline {
+        TfsPipeline::default()
+    }
+
+    /// Opens the files with `options` instead of the default options.
+    pub fn options(mut self, options: TfsReadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Adds a stage keeping only the rows where `predicate` holds for the real column `column`,
+    /// see [`TfsDataFrame::filter_rows`].
+    pub fn filter<F>(self, column: &str, predicate: F) -> Self
+    where
+        F: Fn(f64) -> bool + Send + Sync + 'static,
+    {
+        let column = column.to_owned();
+        self.stage(move |df| df.filter_rows(&column, &predicate))
+    }
+
+    /// Adds a stage computing a derived column, see [`TfsDataFrame::derive_column`].
+    pub fn derive<F>(self, name: &str, inputs: &[&str], expression: &str, f: F) -> Self
+    where
+        F: Fn(&[f64]) -> f64 + Send + Sync + 'static,
+    {
+        let name = name.to_owned();
+        let inputs: Vec<String> = inputs.iter().map(|i| String::from(*i)).collect();
+        let expression = expression.to_owned();
+        self.stage(move |df| {
+            let inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+            df.derive_column(&name, &inputs, &expression, &f)
+        })
+    }
+
+    /// Adds a stage failing files that violate `schema`, e.g. its checks of the values, see
+    /// [`TfsDataFrame::validate`]. The error lists all violations.
+    pub fn validate(self, schema: Schema) -> Self {
+        self.stage(move |df| {
+            let violations = df.validate(&schema)?;
+            if violations.is_empty() {
+                return Ok(());
+            }
+            let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+            anyhow::bail!("violates the schema: {}", violations.join("; "))
+        })
+    }
+
+    /// Adds a custom stage.
+    pub fn stage<F>(mut self, stage: F) -> Self
+    where
+        F: Fn(&mut TfsDataFrame<f64>) -> anyhow::Result<()> + Send + Sync + 'static,
+    {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Writes the processed frames into `dir`, under the name of the input file. Without it the
+    /// results are not written.
+    pub fn write_to<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.output_dir = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Runs the pipeline on a single file and returns the processed frame.
+    pub fn run<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<TfsDataFrame<f64>> {
+        self.process(path.as_ref()).map(|(df, _)| df)
+    }
+
+    /// Runs the pipeline on all files with the extension `.tfs` in `dir` and its
+    /// subdirectories, see [`TfsPipeline::run_all`].
+    pub fn run_dir<P: AsRef<Path>>(&self, dir: P) -> anyhow::Result<PipelineReport> {
+        let mut files = Vec::new();
+        collect_tfs_files(dir.as_ref(), &mut files)?;
+        files.sort();
+        Ok(self.run_all(&files))
+    }
+
+    /// Runs the pipeline on `paths`, using one thread per available core or at most
+    /// [`TfsReadOptions::max_threads`] of the read options. The report is the same for any
+    /// number of threads: files are ordered like `paths`, whichever thread finished first. With
+    /// [`TfsReadOptions::deterministic`] the files are processed in that order on this thread.
+    pub fn run_all(&self, paths: &[PathBuf]) -> PipelineReport {
+        if self.options.deterministic {
+            let results = paths.iter().map(|path| self.process(path)).enumerate();
+            return report(paths, results);
+        }
+        let n_threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(self.options.max_threads.unwrap_or(usize::MAX))
+            .min(paths.len());
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(paths.len()));
+
+        std::thread::scope(|scope| {
+            for _ in 0..n_threads {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = paths.get(index) else {
+                        break;
+                    };
+                    let result = self.process(path);
+                    results.lock().unwrap().push((index, result));
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(index, _)| *index);
+        report(paths, results)
+    }
+
+    fn process(&self, path: &Path) -> anyhow::Result<(TfsDataFrame<f64>, Option<PathBuf>)> {
+        let mut df = TfsDataFrame::open_with(path, &self.options)?;
+        for stage in &self.stages {
+            stage(&mut df)?;
+        }
+
+        let output = match &self.output_dir {
+            Some(dir) => {
+                let file_name = path
+                    .file_name()
+                    .ok_or_else(|| anyhow::anyhow!("'{}' is not a file", path.display()))?;
+                let output = dir.join(file_name);
+                df.write(&output)?;
+                Some(output)
+            }
+            None => None,
+        };
+        Ok((df, output))
+    }
+}
+
+/// A file processed successfully by [`TfsPipeline::run_all`].
+#[derive(Debug, Clone)]
+pub struct ProcessedFile {
+    pub path: PathBuf,
+    /// Number of rows after all stages.
+    pub rows: usize,
+    /// Where the result was written, if the pipeline writes its results.
+    pub output: Option<PathBuf>,
+}
+
+/// Summary of a run of [`TfsPipeline::run_all`].
+#[derive(Debug, Clone, Default)]
+pub struct PipelineReport {
+    /// The files processed successfully, ordered like the input.
+    pub processed: Vec<ProcessedFile>,
+    /// The files for which a stage failed, with the reason.
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl PipelineReport {
+    pub fn is_success(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for PipelineReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} files processed, {} failed",
+            self.processed.len(),
+            self.errors.len()
+        )?;
+        for (path, err) in &self.errors {
+            writeln!(f, "  {}: {}", path.display(), err)?;
+        }
+        Ok(())
+    }
+}
+
+/// The report of the `results` of `paths`, ordered by their index.
+fn report<I>(paths: &[PathBuf], results: I) -> PipelineReport
+where
+    I: IntoIterator<Item = (usize, anyhow::Result<(TfsDataFrame<f64>, Option<PathBuf>)>)>,
+{
+    let mut report = PipelineReport::default();
+    for (index, result) in results {
+        let path = paths[index].clone();
+        match result {
+            Ok((df, output)) => report.processed.push(ProcessedFile {
+                path,
+                rows: df.len(),
+                output,
+            }),
+            Err(err) => report.errors.push((path, err.to_string())),
+        }
+    }
+    report
+}
@@ -0,0 +1,250 @@
+//! The digits real columns are written with.
+//!
+//! Reals are written with 17 significant digits, enough to read every value back exactly.
+//! [`TfsDataFrame::set_precision`] writes a column with fewer, e.g. to keep files of
+//! measurements small. [`TfsDataFrame::precision_losses`] lists the columns whose values would
+//! then change by more than the relative tolerance of their [`Precision`], and the digits they
+//! need. Columns set to [`Precision::escalating`] are written with these digits instead:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::precision::Precision;
+//! let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! df.set_precision("S", Some(Precision::new(4, 1e-6)));
+//! df.set_precision("BETX", Some(Precision::new(4, 1e-3)));
+//!
+//! let losses = df.precision_losses().unwrap();
+//! assert_eq!(losses.len(), 1);
+//! assert_eq!((losses[0].column.as_str(), losses[0].needed_digits), ("S", 5));
+//! ```
+//!
+//! [`TfsDataFrame::write_checked`] writes a frame and returns these losses, with the `tracing`
+//! feature writing a frame with such losses also logs a warning.
+//!
+//! The other columns are written in the [`RealFormat`] of the frame. With
+//! [`RealFormat::Shortest`] every value is written with the fewest digits that read back to
+//! the same bits, so that reading and writing a file again gives the same file:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::precision::RealFormat;
+//! let mut df = TfsDataFrame::<f64>::new(
+//!     Vec::new(),
+//!     tfs::polars::df!("X" => [0.1 + 0.2, 2.5]).unwrap(),
+//! );
+//! df.set_real_format(RealFormat::Shortest);
+//!
+//! let mut written = Vec::new();
+//! df.write_to(&mut written).unwrap();
+//! let written = String::from_utf8(written).unwrap();
+//! assert!(written.contains(" 0.30000000000000004\n") && written.contains(" 2.5\n"));
+//! ```
+use polars::prelude::NumericNative;
+use std::fmt;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Significant digits of a lossless real.
+pub const MAX_DIGITS: usize = 17;
+
+/// How the real columns without a [`Precision`] are written. Header values are always written
+/// with the fewest digits that read back exactly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RealFormat {
+    /// [`MAX_DIGITS`] significant digits in scientific notation, e.g. `2.5000000000000000e0`.
+    #[default]
+    Scientific,
+    /// The shortest text reading back to the same bits, e.g. `2.5`, `1e-7` or `-0.0`.
+    Shortest,
+}
+
+/// How the values of a real column are written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Precision {
+    /// Significant digits, between 1 and [`MAX_DIGITS`].
+    pub digits: usize,
+    /// Largest relative change of a value written and read back that is not a loss.
+    pub tolerance: f64,
+    /// Whether columns with losses are written with as many digits as they need.
+    pub escalate: bool,
+}
+
+impl Precision {
+    pub fn new(digits: usize, tolerance: f64) -> Precision {
+        Precision {
+            digits: digits.clamp(1, MAX_DIGITS),
+            tolerance,
+            escalate: false,
+        }
+    }
+
+    /// Writes the column with more digits where its values would change beyond the tolerance.
+    pub fn escalating(mut self) -> Self {
+        self.escalate = true;
+        self
+    }
+
+    /// Whether `value` written with `digits` digits and read back is within the tolerance.
+    fn keeps(&self, value: f64, digits: usize) -> bool {
+        if !value.is_finite() {
+            return true;
+        }
+        let written: f64 = format_real(value, digits).parse().unwrap_or(f64::NAN);
+        (written - value).abs() <= self.tolerance * value.abs()
+    }
+
+    /// The digits needed to write all `values` within the tolerance, at least `self.digits`.
+    fn needed_digits(&self, values: &[f64]) -> usize {
+        values.iter().fold(self.digits, |digits, v| {
+            (digits..MAX_DIGITS)
+                .find(|d| self.keeps(*v, *d))
+                .unwrap_or(MAX_DIGITS)
+        })
+    }
+}
+
+/// A real column whose values change beyond the tolerance when written with its precision.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecisionLoss {
+    pub column: String,
+    pub precision: Precision,
+    /// Number of values changing beyond the tolerance.
+    pub values: usize,
+    /// The row of the first of them.
+    pub first_row: usize,
+    /// The largest relative change of a value.
+    pub max_error: f64,
+    /// The digits needed to stay within the tolerance, which escalating columns are written
+    /// with.
+    pub needed_digits: usize,
+}
+
+impl fmt::Display for PrecisionLoss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "column '{}': {} values change by up to {:.1e} with {} digits (the first in row {}), {} digits are needed",
+            self.column,
+            self.values,
+            self.max_error,
+            self.precision.digits,
+            self.first_row,
+            self.needed_digits
+        )?;
+        if self.precision.escalate {
+            write!(f, " and used")?;
+        }
+        Ok(())
+    }
+}
+
+/// `value` in the format of the writer with `digits` significant digits.
+pub(crate) fn format_real(value: f64, digits: usize) -> String {
+    format!("{:.*e}", digits.clamp(1, MAX_DIGITS) - 1, value)
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Writes the real column `column` with `precision`, or with all digits again if
+    /// `precision` is `None`.
+    pub fn set_precision(&mut self, column: &str, precision: Option<Precision>) {
+        match precision {
+            Some(precision) => self.precisions.insert(column.to_owned(), precision),
+            None => self.precisions.remove(column),
+        };
+    }
+
+    /// The format of the real columns written without a precision.
+    pub fn real_format(&self) -> RealFormat {
+        self.real_format
+    }
+
+    /// Writes the real columns without a precision in `format`, see the
+    /// [module documentation](crate::precision).
+    pub fn set_real_format(&mut self, format: RealFormat) {
+        self.real_format = format;
+    }
+
+    /// The columns whose values change beyond the tolerance of their precision when written,
+    /// see the [module documentation](crate::precision).
+    pub fn precision_losses(&self) -> anyhow::Result<Vec<PrecisionLoss>> {
+        let mut losses = Vec::new();
+        for name in self.column_names() {
+            let Some(precision) = self.precisions.get(name) else {
+                continue;
+            };
+            let column = self.column(name)?;
+            if !column.dtype().is_float() {
+                continue;
+            }
+            let values = Option::<f64>::from_column(column)?;
+
+            let mut loss = PrecisionLoss {
+                column: name.to_owned(),
+                precision: *precision,
+                values: 0,
+                first_row: 0,
+                max_error: 0.0,
+                needed_digits: precision.digits,
+            };
+            for (row, v) in values.iter().enumerate() {
+                let Some(v) = v else {
+                    continue;
+                };
+                if precision.keeps(*v, precision.digits) {
+                    continue;
+                }
+                if loss.values == 0 {
+                    loss.first_row = row;
+                }
+                loss.values += 1;
+                let written: f64 = format_real(*v, precision.digits).parse()?;
+                loss.max_error = loss.max_error.max(((written - v) / v).abs());
+            }
+            if loss.values > 0 {
+                let values: Vec<f64> = values.into_iter().flatten().collect();
+                loss.needed_digits = precision.needed_digits(&values);
+                losses.push(loss);
+            }
+        }
+        Ok(losses)
+    }
+
+    /// Writes the frame to `path` like [`TfsDataFrame::write`] and returns the columns that lost
+    /// precision, see [`TfsDataFrame::precision_losses`]. Escalating columns are returned as
+    /// well, although they are written with the digits they need.
+    pub fn write_checked<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<Vec<PrecisionLoss>>
+    where
+        T: fmt::Display,
+    {
+        let file = BufWriter::new(File::create(path.as_ref())?);
+        Ok(self.write_frame(file)?)
+    }
+
+    /// The digits each real column of `names` is written with, `None` for all digits, and the
+    /// precision losses of the frame.
+    pub(crate) fn written_digits(
+        &self,
+        names: &[&str],
+    ) -> anyhow::Result<(Vec<Option<usize>>, Vec<PrecisionLoss>)> {
+        let losses = self.precision_losses()?;
+        #[cfg(feature = "tracing")]
+        for loss in &losses {
+            tracing::warn!("{}", loss);
+        }
+        let digits = names
+            .iter()
+            .map(|name| {
+                let precision = self.precisions.get(*name)?;
+                let escalated = losses
+                    .iter()
+                    .find(|loss| loss.column == *name && precision.escalate);
+                Some(escalated.map_or(precision.digits, |loss| loss.needed_digits))
+            })
+            .collect();
+        Ok((digits, losses))
+    }
+}
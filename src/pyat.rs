@@ -0,0 +1,335 @@
+//! Lattice summaries exported from pyAT, as CSV or JSON.
+//!
+//! pyAT has no file format for its optics, users export the results of `linopt6` and
+//! `ring.get_optics` with pandas or `json.dump`. [`read`] takes both and renames the columns to
+//! their MAD-X names: `s_pos` to `S`, `beta_x` (or the element `[0]` of an array `beta`) to
+//! `BETX`, `mu_x` in radians to `MUX` in units of 2π, `dispersion_px` to `DPX`, ...
+//!
+//! CSV files have one row per element. JSON files are either such a list of row objects, or an
+//! object with the rows (or one array per column) in `elemdata` and global values like `tune`,
+//! `chromaticity` and `energy` (in eV) in `ringdata`:
+//!
+//! ```
+//! let json = r#"{
+//!     "ringdata": { "tune": [0.31, 0.32], "energy": 6e9 },
+//!     "elemdata": [
+//!         { "name": "START", "s_pos": 0.0, "beta": [10.0, 20.0], "mu": [0.0, 0.0] },
+//!         { "name": "QF", "s_pos": 12.5, "beta": [15.0, 8.0], "mu": [1.2, 1.1] }
+//!     ]
+//! }"#;
+//! let df = tfs::pyat::parse_json::<f64>(json).unwrap();
+//! assert_eq!(df.column_names(), ["NAME", "S", "BETX", "BETY", "MUX", "MUY"]);
+//! assert_eq!(*df.propd("ENERGY"), 6.0);
+//! ```
+//!
+//! The header gets `TYPE` and `ORIGIN` and, if not given, the tunes `Q1` and `Q2` from the
+//! phase advance and the `LENGTH` from `S` at the last element.
+use polars::prelude::{Column, DataFrame, NamedFrom, NumericNative};
+use polars::series::Series;
+use serde_json::Value;
+use std::f64::consts::PI;
+use std::path::Path;
+
+use crate::dataframe::DataValue;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::{Properties, TfsDataFrame};
+
+/// pyAT names, lower case, with their MAD-X names and the factor to MAD-X units.
+const NAMES: [(&str, &str, f64); 30] = [
+    ("name", "NAME", 1.0),
+    ("famname", "NAME", 1.0),
+    ("element", "NAME", 1.0),
+    ("s_pos", "S", 1.0),
+    ("s", "S", 1.0),
+    ("beta_x", "BETX", 1.0),
+    ("beta_y", "BETY", 1.0),
+    ("alpha_x", "ALFX", 1.0),
+    ("alpha_y", "ALFY", 1.0),
+    ("mu_x", "MUX", 1.0 / (2.0 * PI)),
+    ("mu_y", "MUY", 1.0 / (2.0 * PI)),
+    ("dispersion_x", "DX", 1.0),
+    ("dispersion_px", "DPX", 1.0),
+    ("dispersion_y", "DY", 1.0),
+    ("dispersion_py", "DPY", 1.0),
+    ("eta_x", "DX", 1.0),
+    ("eta_px", "DPX", 1.0),
+    ("eta_y", "DY", 1.0),
+    ("eta_py", "DPY", 1.0),
+    ("closed_orbit_x", "X", 1.0),
+    ("closed_orbit_px", "PX", 1.0),
+    ("closed_orbit_y", "Y", 1.0),
+    ("closed_orbit_py", "PY", 1.0),
+    ("tune_x", "Q1", 1.0),
+    ("tune_y", "Q2", 1.0),
+    ("chromaticity_x", "DQ1", 1.0),
+    ("chromaticity_y", "DQ2", 1.0),
+    ("energy", "ENERGY", 1e-9),
+    ("circumference", "LENGTH", 1.0),
+    ("harmonic_number", "HARMON", 1.0),
+];
+
+/// The suffixes of the elements of pyAT's arrays, by their length.
+fn suffixes(len: usize) -> Option<&'static [&'static str]> {
+    match len {
+        2 => Some(&["x", "y"]),
+        3 => Some(&["x", "y", "z"]),
+        4 => Some(&["x", "px", "y", "py"]),
+        6 => Some(&["x", "px", "y", "py", "dp", "ct"]),
+        _ => None,
+    }
+}
+
+/// The MAD-X name of the pyAT name `name` and the factor to MAD-X units. Unknown names are
+/// upper-cased.
+pub fn madx_name(name: &str) -> (String, f64) {
+    let lower = name.trim().to_ascii_lowercase();
+    NAMES
+        .iter()
+        .find(|(pyat, _, _)| *pyat == lower)
+        .map(|(_, madx, factor)| (madx.to_string(), *factor))
+        .unwrap_or_else(|| (name.trim().to_ascii_uppercase(), 1.0))
+}
+
+/// Reads a pyAT summary, as JSON if it starts with `{` or `[` and as CSV otherwise.
+pub fn read<T, P>(path: P) -> anyhow::Result<TfsDataFrame<T>>
+where
+    T: std::str::FromStr + NumericNative,
+    P: AsRef<Path>,
+{
+    let text = std::fs::read_to_string(path)?;
+    if text.trim_start().starts_with(['{', '[']) {
+        parse_json(&text)
+    } else {
+        parse_csv(&text)
+    }
+}
+
+/// Parses a CSV summary with a header line. An unnamed first column, the index written by
+/// pandas, is dropped.
+pub fn parse_csv<T>(text: &str) -> anyhow::Result<TfsDataFrame<T>>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let names = csv_fields(
+        lines
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("the CSV file is empty"))?,
+    );
+    let mut cells = vec![Vec::new(); names.len()];
+    for (row, line) in lines.enumerate() {
+        let fields = csv_fields(line);
+        anyhow::ensure!(
+            fields.len() == names.len(),
+            "row {} has {} fields but there are {} columns",
+            row,
+            fields.len(),
+            names.len()
+        );
+        for (column, field) in cells.iter_mut().zip(fields) {
+            column.push(Value::String(field));
+        }
+    }
+
+    let columns = names
+        .iter()
+        .zip(cells)
+        .filter(|(name, _)| !name.is_empty() && !name.starts_with("Unnamed:"))
+        .map(|(name, cells)| (name.clone(), cells))
+        .collect();
+    frame(Vec::new(), columns)
+}
+
+/// Parses a JSON summary, see the [module documentation](self).
+pub fn parse_json<T>(text: &str) -> anyhow::Result<TfsDataFrame<T>>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    let json: Value = serde_json::from_str(text)?;
+    let (globals, elements) = match &json {
+        Value::Array(_) => (None, &json),
+        Value::Object(object) => (
+            object.get("ringdata").or_else(|| object.get("globals")),
+            object
+                .get("elemdata")
+                .or_else(|| object.get("elements"))
+                .ok_or_else(|| anyhow::anyhow!("the summary has no elemdata"))?,
+        ),
+        _ => anyhow::bail!("the summary is neither a list nor an object"),
+    };
+
+    let mut header = Vec::new();
+    if let Some(globals) = globals {
+        let Value::Object(globals) = globals else {
+            anyhow::bail!("the ringdata is not an object");
+        };
+        for (name, value) in globals {
+            for (name, value) in flatten(name, value) {
+                header.push((name, value));
+            }
+        }
+    }
+
+    let mut columns: Vec<(String, Vec<Value>)> = Vec::new();
+    match elements {
+        Value::Array(rows) => {
+            for (row, element) in rows.iter().enumerate() {
+                let Value::Object(element) = element else {
+                    anyhow::bail!("element {} is not an object", row);
+                };
+                for (name, value) in element {
+                    for (name, value) in flatten(name, value) {
+                        let index = match columns.iter().position(|(n, _)| *n == name) {
+                            Some(index) => index,
+                            None => {
+                                columns.push((name, vec![Value::Null; row]));
+                                columns.len() - 1
+                            }
+                        };
+                        columns[index].1.push(value);
+                    }
+                }
+                for (_, values) in &mut columns {
+                    values.resize(row + 1, Value::Null);
+                }
+            }
+        }
+        Value::Object(object) => {
+            for (name, values) in object {
+                let Value::Array(values) = values else {
+                    anyhow::bail!("the column '{}' is not a list", name);
+                };
+                let mut split: Vec<(String, Vec<Value>)> = Vec::new();
+                for value in values {
+                    for (i, (name, value)) in flatten(name, value).into_iter().enumerate() {
+                        if i == split.len() {
+                            split.push((name, Vec::new()));
+                        }
+                        split[i].1.push(value);
+                    }
+                }
+                columns.extend(split);
+            }
+        }
+        _ => anyhow::bail!("the elemdata is neither a list nor an object"),
+    }
+    frame(header, columns)
+}
+
+/// Splits arrays like `beta: [10, 20]` into `beta_x` and `beta_y`.
+fn flatten(name: &str, value: &Value) -> Vec<(String, Value)> {
+    match value {
+        Value::Array(items) => match suffixes(items.len()) {
+            Some(suffixes) => suffixes
+                .iter()
+                .zip(items)
+                .map(|(suffix, item)| (format!("{}_{}", name, suffix), item.clone()))
+                .collect(),
+            None => vec![(name.to_owned(), Value::String(value.to_string()))],
+        },
+        value => vec![(name.to_owned(), value.clone())],
+    }
+}
+
+/// Builds the frame from pyAT names and values, adding the synthesized header entries.
+fn frame<T>(
+    globals: Vec<(String, Value)>,
+    columns: Vec<(String, Vec<Value>)>,
+) -> anyhow::Result<TfsDataFrame<T>>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    let mut header = Properties::new();
+    header.insert("TYPE".to_owned(), DataValue::Text("TWISS".to_owned()));
+    header.insert("ORIGIN".to_owned(), DataValue::Text("pyAT".to_owned()));
+    for (name, value) in globals {
+        let (name, factor) = madx_name(&name);
+        let value = match value {
+            Value::Number(n) if n.is_i64() && factor == 1.0 => {
+                DataValue::Integer(n.as_i64().unwrap())
+            }
+            Value::Number(n) => real(n.as_f64().unwrap_or(f64::NAN) * factor)?,
+            Value::String(s) => DataValue::Text(s),
+            Value::Bool(b) => DataValue::Boolean(b),
+            value => DataValue::Text(value.to_string()),
+        };
+        header.insert(name, value);
+    }
+
+    let mut series = Vec::new();
+    for (name, values) in columns {
+        let (name, factor) = madx_name(&name);
+        anyhow::ensure!(
+            series.iter().all(|s: &Series| s.name().as_str() != name),
+            "the columns of the summary have the MAD-X name '{}' twice",
+            name
+        );
+        let reals: Option<Vec<f64>> = values
+            .iter()
+            .map(|value| match value {
+                Value::Number(n) => n.as_f64(),
+                Value::Null => Some(f64::NAN),
+                Value::String(s) if s.trim().is_empty() => Some(f64::NAN),
+                Value::String(s) => s.trim().parse().ok(),
+                _ => None,
+            })
+            .collect();
+        series.push(match reals {
+            Some(reals) if name != "NAME" => {
+                let reals: Vec<f64> = reals.into_iter().map(|r| r * factor).collect();
+                Series::new(name.into(), reals)
+            }
+            _ => {
+                let texts: Vec<String> = values
+                    .into_iter()
+                    .map(|value| match value {
+                        Value::String(s) => s,
+                        Value::Null => String::new(),
+                        value => value.to_string(),
+                    })
+                    .collect();
+                Series::new(name.into(), texts)
+            }
+        });
+    }
+
+    let last = |name: &str| -> Option<f64> {
+        let column = series.iter().find(|s| s.name().as_str() == name)?;
+        f64::from_column(column).ok()?.last().copied()
+    };
+    for (key, column) in [("Q1", "MUX"), ("Q2", "MUY"), ("LENGTH", "S")] {
+        if let (false, Some(value)) = (header.contains_key(key), last(column)) {
+            header.insert(key.to_owned(), real(value)?);
+        }
+    }
+
+    let columns = series.into_iter().map(Column::from).collect();
+    Ok(TfsDataFrame::new(header, DataFrame::new(columns)?))
+}
+
+fn real<T: std::str::FromStr>(value: f64) -> anyhow::Result<DataValue<T>> {
+    value
+        .to_string()
+        .parse()
+        .map(DataValue::Real)
+        .map_err(|_| anyhow::anyhow!("can't represent {}", value))
+}
+
+/// Splits a CSV line at commas outside of double quotes.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches(['\r', '\n']).chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields.into_iter().map(|f| f.trim().to_owned()).collect()
+}
@@ -0,0 +1,425 @@
+//! The stages of reading a tfs file: the header up to the `*` and `$` lines, then the data rows.
+use polars::prelude::{polars_bail, Column, NamedFrom, PolarsError};
+use polars::series::Series;
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+
+use crate::dataframe::DataValue;
+use crate::dialect::Dialect;
+use crate::lineage::Lineage;
+use crate::options::{MissingFields, TfsReadOptions};
+use crate::parse::{parse_real_lenient, ParseWarning};
+use crate::tfsdataframe::Properties;
+use crate::types::ColumnKind;
+
+/// Everything in front of the data rows.
+pub(crate) struct ParsedHeader<T> {
+    pub properties: Properties<T>,
+    pub lineage: HashMap<String, Lineage>,
+    pub warnings: Vec<ParseWarning>,
+    pub colnames: Vec<String>,
+    pub coltypes: Vec<String>,
+    /// Given in the options or detected.
+    pub dialect: Dialect,
+}
+
+/// Reads the header, leaving `reader` at the first data row. Returns the header and the number of
+/// bytes read.
+pub(crate) fn read_header<T, R>(
+    reader: &mut R,
+    options: &TfsReadOptions,
+) -> Result<(ParsedHeader<T>, u64), PolarsError>
+where
+    T: std::str::FromStr,
+    R: BufRead,
+{
+    let mut properties = Properties::new();
+    let mut lineage = HashMap::new();
+    let mut warnings = vec![];
+    let mut colnames = vec![];
+    let mut coltypes = vec![];
+    let mut header_codes = vec![];
+    let mut bytes_read = 0;
+    // until the dialect is known, read what any dialect could have written
+    let value_dialect = options.dialect.unwrap_or(Dialect::MadNg);
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        let n = reader.read_line(&mut line)?;
+        if n == 0 {
+            polars_bail!(NoData: "the file ended before the column names and types were given");
+        }
+        bytes_read += n as u64;
+        let mut line_it = line.split_whitespace();
+
+        match line_it.next() {
+            Some("*") => colnames.extend(line_it.map(String::from)),
+            Some("$") => coltypes.extend(line_it.map(String::from)),
+            Some("#") => lineage.extend(Lineage::parse_comment(&line)),
+            Some("@") => {
+                let invalid = || {
+                    PolarsError::ComputeError(
+                        format!("invalid header line '{}'", line.trim_end()).into(),
+                    )
+                };
+                let name = String::from(line_it.next().ok_or_else(invalid)?);
+                let code = line_it.next().ok_or_else(invalid)?;
+                header_codes.push(code.to_owned());
+                let value = match code {
+                    "%le" => {
+                        let token = line_it.next().ok_or_else(invalid)?;
+                        let (value, lenient) =
+                            parse_real_lenient(token, options).ok_or_else(invalid)?;
+                        if lenient {
+                            warnings.push(ParseWarning::DecimalCommaProperty {
+                                key: name.clone(),
+                                token: token.to_owned(),
+                            });
+                        }
+                        DataValue::Real(value)
+                    }
+                    "%d" => DataValue::Integer(
+                        line_it
+                            .next()
+                            .and_then(|token| token.parse().ok())
+                            .ok_or_else(invalid)?,
+                    ),
+                    _ => {
+                        let value = line_it.collect::<Vec<_>>().join(" ");
+                        value_dialect
+                            .parse_value(code, &value)
+                            .unwrap_or_else(|| DataValue::Text(value.trim_matches('\"').to_owned()))
+                    }
+                };
+                if properties.insert(name.clone(), value).is_some() {
+                    warnings.push(ParseWarning::DuplicateHeader { key: name });
+                }
+            }
+            Some(_) => warnings.push(ParseWarning::SkippedLine {
+                line: line.trim_end().to_owned(),
+            }),
+            None => {}
+        }
+        if !colnames.is_empty() && !coltypes.is_empty() {
+            break; // we have parsed the header, pass on to reading the data lines
+        }
+    }
+
+    if colnames.len() > coltypes.len() {
+        polars_bail!(
+            ShapeMismatch: "the header has {} column names but {} types, the columns {} have no type",
+            colnames.len(),
+            coltypes.len(),
+            colnames[coltypes.len()..].join(", ")
+        );
+    }
+    if coltypes.len() > colnames.len() {
+        polars_bail!(
+            ShapeMismatch: "the header has {} column names but {} types, the types {} have no column name",
+            colnames.len(),
+            coltypes.len(),
+            coltypes[colnames.len()..].join(", ")
+        );
+    }
+
+    let dialect = options.dialect.unwrap_or_else(|| {
+        let origin = match properties.get("ORIGIN") {
+            Some(DataValue::Text(origin)) => Some(origin.as_str()),
+            _ => None,
+        };
+        let codes = header_codes.iter().chain(&coltypes).map(String::as_str);
+        Dialect::detect(codes, origin)
+    });
+
+    Ok((
+        ParsedHeader {
+            properties,
+            lineage,
+            warnings,
+            colnames,
+            coltypes,
+            dialect,
+        },
+        bytes_read,
+    ))
+}
+
+/// The values of one column read so far.
+enum ColumnBuffer {
+    Real(Vec<f64>),
+    Integer(Vec<Option<i64>>),
+    Text(Vec<String>),
+    Boolean(Vec<Option<bool>>),
+}
+
+impl ColumnBuffer {
+    fn push_default(&mut self, real: f64, text: &str) {
+        match self {
+            ColumnBuffer::Real(vec) => vec.push(real),
+            ColumnBuffer::Integer(vec) => vec.push(None),
+            ColumnBuffer::Boolean(vec) => vec.push(None),
+            ColumnBuffer::Text(vec) => vec.push(text.to_owned()),
+        }
+    }
+}
+
+/// Collects data rows into columns.
+pub(crate) struct BodyParser {
+    colnames: Vec<String>,
+    /// The type code of every column and how it was interpreted.
+    codes: Vec<(String, ColumnKind)>,
+    columns: Vec<ColumnBuffer>,
+    /// Values read as `NaN`, for every column.
+    sentinels: Vec<Vec<f64>>,
+    /// Rows filled in for missing fields with [`MissingFields::Null`], for every column. Kept
+    /// per column, wide frames with many missing fields would take a scan of all of them per
+    /// column otherwise.
+    nulls: Vec<Vec<usize>>,
+    row: usize,
+    /// Bytes taken by a row, without the content of texts.
+    row_bytes: usize,
+    /// Bytes taken by the content of texts.
+    text_bytes: usize,
+}
+
+impl BodyParser {
+    /// Sets up the columns according to their type codes. Columns with a code unknown to the
+    /// type registry of `options` and to `dialect` are read as text, with a warning.
+    pub fn new(
+        colnames: &[String],
+        coltypes: &[String],
+        dialect: Dialect,
+        options: &TfsReadOptions,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> BodyParser {
+        let mut columns = vec![];
+        let mut codes = vec![];
+        let mut sentinels = vec![];
+
+        // setup columns
+        for (colname, coltype) in colnames.iter().zip(coltypes) {
+            let kind = options.types.kind(coltype);
+            let kind = kind
+                .or_else(|| dialect.column_kind(coltype))
+                .unwrap_or_else(|| {
+                    warnings.push(ParseWarning::UnknownTypeCode {
+                        column: colname.clone(),
+                        code: coltype.clone(),
+                    });
+                    ColumnKind::Text
+                });
+            columns.push(match kind {
+                ColumnKind::Real => ColumnBuffer::Real(Vec::new()),
+                ColumnKind::Integer => ColumnBuffer::Integer(Vec::new()),
+                ColumnKind::Text => ColumnBuffer::Text(Vec::new()),
+                ColumnKind::Boolean => ColumnBuffer::Boolean(Vec::new()),
+            });
+            codes.push((coltype.clone(), kind));
+            sentinels.push(options.sentinels_of(colname).to_vec());
+        }
+
+        let row_bytes = columns
+            .iter()
+            .map(|column| match column {
+                ColumnBuffer::Real(_) => std::mem::size_of::<f64>(),
+                ColumnBuffer::Integer(_) => std::mem::size_of::<Option<i64>>(),
+                ColumnBuffer::Text(_) => std::mem::size_of::<String>(),
+                ColumnBuffer::Boolean(_) => std::mem::size_of::<Option<bool>>(),
+            })
+            .sum();
+        BodyParser {
+            colnames: colnames.to_vec(),
+            codes,
+            sentinels,
+            columns,
+            nulls: vec![Vec::new(); colnames.len()],
+            row: 0,
+            row_bytes,
+            text_bytes: 0,
+        }
+    }
+
+    /// Parses one data row.
+    pub fn parse_line(
+        &mut self,
+        line: &str,
+        options: &TfsReadOptions,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Result<(), PolarsError> {
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        if line.trim_start().starts_with('#') {
+            warnings.push(ParseWarning::SkippedLine {
+                line: line.trim_end().to_owned(),
+            });
+            return Ok(());
+        }
+        let line_it = line.split_whitespace();
+        let mut n_fields = 0;
+        for (icol, (idata, icolumn)) in line_it.zip(self.columns.iter_mut()).enumerate() {
+            match icolumn {
+                ColumnBuffer::Real(ref mut vec) => match parse_real_lenient(idata, options) {
+                    Some((value, lenient)) => {
+                        if lenient {
+                            warnings.push(ParseWarning::DecimalComma {
+                                column: self.colnames[icol].clone(),
+                                row: self.row,
+                                token: idata.to_owned(),
+                            });
+                        }
+                        if self.sentinels[icol].contains(&value) {
+                            vec.push(f64::NAN)
+                        } else {
+                            vec.push(value)
+                        }
+                    }
+                    None => {
+                        warnings.push(ParseWarning::NanCoercion {
+                            column: self.colnames[icol].clone(),
+                            row: self.row,
+                            token: idata.to_owned(),
+                        });
+                        vec.push(f64::NAN)
+                    }
+                },
+                ColumnBuffer::Integer(ref mut vec) => {
+                    let value = idata.parse().ok();
+                    if value.is_none() {
+                        warnings.push(ParseWarning::NullCoercion {
+                            column: self.colnames[icol].clone(),
+                            row: self.row,
+                            token: idata.to_owned(),
+                        });
+                    }
+                    vec.push(value)
+                }
+                ColumnBuffer::Boolean(ref mut vec) => {
+                    let value = idata.to_ascii_lowercase().parse().ok();
+                    if value.is_none() {
+                        warnings.push(ParseWarning::NullCoercion {
+                            column: self.colnames[icol].clone(),
+                            row: self.row,
+                            token: idata.to_owned(),
+                        });
+                    }
+                    vec.push(value)
+                }
+                ColumnBuffer::Text(ref mut vec) => {
+                    let text = idata.trim_matches('\"');
+                    self.text_bytes += text.len();
+                    vec.push(text.to_owned())
+                }
+            }
+            n_fields += 1;
+        }
+
+        if n_fields < self.columns.len() {
+            match &options.missing_fields {
+                MissingFields::Error => polars_bail!(
+                    ShapeMismatch: "row {} has {} fields but there are {} columns",
+                    self.row,
+                    n_fields,
+                    self.columns.len()
+                ),
+                MissingFields::Null => {
+                    for (icol, column) in self.columns.iter_mut().enumerate().skip(n_fields) {
+                        column.push_default(f64::NAN, "");
+                        self.nulls[icol].push(self.row);
+                    }
+                }
+                MissingFields::Fill { real, text } => {
+                    for column in self.columns.iter_mut().skip(n_fields) {
+                        column.push_default(*real, text);
+                    }
+                }
+            }
+        }
+        self.row += 1;
+        Ok(())
+    }
+
+    /// Number of rows parsed so far.
+    pub fn rows(&self) -> usize {
+        self.row
+    }
+
+    /// Estimate of the memory taken by the rows parsed so far, in bytes.
+    pub fn memory(&self) -> usize {
+        self.row * self.row_bytes + self.text_bytes
+    }
+
+    /// The type codes of the columns, by column name.
+    pub fn type_codes(&self) -> HashMap<String, (String, ColumnKind)> {
+        self.colnames
+            .iter()
+            .cloned()
+            .zip(self.codes.iter().cloned())
+            .collect()
+    }
+
+    /// The `NaN` sentinel to write for every real column that has one.
+    pub fn write_sentinels(&self) -> HashMap<String, f64> {
+        self.colnames
+            .iter()
+            .zip(&self.codes)
+            .zip(&self.sentinels)
+            .filter(|((_, (_, kind)), _)| *kind == ColumnKind::Real)
+            .filter_map(|((name, _), sentinels)| Some((name.clone(), *sentinels.first()?)))
+            .collect()
+    }
+
+    /// Builds the columns from the rows parsed so far.
+    pub fn finish(mut self) -> Vec<Column> {
+        self.take_columns()
+    }
+
+    /// Builds the columns from the rows parsed so far and starts new, empty ones.
+    pub fn take_columns(&mut self) -> Vec<Column> {
+        let columns: Vec<ColumnBuffer> = self
+            .columns
+            .iter_mut()
+            .map(|column| match column {
+                ColumnBuffer::Real(v) => ColumnBuffer::Real(std::mem::take(v)),
+                ColumnBuffer::Integer(v) => ColumnBuffer::Integer(std::mem::take(v)),
+                ColumnBuffer::Text(v) => ColumnBuffer::Text(std::mem::take(v)),
+                ColumnBuffer::Boolean(v) => ColumnBuffer::Boolean(std::mem::take(v)),
+            })
+            .collect();
+        let all_nulls = std::mem::replace(&mut self.nulls, vec![Vec::new(); self.columns.len()]);
+        self.row = 0;
+        self.text_bytes = 0;
+
+        let mut serieses: Vec<Column> = vec![];
+        for ((name, column), nulls) in self.colnames.iter().zip(columns).zip(all_nulls) {
+            let nulls: HashSet<usize> = nulls.into_iter().collect();
+            let valid = |row: &usize| !nulls.contains(row);
+
+            let series = match column {
+                ColumnBuffer::Text(v) if nulls.is_empty() => Series::new(name.into(), &v),
+                ColumnBuffer::Real(v) if nulls.is_empty() => Series::new(name.into(), v),
+                ColumnBuffer::Integer(v) => Series::new(name.into(), v),
+                ColumnBuffer::Boolean(v) => Series::new(name.into(), v),
+                ColumnBuffer::Text(v) => {
+                    let v: Vec<Option<String>> = v
+                        .into_iter()
+                        .enumerate()
+                        .map(|(row, t)| valid(&row).then_some(t))
+                        .collect();
+                    Series::new(name.into(), v)
+                }
+                ColumnBuffer::Real(v) => {
+                    let v: Vec<Option<f64>> = v
+                        .into_iter()
+                        .enumerate()
+                        .map(|(row, r)| valid(&row).then_some(r))
+                        .collect();
+                    Series::new(name.into(), v)
+                }
+            };
+            serieses.push(series.into());
+        }
+        serieses
+    }
+}
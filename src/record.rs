@@ -0,0 +1,130 @@
+//! Typed rows of a tfs file.
+//!
+//! Structs implementing [`TfsRecord`] map their fields to columns. With the `derive` feature,
+//! `#[derive(TfsRecord)]` implements it for structs with named fields, every field is read from
+//! the column with its uppercase name or the name given by `#[tfs(rename = "...")]`:
+//!
+//! ```ignore
+//! use tfs::TfsRecord;
+//!
+//! #[derive(TfsRecord)]
+//! struct Element {
+//!     name: String,
+//!     s: f64,
+//!     #[tfs(rename = "BETX")]
+//!     beta_x: f64,
+//! }
+//!
+//! let df = tfs::TfsDataFrame::<f64>::open("twiss.tfs")?;
+//! let elements: Vec<Element> = df.records()?;
+//! ```
+//!
+//! The columns are looked up once and converted as a whole, so reading records is about as fast
+//! as iterating over the columns by hand.
+use polars::prelude::{DataType, NamedFrom};
+use polars::series::Series;
+
+/// Types that can be stored in a column, the fields of a [`TfsRecord`].
+pub trait ColumnValue: Sized + Clone {
+    /// Converts the whole column.
+    fn from_column(column: &Series) -> anyhow::Result<Vec<Self>>;
+
+    /// Builds a column from the values.
+    fn to_column(name: &str, values: Vec<Self>) -> Series;
+}
+
+/// Missing values are read as `NaN`.
+impl ColumnValue for f64 {
+    fn from_column(column: &Series) -> anyhow::Result<Vec<Self>> {
+        Ok(column
+            .cast(&DataType::Float64)?
+            .f64()?
+            .iter()
+            .map(|v| v.unwrap_or(f64::NAN))
+            .collect())
+    }
+
+    fn to_column(name: &str, values: Vec<Self>) -> Series {
+        Series::new(name.into(), values)
+    }
+}
+
+impl ColumnValue for i64 {
+    fn from_column(column: &Series) -> anyhow::Result<Vec<Self>> {
+        column
+            .cast(&DataType::Int64)?
+            .i64()?
+            .iter()
+            .map(|v| {
+                v.ok_or_else(|| anyhow::anyhow!("column '{}' has missing values", column.name()))
+            })
+            .collect()
+    }
+
+    fn to_column(name: &str, values: Vec<Self>) -> Series {
+        Series::new(name.into(), values)
+    }
+}
+
+/// Missing values are read as empty strings.
+impl ColumnValue for String {
+    fn from_column(column: &Series) -> anyhow::Result<Vec<Self>> {
+        Ok(column
+            .str()?
+            .iter()
+            .map(|v| v.unwrap_or_default().to_owned())
+            .collect())
+    }
+
+    fn to_column(name: &str, values: Vec<Self>) -> Series {
+        Series::new(name.into(), values)
+    }
+}
+
+impl ColumnValue for Option<f64> {
+    fn from_column(column: &Series) -> anyhow::Result<Vec<Self>> {
+        Ok(column.cast(&DataType::Float64)?.f64()?.iter().collect())
+    }
+
+    fn to_column(name: &str, values: Vec<Self>) -> Series {
+        Series::new(name.into(), values)
+    }
+}
+
+impl ColumnValue for Option<i64> {
+    fn from_column(column: &Series) -> anyhow::Result<Vec<Self>> {
+        Ok(column.cast(&DataType::Int64)?.i64()?.iter().collect())
+    }
+
+    fn to_column(name: &str, values: Vec<Self>) -> Series {
+        Series::new(name.into(), values)
+    }
+}
+
+impl ColumnValue for Option<String> {
+    fn from_column(column: &Series) -> anyhow::Result<Vec<Self>> {
+        Ok(column
+            .str()?
+            .iter()
+            .map(|v| v.map(|s| s.to_owned()))
+            .collect())
+    }
+
+    fn to_column(name: &str, values: Vec<Self>) -> Series {
+        Series::new(name.into(), values)
+    }
+}
+
+/// Structs representing one row of a tfs file. Usually implemented with `#[derive(TfsRecord)]`
+/// (feature `derive`), see [`TfsDataFrame::records`](crate::TfsDataFrame::records) and
+/// [`TfsDataFrame::from_records`](crate::TfsDataFrame::from_records).
+pub trait TfsRecord: Sized {
+    /// The column names, in the order of the fields.
+    fn columns() -> Vec<&'static str>;
+
+    /// Builds the records from the columns given in the order of [`TfsRecord::columns`].
+    fn from_series(columns: &[&Series]) -> anyhow::Result<Vec<Self>>;
+
+    /// Builds the columns from the records, in the order of [`TfsRecord::columns`].
+    fn to_series(records: &[Self]) -> Vec<Series>;
+}
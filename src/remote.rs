@@ -0,0 +1,305 @@
+//! Reading tfs files from remote storage, e.g. model files on EOS.
+//!
+//! A [`Fetcher`] opens the files of some URL schemes for reading. [`TfsDataFrame::open_url`]
+//! reads a file through one, so that any storage can be plugged in by implementing the trait.
+//! [`LocalFetcher`] reads `file://` URLs. With the feature `xrootd`, `XrdcpFetcher` reads
+//! `root://` URLs with the `xrdcp` command of the XRootD client. With the feature
+//! `object-store`, `ObjectStoreFetcher` and `TfsDataFrame::open_object_store` read the cloud
+//! object stores S3, Google Cloud Storage and Azure, streaming the files instead of downloading
+//! them first. Files ending in `.gz` are decompressed while they are read:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::remote::LocalFetcher;
+//! let df = TfsDataFrame::<f64>::open_url("file://test/test.tfs", &LocalFetcher).unwrap();
+//! assert_eq!(df.len(), 5);
+//! ```
+use flate2::read::MultiGzDecoder;
+use polars::prelude::NumericNative;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::options::TfsReadOptions;
+use crate::stages::TfsHeaderParser;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Opens remote files for reading, see the [module documentation](self).
+pub trait Fetcher {
+    /// The URL schemes the fetcher reads, without `://`.
+    fn schemes(&self) -> &[&str];
+
+    /// Opens the file at `url`, whose scheme is one of [`Fetcher::schemes`].
+    fn fetch(&self, url: &str) -> anyhow::Result<Box<dyn BufRead>>;
+}
+
+/// The scheme of `url` and the rest after `://`.
+fn split_url(url: &str) -> anyhow::Result<(&str, &str)> {
+    url.split_once("://")
+        .ok_or_else(|| anyhow::anyhow!("'{}' is not a URL, it has no '://'", url))
+}
+
+/// The host and path of `url`, without the query and fragment.
+fn url_path(url: &str) -> anyhow::Result<&str> {
+    let (_, rest) = split_url(url)?;
+    Ok(rest.split(['?', '#']).next().unwrap_or(rest))
+}
+
+/// Reads `file://` URLs from the local file system, with paths resolved like
+/// [`TfsDataFrame::open`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalFetcher;
+
+impl Fetcher for LocalFetcher {
+    fn schemes(&self) -> &[&str] {
+        &["file"]
+    }
+
+    fn fetch(&self, url: &str) -> anyhow::Result<Box<dyn BufRead>> {
+        let (_, path) = split_url(url)?;
+        let path = crate::paths::resolve_path(path.as_ref(), &[])?;
+        Ok(Box::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+/// Reads `root://` URLs by running `xrdcp`, which has to be installed. The whole file is read
+/// before it is parsed, so that failed transfers are reported with the output of `xrdcp`.
+#[cfg(feature = "xrootd")]
+#[derive(Debug, Clone)]
+pub struct XrdcpFetcher {
+    program: std::path::PathBuf,
+}
+
+#[cfg(feature = "xrootd")]
+impl Default for XrdcpFetcher {
+    fn default() -> Self {
+        XrdcpFetcher {
+            program: "xrdcp".into(),
+        }
+    }
+}
+
+#[cfg(feature = "xrootd")]
+impl XrdcpFetcher {
+    pub fn new() -> XrdcpFetcher {
+        XrdcpFetcher::default()
+    }
+
+    /// Runs `program` instead of the `xrdcp` found in `PATH`.
+    pub fn program<P: AsRef<std::path::Path>>(mut self, program: P) -> Self {
+        self.program = program.as_ref().to_owned();
+        self
+    }
+}
+
+#[cfg(feature = "xrootd")]
+impl Fetcher for XrdcpFetcher {
+    fn schemes(&self) -> &[&str] {
+        &["root", "xroot"]
+    }
+
+    fn fetch(&self, url: &str) -> anyhow::Result<Box<dyn BufRead>> {
+        let output = std::process::Command::new(&self.program)
+            .args(["--silent", url, "-"])
+            .output()
+            .map_err(|err| {
+                anyhow::anyhow!(
+                    "couldn't run '{}' to read '{}', is the XRootD client installed? {}",
+                    self.program.display(),
+                    url,
+                    err
+                )
+            })?;
+        anyhow::ensure!(
+            output.status.success(),
+            "'{}' failed to read '{}' ({}): {}",
+            self.program.display(),
+            url,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        Ok(Box::new(std::io::Cursor::new(output.stdout)))
+    }
+}
+
+/// Reads the URLs of the cloud object stores supported by `object_store`: `s3://` and `s3a://`
+/// for S3, `gs://` or `gcs://` for Google Cloud Storage, `az://`, `azure://`, `abfs://`,
+/// `abfss://` and `adl://` for Azure, and `file://` with an absolute path.
+///
+/// The stores are configured like `object_store` from the environment, e.g. `AWS_REGION`,
+/// `AWS_ACCESS_KEY_ID` or `GOOGLE_SERVICE_ACCOUNT`, and from [`ObjectStoreFetcher::option`]. The
+/// files are fetched on a runtime of their own, so they can't be read from async code.
+#[cfg(feature = "object-store")]
+#[derive(Debug, Clone, Default)]
+pub struct ObjectStoreFetcher {
+    options: Vec<(String, String)>,
+}
+
+#[cfg(feature = "object-store")]
+impl ObjectStoreFetcher {
+    pub fn new() -> ObjectStoreFetcher {
+        ObjectStoreFetcher::default()
+    }
+
+    /// Sets the configuration `key` of the store, e.g. `aws_endpoint` for S3 compatible stores,
+    /// overriding the environment. Keys the store doesn't know fail the fetch.
+    pub fn option(mut self, key: &str, value: &str) -> Self {
+        self.options
+            .push((key.to_ascii_lowercase(), value.to_owned()));
+        self
+    }
+}
+
+/// Builds a store with `$builder` for `$url`, configured from the environment and then from the
+/// key-value pairs `$options`.
+#[cfg(feature = "object-store")]
+macro_rules! configured {
+    ($builder:ty, $url:expr, $options:expr) => {{
+        let mut builder = <$builder>::from_env().with_url($url.as_str());
+        for (key, value) in &$options {
+            let config = key
+                .parse()
+                .map_err(|err| anyhow::anyhow!("invalid store option '{}': {}", key, err))?;
+            builder = builder.with_config(config, value.clone());
+        }
+        Box::new(builder.build()?)
+    }};
+}
+
+#[cfg(feature = "object-store")]
+impl Fetcher for ObjectStoreFetcher {
+    fn schemes(&self) -> &[&str] {
+        &[
+            "s3", "s3a", "gs", "gcs", "az", "azure", "abfs", "abfss", "adl", "file",
+        ]
+    }
+
+    fn fetch(&self, url: &str) -> anyhow::Result<Box<dyn BufRead>> {
+        let (scheme, rest) = split_url(url)?;
+        let parsed = match scheme.eq_ignore_ascii_case("gcs") {
+            true => url::Url::parse(&format!("gs://{}", rest))?,
+            false => url::Url::parse(url)?,
+        };
+        let (scheme, path) = object_store::ObjectStoreScheme::parse(&parsed)?;
+        let store: Box<dyn object_store::ObjectStore> = match scheme {
+            object_store::ObjectStoreScheme::Local => {
+                Box::new(object_store::local::LocalFileSystem::new())
+            }
+            object_store::ObjectStoreScheme::AmazonS3 => {
+                configured!(object_store::aws::AmazonS3Builder, parsed, self.options)
+            }
+            object_store::ObjectStoreScheme::GoogleCloudStorage => configured!(
+                object_store::gcp::GoogleCloudStorageBuilder,
+                parsed,
+                self.options
+            ),
+            object_store::ObjectStoreScheme::MicrosoftAzure => {
+                configured!(
+                    object_store::azure::MicrosoftAzureBuilder,
+                    parsed,
+                    self.options
+                )
+            }
+            scheme => anyhow::bail!("'{}' is a {:?} URL, not an object store", url, scheme),
+        };
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let stream = runtime
+            .block_on(store.get(&path))
+            .map_err(|err| anyhow::anyhow!("couldn't read '{}': {}", url, err))?
+            .into_stream();
+        Ok(Box::new(StreamReader {
+            runtime,
+            stream,
+            chunk: bytes::Bytes::new(),
+        }))
+    }
+}
+
+/// Reads the chunks of an object as they arrive.
+#[cfg(feature = "object-store")]
+struct StreamReader {
+    runtime: tokio::runtime::Runtime,
+    stream: futures::stream::BoxStream<'static, object_store::Result<bytes::Bytes>>,
+    chunk: bytes::Bytes,
+}
+
+#[cfg(feature = "object-store")]
+impl std::io::Read for StreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "object-store")]
+impl BufRead for StreamReader {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        use futures::StreamExt;
+
+        while self.chunk.is_empty() {
+            match self.runtime.block_on(self.stream.next()) {
+                Some(chunk) => self.chunk = chunk.map_err(std::io::Error::other)?,
+                None => break,
+            }
+        }
+        Ok(&self.chunk)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        bytes::Buf::advance(&mut self.chunk, amt);
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T>
+where
+    <T as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    /// Reads the tfs file at `url` through `fetcher`, see the [module documentation](self).
+    pub fn open_url(url: &str, fetcher: &dyn Fetcher) -> anyhow::Result<TfsDataFrame<T>> {
+        TfsDataFrame::open_url_with(url, fetcher, &TfsReadOptions::default())
+    }
+
+    /// Reads the tfs file at `url` like [`TfsDataFrame::open_url`], parsing it according to
+    /// `options`.
+    pub fn open_url_with(
+        url: &str,
+        fetcher: &dyn Fetcher,
+        options: &TfsReadOptions,
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let (scheme, _) = split_url(url)?;
+        anyhow::ensure!(
+            fetcher
+                .schemes()
+                .iter()
+                .any(|s| s.eq_ignore_ascii_case(scheme)),
+            "'{}' is a {}:// URL, the fetcher reads {}",
+            url,
+            scheme,
+            fetcher
+                .schemes()
+                .iter()
+                .map(|s| format!("{}://", s))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let mut reader = fetcher.fetch(url)?;
+        if url_path(url)?.ends_with(".gz") {
+            reader = Box::new(BufReader::new(MultiGzDecoder::new(reader)));
+        }
+        Ok(TfsHeaderParser::with_options(reader, options.clone())
+            .parse()?
+            .finish()?)
+    }
+
+    /// Reads the tfs file at `url` from a cloud object store through an [`ObjectStoreFetcher`]
+    /// configured from the environment, see the [module documentation](self).
+    #[cfg(feature = "object-store")]
+    pub fn open_object_store(url: &str) -> anyhow::Result<TfsDataFrame<T>> {
+        TfsDataFrame::open_url(url, &ObjectStoreFetcher::new())
+    }
+}
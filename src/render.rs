@@ -0,0 +1,256 @@
+//! Tables for logbooks and papers.
+//!
+//! [`TfsDataFrame::to_markdown`] and [`TfsDataFrame::to_latex`] render the columns as a
+//! Markdown or LaTeX (booktabs) table. Header entries can be added below the table as
+//! footnotes:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::render::FloatFormat;
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//!
+//! let markdown = df.to_markdown(3).unwrap().footnotes(&["Q1", "Q2"]).to_string();
+//! assert!(markdown.starts_with("| NAME | S |"));
+//!
+//! let latex = df
+//!     .to_latex(&["NAME", "BETX", "BETY"], FloatFormat::Fixed(2))
+//!     .unwrap()
+//!     .to_string();
+//! assert!(latex.contains(r"BPM1 & 192.29 & "));
+//! ```
+use polars::prelude::{AnyValue, NumericNative};
+use polars::series::Series;
+use std::fmt;
+
+use crate::tfsdataframe::TfsDataFrame;
+
+/// How real numbers are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatFormat {
+    /// The shortest representation that reads back as the same number.
+    #[default]
+    Auto,
+    /// A fixed number of decimals, `192.29`.
+    Fixed(usize),
+    /// Scientific notation with a fixed number of decimals, `1.92e2`, in LaTeX
+    /// `$1.92 \times 10^{2}$`.
+    Scientific(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Markup {
+    Markdown,
+    Latex,
+}
+
+/// A table rendered by [`TfsDataFrame::to_markdown`] or [`TfsDataFrame::to_latex`], written with
+/// its `Display` implementation.
+pub struct Table<'a, T: std::str::FromStr + NumericNative> {
+    df: &'a TfsDataFrame<T>,
+    markup: Markup,
+    columns: Vec<&'a Series>,
+    max_rows: Option<usize>,
+    float_format: FloatFormat,
+    footnotes: Vec<String>,
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Renders all columns as a Markdown table with at most `max_rows` rows, further rows are
+    /// elided.
+    pub fn to_markdown(&self, max_rows: usize) -> anyhow::Result<Table<'_, T>> {
+        let columns = self
+            .column_names()
+            .into_iter()
+            .map(|name| self.column(name))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Table {
+            df: self,
+            markup: Markup::Markdown,
+            columns,
+            max_rows: Some(max_rows),
+            float_format: FloatFormat::Auto,
+            footnotes: Vec::new(),
+        })
+    }
+
+    /// Renders `columns` as a LaTeX `tabular` with the rules of the booktabs package.
+    pub fn to_latex(
+        &self,
+        columns: &[&str],
+        float_fmt: FloatFormat,
+    ) -> anyhow::Result<Table<'_, T>> {
+        let columns = columns
+            .iter()
+            .map(|name| self.column(name))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Table {
+            df: self,
+            markup: Markup::Latex,
+            columns,
+            max_rows: None,
+            float_format: float_fmt,
+            footnotes: Vec::new(),
+        })
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> Table<'_, T> {
+    /// Adds the header entries `keys` as footnotes, in this order. Keys that aren't in the
+    /// header are left out.
+    pub fn footnotes(mut self, keys: &[&str]) -> Self
+    where
+        T: fmt::Display,
+    {
+        self.footnotes = keys
+            .iter()
+            .filter_map(|key| {
+                self.df
+                    .properties
+                    .get(*key)
+                    .map(|value| format!("{} = {}", key, value))
+            })
+            .collect();
+        self
+    }
+
+    /// Sets how real numbers are written.
+    pub fn float_format(mut self, float_format: FloatFormat) -> Self {
+        self.float_format = float_format;
+        self
+    }
+
+    fn cell(&self, column: &Series, row: usize) -> String {
+        let text = match column.get(row) {
+            Ok(AnyValue::Null) | Err(_) => String::new(),
+            Ok(AnyValue::String(s)) => s.to_owned(),
+            Ok(AnyValue::Float64(v)) => return self.real(v),
+            Ok(AnyValue::Float32(v)) => return self.real(v as f64),
+            Ok(v) => v.to_string(),
+        };
+        self.escape(&text)
+    }
+
+    fn real(&self, v: f64) -> String {
+        match (self.float_format, self.markup) {
+            (FloatFormat::Auto, _) => v.to_string(),
+            (FloatFormat::Fixed(decimals), _) => format!("{:.*}", decimals, v),
+            (FloatFormat::Scientific(decimals), Markup::Markdown) => format!("{:.*e}", decimals, v),
+            (FloatFormat::Scientific(decimals), Markup::Latex) => {
+                let formatted = format!("{:.*e}", decimals, v);
+                match formatted.split_once('e') {
+                    Some((mantissa, exponent)) if v.is_finite() => {
+                        format!(r"${} \times 10^{{{}}}$", mantissa, exponent)
+                    }
+                    _ => formatted,
+                }
+            }
+        }
+    }
+
+    fn escape(&self, text: &str) -> String {
+        match self.markup {
+            Markup::Markdown => text.replace('|', r"\|"),
+            Markup::Latex => text
+                .chars()
+                .map(|c| match c {
+                    '\\' => r"\textbackslash{}".to_owned(),
+                    '~' => r"\textasciitilde{}".to_owned(),
+                    '^' => r"\textasciicircum{}".to_owned(),
+                    '&' | '%' | '$' | '#' | '_' | '{' | '}' => format!(r"\{}", c),
+                    c => c.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    fn rows(&self) -> usize {
+        self.max_rows
+            .map_or(self.df.len(), |max_rows| max_rows.min(self.df.len()))
+    }
+
+    fn write_markdown(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let row = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+
+        writeln!(
+            f,
+            "{}",
+            row(self.columns.iter().map(|c| self.escape(c.name())).collect())
+        )?;
+        let rules = self
+            .columns
+            .iter()
+            .map(|c| {
+                if c.dtype().is_primitive_numeric() {
+                    "---:"
+                } else {
+                    "---"
+                }
+            })
+            .collect::<Vec<_>>();
+        writeln!(f, "|{}|", rules.join("|"))?;
+
+        for r in 0..self.rows() {
+            writeln!(
+                f,
+                "{}",
+                row(self.columns.iter().map(|c| self.cell(c, r)).collect())
+            )?;
+        }
+        if self.rows() < self.df.len() {
+            writeln!(f, "{}", row(vec!["...".to_owned(); self.columns.len()]))?;
+            writeln!(f, "\n_{} of {} rows_", self.rows(), self.df.len())?;
+        }
+
+        if !self.footnotes.is_empty() {
+            writeln!(f)?;
+            for footnote in &self.footnotes {
+                writeln!(f, "- {}", self.escape(footnote))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_latex(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let alignment: String = self
+            .columns
+            .iter()
+            .map(|c| {
+                if c.dtype().is_primitive_numeric() {
+                    'r'
+                } else {
+                    'l'
+                }
+            })
+            .collect();
+        writeln!(f, r"\begin{{tabular}}{{{}}}", alignment)?;
+        writeln!(f, r"\toprule")?;
+        let names: Vec<String> = self.columns.iter().map(|c| self.escape(c.name())).collect();
+        writeln!(f, r"{} \\", names.join(" & "))?;
+        writeln!(f, r"\midrule")?;
+
+        for r in 0..self.rows() {
+            let cells: Vec<String> = self.columns.iter().map(|c| self.cell(c, r)).collect();
+            writeln!(f, r"{} \\", cells.join(" & "))?;
+        }
+        writeln!(f, r"\bottomrule")?;
+
+        for footnote in &self.footnotes {
+            writeln!(
+                f,
+                r"\multicolumn{{{}}}{{l}}{{\footnotesize {}}} \\",
+                self.columns.len(),
+                self.escape(footnote)
+            )?;
+        }
+        writeln!(f, r"\end{{tabular}}")
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> fmt::Display for Table<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.markup {
+            Markup::Markdown => self.write_markdown(f),
+            Markup::Latex => self.write_latex(f),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Summary of the data-quality issues found while loading a file.
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let report = df.load_report();
+//!
+//! assert!(report.is_clean());
+//! println!("{}", report);
+//! ```
+use polars::prelude::NumericNative;
+use std::fmt;
+
+use crate::parse::ParseWarning;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// The [`ParseWarning`]s of a load, grouped by kind.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadReport<'a> {
+    pub warnings: &'a [ParseWarning],
+}
+
+impl<'a> LoadReport<'a> {
+    /// `true` if nothing was found.
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    /// Fields of real columns that weren't numbers and were read as `NaN`.
+    pub fn nan_coercions(&self) -> impl Iterator<Item = &'a ParseWarning> {
+        self.filter(|w| matches!(w, ParseWarning::NanCoercion { .. }))
+    }
+
+    /// Fields of integer and boolean columns that weren't valid and were read as missing.
+    pub fn null_coercions(&self) -> impl Iterator<Item = &'a ParseWarning> {
+        self.filter(|w| matches!(w, ParseWarning::NullCoercion { .. }))
+    }
+
+    /// Lines that were neither header lines nor data rows.
+    pub fn skipped_lines(&self) -> impl Iterator<Item = &'a ParseWarning> {
+        self.filter(|w| matches!(w, ParseWarning::SkippedLine { .. }))
+    }
+
+    /// Columns read as text because of an unknown type code.
+    pub fn unknown_type_codes(&self) -> impl Iterator<Item = &'a ParseWarning> {
+        self.filter(|w| matches!(w, ParseWarning::UnknownTypeCode { .. }))
+    }
+
+    /// Header keys that appeared more than once.
+    pub fn duplicate_headers(&self) -> impl Iterator<Item = &'a ParseWarning> {
+        self.filter(|w| matches!(w, ParseWarning::DuplicateHeader { .. }))
+    }
+
+    /// Values only accepted because of a decimal comma.
+    pub fn decimal_commas(&self) -> impl Iterator<Item = &'a ParseWarning> {
+        self.filter(|w| {
+            matches!(
+                w,
+                ParseWarning::DecimalComma { .. } | ParseWarning::DecimalCommaProperty { .. }
+            )
+        })
+    }
+
+    fn filter(
+        &self,
+        predicate: impl Fn(&ParseWarning) -> bool,
+    ) -> impl Iterator<Item = &'a ParseWarning> {
+        self.warnings.iter().filter(move |w| predicate(w))
+    }
+}
+
+impl fmt::Display for LoadReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} NaN coercions, {} null coercions, {} skipped lines, {} unknown type codes, \
+             {} duplicate headers, {} decimal commas",
+            self.nan_coercions().count(),
+            self.null_coercions().count(),
+            self.skipped_lines().count(),
+            self.unknown_type_codes().count(),
+            self.duplicate_headers().count(),
+            self.decimal_commas().count()
+        )?;
+        for warning in self.warnings {
+            write!(f, "\n  {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Returns the issues found while loading the frame, see [`LoadReport`].
+    pub fn load_report(&self) -> LoadReport<'_> {
+        LoadReport {
+            warnings: &self.warnings,
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Reshaping frames from the wide to the long form.
+//!
+//! [`TfsDataFrame::melt`] turns columns into rows: every value of the value columns becomes a
+//! row of its own, with the name of its column in `VARIABLE`, the value in `VALUE` and the
+//! values of the id columns of its row. Long frames are easier to group, filter and plot by
+//! column, e.g. all beta functions of both planes at once:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let betas = df.melt(&["NAME", "S"], &["BETX", "BETY"]).unwrap();
+//! assert_eq!(betas.column_names(), ["NAME", "S", "VARIABLE", "VALUE"]);
+//! assert_eq!(betas.len(), 10);
+//! assert_eq!(betas.column("VARIABLE").unwrap().str().unwrap().get(5), Some("BETY"));
+//! ```
+use polars::prelude::{Column, DataFrame, DataType, NamedFrom, NumericNative};
+use polars::series::Series;
+
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Column of a melted frame naming the column a value comes from.
+pub const VARIABLE_COLUMN: &str = "VARIABLE";
+/// Column of a melted frame holding the values.
+pub const VALUE_COLUMN: &str = "VALUE";
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Melts the `value_vars` into the rows of a long frame, see the
+    /// [module documentation](self). The rows are ordered by value column, then like this
+    /// frame. Without `value_vars` all columns but the `id_vars` are melted.
+    ///
+    /// The values are reals if all value columns are numeric or boolean and texts if all are
+    /// text, other mixtures fail. The header is kept.
+    pub fn melt(&self, id_vars: &[&str], value_vars: &[&str]) -> anyhow::Result<TfsDataFrame<T>> {
+        let value_vars: Vec<&str> = if value_vars.is_empty() {
+            self.column_names()
+                .into_iter()
+                .filter(|name| !id_vars.contains(name))
+                .collect()
+        } else {
+            value_vars.to_vec()
+        };
+
+        let values = value_vars
+            .iter()
+            .map(|name| self.column(name))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let numeric = |s: &&Series| s.dtype().is_primitive_numeric() || s.dtype().is_bool();
+        let dtype = if values.iter().all(numeric) {
+            DataType::Float64
+        } else if values.iter().all(|s| s.dtype().is_string()) {
+            DataType::String
+        } else {
+            anyhow::bail!(
+                "the columns {} mix text with other values",
+                value_vars.join(", ")
+            );
+        };
+
+        let ids = id_vars
+            .iter()
+            .map(|name| self.column(name))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut melted: Vec<Series> = ids
+            .iter()
+            .map(|id| Series::new_empty(id.name().clone(), id.dtype()))
+            .collect();
+        let mut variables: Vec<&str> = Vec::with_capacity(self.len() * value_vars.len());
+        let mut melted_values = Series::new_empty(VALUE_COLUMN.into(), &dtype);
+        for (name, column) in value_vars.iter().zip(&values) {
+            for (melted, id) in melted.iter_mut().zip(&ids) {
+                melted.append(id)?;
+            }
+            variables.extend(std::iter::repeat_n(*name, column.len()));
+            melted_values.append(&column.cast(&dtype)?.with_name(VALUE_COLUMN.into()))?;
+        }
+
+        let mut columns: Vec<Column> = melted.into_iter().map(Column::from).collect();
+        columns.push(Series::new(VARIABLE_COLUMN.into(), variables).into());
+        columns.push(melted_values.into());
+        Ok(TfsDataFrame::new(
+            self.properties.clone(),
+            DataFrame::new(columns)?,
+        ))
+    }
+}
@@ -0,0 +1,138 @@
+//! Resonance lines of the tunes.
+//!
+//! A [`ResonanceLine`] `m Qx + n Qy` of order `|m| + |n|` appears in turn-by-turn spectra at
+//! the fractional part of `m Q1 + n Q2`, folded into `[0, 0.5]`. The same number is the
+//! distance of the working point to the resonance `m Qx + n Qy = p`.
+//! [`TfsDataFrame::resonance_lines`] computes the lines up to some order from the tunes in the
+//! `Q1` and `Q2` headers, [`TfsDataFrame::flag_resonances`] marks the rows of a spectrum whose
+//! frequency lies on one of them:
+//!
+//! ```
+//! # use tfs::{DataValue, TfsDataFrame};
+//! let mut spectrum = TfsDataFrame::<f64>::new(
+//!     vec![
+//!         ("Q1".to_owned(), DataValue::Real(62.28)),
+//!         ("Q2".to_owned(), DataValue::Real(60.31)),
+//!     ],
+//!     tfs::polars::df!("FREQ" => [0.28, 0.03, 0.4175], "AMP" => [1.0, 0.01, 0.002]).unwrap(),
+//! );
+//! let lines = spectrum.resonance_lines(1..=2).unwrap();
+//! assert_eq!(lines.len(), 6);
+//!
+//! assert_eq!(spectrum.flag_resonances("FREQ", &lines, 1e-3).unwrap(), 2);
+//! let flags = spectrum.column("RESONANCE").unwrap();
+//! assert_eq!(flags.str().unwrap().get(1), Some("Qx-Qy"));
+//! ```
+use polars::prelude::NumericNative;
+use std::fmt;
+
+use crate::formula::property;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// The column written by [`TfsDataFrame::flag_resonances`].
+pub const RESONANCE_COLUMN: &str = "RESONANCE";
+
+/// The line `qx Qx + qy Qy` for given tunes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResonanceLine {
+    /// The multiple of the horizontal tune, never negative.
+    pub qx: i64,
+    /// The multiple of the vertical tune, positive if `qx` is zero.
+    pub qy: i64,
+    /// The fractional part of `qx Q1 + qy Q2` folded into `[0, 0.5]`.
+    pub frequency: f64,
+}
+
+impl ResonanceLine {
+    pub fn new(qx: i64, qy: i64, q1: f64, q2: f64) -> ResonanceLine {
+        let frac = (qx as f64 * q1 + qy as f64 * q2).rem_euclid(1.0);
+        ResonanceLine {
+            qx,
+            qy,
+            frequency: frac.min(1.0 - frac),
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        (self.qx.unsigned_abs() + self.qy.unsigned_abs()) as usize
+    }
+}
+
+/// The lines of `orders` for the tunes `q1` and `q2`, by order.
+pub fn resonance_lines<I>(q1: f64, q2: f64, orders: I) -> Vec<ResonanceLine>
+where
+    I: IntoIterator<Item = usize>,
+{
+    let mut lines = Vec::new();
+    for order in orders {
+        let order = order as i64;
+        for qx in 0..=order {
+            let qy = order - qx;
+            if qx == 0 && qy == 0 {
+                continue;
+            }
+            lines.push(ResonanceLine::new(qx, qy, q1, q2));
+            if qx != 0 && qy != 0 {
+                lines.push(ResonanceLine::new(qx, -qy, q1, q2));
+            }
+        }
+    }
+    lines
+}
+
+impl fmt::Display for ResonanceLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (multiple, tune, first) in [(self.qx, "Qx", true), (self.qy, "Qy", self.qx == 0)] {
+            match multiple {
+                0 => {}
+                1 if first => write!(f, "{}", tune)?,
+                1 => write!(f, "+{}", tune)?,
+                -1 => write!(f, "-{}", tune)?,
+                m if m > 0 && !first => write!(f, "+{}{}", m, tune)?,
+                m => write!(f, "{}{}", m, tune)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// The resonance lines of `orders` (e.g. `1..=3`) for the tunes in the `Q1` and `Q2`
+    /// headers, see the [module documentation](crate::resonance).
+    pub fn resonance_lines<I>(&self, orders: I) -> anyhow::Result<Vec<ResonanceLine>>
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        let q1 = property(self, "Q1")?;
+        let q2 = property(self, "Q2")?;
+        Ok(resonance_lines(q1, q2, orders))
+    }
+
+    /// Writes the name of the line of `lines` closest to the frequency in the column `column`
+    /// into the column `RESONANCE`, if it is closer than `tolerance`. Frequencies above `0.5`
+    /// are folded like the ones of the lines. Returns the number of rows on a line.
+    pub fn flag_resonances(
+        &mut self,
+        column: &str,
+        lines: &[ResonanceLine],
+        tolerance: f64,
+    ) -> anyhow::Result<usize> {
+        let flags: Vec<Option<String>> = Option::<f64>::from_column(self.column(column)?)?
+            .into_iter()
+            .map(|frequency| {
+                let frac = frequency?.rem_euclid(1.0);
+                let frequency = frac.min(1.0 - frac);
+                lines
+                    .iter()
+                    .map(|line| (line, (line.frequency - frequency).abs()))
+                    .filter(|(_, distance)| *distance <= tolerance)
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(line, _)| line.to_string())
+            })
+            .collect();
+        let flagged = flags.iter().flatten().count();
+        self.set_column(Option::<String>::to_column(RESONANCE_COLUMN, flags))?;
+        Ok(flagged)
+    }
+}
@@ -0,0 +1,118 @@
+//! Random sampling of rows and bootstrap error estimates.
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//!
+//! let sample = df.sample(3, false, 42).unwrap();
+//! assert_eq!(sample.len(), 3);
+//!
+//! let mean_betx = df
+//!     .bootstrap_stat("BETX", |v| v.iter().sum::<f64>() / v.len() as f64, 200)
+//!     .unwrap();
+//! assert!(mean_betx.std_err > 0.0);
+//! ```
+use polars::prelude::{IdxCa, IdxSize, NumericNative};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Seed of the resampling in [`TfsDataFrame::bootstrap_stat`], fixed so that error bars are
+/// reproducible.
+const BOOTSTRAP_SEED: u64 = 0;
+
+/// Result of [`TfsDataFrame::bootstrap_stat`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BootstrapEstimate {
+    /// The statistic of the full column.
+    pub value: f64,
+    /// Mean of the statistic over the resamples.
+    pub mean: f64,
+    /// Standard deviation of the statistic over the resamples, the estimate of its standard
+    /// error.
+    pub std_err: f64,
+    /// Number of resamples.
+    pub n_iter: usize,
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Returns a frame with `n` randomly chosen rows and the same header. Without replacement
+    /// every row is chosen at most once and `n` can't exceed the number of rows. The same `seed`
+    /// gives the same rows.
+    pub fn sample(
+        &self,
+        n: usize,
+        with_replacement: bool,
+        seed: u64,
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let indices: Vec<IdxSize> = if with_replacement {
+            if self.is_empty() && n > 0 {
+                anyhow::bail!("can't sample rows from an empty frame");
+            }
+            (0..n)
+                .map(|_| rng.random_range(0..self.len()) as IdxSize)
+                .collect()
+        } else {
+            if n > self.len() {
+                anyhow::bail!(
+                    "can't sample {} rows without replacement from {} rows",
+                    n,
+                    self.len()
+                );
+            }
+            rand::seq::index::sample(&mut rng, self.len(), n)
+                .into_iter()
+                .map(|i| i as IdxSize)
+                .collect()
+        };
+
+        let df = self.df()?.take(&IdxCa::from_vec("idx".into(), indices))?;
+        Ok(self.with_rows(df))
+    }
+
+    /// Estimates the uncertainty of `stat` applied to the real column `column` by recomputing it
+    /// on `n_iter` resamples (with replacement) of the column. `NaN` values are dropped first.
+    pub fn bootstrap_stat<F>(
+        &self,
+        column: &str,
+        stat: F,
+        n_iter: usize,
+    ) -> anyhow::Result<BootstrapEstimate>
+    where
+        F: Fn(&[f64]) -> f64,
+    {
+        let values: Vec<f64> = f64::from_column(self.column(column)?)?
+            .into_iter()
+            .filter(|v| !v.is_nan())
+            .collect();
+        if values.is_empty() {
+            anyhow::bail!("column '{}' has no values to resample", column);
+        }
+        if n_iter < 2 {
+            anyhow::bail!("the bootstrap needs at least 2 iterations");
+        }
+
+        let mut rng = StdRng::seed_from_u64(BOOTSTRAP_SEED);
+        let mut resample = vec![0.0; values.len()];
+        let stats: Vec<f64> = (0..n_iter)
+            .map(|_| {
+                for v in resample.iter_mut() {
+                    *v = values[rng.random_range(0..values.len())];
+                }
+                stat(&resample)
+            })
+            .collect();
+
+        let mean = stats.iter().sum::<f64>() / n_iter as f64;
+        let variance = stats.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n_iter - 1) as f64;
+        Ok(BootstrapEstimate {
+            value: stat(&values),
+            mean,
+            std_err: variance.sqrt(),
+            n_iter,
+        })
+    }
+}
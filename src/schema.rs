@@ -0,0 +1,362 @@
+//! Checking files against a schema.
+//!
+//! A [`Schema`] lists the header entries and columns a file should have, with their kind and
+//! optionally a range of values or a regular expression for text. It is written as JSON:
+//!
+//! ```json
+//! {
+//!     "headers": [
+//!         { "name": "Q1", "kind": "real", "min": 0, "max": 100 },
+//!         { "name": "COMMENT", "required": false }
+//!     ],
+//!     "columns": [
+//!         { "name": "NAME", "kind": "text", "pattern": "^[A-Z]" },
+//!         { "name": "S", "kind": "real", "min": 0 }
+//!     ],
+//!     "checks": ["BETX > 0", "|X| < 0.1"]
+//! }
+//! ```
+//!
+//! The checks are [row filters](crate::expr) every row has to pass. Their violations list the
+//! failing elements, so that corrupted output can be traced back, e.g. in a
+//! [pipeline](crate::pipeline::TfsPipeline::validate). Rows with missing values in the columns of
+//! a check aren't checked.
+//!
+//! [`TfsDataFrame::validate`] returns every [`Violation`] of the schema, an empty list if the
+//! frame matches it:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::schema::Schema;
+//! let schema = Schema::from_json(r#"{ "columns": [{ "name": "BETX", "min": 0 }] }"#).unwrap();
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! assert!(df.validate(&schema).unwrap().is_empty());
+//! ```
+use polars::prelude::{DataType, NumericNative};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+use crate::dataframe::DataValue;
+use crate::expr::RowFilter;
+use crate::mask::NAME_COLUMN;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+use crate::types::ColumnKind;
+
+/// The header entries and columns expected in a file. Read from JSON, or filled in starting from
+/// [`Schema::default`], as more fields may be added.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct Schema {
+    #[serde(default)]
+    pub headers: Vec<Rule>,
+    #[serde(default)]
+    pub columns: Vec<Rule>,
+    /// Conditions on the values of every row, see the [module documentation](self).
+    #[serde(default)]
+    pub checks: Vec<String>,
+}
+
+/// The expectations on a single header entry or column.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub name: String,
+    /// Whether a missing entry or column is a violation, `true` if not given.
+    #[serde(default = "required")]
+    pub required: bool,
+    pub kind: Option<ColumnKind>,
+    /// The smallest allowed number. Missing values and `NaN` aren't checked.
+    pub min: Option<f64>,
+    /// The largest allowed number.
+    pub max: Option<f64>,
+    /// A regular expression text values have to match.
+    pub pattern: Option<String>,
+}
+
+fn required() -> bool {
+    true
+}
+
+/// Whether a [`Violation`] concerns a header entry, a column or a check of the rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Target {
+    Header,
+    Column,
+    /// A check of [`Schema::checks`], named by its expression.
+    Check,
+}
+
+/// A header entry or column that doesn't match its [`Rule`], or a failed check.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Violation {
+    pub target: Target,
+    pub name: String,
+    pub message: String,
+    /// The elements failing a check, by `NAME` or by row if the frame has no names.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub elements: Vec<String>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let target = match self.target {
+            Target::Header => "header entry",
+            Target::Column => "column",
+            Target::Check => "check",
+        };
+        write!(f, "{} '{}' {}", target, self.name, self.message)
+    }
+}
+
+impl Schema {
+    pub fn from_json(json: &str) -> anyhow::Result<Schema> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Reads a schema from a JSON file.
+    pub fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<Schema> {
+        Schema::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Collects the violations of one header entry or column.
+struct Check<'a> {
+    rule: &'a Rule,
+    target: Target,
+    violations: Vec<Violation>,
+}
+
+impl Check<'_> {
+    fn fail(&mut self, message: String) {
+        self.violations.push(Violation {
+            target: self.target,
+            name: self.rule.name.clone(),
+            message,
+            elements: Vec::new(),
+        });
+    }
+
+    fn kind(&mut self, kind: ColumnKind) {
+        if let Some(expected) = self.rule.kind {
+            if kind != expected {
+                self.fail(format!(
+                    "is {}, expected {}",
+                    kind.canonical_code(),
+                    expected.canonical_code()
+                ));
+            }
+        }
+    }
+
+    /// Checks `values`, the rows of a column or the single value of a header entry.
+    fn range(&mut self, values: impl Iterator<Item = f64>) {
+        let (min, max) = (self.rule.min, self.rule.max);
+        let mut below = (0, None);
+        let mut above = (0, None);
+        for (row, v) in values.enumerate() {
+            if min.is_some_and(|min| v < min) {
+                below.0 += 1;
+                below.1.get_or_insert(row);
+            }
+            if max.is_some_and(|max| v > max) {
+                above.0 += 1;
+                above.1.get_or_insert(row);
+            }
+        }
+        for ((count, first), bound, limit) in [
+            (below, "below the minimum", min),
+            (above, "above the maximum", max),
+        ] {
+            if let (Some(first), Some(limit)) = (first, limit) {
+                self.fail(self.out_of(count, first, &format!("{} {}", bound, limit)));
+            }
+        }
+    }
+
+    /// Checks text values, missing values (`None`) aren't checked.
+    fn pattern<'v>(&mut self, values: impl Iterator<Item = Option<&'v str>>) -> anyhow::Result<()> {
+        let Some(pattern) = &self.rule.pattern else {
+            return Ok(());
+        };
+        let regex = Regex::new(pattern)?;
+        let mut mismatches = (0, None);
+        for (row, v) in values.enumerate() {
+            if v.is_some_and(|v| !regex.is_match(v)) {
+                mismatches.0 += 1;
+                mismatches.1.get_or_insert(row);
+            }
+        }
+        if let (count, Some(first)) = mismatches {
+            let message = self.out_of(count, first, &format!("not matching '{}'", pattern));
+            self.fail(message);
+        }
+        Ok(())
+    }
+
+    /// Describes `count` failed values, the first in row `first`. Header entries have only one.
+    fn out_of(&self, count: usize, first: usize, reason: &str) -> String {
+        match self.target {
+            Target::Header => format!("is {}", reason),
+            Target::Column | Target::Check => format!(
+                "has {} values {}, the first in row {}",
+                count, reason, first
+            ),
+        }
+    }
+
+    /// Bounds and patterns that can't apply to values of `kind`.
+    fn inapplicable(&mut self, kind: ColumnKind) {
+        let numeric = kind != ColumnKind::Text;
+        if numeric && self.rule.pattern.is_some() {
+            self.fail("has numbers, a pattern needs text".to_owned());
+        }
+        if !numeric && (self.rule.min.is_some() || self.rule.max.is_some()) {
+            self.fail("has text, a minimum or maximum needs numbers".to_owned());
+        }
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Checks the frame against `schema` and returns the violations, in the order of the rules.
+    /// Fails only if a pattern of the schema isn't a valid regular expression.
+    pub fn validate(&self, schema: &Schema) -> anyhow::Result<Vec<Violation>> {
+        let mut violations = Vec::new();
+
+        for rule in &schema.headers {
+            let mut check = Check {
+                rule,
+                target: Target::Header,
+                violations: Vec::new(),
+            };
+            match self.properties.get(&rule.name) {
+                None if rule.required => check.fail("is missing".to_owned()),
+                None => {}
+                Some(value) => {
+                    let kind = match value {
+                        DataValue::Real(_) => ColumnKind::Real,
+                        DataValue::Integer(_) => ColumnKind::Integer,
+                        DataValue::Text(_) | DataValue::List(_) => ColumnKind::Text,
+                        DataValue::Boolean(_) => ColumnKind::Boolean,
+                    };
+                    check.kind(kind);
+                    check.inapplicable(kind);
+                    match value {
+                        DataValue::Real(r) => check.range(r.to_f64().into_iter()),
+                        DataValue::Integer(i) => check.range(std::iter::once(*i as f64)),
+                        DataValue::Boolean(b) => check.range(std::iter::once(*b as i64 as f64)),
+                        DataValue::Text(t) => check.pattern(std::iter::once(Some(t.as_str())))?,
+                        DataValue::List(_) => {
+                            check.pattern(std::iter::once(Some(value.to_string().as_str())))?
+                        }
+                    }
+                }
+            }
+            violations.append(&mut check.violations);
+        }
+
+        let names = self.column_names();
+        for rule in &schema.columns {
+            let mut check = Check {
+                rule,
+                target: Target::Column,
+                violations: Vec::new(),
+            };
+            if !names.contains(&rule.name.as_str()) {
+                if rule.required {
+                    check.fail("is missing".to_owned());
+                }
+                violations.append(&mut check.violations);
+                continue;
+            }
+
+            let column = self.column(&rule.name)?;
+            let kind = ColumnKind::of_dtype(column.dtype());
+            check.kind(kind);
+            check.inapplicable(kind);
+            if column.dtype().is_primitive_numeric() {
+                let values = Option::<f64>::from_column(column)?;
+                check.range(values.into_iter().map(|v| v.unwrap_or(f64::NAN)));
+            } else if column.dtype() == &DataType::String {
+                let values = Option::<String>::from_column(column)?;
+                check.pattern(values.iter().map(|v| v.as_deref()))?;
+            }
+            violations.append(&mut check.violations);
+        }
+
+        for check in &schema.checks {
+            violations.extend(self.check_rows(check)?);
+        }
+        Ok(violations)
+    }
+
+    /// The violation of the check `source`, if any row fails it.
+    fn check_rows(&self, source: &str) -> anyhow::Result<Option<Violation>> {
+        let filter = source.parse::<RowFilter>()?;
+        let violation = |message: String, elements: Vec<String>| Violation {
+            target: Target::Check,
+            name: source.to_owned(),
+            message,
+            elements,
+        };
+        let names = self.column_names();
+        if let Some(missing) = filter.columns().into_iter().find(|c| !names.contains(c)) {
+            let message = format!("uses the missing column '{}'", missing);
+            return Ok(Some(violation(message, Vec::new())));
+        }
+
+        let mut checked = vec![true; self.len()];
+        for name in filter.columns() {
+            let column = self.column(name)?;
+            let present: Vec<bool> = if column.dtype().is_primitive_numeric() {
+                Option::<f64>::from_column(column)?
+                    .into_iter()
+                    .map(|v| v.is_some_and(|v| !v.is_nan()))
+                    .collect()
+            } else {
+                column
+                    .is_not_null()
+                    .into_iter()
+                    .map(|v| v == Some(true))
+                    .collect()
+            };
+            for (checked, present) in checked.iter_mut().zip(present) {
+                *checked &= present;
+            }
+        }
+
+        let holds = filter.mask(self)?;
+        let failing: Vec<usize> = (0..self.len())
+            .filter(|row| checked[*row] && !holds[*row])
+            .collect();
+        let Some(first) = failing.first() else {
+            return Ok(None);
+        };
+        let message = format!(
+            "fails for {} rows, the first in row {}",
+            failing.len(),
+            first
+        );
+        let elements = match self.column(NAME_COLUMN) {
+            Ok(column) if column.dtype() == &DataType::String => {
+                let names = Option::<String>::from_column(column)?;
+                failing
+                    .iter()
+                    .map(|row| {
+                        names[*row]
+                            .clone()
+                            .unwrap_or_else(|| format!("row {}", row))
+                    })
+                    .collect()
+            }
+            _ => failing.iter().map(|row| format!("row {}", row)).collect(),
+        };
+        Ok(Some(violation(message, elements)))
+    }
+}
@@ -0,0 +1,287 @@
+//! Reading SDDS files, the Self Describing Data Sets written by elegant.
+//!
+//! Only the ascii mode is supported, binary files can be converted with
+//! `sddsconvert -ascii`. The parameters become the header of the frame and the columns of the
+//! first page its data, both under their SDDS names:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let df = TfsDataFrame::<f64>::from_sdds_str(
+//!     "SDDS1\n\
+//!      &parameter name=nux, type=double, &end\n\
+//!      &column name=ElementName, type=string, &end\n\
+//!      &column name=s, units=m, type=double, &end\n\
+//!      &data mode=ascii, &end\n\
+//!      ! page number 1\n\
+//!      6.28\n\
+//!      2\n\
+//!      _BEG_ 0.0\n\
+//!      Q1 1.5\n",
+//! )
+//! .unwrap();
+//! assert_eq!(df.len(), 2);
+//! ```
+use polars::prelude::{Column, DataFrame, NamedFrom, NumericNative};
+use polars::series::Series;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::dataframe::DataValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// A parameter or column definition, the fields of a `&parameter` or `&column` namelist.
+#[derive(Debug, Clone)]
+pub(crate) struct Definition {
+    pub name: String,
+    pub kind: String,
+    fixed_value: Option<String>,
+}
+
+/// The first page of an SDDS file, before it becomes a frame.
+pub(crate) struct Sdds {
+    /// The parameters with their values as written.
+    pub parameters: Vec<(Definition, String)>,
+    pub columns: Vec<Series>,
+}
+
+impl Sdds {
+    /// Parses an ascii SDDS file.
+    pub fn parse(text: &str) -> anyhow::Result<Sdds> {
+        let mut lines = text.lines();
+        let version = lines.next().unwrap_or_default();
+        anyhow::ensure!(version.starts_with("SDDS"), "not an SDDS file");
+
+        let mut parameters = Vec::new();
+        let mut columns = Vec::new();
+        let row_counts = loop {
+            let Some(line) = lines.next() else {
+                anyhow::bail!("the SDDS file ended before its &data namelist");
+            };
+            let Some((group, fields)) = namelist(line.trim()) else {
+                continue;
+            };
+            let definition = || -> anyhow::Result<Definition> {
+                Ok(Definition {
+                    name: fields
+                        .get("name")
+                        .ok_or_else(|| anyhow::anyhow!("a &{} has no name", group))?
+                        .clone(),
+                    kind: fields.get("type").cloned().unwrap_or_default(),
+                    fixed_value: fields.get("fixed_value").cloned(),
+                })
+            };
+            match group.as_str() {
+                "parameter" => parameters.push(definition()?),
+                "column" => columns.push(definition()?),
+                "array" => anyhow::bail!("SDDS arrays are not supported"),
+                "data" => {
+                    let mode = fields.get("mode").map_or("binary", String::as_str);
+                    anyhow::ensure!(
+                        mode == "ascii",
+                        "only ascii SDDS files are supported, convert with `sddsconvert -ascii`"
+                    );
+                    break fields.get("no_row_counts").is_none_or(|n| n == "0");
+                }
+                _ => {}
+            }
+        };
+
+        let mut lines = lines.filter(|line| !line.trim_start().starts_with('!'));
+        let mut values = Vec::new();
+        for parameter in &parameters {
+            let value = match &parameter.fixed_value {
+                Some(value) => value.clone(),
+                None => lines
+                    .next()
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("the parameter '{}' has no value", parameter.name)
+                    })?
+                    .trim()
+                    .to_owned(),
+            };
+            let value = if parameter.kind == "string" {
+                unquote(&value)
+            } else {
+                value
+            };
+            values.push((parameter.clone(), value));
+        }
+
+        let n_rows = if row_counts {
+            let line = lines.next().unwrap_or_default();
+            Some(
+                line.trim()
+                    .parse::<usize>()
+                    .map_err(|_| anyhow::anyhow!("invalid row count '{}'", line.trim()))?,
+            )
+        } else {
+            None
+        };
+
+        let mut cells: Vec<Vec<String>> = vec![Vec::new(); columns.len()];
+        let mut row = 0;
+        while n_rows.is_none_or(|n| row < n) {
+            let Some(line) = lines.next() else {
+                anyhow::ensure!(n_rows.is_none(), "the SDDS file ended after {} rows", row);
+                break;
+            };
+            if line.trim().is_empty() {
+                if n_rows.is_none() {
+                    break;
+                }
+                continue;
+            }
+            let fields = tokens(line);
+            anyhow::ensure!(
+                fields.len() == columns.len(),
+                "row {} has {} fields but there are {} columns",
+                row,
+                fields.len(),
+                columns.len()
+            );
+            for (column, field) in cells.iter_mut().zip(fields) {
+                column.push(field);
+            }
+            row += 1;
+        }
+
+        let columns = columns
+            .iter()
+            .zip(cells)
+            .map(|(column, cells)| column_series(column, cells))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Sdds {
+            parameters: values,
+            columns,
+        })
+    }
+
+    pub fn into_frame<T>(self) -> anyhow::Result<TfsDataFrame<T>>
+    where
+        T: std::str::FromStr + NumericNative,
+    {
+        let parameters = self
+            .parameters
+            .iter()
+            .map(|(parameter, value)| Ok((parameter.name.clone(), parse_value(parameter, value)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let columns = self.columns.into_iter().map(Column::from).collect();
+        Ok(TfsDataFrame::new(parameters, DataFrame::new(columns)?))
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Reads the first page of an ascii SDDS file, see the [module documentation](crate::sdds).
+    pub fn open_sdds<P: AsRef<Path>>(path: P) -> anyhow::Result<TfsDataFrame<T>> {
+        TfsDataFrame::from_sdds_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses the first page of an ascii SDDS file.
+    pub fn from_sdds_str(text: &str) -> anyhow::Result<TfsDataFrame<T>> {
+        Sdds::parse(text)?.into_frame()
+    }
+}
+
+fn is_integer(kind: &str) -> bool {
+    matches!(
+        kind,
+        "long" | "short" | "ulong" | "ushort" | "long64" | "ulong64"
+    )
+}
+
+fn is_real(kind: &str) -> bool {
+    matches!(kind, "double" | "float" | "longdouble")
+}
+
+fn parse_value<T: std::str::FromStr>(
+    definition: &Definition,
+    value: &str,
+) -> anyhow::Result<DataValue<T>> {
+    let invalid = || anyhow::anyhow!("invalid value '{}' of '{}'", value, definition.name);
+    Ok(if is_real(&definition.kind) {
+        DataValue::Real(value.parse().map_err(|_| invalid())?)
+    } else if is_integer(&definition.kind) {
+        DataValue::Integer(value.parse().map_err(|_| invalid())?)
+    } else {
+        DataValue::Text(value.to_owned())
+    })
+}
+
+fn column_series(definition: &Definition, cells: Vec<String>) -> anyhow::Result<Series> {
+    let name = definition.name.as_str().into();
+    let invalid = |cell: &str| anyhow::anyhow!("invalid value '{}' in '{}'", cell, definition.name);
+    Ok(if is_real(&definition.kind) {
+        let values = cells
+            .iter()
+            .map(|cell| cell.parse::<f64>().map_err(|_| invalid(cell)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Series::new(name, values)
+    } else if is_integer(&definition.kind) {
+        let values = cells
+            .iter()
+            .map(|cell| cell.parse::<i64>().map_err(|_| invalid(cell)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Series::new(name, values)
+    } else {
+        Series::new(name, cells)
+    })
+}
+
+/// Parses a namelist like `&column name=s, units=m, type=double, &end` into its group and
+/// fields. Returns `None` for other lines.
+fn namelist(line: &str) -> Option<(String, HashMap<String, String>)> {
+    let body = line.strip_prefix('&')?.trim_end().strip_suffix("&end")?;
+    let (group, body) = body.split_once(char::is_whitespace).unwrap_or((body, ""));
+
+    let mut fields = HashMap::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    for c in body.chars().chain(std::iter::once(',')) {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                if let Some((key, value)) = field.split_once('=') {
+                    fields.insert(key.trim().to_owned(), value.trim().to_owned());
+                }
+                field.clear();
+            }
+            c => field.push(c),
+        }
+    }
+    Some((group.to_owned(), fields))
+}
+
+/// Splits a data row into its fields, strings may be quoted.
+fn tokens(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut token = String::new();
+        if c == '"' {
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => token.extend(chars.next()),
+                    '"' => break,
+                    c => token.push(c),
+                }
+            }
+        } else {
+            token.push(c);
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                token.push(c);
+            }
+        }
+        tokens.push(token);
+    }
+    tokens
+}
+
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(value) => value.replace("\\\"", "\""),
+        None => value.to_owned(),
+    }
+}
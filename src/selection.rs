@@ -0,0 +1,274 @@
+//! Sub-frames selected by rows and columns, like indexing in pandas.
+//!
+//! [`TfsDataFrame::get`] takes the rows as a range, a list of rows or a boolean mask and the
+//! columns as a name or a list of names, `..` selects everything:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let mask = vec![true, false, true, false, true];
+//! let odd = df.get(&mask, &["NAME", "BETX"]).unwrap();
+//! assert_eq!((odd.len(), odd.column_count()), (3, 2));
+//!
+//! let last = df.get(vec![4, 3], ..).unwrap();
+//! assert_eq!(last.column("NAME").unwrap().str().unwrap().get(0), Some("BPMYB.5L2.B1"));
+//! assert_eq!(df.get(1..=2, "S").unwrap().len(), 2);
+//! ```
+//!
+//! `std::ops::Index` can only return references, so `df["BETX"]` gives a column but sub-frames
+//! come from [`TfsDataFrame::get`].
+use polars::prelude::{BooleanChunked, IdxCa, IdxSize, NewChunkedArray, NumericNative};
+use polars::series::Series;
+use std::ops::{self, Bound, RangeBounds};
+
+use crate::tfsdataframe::TfsDataFrame;
+
+/// The rows selected by [`TfsDataFrame::get`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowSelection {
+    /// A range of rows, shared with the frame instead of copied.
+    Range(Bound<usize>, Bound<usize>),
+    /// The rows at these positions, in this order, repetitions allowed.
+    Rows(Vec<usize>),
+    /// The rows where the mask is `true`, it needs one value per row.
+    Mask(Vec<bool>),
+}
+
+macro_rules! range_selection {
+    ($($range:ty),*) => {
+        $(
+            impl From<$range> for RowSelection {
+                fn from(range: $range) -> Self {
+                    RowSelection::Range(range.start_bound().cloned(), range.end_bound().cloned())
+                }
+            }
+        )*
+    };
+}
+
+range_selection!(
+    ops::Range<usize>,
+    ops::RangeInclusive<usize>,
+    ops::RangeFrom<usize>,
+    ops::RangeTo<usize>,
+    ops::RangeToInclusive<usize>,
+    ops::RangeFull
+);
+
+impl From<usize> for RowSelection {
+    fn from(row: usize) -> Self {
+        RowSelection::Rows(vec![row])
+    }
+}
+
+impl From<Vec<usize>> for RowSelection {
+    fn from(rows: Vec<usize>) -> Self {
+        RowSelection::Rows(rows)
+    }
+}
+
+impl From<&[usize]> for RowSelection {
+    fn from(rows: &[usize]) -> Self {
+        RowSelection::Rows(rows.to_vec())
+    }
+}
+
+impl From<&Vec<usize>> for RowSelection {
+    fn from(rows: &Vec<usize>) -> Self {
+        RowSelection::Rows(rows.clone())
+    }
+}
+
+impl From<Vec<bool>> for RowSelection {
+    fn from(mask: Vec<bool>) -> Self {
+        RowSelection::Mask(mask)
+    }
+}
+
+impl From<&[bool]> for RowSelection {
+    fn from(mask: &[bool]) -> Self {
+        RowSelection::Mask(mask.to_vec())
+    }
+}
+
+impl From<&Vec<bool>> for RowSelection {
+    fn from(mask: &Vec<bool>) -> Self {
+        RowSelection::Mask(mask.clone())
+    }
+}
+
+impl<const N: usize> From<&[bool; N]> for RowSelection {
+    fn from(mask: &[bool; N]) -> Self {
+        RowSelection::Mask(mask.to_vec())
+    }
+}
+
+/// The columns selected by [`TfsDataFrame::get`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnSelection {
+    All,
+    /// The columns with these names, in this order.
+    Names(Vec<String>),
+}
+
+impl From<ops::RangeFull> for ColumnSelection {
+    fn from(_: ops::RangeFull) -> Self {
+        ColumnSelection::All
+    }
+}
+
+impl From<&str> for ColumnSelection {
+    fn from(name: &str) -> Self {
+        ColumnSelection::Names(vec![name.to_owned()])
+    }
+}
+
+impl From<&[&str]> for ColumnSelection {
+    fn from(names: &[&str]) -> Self {
+        ColumnSelection::Names(names.iter().map(|n| n.to_string()).collect())
+    }
+}
+
+impl<const N: usize> From<&[&str; N]> for ColumnSelection {
+    fn from(names: &[&str; N]) -> Self {
+        ColumnSelection::from(&names[..])
+    }
+}
+
+impl<const N: usize> From<[&str; N]> for ColumnSelection {
+    fn from(names: [&str; N]) -> Self {
+        ColumnSelection::from(&names[..])
+    }
+}
+
+impl From<Vec<&str>> for ColumnSelection {
+    fn from(names: Vec<&str>) -> Self {
+        ColumnSelection::from(&names[..])
+    }
+}
+
+impl From<Vec<String>> for ColumnSelection {
+    fn from(names: Vec<String>) -> Self {
+        ColumnSelection::Names(names)
+    }
+}
+
+impl RowSelection {
+    /// Whether each of `len` rows is selected. Fails if a selected row is out of the rows.
+    pub(crate) fn to_mask(&self, len: usize) -> anyhow::Result<Vec<bool>> {
+        match self {
+            RowSelection::Range(start, end) => {
+                let range = resolve_range(*start, *end, len)?;
+                anyhow::ensure!(
+                    range.end <= len,
+                    "rows {}..{} are out of the {} rows of the frame",
+                    range.start,
+                    range.end,
+                    len
+                );
+                Ok((0..len).map(|row| range.contains(&row)).collect())
+            }
+            RowSelection::Rows(rows) => {
+                check_rows(rows, len)?;
+                let mut mask = vec![false; len];
+                for row in rows {
+                    mask[*row] = true;
+                }
+                Ok(mask)
+            }
+            RowSelection::Mask(mask) => {
+                check_mask(mask, len)?;
+                Ok(mask.clone())
+            }
+        }
+    }
+}
+
+/// The rows of the bounds, failing for a bound past the last row `usize::MAX`.
+fn resolve_range(
+    start: Bound<usize>,
+    end: Bound<usize>,
+    len: usize,
+) -> anyhow::Result<ops::Range<usize>> {
+    let after = |row: usize| {
+        row.checked_add(1)
+            .ok_or_else(|| anyhow::anyhow!("the row after {} is out of range", row))
+    };
+    let start = match start {
+        Bound::Included(start) => start,
+        Bound::Excluded(start) => after(start)?,
+        Bound::Unbounded => 0,
+    };
+    let end = match end {
+        Bound::Included(end) => after(end)?,
+        Bound::Excluded(end) => end,
+        Bound::Unbounded => len,
+    };
+    Ok(start..end)
+}
+
+fn check_rows(rows: &[usize], len: usize) -> anyhow::Result<()> {
+    match rows.iter().find(|row| **row >= len) {
+        Some(row) => anyhow::bail!("row {} is out of the {} rows of the frame", row, len),
+        None => Ok(()),
+    }
+}
+
+fn check_mask(mask: &[bool], len: usize) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        mask.len() == len,
+        "the mask has {} values for {} rows",
+        mask.len(),
+        len
+    );
+    Ok(())
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// The sub-frame of the rows `rows` and the columns `cols`, with the header of this frame,
+    /// see the [module documentation](self). Ranges share the values with this frame like
+    /// [`TfsDataFrame::view`], lists of rows and masks copy them.
+    pub fn get<R, C>(&self, rows: R, cols: C) -> anyhow::Result<TfsDataFrame<T>>
+    where
+        R: Into<RowSelection>,
+        C: Into<ColumnSelection>,
+    {
+        let names: Vec<String> = match cols.into() {
+            ColumnSelection::All => self.column_names().into_iter().map(String::from).collect(),
+            ColumnSelection::Names(names) => names,
+        };
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let len = self.len();
+
+        match rows.into() {
+            RowSelection::Range(start, end) => self.view(resolve_range(start, end, len)?, &names),
+            RowSelection::Rows(rows) => {
+                check_rows(&rows, len)?;
+                let rows: Vec<IdxSize> = rows.into_iter().map(|row| row as IdxSize).collect();
+                let mut frame = self.view(0..len, &names)?;
+                frame.df = frame.df.take(&IdxCa::from_vec("rows".into(), rows))?;
+                Ok(frame)
+            }
+            RowSelection::Mask(mask) => {
+                check_mask(&mask, len)?;
+                let mut frame = self.view(0..len, &names)?;
+                frame.df = frame
+                    .df
+                    .filter(&BooleanChunked::from_slice("mask".into(), &mask))?;
+                Ok(frame)
+            }
+        }
+    }
+}
+
+/// The column `name`, panics if there is none, see [`TfsDataFrame::column`].
+impl<T: std::str::FromStr + NumericNative> ops::Index<&str> for TfsDataFrame<T> {
+    type Output = Series;
+
+    fn index(&self, name: &str) -> &Series {
+        match self.column(name) {
+            Ok(column) => column,
+            Err(err) => panic!("{}", err),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Neighbours of elements along the beam line.
+//!
+//! An [`ElementSequence`] orders the elements of a frame by `S`, keeping the order of the rows
+//! for elements at the same position, and answers questions like "the nearest quadrupole
+//! upstream of this BPM". Elements are selected by class, their `KEYWORD` ignoring case, or
+//! [`ALL_CLASSES`] for any element:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let quad = df.previous_element("BPMYB.5L2.B1", "quadrupole").unwrap().unwrap();
+//! assert_eq!(quad.name, "MQY.A5L2.B1");
+//!
+//! let drifts = df.elements_between("BPM1", "BPMYB.5L2.B1", "DRIFT").unwrap();
+//! assert_eq!(drifts.len(), 2);
+//! ```
+//!
+//! The methods of the frame build the sequence for every query, for many queries build it
+//! once with [`TfsDataFrame::sequence`]. A sequence of a ring made [`ElementSequence::cyclic`]
+//! continues past its end at its start.
+use polars::prelude::NumericNative;
+use std::collections::HashMap;
+
+pub use crate::errors::ALL_CLASSES;
+use crate::errors::KEYWORD_COLUMN;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// An element of an [`ElementSequence`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Element {
+    /// The row of the element in the frame.
+    pub row: usize,
+    pub name: String,
+    /// The class of the element, empty if the frame has no `KEYWORD` column.
+    pub keyword: String,
+    pub s: f64,
+}
+
+impl Element {
+    fn is_a(&self, class: &str) -> bool {
+        class == ALL_CLASSES || self.keyword.eq_ignore_ascii_case(class)
+    }
+}
+
+/// The elements of a frame in the order of `S`, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct ElementSequence {
+    elements: Vec<Element>,
+    /// The position of the first element of every name.
+    positions: HashMap<String, usize>,
+    cyclic: bool,
+}
+
+impl ElementSequence {
+    /// Makes queries continue past the end of the sequence at its start and the other way
+    /// round, for rings.
+    pub fn cyclic(mut self, cyclic: bool) -> Self {
+        self.cyclic = cyclic;
+        self
+    }
+
+    pub fn elements(&self) -> &[Element] {
+        &self.elements
+    }
+
+    fn position(&self, name: &str) -> anyhow::Result<usize> {
+        self.positions
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("there is no element '{}'", name))
+    }
+
+    /// The positions after `position`, up to the end or, in a cyclic sequence, up to the one
+    /// before `position`.
+    fn downstream(&self, position: usize) -> impl Iterator<Item = usize> {
+        let len = self.elements.len();
+        let wrapped = if self.cyclic { position } else { 0 };
+        (position + 1..len).chain(0..wrapped)
+    }
+
+    /// The first element of `class` downstream of the element `name`.
+    pub fn next(&self, name: &str, class: &str) -> anyhow::Result<Option<&Element>> {
+        let position = self.position(name)?;
+        Ok(self
+            .downstream(position)
+            .map(|p| &self.elements[p])
+            .find(|e| e.is_a(class)))
+    }
+
+    /// The first element of `class` upstream of the element `name`.
+    pub fn previous(&self, name: &str, class: &str) -> anyhow::Result<Option<&Element>> {
+        let position = self.position(name)?;
+        let len = self.elements.len();
+        let wrapped = if self.cyclic { position + 1 } else { len };
+        Ok((0..position)
+            .rev()
+            .chain((wrapped..len).rev())
+            .map(|p| &self.elements[p])
+            .find(|e| e.is_a(class)))
+    }
+
+    /// The elements of `class` strictly between the elements `from` and `to`, going downstream
+    /// from `from`. Fails if `to` is upstream of `from`, unless the sequence is cyclic.
+    pub fn between(&self, from: &str, to: &str, class: &str) -> anyhow::Result<Vec<&Element>> {
+        let (start, end) = (self.position(from)?, self.position(to)?);
+        anyhow::ensure!(
+            start <= end || self.cyclic,
+            "'{}' is upstream of '{}'",
+            to,
+            from
+        );
+        Ok(self
+            .downstream(start)
+            .take_while(|p| *p != end)
+            .map(|p| &self.elements[p])
+            .filter(|e| e.is_a(class))
+            .collect())
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// The elements of the frame in the order of `S`, from the columns `NAME`, `S` and, if
+    /// present, `KEYWORD`. Rows with a missing name or position are left out.
+    pub fn sequence(&self) -> anyhow::Result<ElementSequence> {
+        let names = Option::<String>::from_column(self.column("NAME")?)?;
+        let positions = Option::<f64>::from_column(self.column("S")?)?;
+        let keywords = match self.column(KEYWORD_COLUMN) {
+            Ok(column) => Option::<String>::from_column(column)?,
+            Err(_) => vec![None; self.len()],
+        };
+
+        let mut elements: Vec<Element> = names
+            .into_iter()
+            .zip(positions)
+            .zip(keywords)
+            .enumerate()
+            .filter_map(|(row, ((name, s), keyword))| {
+                Some(Element {
+                    row,
+                    name: name?,
+                    keyword: keyword.unwrap_or_default(),
+                    s: s?,
+                })
+            })
+            .collect();
+        elements.sort_by(|a, b| a.s.total_cmp(&b.s));
+
+        let mut positions = HashMap::new();
+        for (position, element) in elements.iter().enumerate() {
+            positions.entry(element.name.clone()).or_insert(position);
+        }
+        Ok(ElementSequence {
+            elements,
+            positions,
+            cyclic: false,
+        })
+    }
+
+    /// The first element of `class` downstream of the element `name`, see
+    /// [`ElementSequence::next`].
+    pub fn next_element(&self, name: &str, class: &str) -> anyhow::Result<Option<Element>> {
+        Ok(self.sequence()?.next(name, class)?.cloned())
+    }
+
+    /// The first element of `class` upstream of the element `name`, see
+    /// [`ElementSequence::previous`].
+    pub fn previous_element(&self, name: &str, class: &str) -> anyhow::Result<Option<Element>> {
+        Ok(self.sequence()?.previous(name, class)?.cloned())
+    }
+
+    /// The elements of `class` strictly between the elements `from` and `to`, see
+    /// [`ElementSequence::between`].
+    pub fn elements_between(
+        &self,
+        from: &str,
+        to: &str,
+        class: &str,
+    ) -> anyhow::Result<Vec<Element>> {
+        let sequence = self.sequence()?;
+        let between = sequence.between(from, to, class)?;
+        Ok(between.into_iter().cloned().collect())
+    }
+}
@@ -0,0 +1,179 @@
+//! Smoothing of noisy columns, e.g. measured beta functions before comparing them with the
+//! model.
+//!
+//! [`TfsDataFrame::smooth`] filters a numeric column with a [`Kernel`] over neighbouring rows and
+//! returns the result as a new column, like the [running operations](crate::cumulative):
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::smoothing::Kernel;
+//! # use tfs::polars::prelude::*;
+//! let df = TfsDataFrame::<f64>::new(
+//!     vec![],
+//!     df!("BETX" => [10.0, 13.0, 10.0, 13.0, 10.0]).unwrap(),
+//! );
+//! let smooth = df.smooth("BETX", Kernel::MovingAverage(3)).unwrap();
+//! assert_eq!(smooth.name().as_str(), "SMOOTH(BETX)");
+//! assert_eq!(smooth.f64().unwrap().get(1), Some(11.0));
+//!
+//! let fitted = df.smooth("BETX", Kernel::SavitzkyGolay { window: 5, order: 0 }).unwrap();
+//! assert!((fitted.f64().unwrap().get(0).unwrap() - 11.2).abs() < 1e-12);
+//! ```
+//!
+//! The windows count rows, not distances along `S`. Windows are cut at the ends of the frame,
+//! Savitzky–Golay windows are shifted inside instead so that the polynomial still fits a whole
+//! window. Missing values stay missing and are left out of the windows of other rows.
+use polars::prelude::NumericNative;
+use polars::series::Series;
+
+use crate::cumulative::numbers;
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// The filter applied by [`TfsDataFrame::smooth`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kernel {
+    /// The mean over an odd number of rows centred on each row.
+    MovingAverage(usize),
+    /// The mean weighted by a Gaussian with this standard deviation in rows, cut at four
+    /// standard deviations.
+    Gaussian(f64),
+    /// The value at each row of the polynomial of `order` fitted by least squares to an odd
+    /// number of rows around it. Keeps peaks better than the means.
+    SavitzkyGolay { window: usize, order: usize },
+}
+
+impl Kernel {
+    fn check(&self) -> anyhow::Result<()> {
+        match *self {
+            Kernel::MovingAverage(window) => check_window(window),
+            Kernel::Gaussian(sigma) => {
+                anyhow::ensure!(
+                    sigma > 0.0 && sigma.is_finite(),
+                    "the standard deviation {} of a Gaussian kernel is not positive",
+                    sigma
+                );
+                Ok(())
+            }
+            Kernel::SavitzkyGolay { window, order } => {
+                check_window(window)?;
+                anyhow::ensure!(
+                    order < window,
+                    "a polynomial of order {} can't be fitted to {} rows",
+                    order,
+                    window
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+fn check_window(window: usize) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        window % 2 == 1,
+        "the window of {} rows has no centre, it needs an odd number of rows",
+        window
+    );
+    Ok(())
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// The numeric column `name` filtered with `kernel`, named `SMOOTH(name)`, see the
+    /// [module documentation](self).
+    pub fn smooth(&self, name: &str, kernel: Kernel) -> anyhow::Result<Series> {
+        kernel.check()?;
+        let values = numbers(self, name)?;
+        let n = values.len();
+        let smooth: Vec<Option<f64>> = (0..n)
+            .map(|i| {
+                values[i]?;
+                Some(match kernel {
+                    Kernel::MovingAverage(window) => weighted_mean(&values, i, window / 2, |_| 1.0),
+                    Kernel::Gaussian(sigma) => {
+                        let half = (4.0 * sigma).ceil() as usize;
+                        weighted_mean(&values, i, half, |d| (-0.5 * (d / sigma).powi(2)).exp())
+                    }
+                    Kernel::SavitzkyGolay { window, order } => {
+                        let start = i.saturating_sub(window / 2).min(n.saturating_sub(window));
+                        let rows = start..(start + window).min(n);
+                        polynomial_at(&values, rows, i, order, (window / 2).max(1) as f64)
+                    }
+                })
+            })
+            .collect();
+        Ok(Option::<f64>::to_column(
+            &format!("SMOOTH({})", name),
+            smooth,
+        ))
+    }
+}
+
+/// The mean of the present values within `half` rows of `row`, weighted by their distance.
+fn weighted_mean<W>(values: &[Option<f64>], row: usize, half: usize, weight: W) -> f64
+where
+    W: Fn(f64) -> f64,
+{
+    let rows = row.saturating_sub(half)..(row + half + 1).min(values.len());
+    let (sum, weights) = rows
+        .filter_map(|j| values[j].map(|v| (v, weight(j.abs_diff(row) as f64))))
+        .fold((0.0, 0.0), |(sum, weights), (v, w)| {
+            (sum + w * v, weights + w)
+        });
+    sum / weights
+}
+
+/// The value at `row` of the polynomial of `order` fitted to the present values in `rows`,
+/// lowered to the number of present values. Distances are divided by `scale` to keep the fit
+/// well conditioned.
+fn polynomial_at(
+    values: &[Option<f64>],
+    rows: std::ops::Range<usize>,
+    row: usize,
+    order: usize,
+    scale: f64,
+) -> f64 {
+    let points: Vec<(f64, f64)> = rows
+        .filter_map(|j| values[j].map(|v| ((j as f64 - row as f64) / scale, v)))
+        .collect();
+    let n = (order + 1).min(points.len());
+
+    // Normal equations of the least squares fit, the constant term is the value at `row`.
+    let mut a = vec![vec![0.0; n + 1]; n];
+    for (t, v) in &points {
+        let powers: Vec<f64> = (0..n).map(|k| t.powi(k as i32)).collect();
+        for (r, equation) in a.iter_mut().enumerate() {
+            for (entry, power) in equation.iter_mut().zip(&powers) {
+                *entry += powers[r] * power;
+            }
+            equation[n] += powers[r] * v;
+        }
+    }
+    solve(a)[0]
+}
+
+/// Solves the linear system given by the rows of the augmented matrix `a` by Gaussian
+/// elimination with partial pivoting.
+fn solve(mut a: Vec<Vec<f64>>) -> Vec<f64> {
+    let n = a.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|r, s| a[*r][col].abs().total_cmp(&a[*s][col].abs()))
+            .unwrap_or(col);
+        a.swap(col, pivot);
+        let (above, below) = a.split_at_mut(col + 1);
+        let pivot_row = &above[col];
+        for equation in below {
+            let factor = equation[col] / pivot_row[col];
+            for (entry, p) in equation[col..].iter_mut().zip(&pivot_row[col..]) {
+                *entry -= factor * p;
+            }
+        }
+    }
+    let mut x = vec![0.0; n];
+    for r in (0..n).rev() {
+        let known: f64 = (r + 1..n).map(|c| a[r][c] * x[c]).sum();
+        x[r] = (a[r][n] - known) / a[r][r];
+    }
+    x
+}
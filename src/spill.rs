@@ -0,0 +1,243 @@
+//! Frames larger than the memory, kept in temporary files.
+//!
+//! A [`SpilledFrame`] splits the rows of a frame into chunks and writes each chunk to an Arrow
+//! IPC file in a temporary directory (in `TMPDIR`, so that the disk can be chosen). Only the
+//! header stays in memory. Columns and chunks are read back when they are asked for, a single
+//! column reads only its own part of every file:
+//!
+//! ```
+//! # use tfs::spill::SpilledFrame;
+//! let spilled = SpilledFrame::<f64>::open("test/test.tfs", 2).unwrap();
+//! assert_eq!((spilled.len(), spilled.n_chunks()), (5, 3));
+//!
+//! let s = spilled.column("S").unwrap();
+//! assert_eq!(s.len(), 5);
+//!
+//! for chunk in spilled.chunks() {
+//!     assert!(chunk.unwrap().len() <= 2);
+//! }
+//! ```
+//!
+//! [`SpilledFrame::open`] reads the file chunk by chunk, so the whole file is never in memory.
+//! The temporary files are deleted when the frame is dropped.
+use polars::prelude::{CompatLevel, DataFrame, NumericNative, PolarsError, SchemaRef};
+use polars::series::Series;
+use polars_arrow::io::ipc::read::{read_file_metadata, FileReader};
+use polars_arrow::io::ipc::write::{FileWriter, WriteOptions};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::dataframe::DataValue;
+use crate::options::TfsReadOptions;
+use crate::stages::TfsHeaderParser;
+use crate::tfsdataframe::{Properties, TfsDataFrame, NROWS_KEY};
+
+/// Counter making the names of the temporary directories unique within the process.
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary directory, deleted with its content when dropped.
+struct SpillDir(PathBuf);
+
+impl SpillDir {
+    fn create() -> std::io::Result<SpillDir> {
+        loop {
+            let name = format!(
+                "tfs-spill-{}-{}",
+                std::process::id(),
+                NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+            );
+            let path = std::env::temp_dir().join(name);
+            match std::fs::create_dir(&path) {
+                Ok(()) => return Ok(SpillDir(path)),
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl Drop for SpillDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A chunk of rows in an Arrow IPC file.
+struct Chunk {
+    path: PathBuf,
+    rows: usize,
+}
+
+/// A frame whose rows are kept in temporary files, see the [module documentation](self).
+pub struct SpilledFrame<T> {
+    pub properties: Properties<T>,
+    schema: SchemaRef,
+    chunks: Vec<Chunk>,
+    dir: SpillDir,
+}
+
+impl<T: std::str::FromStr + NumericNative> SpilledFrame<T> {
+    /// Reads the tfs file at `path` in chunks of `chunk_rows` rows, writing each chunk to a
+    /// temporary file before the next one is read.
+    pub fn open<P: AsRef<Path>>(path: P, chunk_rows: usize) -> anyhow::Result<SpilledFrame<T>>
+    where
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        SpilledFrame::open_with(path, chunk_rows, &TfsReadOptions::default())
+    }
+
+    /// Like [`SpilledFrame::open`], parsing the file according to `options`. A
+    /// [`TfsReadOptions::max_memory`] limit applies to every chunk.
+    pub fn open_with<P: AsRef<Path>>(
+        path: P,
+        chunk_rows: usize,
+        options: &TfsReadOptions,
+    ) -> anyhow::Result<SpilledFrame<T>>
+    where
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        anyhow::ensure!(chunk_rows > 0, "chunks need at least one row");
+        let reader = BufReader::new(File::open(path.as_ref())?);
+        let mut body = TfsHeaderParser::with_options(reader, options.clone()).parse::<T>()?;
+
+        let mut spilled = SpilledFrame {
+            properties: body.header().properties.clone(),
+            schema: body.take_rows()?.schema().clone(),
+            chunks: Vec::new(),
+            dir: SpillDir::create()?,
+        };
+        while body.read_rows(chunk_rows)? > 0 {
+            spilled.push(body.take_rows()?)?;
+        }
+
+        if let Some(DataValue::Integer(nrows)) = spilled.properties.get(NROWS_KEY) {
+            anyhow::ensure!(
+                usize::try_from(*nrows).ok() == Some(spilled.len()),
+                "the header declares {} rows but {} were read, the file is probably truncated",
+                nrows,
+                spilled.len()
+            );
+        }
+        Ok(spilled)
+    }
+
+    /// Moves the rows of `df` to temporary files, in chunks of `chunk_rows` rows.
+    pub fn from_frame(df: TfsDataFrame<T>, chunk_rows: usize) -> anyhow::Result<SpilledFrame<T>> {
+        anyhow::ensure!(chunk_rows > 0, "chunks need at least one row");
+        let full = df.df()?.into_owned();
+        let mut spilled = SpilledFrame {
+            schema: full.schema().clone(),
+            properties: df.properties,
+            chunks: Vec::new(),
+            dir: SpillDir::create()?,
+        };
+        let mut offset = 0;
+        while offset < full.height() {
+            spilled.push(full.slice(offset as i64, chunk_rows))?;
+            offset += chunk_rows;
+        }
+        Ok(spilled)
+    }
+
+    /// Writes `chunk` to the next temporary file.
+    fn push(&mut self, chunk: DataFrame) -> anyhow::Result<()> {
+        let path = self.dir.0.join(format!("{}.arrow", self.chunks.len()));
+        let rows = chunk.height();
+        let batch = chunk.rechunk_to_record_batch(CompatLevel::newest());
+
+        let file = BufWriter::new(File::create(&path)?);
+        let options = WriteOptions { compression: None };
+        let mut writer =
+            FileWriter::try_new(file, Arc::new(batch.schema().clone()), None, options)?;
+        writer.write(&batch, None)?;
+        writer.finish()?;
+
+        self.chunks.push(Chunk { path, rows });
+        Ok(())
+    }
+
+    /// Reads the chunk `index`, only the columns at the positions `projection` if given.
+    fn read(&self, index: usize, projection: Option<Vec<usize>>) -> anyhow::Result<DataFrame> {
+        let chunk = self
+            .chunks
+            .get(index)
+            .ok_or_else(|| anyhow::anyhow!("there are only {} chunks", self.chunks.len()))?;
+        let mut file = BufReader::new(File::open(&chunk.path)?);
+        let metadata = read_file_metadata(&mut file)?;
+        let batch = FileReader::new(file, metadata, projection, None)
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("{} is empty", chunk.path.display()))??;
+        Ok(DataFrame::from(batch))
+    }
+
+    /// Number of rows.
+    pub fn len(&self) -> usize {
+        self.chunks.iter().map(|c| c.rows).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn n_chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Names of the columns in order.
+    pub fn column_names(&self) -> Vec<&str> {
+        self.schema.iter_names().map(|name| name.as_str()).collect()
+    }
+
+    /// Reads the column `name` from all chunks.
+    pub fn column(&self, name: &str) -> anyhow::Result<Series> {
+        let (position, _, dtype) = self
+            .schema
+            .get_full(name)
+            .ok_or_else(|| PolarsError::ColumnNotFound(name.to_owned().into()))?;
+        let mut series = Series::new_empty(name.into(), dtype);
+        for index in 0..self.chunks.len() {
+            let chunk = self.read(index, Some(vec![position]))?;
+            series.append(chunk.column(name)?.as_materialized_series())?;
+        }
+        Ok(series)
+    }
+
+    /// Reads the chunk `index` with all columns and the header.
+    pub fn chunk(&self, index: usize) -> anyhow::Result<TfsDataFrame<T>> {
+        Ok(TfsDataFrame::new(
+            self.properties.clone(),
+            self.read(index, None)?,
+        ))
+    }
+
+    /// Reads the chunks one after the other, e.g. to process a frame that doesn't fit into
+    /// memory.
+    pub fn chunks(&self) -> impl Iterator<Item = anyhow::Result<TfsDataFrame<T>>> + '_ {
+        (0..self.chunks.len()).map(|index| self.chunk(index))
+    }
+
+    /// Reads the columns `names` of all rows into memory.
+    pub fn select(&self, names: &[&str]) -> anyhow::Result<TfsDataFrame<T>> {
+        let columns = names
+            .iter()
+            .map(|name| Ok(self.column(name)?.into()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(TfsDataFrame::new(
+            self.properties.clone(),
+            DataFrame::new(columns)?,
+        ))
+    }
+
+    /// Reads all rows into memory.
+    pub fn into_frame(self) -> anyhow::Result<TfsDataFrame<T>> {
+        let mut df = DataFrame::empty_with_schema(&self.schema);
+        for index in 0..self.chunks.len() {
+            df.vstack_mut(&self.read(index, None)?)?;
+        }
+        df.align_chunks();
+        Ok(TfsDataFrame::new(self.properties, df))
+    }
+}
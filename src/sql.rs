@@ -0,0 +1,133 @@
+//! SQL queries over tfs files, with the SQL engine of polars (feature `sql`).
+//!
+//! Frames, files and whole directories are registered as tables of a [`TfsSqlContext`]. Files
+//! can also be named directly in the query, as a table named by a quoted path:
+//!
+//! ```
+//! # use tfs::sql::TfsSqlContext;
+//! let mut context = TfsSqlContext::new();
+//! let bpms = context
+//!     .query("SELECT NAME, BETX FROM 'test/test.tfs' WHERE NAME LIKE 'BPM%'")
+//!     .unwrap();
+//!
+//! assert_eq!(bpms.column_names(), vec!["NAME", "BETX"]);
+//! assert_eq!(bpms.len(), 2);
+//! ```
+use polars::prelude::{IntoLazy, NamedFrom};
+use polars::series::Series;
+use polars::sql::SQLContext;
+use sqlparser::ast::visit_relations_mut;
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+use std::ops::ControlFlow;
+use std::path::Path;
+
+use crate::catalog::collect_tfs_files;
+use crate::join::stack_diagonal;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Name of the column holding the file of each row in directory tables.
+pub const FILE_COLUMN: &str = "FILE";
+
+/// Tables to run SQL queries on.
+#[derive(Default)]
+pub struct TfsSqlContext {
+    context: SQLContext,
+}
+
+impl TfsSqlContext {
+    pub fn new() -> TfsSqlContext {
+        TfsSqlContext::default()
+    }
+
+    /// Names of the registered tables, sorted.
+    pub fn tables(&self) -> Vec<String> {
+        self.context.get_tables()
+    }
+
+    /// Registers the columns of `df` as table `name`, replacing a previous table of that name.
+    pub fn register(&mut self, name: &str, df: &TfsDataFrame<f64>) -> anyhow::Result<()> {
+        self.context.register(name, df.df()?.into_owned().lazy());
+        Ok(())
+    }
+
+    /// Registers the tfs file at `path` as a table named after the file stem, returns the name.
+    pub fn register_file<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<String> {
+        let path = path.as_ref();
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| anyhow::anyhow!("invalid file name {}", path.display()))?
+            .to_owned();
+        self.register(&name, &open(path)?)?;
+        Ok(name)
+    }
+
+    /// Registers all tfs files below `dir` as one table `name`. The columns of all files are
+    /// combined, columns missing in some files are null there. The [`FILE_COLUMN`] holds the
+    /// path of the file of each row, relative to `dir`.
+    pub fn register_dir<P: AsRef<Path>>(&mut self, name: &str, dir: P) -> anyhow::Result<()> {
+        let dir = dir.as_ref();
+        let mut files = Vec::new();
+        collect_tfs_files(dir, &mut files)?;
+        files.sort();
+
+        let mut frames = Vec::with_capacity(files.len());
+        for file in files {
+            let mut df = open(&file)?.df()?.into_owned();
+            let relative = file
+                .strip_prefix(dir)
+                .unwrap_or(&file)
+                .display()
+                .to_string();
+            df.with_column(Series::new(FILE_COLUMN.into(), vec![relative; df.height()]))?;
+            frames.push(df);
+        }
+
+        self.context.register(name, stack_diagonal(frames)?.lazy());
+        Ok(())
+    }
+
+    /// Runs `query` and returns the result as a frame without header. Tables named by a quoted
+    /// path, `'path'`, are registered under their path (directories like in
+    /// [`TfsSqlContext::register_dir`]) before running the query.
+    pub fn query(&mut self, query: &str) -> anyhow::Result<TfsDataFrame<f64>> {
+        let query = self.register_quoted_paths(query)?;
+        let df = self.context.execute(&query)?.collect()?;
+        Ok(TfsDataFrame::new(vec![], df))
+    }
+
+    /// Registers the tables of `query` named by a quoted path that exists and returns the query
+    /// with the quotes of those names replaced by the quotes of an identifier, `"path"`.
+    fn register_quoted_paths(&mut self, query: &str) -> anyhow::Result<String> {
+        let mut statements = Parser::parse_sql(&GenericDialect, query)?;
+        let mut paths = Vec::new();
+        let _ = visit_relations_mut(&mut statements, |name| {
+            if let [ident] = name.0.as_mut_slice() {
+                if ident.quote_style == Some('\'') && Path::new(&ident.value).exists() {
+                    ident.quote_style = Some('"');
+                    paths.push(ident.value.clone());
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+
+        for quoted in paths {
+            let path = Path::new(&quoted);
+            if path.is_dir() {
+                self.register_dir(&quoted, path)?;
+            } else {
+                self.register(&quoted, &open(path)?)?;
+            }
+        }
+        Ok(statements
+            .iter()
+            .map(|statement| statement.to_string())
+            .collect::<Vec<_>>()
+            .join("; "))
+    }
+}
+
+fn open(path: &Path) -> anyhow::Result<TfsDataFrame<f64>> {
+    TfsDataFrame::open(path).map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))
+}
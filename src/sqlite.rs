@@ -0,0 +1,413 @@
+//! SQLite databases as a queryable archive of tfs files (feature `sqlite`).
+//!
+//! [`TfsDataFrame::to_sqlite`] writes the columns as a table of the database and the header to
+//! the companion table [`HEADER_TABLE`], with one row per entry and the name of the table, so
+//! that one database can hold many frames. [`TfsDataFrame::open_sqlite`] reads them back:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let dir = tempfile::tempdir().unwrap();
+//! let path = dir.path().join("campaign.sqlite");
+//! df.to_sqlite(&path, "twiss_b1").unwrap();
+//!
+//! let back = TfsDataFrame::<f64>::open_sqlite(&path, "twiss_b1").unwrap();
+//! assert!(back.diff(&df).unwrap().is_empty());
+//! assert_eq!(back.properties, df.properties);
+//! ```
+//!
+//! Real columns are stored as `REAL`, integer columns as `INTEGER`, boolean columns as
+//! `BOOLEAN` (0 or 1) and everything else as `TEXT`. SQLite has no `NaN`, real values that are
+//! `NaN` are stored as the text `'NaN'`. The type codes of the columns, their `NaN` sentinels and
+//! the dialect of the frame are kept in the table [`METADATA_TABLE`], so that the frame is written
+//! to a tfs file as it was read.
+use polars::prelude::{Column, DataFrame, DataType, NamedFrom, NumericNative, Series};
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, OpenFlags};
+use std::path::Path;
+
+use crate::dataframe::DataValue;
+use crate::dialect::{Dialect, BOOLEAN_CODE, TABLE_CODE};
+use crate::tfsdataframe::{Properties, TfsDataFrame};
+use crate::types::ColumnKind;
+
+/// The table holding the headers of all frames of a database.
+pub const HEADER_TABLE: &str = "tfs_header";
+
+/// The table holding the type codes, `NaN` sentinels and dialects of all frames of a database.
+/// Entries of the whole frame have an empty column name.
+pub const METADATA_TABLE: &str = "tfs_metadata";
+
+/// The text stored for `NaN` reals.
+const NAN: &str = "NaN";
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Writes the frame as the table `table` of the SQLite database at `path`, see the
+    /// [module documentation](self). The database is created if it doesn't exist, a table of
+    /// the same name and its header are replaced.
+    pub fn to_sqlite<P: AsRef<Path>>(&self, path: P, table: &str) -> anyhow::Result<()>
+    where
+        T: std::fmt::Display,
+    {
+        let full_df = self.df()?;
+        anyhow::ensure!(
+            full_df.width() > 0,
+            "can't write a frame without columns as table '{}'",
+            table
+        );
+
+        let mut connection = Connection::open(path)?;
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {HEADER_TABLE} (
+                tbl TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                key TEXT NOT NULL,
+                code TEXT NOT NULL,
+                value,
+                PRIMARY KEY (tbl, key)
+            );
+            CREATE TABLE IF NOT EXISTS {METADATA_TABLE} (
+                tbl TEXT NOT NULL,
+                col TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value,
+                PRIMARY KEY (tbl, col, key)
+            );
+            DROP TABLE IF EXISTS {};",
+            quote(table)
+        ))?;
+        for metadata in [HEADER_TABLE, METADATA_TABLE] {
+            transaction.execute(
+                &format!("DELETE FROM {metadata} WHERE tbl = ?1"),
+                params![table],
+            )?;
+        }
+
+        {
+            let mut insert = transaction.prepare(&format!(
+                "INSERT INTO {HEADER_TABLE} (tbl, position, key, code, value) \
+                 VALUES (?1, ?2, ?3, ?4, ?5)"
+            ))?;
+            for (position, (key, value)) in self.properties.iter().enumerate() {
+                let (code, value) = header_value(value);
+                insert.execute(params![table, position as i64, key, code, value])?;
+            }
+
+            let mut insert = transaction.prepare(&format!(
+                "INSERT INTO {METADATA_TABLE} (tbl, col, key, value) VALUES (?1, ?2, ?3, ?4)"
+            ))?;
+            insert.execute(params![table, "", "dialect", self.dialect.to_string()])?;
+            for (column, (code, kind)) in &self.type_codes {
+                insert.execute(params![table, column, "code", code])?;
+                insert.execute(params![table, column, "kind", kind_name(*kind)])?;
+            }
+            for (column, sentinel) in &self.nan_sentinels {
+                insert.execute(params![table, column, "nan_sentinel", sentinel])?;
+            }
+        }
+
+        let mut definitions = Vec::new();
+        let mut columns = Vec::new();
+        for column in full_df.get_columns() {
+            let kind = ColumnKind::of_dtype(column.dtype());
+            let series = column.as_materialized_series();
+            let values: Vec<Value> = match kind {
+                ColumnKind::Real => series
+                    .cast(&DataType::Float64)?
+                    .f64()?
+                    .iter()
+                    .map(|r| match r {
+                        Some(r) if r.is_nan() => Value::Text(NAN.to_owned()),
+                        r => Value::from(r),
+                    })
+                    .collect(),
+                ColumnKind::Integer => series
+                    .cast(&DataType::Int64)?
+                    .i64()?
+                    .iter()
+                    .map(Value::from)
+                    .collect(),
+                ColumnKind::Boolean => series
+                    .cast(&DataType::Boolean)?
+                    .bool()?
+                    .iter()
+                    .map(Value::from)
+                    .collect(),
+                ColumnKind::Text => series
+                    .cast(&DataType::String)?
+                    .str()?
+                    .iter()
+                    .map(|t| Value::from(t.map(str::to_owned)))
+                    .collect(),
+            };
+            definitions.push(format!("{} {}", quote(column.name()), sql_type(kind)));
+            columns.push(values);
+        }
+        transaction.execute_batch(&format!(
+            "CREATE TABLE {} ({});",
+            quote(table),
+            definitions.join(", ")
+        ))?;
+
+        {
+            let placeholders = vec!["?"; columns.len()].join(", ");
+            let mut insert = transaction.prepare(&format!(
+                "INSERT INTO {} VALUES ({})",
+                quote(table),
+                placeholders
+            ))?;
+            for row in 0..self.len() {
+                insert.execute(rusqlite::params_from_iter(
+                    columns.iter().map(|values| &values[row]),
+                ))?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Reads the table `table` of the SQLite database at `path` with its header, see the
+    /// [module documentation](self). Tables not written by [`TfsDataFrame::to_sqlite`] are read
+    /// without header.
+    pub fn open_sqlite<P: AsRef<Path>>(path: P, table: &str) -> anyhow::Result<TfsDataFrame<T>> {
+        let path = path.as_ref();
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+        let mut info = connection.prepare(&format!("PRAGMA table_info({})", quote(table)))?;
+        let definitions = info
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        anyhow::ensure!(
+            !definitions.is_empty(),
+            "there is no table '{}' in {}",
+            table,
+            path.display()
+        );
+
+        let mut values: Vec<Vec<Value>> = vec![Vec::new(); definitions.len()];
+        let mut select =
+            connection.prepare(&format!("SELECT * FROM {} ORDER BY rowid", quote(table)))?;
+        let mut rows = select.query([])?;
+        while let Some(row) = rows.next()? {
+            for (i, column) in values.iter_mut().enumerate() {
+                column.push(row.get(i)?);
+            }
+        }
+        let columns = definitions
+            .iter()
+            .zip(values)
+            .map(|((name, declared), values)| column(name, declared, values))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut df = TfsDataFrame::new(read_header(&connection, table)?, DataFrame::new(columns)?);
+        read_metadata(&connection, table, &mut df)?;
+        Ok(df)
+    }
+}
+
+/// Quotes `name` as an SQL identifier.
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn kind_name(kind: ColumnKind) -> &'static str {
+    match kind {
+        ColumnKind::Real => "real",
+        ColumnKind::Integer => "integer",
+        ColumnKind::Boolean => "boolean",
+        ColumnKind::Text => "text",
+    }
+}
+
+fn sql_type(kind: ColumnKind) -> &'static str {
+    match kind {
+        ColumnKind::Real => "REAL",
+        ColumnKind::Integer => "INTEGER",
+        ColumnKind::Boolean => "BOOLEAN",
+        ColumnKind::Text => "TEXT",
+    }
+}
+
+/// The type code and the stored value of a header entry.
+fn header_value<T: std::fmt::Display + NumericNative>(value: &DataValue<T>) -> (String, Value) {
+    match value {
+        DataValue::Text(t) => ("%s".to_owned(), Value::Text(t.clone())),
+        DataValue::Real(r) => match r.to_f64().unwrap_or(f64::NAN) {
+            r if r.is_nan() => ("%le".to_owned(), Value::Text(NAN.to_owned())),
+            r => ("%le".to_owned(), Value::Real(r)),
+        },
+        DataValue::Integer(i) => ("%d".to_owned(), Value::Integer(*i)),
+        DataValue::Boolean(b) => (BOOLEAN_CODE.to_owned(), Value::Integer(*b as i64)),
+        DataValue::List(_) => {
+            let (code, text) = Dialect::MadNg.format_value(value);
+            (code, Value::Text(text))
+        }
+    }
+}
+
+/// Reads the header of `table`, which is empty if the database has no [`HEADER_TABLE`].
+fn read_header<T: std::str::FromStr>(
+    connection: &Connection,
+    table: &str,
+) -> anyhow::Result<Properties<T>> {
+    let mut properties = Properties::new();
+    if !table_exists(connection, HEADER_TABLE)? {
+        return Ok(properties);
+    }
+
+    let mut select = connection.prepare(&format!(
+        "SELECT key, code, value FROM {HEADER_TABLE} WHERE tbl = ?1 ORDER BY position"
+    ))?;
+    let mut rows = select.query(params![table])?;
+    while let Some(row) = rows.next()? {
+        let (key, code, value): (String, String, Value) = (row.get(0)?, row.get(1)?, row.get(2)?);
+        let invalid = || anyhow::anyhow!("invalid value {:?} of header entry '{}'", value, key);
+        let parsed = match (code.as_str(), &value) {
+            ("%s", Value::Text(t)) => DataValue::Text(t.clone()),
+            ("%le", Value::Real(r)) => {
+                DataValue::Real(r.to_string().parse().map_err(|_| invalid())?)
+            }
+            ("%le", Value::Null) => DataValue::Real("nan".parse().map_err(|_| invalid())?),
+            ("%le", Value::Text(t)) if t == NAN => {
+                DataValue::Real("nan".parse().map_err(|_| invalid())?)
+            }
+            ("%d", Value::Integer(i)) => DataValue::Integer(*i),
+            (BOOLEAN_CODE, Value::Integer(i)) => DataValue::Boolean(*i != 0),
+            (TABLE_CODE, Value::Text(t)) => {
+                Dialect::MadNg.parse_value(&code, t).ok_or_else(invalid)?
+            }
+            _ => return Err(invalid()),
+        };
+        properties.insert(key, parsed);
+    }
+    Ok(properties)
+}
+
+/// Sets the type codes, `NaN` sentinels and dialect of `df` stored for `table`, if the database
+/// has a [`METADATA_TABLE`].
+fn read_metadata<T: std::str::FromStr + NumericNative>(
+    connection: &Connection,
+    table: &str,
+    df: &mut TfsDataFrame<T>,
+) -> anyhow::Result<()> {
+    if !table_exists(connection, METADATA_TABLE)? {
+        return Ok(());
+    }
+
+    let mut codes = std::collections::HashMap::new();
+    let mut kinds = std::collections::HashMap::new();
+    let mut select = connection.prepare(&format!(
+        "SELECT col, key, value FROM {METADATA_TABLE} WHERE tbl = ?1"
+    ))?;
+    let mut rows = select.query(params![table])?;
+    while let Some(row) = rows.next()? {
+        let (column, key, value): (String, String, Value) = (row.get(0)?, row.get(1)?, row.get(2)?);
+        let invalid = || anyhow::anyhow!("invalid metadata {:?} '{}' of '{}'", value, key, column);
+        match (key.as_str(), &value) {
+            ("dialect", Value::Text(t)) => df.dialect = t.parse()?,
+            ("code", Value::Text(t)) => {
+                codes.insert(column, t.clone());
+            }
+            ("kind", Value::Text(t)) => {
+                let kind = [
+                    ColumnKind::Real,
+                    ColumnKind::Integer,
+                    ColumnKind::Boolean,
+                    ColumnKind::Text,
+                ]
+                .into_iter()
+                .find(|&kind| kind_name(kind) == t)
+                .ok_or_else(invalid)?;
+                kinds.insert(column, kind);
+            }
+            ("nan_sentinel", Value::Real(r)) => {
+                df.nan_sentinels.insert(column, *r);
+            }
+            ("nan_sentinel", Value::Integer(i)) => {
+                df.nan_sentinels.insert(column, *i as f64);
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    for (column, code) in codes {
+        let kind = kinds
+            .remove(&column)
+            .ok_or_else(|| anyhow::anyhow!("the type code of '{}' has no column kind", column))?;
+        df.type_codes.insert(column, (code, kind));
+    }
+    Ok(())
+}
+
+fn table_exists(connection: &Connection, table: &str) -> anyhow::Result<bool> {
+    Ok(connection.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+        params![table],
+        |row| row.get(0),
+    )?)
+}
+
+/// The column `name` with the `values` read from a column of the SQL type `declared`.
+fn column(name: &str, declared: &str, values: Vec<Value>) -> anyhow::Result<Column> {
+    let invalid = |value: &Value| {
+        anyhow::anyhow!(
+            "invalid value {:?} in the {} column '{}'",
+            value,
+            declared,
+            name
+        )
+    };
+    let series = match declared.to_ascii_uppercase().as_str() {
+        "REAL" | "DOUBLE" | "FLOAT" => Series::new(
+            name.into(),
+            values
+                .iter()
+                .map(|value| match value {
+                    Value::Null => Ok(None),
+                    Value::Real(r) => Ok(Some(*r)),
+                    Value::Text(t) if t == NAN => Ok(Some(f64::NAN)),
+                    Value::Integer(i) => Ok(Some(*i as f64)),
+                    value => Err(invalid(value)),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        "INTEGER" | "INT" | "BIGINT" => Series::new(
+            name.into(),
+            values
+                .iter()
+                .map(|value| match value {
+                    Value::Null => Ok(None),
+                    Value::Integer(i) => Ok(Some(*i)),
+                    value => Err(invalid(value)),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        "BOOLEAN" => Series::new(
+            name.into(),
+            values
+                .iter()
+                .map(|value| match value {
+                    Value::Null => Ok(None),
+                    Value::Integer(i) => Ok(Some(*i != 0)),
+                    value => Err(invalid(value)),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        _ => Series::new(
+            name.into(),
+            values
+                .into_iter()
+                .map(|value| match value {
+                    Value::Null => None,
+                    Value::Text(t) => Some(t),
+                    Value::Integer(i) => Some(i.to_string()),
+                    Value::Real(r) => Some(r.to_string()),
+                    Value::Blob(b) => Some(String::from_utf8_lossy(&b).into_owned()),
+                })
+                .collect::<Vec<_>>(),
+        ),
+    };
+    Ok(series.into())
+}
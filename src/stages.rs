@@ -0,0 +1,293 @@
+//! Reading a tfs file in stages.
+//!
+//! [`TfsDataFrame::open`] reads a whole file at once. [`TfsHeaderParser`] and [`TfsBodyParser`]
+//! split this into reading the header and reading the data rows, so that a file can be skipped
+//! or read differently depending on its header without opening it again:
+//!
+//! ```
+//! # use tfs::{TfsDataFrame, TfsHeaderParser};
+//! let mut body = TfsHeaderParser::open("test/test.tfs")
+//!     .unwrap()
+//!     .parse::<f64>()
+//!     .unwrap();
+//!
+//! if body.header().properties.contains_key("SEQUENCE") {
+//!     body.read_rows(2).unwrap();
+//!     assert_eq!(body.rows_read(), 2);
+//!
+//!     let df = body.finish().unwrap();
+//!     assert_eq!(df.len(), 5);
+//! }
+//! ```
+//!
+//! With the `tracing` feature, reading the header, parsing the body and building the columns are
+//! `debug` spans, and the number of rows, rows per second and values coerced to `NaN` are logged
+//! when the body is finished.
+use polars::prelude::{polars_bail, DataFrame, NumericNative, PolarsError};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::dataframe::DataValue;
+use crate::dialect::Dialect;
+use crate::header::TfsHeader;
+use crate::lineage::Lineage;
+use crate::options::TfsReadOptions;
+use crate::parse::ParseWarning;
+use crate::paths::resolve_path;
+use crate::reader::{read_header, BodyParser, ParsedHeader};
+use crate::tfsdataframe::{TfsDataFrame, NROWS_KEY};
+
+/// First stage of reading a tfs file: the header.
+pub struct TfsHeaderParser<R> {
+    reader: R,
+    options: TfsReadOptions,
+}
+
+impl TfsHeaderParser<BufReader<File>> {
+    /// Opens a tfs file for reading with the default options, finding it like
+    /// [`TfsDataFrame::open`](crate::TfsDataFrame::open).
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, PolarsError> {
+        let path = resolve_path(path.as_ref(), &[])?;
+        Ok(TfsHeaderParser::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> TfsHeaderParser<R> {
+    pub fn new(reader: R) -> TfsHeaderParser<R> {
+        TfsHeaderParser::with_options(reader, TfsReadOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: TfsReadOptions) -> TfsHeaderParser<R> {
+        TfsHeaderParser { reader, options }
+    }
+
+    /// Reads the header, up to and including the `*` and `$` lines, and returns the parser of
+    /// the data rows that follow.
+    pub fn parse<T>(mut self) -> Result<TfsBodyParser<T, R>, PolarsError>
+    where
+        T: std::str::FromStr,
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("read_header").entered();
+        let (mut header, position) = read_header(&mut self.reader, &self.options)?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            entries = header.properties.len(),
+            columns = header.colnames.len(),
+            dialect = ?header.dialect,
+            "read the header"
+        );
+        let body = BodyParser::new(
+            &header.colnames,
+            &header.coltypes,
+            header.dialect,
+            &self.options,
+            &mut header.warnings,
+        );
+
+        Ok(TfsBodyParser {
+            reader: self.reader,
+            options: self.options,
+            header: TfsHeader {
+                properties: header.properties,
+                colnames: header.colnames,
+                coltypes: header.coltypes,
+            },
+            lineage: header.lineage,
+            warnings: header.warnings,
+            dialect: header.dialect,
+            body,
+            position,
+            line: String::new(),
+        })
+    }
+}
+
+/// Second stage of reading a tfs file: the data rows. The rows can be read all at once with
+/// [`TfsBodyParser::finish`] or in chunks with [`TfsBodyParser::read_rows`].
+pub struct TfsBodyParser<T, R> {
+    reader: R,
+    options: TfsReadOptions,
+    header: TfsHeader<T>,
+    lineage: HashMap<String, Lineage>,
+    warnings: Vec<ParseWarning>,
+    dialect: Dialect,
+    body: BodyParser,
+    position: u64,
+    line: String,
+}
+
+impl<T, R> TfsBodyParser<T, R>
+where
+    T: std::str::FromStr + NumericNative,
+    R: BufRead,
+{
+    /// The header read by the [`TfsHeaderParser`].
+    pub fn header(&self) -> &TfsHeader<T> {
+        &self.header
+    }
+
+    /// The dialect the file is read in, see [`Dialect`].
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    /// The options the file is read with. Changes of the options for single values (real number
+    /// formats, missing fields) apply to the rows read afterwards, the column types and `NaN`
+    /// sentinels are fixed by the header stage.
+    pub fn options_mut(&mut self) -> &mut TfsReadOptions {
+        &mut self.options
+    }
+
+    /// Number of data rows read so far.
+    pub fn rows_read(&self) -> usize {
+        self.body.rows()
+    }
+
+    /// Number of bytes read from the start of the reader so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Gives access to the underlying reader, e.g. to seek. Reading from it skips these bytes.
+    pub fn reader_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Reads up to `n` data rows, returns the number of rows read. Less than `n` rows are read
+    /// only at the end of the file.
+    pub fn read_rows(&mut self, n: usize) -> Result<usize, PolarsError> {
+        let mut rows = 0;
+        while rows < n {
+            self.line.clear();
+            let n_bytes = self.reader.read_line(&mut self.line)?;
+            if n_bytes == 0 {
+                break;
+            }
+            self.position += n_bytes as u64;
+
+            let before = self.body.rows();
+            self.body
+                .parse_line(&self.line, &self.options, &mut self.warnings)?;
+            rows += self.body.rows() - before;
+
+            if let Some(limit) = self.options.max_memory {
+                if self.body.memory() > limit {
+                    polars_bail!(
+                        ComputeError: "the columns take more than the limit of {} bytes after {} rows",
+                        limit,
+                        self.body.rows()
+                    );
+                }
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Skips up to `n` data rows without parsing them, returns the number of rows skipped.
+    pub fn skip_rows(&mut self, n: usize) -> Result<usize, PolarsError> {
+        let mut rows = 0;
+        while rows < n {
+            self.line.clear();
+            let n_bytes = self.reader.read_line(&mut self.line)?;
+            if n_bytes == 0 {
+                break;
+            }
+            self.position += n_bytes as u64;
+
+            let line = self.line.trim_start();
+            if !line.is_empty() && !line.starts_with('#') {
+                rows += 1;
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Takes the rows read so far as a frame without header, the following rows start anew.
+    /// [`TfsBodyParser::rows_read`] counts from zero again.
+    pub(crate) fn take_rows(&mut self) -> Result<DataFrame, PolarsError> {
+        DataFrame::new(self.body.take_columns())
+    }
+
+    /// Parses `line` as a data row, e.g. a line read from another position of the file.
+    pub fn parse_row(&mut self, line: &str) -> Result<(), PolarsError> {
+        self.body
+            .parse_line(line, &self.options, &mut self.warnings)
+    }
+
+    /// Reads the remaining rows and builds the frame. Checks the number of rows against the
+    /// `NROWS` header entry, if present.
+    pub fn finish(mut self) -> Result<TfsDataFrame<T>, PolarsError> {
+        {
+            #[cfg(feature = "tracing")]
+            let (_span, start) = (
+                tracing::debug_span!("parse_body").entered(),
+                std::time::Instant::now(),
+            );
+            self.read_rows(usize::MAX)?;
+            #[cfg(feature = "tracing")]
+            {
+                let rows = self.body.rows();
+                let nan_coercions = self
+                    .warnings
+                    .iter()
+                    .filter(|w| matches!(w, ParseWarning::NanCoercion { .. }))
+                    .count();
+                tracing::debug!(
+                    rows,
+                    rows_per_second = rows as f64 / start.elapsed().as_secs_f64(),
+                    nan_coercions,
+                    "parsed the body"
+                );
+            }
+        }
+
+        let df = self.into_frame()?;
+        if let Some(DataValue::Integer(nrows)) = df.properties.get(NROWS_KEY) {
+            if usize::try_from(*nrows).ok() != Some(df.len()) {
+                polars_bail!(
+                    ShapeMismatch: "the header declares {} rows but {} were read, the file is probably truncated",
+                    nrows,
+                    df.len()
+                );
+            }
+        }
+        Ok(df)
+    }
+
+    /// Builds the frame from the rows read so far, without reading further.
+    pub fn into_frame(self) -> Result<TfsDataFrame<T>, PolarsError> {
+        let header = ParsedHeader {
+            properties: self.header.properties,
+            lineage: self.lineage,
+            warnings: self.warnings,
+            colnames: self.header.colnames,
+            coltypes: self.header.coltypes,
+            dialect: self.dialect,
+        };
+        let mut df = TfsDataFrame::from_parsed(header, self.body)?;
+        for name in &self.options.compressed_columns {
+            if df.df.get_column_index(name).is_some() {
+                df.compress_column(name)
+                    .map_err(|err| PolarsError::ComputeError(err.to_string().into()))?;
+            }
+        }
+        Ok(df)
+    }
+}
+
+impl<T, R> TfsBodyParser<T, R>
+where
+    T: std::str::FromStr + NumericNative,
+    R: BufRead + Seek,
+{
+    /// Continues reading at byte `position`, which has to be the start of a line of the body.
+    pub fn seek(&mut self, position: u64) -> Result<(), PolarsError> {
+        self.reader.seek(SeekFrom::Start(position))?;
+        self.position = position;
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+//! Aggregates of real columns.
+//!
+//! The statistics of a column are computed on first request and cached in the frame, so
+//! repeated calls (e.g. from plotting or validation code) don't scan the column again. The cache
+//! entry of a column is dropped when the column is replaced.
+use polars::prelude::{DataType, NumericNative};
+
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Minimum, maximum and mean of a column, ignoring `NaN` and missing values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColumnStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Number of values that went into the statistics.
+    pub count: usize,
+}
+
+impl ColumnStats {
+    fn compute(values: impl Iterator<Item = f64>) -> ColumnStats {
+        let mut stats = ColumnStats {
+            min: f64::NAN,
+            max: f64::NAN,
+            mean: f64::NAN,
+            count: 0,
+        };
+        let mut sum = 0.0;
+        for v in values.filter(|v| !v.is_nan()) {
+            stats.min = stats.min.min(v);
+            stats.max = stats.max.max(v);
+            sum += v;
+            stats.count += 1;
+        }
+        if stats.count > 0 {
+            stats.mean = sum / stats.count as f64;
+        }
+        stats
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Returns the statistics of the real column `name`. If all values are `NaN` or missing,
+    /// `min`, `max` and `mean` are `NaN`.
+    ///
+    /// ```
+    /// # use tfs::TfsDataFrame;
+    /// let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+    /// let stats = df.stats("L").unwrap();
+    /// assert_eq!(stats.min, 0.0);
+    /// assert_eq!(stats.max, 3.4);
+    /// ```
+    pub fn stats(&self, name: &str) -> anyhow::Result<ColumnStats> {
+        if let Some(stats) = self.stats_cache.read().unwrap().get(name) {
+            return Ok(*stats);
+        }
+
+        let column = self.column(name)?;
+        if !column.dtype().is_primitive_numeric() {
+            anyhow::bail!("column '{}' is not numeric", name);
+        }
+        let column = column.cast(&DataType::Float64)?;
+        let stats = ColumnStats::compute(column.f64()?.iter().flatten());
+
+        self.stats_cache
+            .write()
+            .unwrap()
+            .insert(name.to_owned(), stats);
+        Ok(stats)
+    }
+
+    /// Minimum of the real column `name`, see [`TfsDataFrame::stats`].
+    pub fn min(&self, name: &str) -> anyhow::Result<f64> {
+        Ok(self.stats(name)?.min)
+    }
+
+    /// Maximum of the real column `name`, see [`TfsDataFrame::stats`].
+    pub fn max(&self, name: &str) -> anyhow::Result<f64> {
+        Ok(self.stats(name)?.max)
+    }
+
+    /// Mean of the real column `name`, see [`TfsDataFrame::stats`].
+    pub fn mean(&self, name: &str) -> anyhow::Result<f64> {
+        Ok(self.stats(name)?.mean)
+    }
+}
@@ -0,0 +1,320 @@
+//! Typed views of the common MAD-X tables.
+//!
+//! [`TwissTable`] and [`SurveyTable`] wrap a [`TfsDataFrame`] that was checked to have the
+//! columns of the table when it was created. Real columns are converted to `f64` and text
+//! columns to strings then, their accessors like [`TwissTable::betx`] only fail if a compressed
+//! or virtual column can't be read. [`SummTable`] reads the single row of a `SUMM` table into a [`Summ`]. A missing
+//! or mistyped column is reported up front, with every
+//! [`Violation`](crate::schema::Violation):
+//!
+//! ```
+//! # use tfs::tables::{SurveyTable, TwissTable};
+//! let twiss = TwissTable::<f64>::open("test/test.tfs").unwrap();
+//! assert_eq!(twiss.betx().unwrap().len(), 5);
+//! assert_eq!(twiss.name().unwrap().get(0), Some("BPM1"));
+//!
+//! let error = SurveyTable::<f64>::open("test/test.tfs").unwrap_err();
+//! assert!(error.to_string().contains("column 'THETA' is missing"));
+//! ```
+//!
+//! The twiss and survey tables dereference to the frame for everything else, but can't be
+//! changed, to keep the columns in place. [`TwissTable::into_inner`] returns the frame.
+use polars::prelude::{DataType, Float64Chunked, NumericNative, StringChunked};
+use std::ops::Deref;
+use std::path::Path;
+
+use crate::record::ColumnValue;
+use crate::schema::{Rule, Schema};
+use crate::tfsdataframe::TfsDataFrame;
+use crate::types::ColumnKind;
+
+/// The schema requiring the columns `text` and `real` of these kinds.
+fn columns_schema(text: &[&str], real: &[&str]) -> Schema {
+    let rule = |name: &&str, kind| Rule {
+        name: name.to_string(),
+        required: true,
+        kind: Some(kind),
+        min: None,
+        max: None,
+        pattern: None,
+    };
+    Schema {
+        headers: Vec::new(),
+        columns: text
+            .iter()
+            .map(|name| rule(name, ColumnKind::Text))
+            .chain(real.iter().map(|name| rule(name, ColumnKind::Real)))
+            .collect(),
+        checks: Vec::new(),
+    }
+}
+
+/// Fails with all violations if `df` doesn't have the columns `text` and `real`.
+fn check_columns<T>(
+    df: &TfsDataFrame<T>,
+    table: &str,
+    text: &[&str],
+    real: &[&str],
+) -> anyhow::Result<()>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    let violations = df.validate(&columns_schema(text, real))?;
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+    anyhow::bail!("not a {} table: {}", table, violations.join(", "))
+}
+
+/// Converts the checked columns `text` and `real` of `df` to strings and `f64`, the types of the
+/// accessors of the tables.
+fn exact_types<T>(df: &mut TfsDataFrame<T>, text: &[&str], real: &[&str]) -> anyhow::Result<()>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    let columns = text
+        .iter()
+        .map(|name| (name, DataType::String))
+        .chain(real.iter().map(|name| (name, DataType::Float64)));
+    for (name, dtype) in columns {
+        if df.column(name)?.dtype() != &dtype {
+            df.cast_column(name, dtype)?;
+        }
+    }
+    Ok(())
+}
+
+macro_rules! madx_table {
+    (
+        $(#[$doc:meta])*
+        $table:ident, $kind:literal,
+        text: [$($text:ident => $text_column:literal),*],
+        real: [$($real:ident => $real_column:literal),*]
+    ) => {
+        $(#[$doc])*
+        #[derive(Debug)]
+        pub struct $table<T: std::str::FromStr + NumericNative>(TfsDataFrame<T>);
+
+        impl<T: std::str::FromStr + NumericNative> $table<T> {
+            const TEXT: &'static [&'static str] = &[$($text_column),*];
+            const REAL: &'static [&'static str] = &[$($real_column),*];
+
+            /// Wraps `df`, failing if it lacks a column of the table or has it with another type.
+            /// Real columns of other float types are converted to `f64`, text columns of other
+            /// types like categoricals to strings.
+            pub fn new(mut df: TfsDataFrame<T>) -> anyhow::Result<Self> {
+                check_columns(&df, $kind, Self::TEXT, Self::REAL)?;
+                exact_types(&mut df, Self::TEXT, Self::REAL)?;
+                Ok($table(df))
+            }
+
+            /// Opens the file at `path` and checks its columns like the constructor.
+            pub fn open<P>(path: P) -> anyhow::Result<Self>
+            where
+                P: AsRef<Path>,
+                <T as std::str::FromStr>::Err: std::fmt::Debug,
+            {
+                Self::new(TfsDataFrame::open(path)?)
+            }
+
+            pub fn into_inner(self) -> TfsDataFrame<T> {
+                self.0
+            }
+
+            $(
+                #[doc = concat!("The `", $text_column, "` column.")]
+                pub fn $text(&self) -> anyhow::Result<&StringChunked> {
+                    Ok(self.0.column($text_column)?.str()?)
+                }
+            )*
+
+            $(
+                #[doc = concat!("The `", $real_column, "` column.")]
+                pub fn $real(&self) -> anyhow::Result<&Float64Chunked> {
+                    Ok(self.0.column($real_column)?.f64()?)
+                }
+            )*
+        }
+
+        impl<T: std::str::FromStr + NumericNative> Deref for $table<T> {
+            type Target = TfsDataFrame<T>;
+
+            fn deref(&self) -> &TfsDataFrame<T> {
+                &self.0
+            }
+        }
+    };
+}
+
+madx_table! {
+    /// The optics of MAD-X's `TWISS`, with the columns `NAME`, `S`, `BETX`, `ALFX`, `MUX`,
+    /// `BETY`, `ALFY` and `MUY`.
+    TwissTable, "twiss",
+    text: [name => "NAME"],
+    real: [
+        s => "S",
+        betx => "BETX",
+        alfx => "ALFX",
+        mux => "MUX",
+        bety => "BETY",
+        alfy => "ALFY",
+        muy => "MUY"
+    ]
+}
+
+madx_table! {
+    /// The positions of MAD-X's `SURVEY`, with the columns `NAME`, `S`, `X`, `Y`, `Z`, `THETA`,
+    /// `PHI` and `PSI`.
+    SurveyTable, "survey",
+    text: [name => "NAME"],
+    real: [
+        s => "S",
+        x => "X",
+        y => "Y",
+        z => "Z",
+        theta => "THETA",
+        phi => "PHI",
+        psi => "PSI"
+    ]
+}
+
+/// The values of a MAD-X `SUMM` table. Columns other than `Q1` and `Q2` depend on the version
+/// of MAD-X and are `None` if missing, like missing values and `NaN`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Summ {
+    pub length: Option<f64>,
+    pub orbit5: Option<f64>,
+    /// The momentum compaction factor.
+    pub alfa: Option<f64>,
+    pub gammatr: Option<f64>,
+    pub q1: f64,
+    pub dq1: Option<f64>,
+    pub betxmax: Option<f64>,
+    pub dxmax: Option<f64>,
+    pub dxrms: Option<f64>,
+    pub xcomax: Option<f64>,
+    pub xcorms: Option<f64>,
+    pub q2: f64,
+    pub dq2: Option<f64>,
+    pub betymax: Option<f64>,
+    pub dymax: Option<f64>,
+    pub dyrms: Option<f64>,
+    pub ycomax: Option<f64>,
+    pub ycorms: Option<f64>,
+    pub deltap: Option<f64>,
+    /// The synchrotron radiation integrals `SYNCH_1` to `SYNCH_5`.
+    pub synch: [Option<f64>; 5],
+    /// The closest tune approach, written since MAD-X 5.06.
+    pub dqmin: Option<f64>,
+}
+
+impl Summ {
+    /// Reads the values of the single row of `df`, which needs real columns `Q1` and `Q2`.
+    pub fn from_frame<T>(df: &TfsDataFrame<T>) -> anyhow::Result<Summ>
+    where
+        T: std::str::FromStr + NumericNative,
+    {
+        check_columns(df, "summ", &[], &["Q1", "Q2"])?;
+        anyhow::ensure!(
+            df.len() == 1,
+            "not a summ table: it has {} rows instead of one",
+            df.len()
+        );
+        let names = df.column_names();
+        let value = |name: &str| -> anyhow::Result<Option<f64>> {
+            if !names.contains(&name) {
+                return Ok(None);
+            }
+            Ok(Option::<f64>::from_column(df.column(name)?)?[0].filter(|v| !v.is_nan()))
+        };
+        let synch = ["SYNCH_1", "SYNCH_2", "SYNCH_3", "SYNCH_4", "SYNCH_5"].map(value);
+        let [s1, s2, s3, s4, s5] = synch;
+        Ok(Summ {
+            length: value("LENGTH")?,
+            orbit5: value("ORBIT5")?,
+            alfa: value("ALFA")?,
+            gammatr: value("GAMMATR")?,
+            q1: value("Q1")?.unwrap_or(f64::NAN),
+            dq1: value("DQ1")?,
+            betxmax: value("BETXMAX")?,
+            dxmax: value("DXMAX")?,
+            dxrms: value("DXRMS")?,
+            xcomax: value("XCOMAX")?,
+            xcorms: value("XCORMS")?,
+            q2: value("Q2")?.unwrap_or(f64::NAN),
+            dq2: value("DQ2")?,
+            betymax: value("BETYMAX")?,
+            dymax: value("DYMAX")?,
+            dyrms: value("DYRMS")?,
+            ycomax: value("YCOMAX")?,
+            ycorms: value("YCORMS")?,
+            deltap: value("DELTAP")?,
+            synch: [s1?, s2?, s3?, s4?, s5?],
+            dqmin: value("DQMIN")?,
+        })
+    }
+}
+
+/// The summary table of MAD-X's `TWISS`, a single row with at least the columns `Q1` and
+/// `Q2`. Its values are read into a [`Summ`] on creation:
+///
+/// ```
+/// # use tfs::TfsDataFrame;
+/// # use tfs::tables::SummTable;
+/// let df = TfsDataFrame::<f64>::new(
+///     Vec::new(),
+///     tfs::polars::df!("Q1" => [62.31], "Q2" => [60.32], "ALFA" => [3.2e-4]).unwrap(),
+/// );
+/// let summ = SummTable::new(df).unwrap();
+/// assert_eq!((summ.q1, summ.alfa, summ.dqmin), (62.31, Some(3.2e-4), None));
+/// ```
+#[derive(Debug)]
+pub struct SummTable<T: std::str::FromStr + NumericNative> {
+    frame: TfsDataFrame<T>,
+    summ: Summ,
+}
+
+impl<T: std::str::FromStr + NumericNative> SummTable<T> {
+    /// Wraps `df`, failing if it lacks `Q1` or `Q2`, has them with another type or doesn't have
+    /// exactly one row.
+    pub fn new(df: TfsDataFrame<T>) -> anyhow::Result<Self> {
+        Ok(SummTable {
+            summ: Summ::from_frame(&df)?,
+            frame: df,
+        })
+    }
+
+    /// Opens the file at `path` and checks it like the constructor.
+    pub fn open<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        Self::new(TfsDataFrame::open(path)?)
+    }
+
+    pub fn into_inner(self) -> TfsDataFrame<T> {
+        self.frame
+    }
+
+    /// The values of the table, also reachable through the fields of the table.
+    pub fn summ(&self) -> &Summ {
+        &self.summ
+    }
+
+    /// The frame of the table.
+    pub fn frame(&self) -> &TfsDataFrame<T> {
+        &self.frame
+    }
+}
+
+/// The fields of [`Summ`], see [`SummTable::frame`] for the frame.
+impl<T: std::str::FromStr + NumericNative> Deref for SummTable<T> {
+    type Target = Summ;
+
+    fn deref(&self) -> &Summ {
+        &self.summ
+    }
+}
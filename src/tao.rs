@@ -0,0 +1,228 @@
+//! The tables of Bmad's Tao, as printed by `show lattice`.
+//!
+//! `show lattice` prints a column per attribute, titled by up to three comment lines: the
+//! attribute, its plane or mode (`a`, `b`, `x`, ...) and its unit. [`parse_lattice`] joins them
+//! to the names `beta_a` or `orbit_x`, renames those to MAD-X (`BETX`, `X`) and converts
+//! millimetres and radians to metres and units of 2π:
+//!
+//! ```
+//! let text = "\
+//! ## Values shown are for the Exit End of each Element:
+//! ## Index  name      key                s       l    beta     phi   orbit
+//! ##                                                     a       a       x
+//! ##                                   [m]     [m]     [m]   [2pi]    [mm]
+//!       0  BEGINNING Beginning_Ele   0.000      --   12.00   0.000   0.000
+//!       1  QF        Quadrupole      0.500   0.500   14.20   0.010   1.250
+//! ";
+//! let df = tfs::tao::parse_lattice::<f64>(text).unwrap();
+//! assert_eq!(df.column_names(), ["INDEX", "NAME", "KEYWORD", "S", "L", "BETX", "MUX", "X"]);
+//! ```
+//!
+//! Comment lines in front of the titles end up in the `COMMENT` header entry, `--` is read as
+//! `NaN`.
+use polars::prelude::{Column, DataFrame, NamedFrom, NumericNative};
+use polars::series::Series;
+use std::f64::consts::PI;
+use std::path::Path;
+
+use crate::dataframe::DataValue;
+use crate::tfsdataframe::{Properties, TfsDataFrame};
+
+/// Tao names, lower case, with their MAD-X names.
+const NAMES: [(&str, &str); 24] = [
+    ("name", "NAME"),
+    ("key", "KEYWORD"),
+    ("s", "S"),
+    ("l", "L"),
+    ("beta_a", "BETX"),
+    ("beta_b", "BETY"),
+    ("alpha_a", "ALFX"),
+    ("alpha_b", "ALFY"),
+    ("phi_a", "MUX"),
+    ("phi_b", "MUY"),
+    ("eta_a", "DX"),
+    ("eta_b", "DY"),
+    ("etap_a", "DPX"),
+    ("etap_b", "DPY"),
+    ("eta_x", "DX"),
+    ("eta_y", "DY"),
+    ("etap_x", "DPX"),
+    ("etap_y", "DPY"),
+    ("orbit_x", "X"),
+    ("orbit_px", "PX"),
+    ("orbit_y", "Y"),
+    ("orbit_py", "PY"),
+    ("orbit_z", "T"),
+    ("orbit_pz", "PT"),
+];
+
+/// Units and the factor to MAD-X units.
+const UNITS: [(&str, f64); 5] = [
+    ("[mm]", 1e-3),
+    ("[mrad]", 1e-3),
+    ("[um]", 1e-6),
+    ("[rad]", 1.0 / (2.0 * PI)),
+    ("[deg]", 1.0 / 360.0),
+];
+
+/// The MAD-X name of the Tao column `name`, like `beta_a`. Unknown names are upper-cased.
+pub fn madx_name(name: &str) -> String {
+    let lower = name.to_ascii_lowercase();
+    NAMES
+        .iter()
+        .find(|(tao, _)| *tao == lower)
+        .map_or_else(|| name.to_ascii_uppercase(), |(_, madx)| madx.to_string())
+}
+
+/// Reads the output of `show lattice` saved to `path`.
+pub fn read_lattice<T, P>(path: P) -> anyhow::Result<TfsDataFrame<T>>
+where
+    T: std::str::FromStr + NumericNative,
+    P: AsRef<Path>,
+{
+    parse_lattice(&std::fs::read_to_string(path)?)
+}
+
+/// Parses the output of `show lattice`, see the [module documentation](self).
+pub fn parse_lattice<T>(text: &str) -> anyhow::Result<TfsDataFrame<T>>
+where
+    T: std::str::FromStr + NumericNative,
+{
+    let lines: Vec<&str> = text.lines().collect();
+    let first_row = lines
+        .iter()
+        .position(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .ok_or_else(|| anyhow::anyhow!("the table has no rows"))?;
+
+    // the titles are the last comment lines in front of the rows, starting with the one naming
+    // the columns
+    let comments: Vec<&str> = lines[..first_row]
+        .iter()
+        .copied()
+        .filter(|line| line.trim_start().starts_with('#'))
+        .collect();
+    let title = comments
+        .iter()
+        .rposition(|line| {
+            line.split_whitespace()
+                .any(|word| word.eq_ignore_ascii_case("name"))
+        })
+        .ok_or_else(|| anyhow::anyhow!("the table has no column titles"))?;
+
+    let titles = words(comments[title]);
+    let mut names: Vec<String> = titles.iter().map(|(_, _, word)| word.to_string()).collect();
+    let mut factors = vec![1.0; names.len()];
+    for line in &comments[title + 1..] {
+        for (start, end, word) in words(line) {
+            let column = closest(&titles, start, end);
+            if word.starts_with('[') {
+                if let Some((_, factor)) = UNITS.iter().find(|(unit, _)| *unit == word) {
+                    factors[column] = *factor;
+                }
+            } else {
+                names[column] = format!("{}_{}", names[column], word);
+            }
+        }
+    }
+
+    let mut cells = vec![Vec::new(); names.len()];
+    for line in &lines[first_row..] {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        anyhow::ensure!(
+            fields.len() == names.len(),
+            "the row '{}' has {} fields but there are {} columns",
+            line.trim(),
+            fields.len(),
+            names.len()
+        );
+        for (column, field) in cells.iter_mut().zip(fields) {
+            column.push(field);
+        }
+    }
+
+    let mut columns: Vec<Column> = Vec::new();
+    for ((name, factor), cells) in names.iter().zip(factors).zip(cells) {
+        let name = madx_name(name);
+        anyhow::ensure!(
+            columns.iter().all(|c| c.name().as_str() != name),
+            "the columns of the table have the MAD-X name '{}' twice",
+            name
+        );
+        columns.push(column(&name, &cells, factor).into());
+    }
+
+    let mut header = Properties::new();
+    header.insert("TYPE".to_owned(), DataValue::Text("TWISS".to_owned()));
+    header.insert("ORIGIN".to_owned(), DataValue::Text("Tao".to_owned()));
+    let notes: Vec<&str> = comments[..title]
+        .iter()
+        .map(|line| line.trim_start().trim_start_matches('#').trim())
+        .filter(|note| !note.is_empty())
+        .collect();
+    if !notes.is_empty() {
+        header.insert("COMMENT".to_owned(), DataValue::Text(notes.join(" ")));
+    }
+    Ok(TfsDataFrame::new(header, DataFrame::new(columns)?))
+}
+
+/// The words of a line with their start and end.
+fn words(line: &str) -> Vec<(usize, usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in line
+        .char_indices()
+        .chain(std::iter::once((line.len(), ' ')))
+    {
+        match (start, c.is_whitespace() || c == '#') {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                words.push((s, i, &line[s..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+/// The title that overlaps most with the word from `start` to `end`, or the nearest.
+fn closest(titles: &[(usize, usize, &str)], start: usize, end: usize) -> usize {
+    let center = |s: usize, e: usize| (s + e) as i64;
+    (0..titles.len())
+        .min_by_key(|i| {
+            let (s, e, _) = titles[*i];
+            let overlap = end.min(e) as i64 - start.max(s) as i64;
+            if overlap > 0 {
+                (0, -overlap)
+            } else {
+                (1, (center(s, e) - center(start, end)).abs())
+            }
+        })
+        .unwrap_or(0)
+}
+
+fn column(name: &str, cells: &[&str], factor: f64) -> Series {
+    if let Ok(integers) = cells
+        .iter()
+        .map(|c| c.parse())
+        .collect::<Result<Vec<i64>, _>>()
+    {
+        if factor == 1.0 && name != "NAME" {
+            return Series::new(name.into(), integers);
+        }
+    }
+    let reals: Option<Vec<f64>> = cells
+        .iter()
+        .map(|cell| match *cell {
+            "--" => Some(f64::NAN),
+            cell => cell.parse::<f64>().ok().map(|r| r * factor),
+        })
+        .collect();
+    match reals {
+        Some(reals) if name != "NAME" => Series::new(name.into(), reals),
+        _ => Series::new(name.into(), cells),
+    }
+}
@@ -0,0 +1,611 @@
+//! Helpers for unit tests of crates working with TFS files.
+//!
+//! [`make_frame`] generates synthetic twiss-like frames so that tests don't depend on files lying
+//! around, and [`roundtrip`] checks that a frame survives being written and read again.
+//!
+//! ```
+//! # use tfs::testing::{make_frame, roundtrip, FrameSpec};
+//! let df = make_frame(&FrameSpec {
+//!     n_elements: 100,
+//!     noise: 1e-3,
+//!     ..Default::default()
+//! });
+//!
+//! assert_eq!(df.len(), 100);
+//! assert_eq!(roundtrip(&df).unwrap().len(), 100);
+//! ```
+//!
+//! [`assert_exact_roundtrip!`](crate::assert_exact_roundtrip) checks that every real reads back
+//! to the same bits.
+//!
+//! [`assert_tfs_eq!`](crate::assert_tfs_eq) compares a generated frame with an expected file,
+//! real numbers up to a relative tolerance. With the environment variable
+//! `TFS_UPDATE_SNAPSHOTS=1` it writes the expected file instead, e.g. to create it the first
+//! time or after an intended change:
+//!
+//! ```no_run
+//! # use tfs::testing::{make_frame, FrameSpec};
+//! let df = make_frame(&FrameSpec::default());
+//! tfs::assert_tfs_eq!(df, "tests/snapshots/synthetic.tfs", rtol = 1e-9);
+//! ```
+//!
+//! For property tests, [`arbitrary_frame`] generates random frames from any random number
+//! generator and [`corrupt`] damages their files. [`fuzz_parser`] feeds such files to the parser,
+//! which has to reject them with an error instead of panicking:
+//!
+//! ```
+//! assert_eq!(tfs::testing::fuzz_parser(0, 50), None);
+//! ```
+//!
+//! With the feature `proptest`, frames implement `proptest::arbitrary::Arbitrary` with the same
+//! kind of frames, shrinking to fewer rows, columns and header entries, see [`FrameLimits`].
+//! The fuzz target `fuzz/fuzz_targets/parse.rs` runs [`parse_bytes`] under cargo-fuzz.
+use polars::prelude::{DataFrame, DataType, NamedFrom, NumericNative, PolarsError};
+use polars::series::Series;
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use std::f64::consts::PI;
+use std::fmt;
+use std::io::Cursor;
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+use crate::dataframe::DataValue;
+use crate::record::ColumnValue;
+use crate::stages::TfsHeaderParser;
+use crate::tfsdataframe::{Properties, TfsDataFrame};
+
+/// Describes the synthetic frame generated by [`make_frame`].
+#[derive(Debug, Clone)]
+pub struct FrameSpec {
+    /// Number of elements (rows) in the frame.
+    pub n_elements: usize,
+    /// Horizontal tune, the total horizontal phase advance.
+    pub q1: f64,
+    /// Vertical tune, the total vertical phase advance.
+    pub q2: f64,
+    /// Length of the machine, the elements are distributed evenly.
+    pub length: f64,
+    /// Relative gaussian noise applied to the beta functions.
+    pub noise: f64,
+    /// Seed of the random number generator, the same spec always gives the same frame.
+    pub seed: u64,
+}
+
+impl Default for FrameSpec {
+    fn default() -> Self {
+        FrameSpec {
+            n_elements: 10,
+            q1: 62.31,
+            q2: 60.32,
+            length: 26658.8832,
+            noise: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Generates a twiss-like frame with the columns `NAME`, `KEYWORD`, `S`, `BETX`, `BETY`, `ALFX`,
+/// `ALFY`, `MUX`, `MUY`, `DX` and the headers `TYPE`, `SEQUENCE`, `LENGTH`, `Q1` and `Q2`.
+///
+/// Elements alternate between focusing quadrupoles (`MQ.F*`) and BPMs (`BPM.*`), the beta
+/// functions oscillate around the values of a smooth lattice with the given tunes.
+pub fn make_frame(spec: &FrameSpec) -> TfsDataFrame<f64> {
+    let n = spec.n_elements;
+    let mut rng = StdRng::seed_from_u64(spec.seed);
+    let noise = Normal::new(0.0, spec.noise).expect("noise level has to be finite and positive");
+
+    let mut names = Vec::with_capacity(n);
+    let mut keywords = Vec::with_capacity(n);
+    let mut s = Vec::with_capacity(n);
+    let mut betx = Vec::with_capacity(n);
+    let mut bety = Vec::with_capacity(n);
+    let mut alfx = Vec::with_capacity(n);
+    let mut alfy = Vec::with_capacity(n);
+    let mut mux = Vec::with_capacity(n);
+    let mut muy = Vec::with_capacity(n);
+    let mut dx = Vec::with_capacity(n);
+
+    // mean beta of a smooth lattice: L / (2 pi Q)
+    let betx_mean = spec.length / (2.0 * PI * spec.q1);
+    let bety_mean = spec.length / (2.0 * PI * spec.q2);
+
+    for i in 0..n {
+        let frac = (i + 1) as f64 / n as f64;
+        // phase of the beta beating, one period per pair of elements
+        let phase = PI * i as f64;
+
+        if i % 2 == 0 {
+            names.push(format!("MQ.F{}", i / 2 + 1));
+            keywords.push("QUADRUPOLE".to_owned());
+        } else {
+            names.push(format!("BPM.{}", i / 2 + 1));
+            keywords.push("MONITOR".to_owned());
+        }
+        s.push(frac * spec.length);
+        betx.push(betx_mean * (1.0 + 0.5 * phase.cos()) * (1.0 + noise.sample(&mut rng)));
+        bety.push(bety_mean * (1.0 - 0.5 * phase.cos()) * (1.0 + noise.sample(&mut rng)));
+        alfx.push(0.5 * phase.sin());
+        alfy.push(-0.5 * phase.sin());
+        mux.push(frac * spec.q1);
+        muy.push(frac * spec.q2);
+        dx.push(1.5 + 0.5 * phase.cos());
+    }
+
+    let df = DataFrame::new(vec![
+        Series::new("NAME".into(), names).into(),
+        Series::new("KEYWORD".into(), keywords).into(),
+        Series::new("S".into(), s).into(),
+        Series::new("BETX".into(), betx).into(),
+        Series::new("BETY".into(), bety).into(),
+        Series::new("ALFX".into(), alfx).into(),
+        Series::new("ALFY".into(), alfy).into(),
+        Series::new("MUX".into(), mux).into(),
+        Series::new("MUY".into(), muy).into(),
+        Series::new("DX".into(), dx).into(),
+    ])
+    .expect("all columns have the same length");
+
+    let mut properties = Properties::new();
+    properties.insert("TYPE".to_owned(), DataValue::Text("TWISS".to_owned()));
+    properties.insert("SEQUENCE".to_owned(), DataValue::Text("SYNTH".to_owned()));
+    properties.insert("LENGTH".to_owned(), DataValue::Real(spec.length));
+    properties.insert("Q1".to_owned(), DataValue::Real(spec.q1));
+    properties.insert("Q2".to_owned(), DataValue::Real(spec.q2));
+
+    TfsDataFrame::new(properties, df)
+}
+
+/// Writes `df` to a temporary file that is deleted when the returned handle is dropped.
+pub fn write_temp<T>(df: &TfsDataFrame<T>) -> Result<NamedTempFile, PolarsError>
+where
+    T: std::str::FromStr + NumericNative + fmt::Display,
+{
+    let file = NamedTempFile::new()?;
+    df.write(file.path())?;
+    Ok(file)
+}
+
+/// Writes `df` to a temporary file and reads it back.
+pub fn roundtrip<T>(df: &TfsDataFrame<T>) -> Result<TfsDataFrame<T>, PolarsError>
+where
+    T: std::str::FromStr + NumericNative + fmt::Display,
+    <T as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    let file = write_temp(df)?;
+    TfsDataFrame::open(file.path())
+}
+
+/// Writes `df` to a temporary file, reads it back and fails unless every real, in the header
+/// and the columns, reads back to the same bits. `NaN`s only need to read back as `NaN`, their
+/// payload isn't written. See [`assert_exact_roundtrip!`](crate::assert_exact_roundtrip).
+pub fn check_exact_roundtrip<T>(df: &TfsDataFrame<T>) -> anyhow::Result<()>
+where
+    T: std::str::FromStr + NumericNative + fmt::Display,
+    <T as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    let read = roundtrip(df)?;
+    // the shortest text of a real differs exactly if its bits differ
+    for (key, value) in &df.properties {
+        let read_value = read.properties.get(key).map(|v| v.to_string());
+        anyhow::ensure!(
+            read_value.as_deref() == Some(value.to_string().as_str()),
+            "header '{}' is {} instead of {}",
+            key,
+            read_value.unwrap_or_else(|| "missing".to_owned()),
+            value
+        );
+    }
+    for name in df.column_names() {
+        let column = df.column(name)?;
+        if !column.dtype().is_float() {
+            continue;
+        }
+        let expected = Option::<f64>::from_column(column)?;
+        let values = Option::<f64>::from_column(read.column(name)?)?;
+        let same = |a: f64, b: f64| a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan());
+        for (row, (expected, value)) in expected.iter().zip(&values).enumerate() {
+            let exact = match (expected, value) {
+                (Some(e), Some(v)) => same(*e, *v),
+                (e, v) => e.is_none() && v.is_none(),
+            };
+            anyhow::ensure!(
+                exact,
+                "column '{}' row {}: {:?} reads back as {:?}",
+                name,
+                row,
+                expected,
+                value
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Panics unless the reals of a frame survive being written and read back bit for bit, see
+/// [`check_exact_roundtrip`](crate::testing::check_exact_roundtrip):
+///
+/// ```
+/// # use tfs::TfsDataFrame;
+/// let df = TfsDataFrame::<f64>::new(
+///     Vec::new(),
+///     tfs::polars::df!("X" => [0.1 + 0.2, -0.0, 5e-324, f64::MAX]).unwrap(),
+/// );
+/// tfs::assert_exact_roundtrip!(df);
+/// ```
+#[macro_export]
+macro_rules! assert_exact_roundtrip {
+    ($df:expr $(,)?) => {
+        if let Err(error) = $crate::testing::check_exact_roundtrip(&$df) {
+            panic!("{:#}", error);
+        }
+    };
+}
+
+/// The environment variable that makes [`compare_snapshot`] write the expected files.
+pub const UPDATE_SNAPSHOTS: &str = "TFS_UPDATE_SNAPSHOTS";
+
+/// Number of differences listed by [`compare_snapshot`].
+const MAX_DIFFERENCES: usize = 10;
+
+/// Compares `df` with the tfs file `expected`: the header, the columns and their values, real
+/// numbers up to the relative tolerance `rtol`. Fails with a list of the differences.
+///
+/// If the environment variable [`UPDATE_SNAPSHOTS`] is set to anything but `0`, `df` is written
+/// to `expected` instead.
+pub fn compare_snapshot<T, P>(df: &TfsDataFrame<T>, expected: P, rtol: f64) -> anyhow::Result<()>
+where
+    T: std::str::FromStr + NumericNative + fmt::Display,
+    <T as std::str::FromStr>::Err: std::fmt::Debug,
+    P: AsRef<Path>,
+{
+    let path = expected.as_ref();
+    if std::env::var(UPDATE_SNAPSHOTS).is_ok_and(|v| !v.is_empty() && v != "0") {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        df.write(path)?;
+        return Ok(());
+    }
+    anyhow::ensure!(
+        path.exists(),
+        "the expected file {} doesn't exist, run with {}=1 to create it",
+        path.display(),
+        UPDATE_SNAPSHOTS
+    );
+    let expected = TfsDataFrame::<T>::open(path)?;
+
+    let close =
+        |a: f64, b: f64| (a.is_nan() && b.is_nan()) || (a - b).abs() <= rtol * a.abs().max(b.abs());
+    let mut differences = Vec::new();
+    for (key, value) in &expected.properties {
+        let equal = match (df.properties.get(key), value) {
+            (None, _) => {
+                differences.push(format!("header entry {} is missing", key));
+                continue;
+            }
+            (Some(DataValue::Real(a)), DataValue::Real(b)) => close(
+                a.to_f64().unwrap_or(f64::NAN),
+                b.to_f64().unwrap_or(f64::NAN),
+            ),
+            (Some(actual), value) => actual == value,
+        };
+        if !equal {
+            differences.push(format!(
+                "header entry {} is {}, expected {}",
+                key, df.properties[key], value
+            ));
+        }
+    }
+    for key in df.properties.keys() {
+        if !expected.properties.contains_key(key) {
+            differences.push(format!("header entry {} is unexpected", key));
+        }
+    }
+
+    let (names, expected_names) = (df.column_names(), expected.column_names());
+    if names != expected_names {
+        differences.push(format!(
+            "the columns are {:?}, expected {:?}",
+            names, expected_names
+        ));
+    } else if df.len() != expected.len() {
+        differences.push(format!(
+            "the frame has {} rows, expected {}",
+            df.len(),
+            expected.len()
+        ));
+    } else {
+        for name in names {
+            let (actual, wanted) = (df.column(name)?, expected.column(name)?);
+            if actual.dtype().is_primitive_numeric() && wanted.dtype().is_primitive_numeric() {
+                let actual = Option::<f64>::from_column(actual)?;
+                let wanted = Option::<f64>::from_column(wanted)?;
+                for (row, (a, b)) in actual.iter().zip(&wanted).enumerate() {
+                    let equal = match (a, b) {
+                        (Some(a), Some(b)) => close(*a, *b),
+                        (a, b) => a == b,
+                    };
+                    if !equal {
+                        differences.push(format!("{}[{}] is {:?}, expected {:?}", name, row, a, b));
+                    }
+                }
+            } else {
+                let actual = Option::<String>::from_column(&actual.cast(&DataType::String)?)?;
+                let wanted = Option::<String>::from_column(&wanted.cast(&DataType::String)?)?;
+                for (row, (a, b)) in actual.iter().zip(&wanted).enumerate() {
+                    if a != b {
+                        differences.push(format!("{}[{}] is {:?}, expected {:?}", name, row, a, b));
+                    }
+                }
+            }
+        }
+    }
+
+    if differences.is_empty() {
+        return Ok(());
+    }
+    let count = differences.len();
+    differences.truncate(MAX_DIFFERENCES);
+    if count > MAX_DIFFERENCES {
+        differences.push(format!("... and {} more", count - MAX_DIFFERENCES));
+    }
+    anyhow::bail!(
+        "the frame differs from {} in {} places:\n  {}",
+        path.display(),
+        count,
+        differences.join("\n  ")
+    )
+}
+
+/// Asserts that a frame equals the tfs file at a path, real numbers up to an optional relative
+/// tolerance `rtol`, see [`compare_snapshot`]. With the environment variable
+/// `TFS_UPDATE_SNAPSHOTS=1` the file is written instead.
+///
+/// ```no_run
+/// # let df = tfs::testing::make_frame(&Default::default());
+/// tfs::assert_tfs_eq!(df, "expected.tfs");
+/// tfs::assert_tfs_eq!(df, "expected.tfs", rtol = 1e-9);
+/// ```
+#[macro_export]
+macro_rules! assert_tfs_eq {
+    ($df:expr, $expected:expr $(,)?) => {
+        $crate::assert_tfs_eq!($df, $expected, rtol = 0.0)
+    };
+    ($df:expr, $expected:expr, rtol = $rtol:expr $(,)?) => {
+        if let Err(error) = $crate::testing::compare_snapshot(&$df, $expected, $rtol) {
+            panic!("{:#}", error);
+        }
+    };
+}
+
+/// Generates a random frame for property tests: up to `max_columns` real, integer and text
+/// columns with up to `max_rows` rows, and header entries of each kind. Reals include `NaN`,
+/// integers missing values, texts in columns are empty or single words, so that every generated
+/// frame can be written and read back unchanged.
+pub fn arbitrary_frame<R: Rng>(
+    rng: &mut R,
+    max_rows: usize,
+    max_columns: usize,
+) -> TfsDataFrame<f64> {
+    let rows = rng.random_range(0..=max_rows);
+    let word = |rng: &mut R| -> String {
+        let len = rng.random_range(0..8);
+        (0..len)
+            .map(|_| *b"ABCXYZ019._-".choose(rng).unwrap() as char)
+            .collect()
+    };
+    let real = |rng: &mut R| -> f64 {
+        match rng.random_range(0..10) {
+            0 => f64::NAN,
+            1 => 0.0,
+            2 => rng.random_range(-1e300..1e300),
+            _ => rng.random_range(-1e3..1e3),
+        }
+    };
+
+    let mut properties = Properties::new();
+    for i in 0..rng.random_range(0..6) {
+        let value = match rng.random_range(0..3) {
+            0 => DataValue::Real(real(rng)),
+            1 => DataValue::Integer(rng.random()),
+            _ => {
+                let words: Vec<String> = (0..3).map(|_| word(rng)).collect();
+                DataValue::Text(
+                    words
+                        .join(" ")
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                )
+            }
+        };
+        properties.insert(format!("KEY{}", i), value);
+    }
+
+    let mut columns = Vec::new();
+    for i in 0..rng.random_range(1..=max_columns.max(1)) {
+        let name = format!("C{}", i);
+        columns.push(match rng.random_range(0..3) {
+            0 => Series::new(
+                name.into(),
+                (0..rows).map(|_| real(rng)).collect::<Vec<_>>(),
+            ),
+            1 => Series::new(
+                name.into(),
+                (0..rows)
+                    .map(|_| rng.random_bool(0.9).then(|| rng.random::<i64>()))
+                    .collect::<Vec<_>>(),
+            ),
+            _ => Series::new(
+                name.into(),
+                (0..rows).map(|_| word(rng)).collect::<Vec<_>>(),
+            ),
+        });
+    }
+    let columns = columns.into_iter().map(Into::into).collect();
+    TfsDataFrame::new(
+        properties,
+        DataFrame::new(columns).expect("columns of the same length"),
+    )
+}
+
+/// Damages a file like a crash, a bad copy or a careless edit would: truncates it, drops,
+/// repeats or swaps lines, drops fields or flips bytes, a few times in a row.
+pub fn corrupt<R: Rng>(rng: &mut R, bytes: &[u8]) -> Vec<u8> {
+    let mut bytes = bytes.to_vec();
+    for _ in 0..rng.random_range(1..4) {
+        let mut lines: Vec<Vec<u8>> = bytes
+            .split_inclusive(|b| *b == b'\n')
+            .map(<[u8]>::to_vec)
+            .collect();
+        let n = lines.len().max(1);
+        match rng.random_range(0..6) {
+            0 => bytes.truncate(rng.random_range(0..=bytes.len())),
+            1 if !lines.is_empty() => {
+                lines.remove(rng.random_range(0..lines.len()));
+                bytes = lines.concat();
+            }
+            2 if !lines.is_empty() => {
+                let line = lines[rng.random_range(0..lines.len())].clone();
+                lines.insert(rng.random_range(0..n), line);
+                bytes = lines.concat();
+            }
+            3 if !lines.is_empty() => {
+                lines.swap(rng.random_range(0..n), rng.random_range(0..n));
+                bytes = lines.concat();
+            }
+            4 if !lines.is_empty() => {
+                let line = &mut lines[rng.random_range(0..n)];
+                let fields: Vec<&[u8]> = line
+                    .split(|b| b.is_ascii_whitespace())
+                    .filter(|f| !f.is_empty())
+                    .collect();
+                if !fields.is_empty() {
+                    let drop = rng.random_range(0..fields.len());
+                    let mut kept = fields
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| *i != drop)
+                        .map(|(_, f)| f.to_vec())
+                        .collect::<Vec<_>>()
+                        .join(&b' ');
+                    kept.push(b'\n');
+                    *line = kept;
+                }
+                bytes = lines.concat();
+            }
+            _ if !bytes.is_empty() => {
+                let i = rng.random_range(0..bytes.len());
+                bytes[i] = *b"@*$#% \n\"-.e0z\xff".choose(rng).unwrap();
+            }
+            _ => {}
+        }
+    }
+    bytes
+}
+
+/// Parses the bytes of a tfs file, the entry point of the fuzzer.
+pub fn parse_bytes(bytes: &[u8]) -> Result<TfsDataFrame<f64>, PolarsError> {
+    TfsHeaderParser::new(Cursor::new(bytes))
+        .parse::<f64>()?
+        .finish()
+}
+
+/// Parses `iterations` corrupted versions of arbitrary frames, starting from `seed`. The parser
+/// may reject them but must not panic; returns the first input it panicked on.
+pub fn fuzz_parser(seed: u64, iterations: usize) -> Option<Vec<u8>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    for _ in 0..iterations {
+        let file = write_temp(&arbitrary_frame(&mut rng, 8, 6)).ok()?;
+        let bytes = std::fs::read(file.path()).ok()?;
+        let input = corrupt(&mut rng, &bytes);
+        if std::panic::catch_unwind(|| parse_bytes(&input)).is_err() {
+            return Some(input);
+        }
+    }
+    None
+}
+
+/// The largest frames generated by `proptest::arbitrary::any::<TfsDataFrame<f64>>()`, 8 rows and
+/// 6 columns by default.
+#[cfg(feature = "proptest")]
+#[derive(Debug, Clone, Copy)]
+pub struct FrameLimits {
+    pub max_rows: usize,
+    pub max_columns: usize,
+}
+
+#[cfg(feature = "proptest")]
+impl Default for FrameLimits {
+    fn default() -> Self {
+        FrameLimits {
+            max_rows: 8,
+            max_columns: 6,
+        }
+    }
+}
+
+/// Frames like those of [`arbitrary_frame`].
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for TfsDataFrame<f64> {
+    type Parameters = FrameLimits;
+    type Strategy = proptest::strategy::BoxedStrategy<TfsDataFrame<f64>>;
+
+    fn arbitrary_with(limits: FrameLimits) -> Self::Strategy {
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+
+        fn real() -> impl Strategy<Value = f64> {
+            prop_oneof![
+                1 => Just(f64::NAN),
+                1 => Just(0.0),
+                1 => -1e300..1e300,
+                7 => -1e3..1e3,
+            ]
+        }
+        fn word() -> impl Strategy<Value = String> {
+            "[ABCXYZ019._-]{0,7}"
+        }
+
+        let header = prop_oneof![
+            real().prop_map(DataValue::Real),
+            any::<i64>().prop_map(DataValue::Integer),
+            vec(word(), 3).prop_map(|words| {
+                let words = words.join(" ");
+                DataValue::Text(words.split_whitespace().collect::<Vec<_>>().join(" "))
+            }),
+        ];
+        let columns = move |rows: usize| {
+            let column = prop_oneof![
+                vec(real(), rows).prop_map(|values| Series::new("".into(), values)),
+                vec(proptest::option::weighted(0.9, any::<i64>()), rows)
+                    .prop_map(|values| Series::new("".into(), values)),
+                vec(word(), rows).prop_map(|values| Series::new("".into(), values)),
+            ];
+            vec(column, 1..=limits.max_columns.max(1))
+        };
+
+        (0..=limits.max_rows)
+            .prop_flat_map(move |rows| (vec(header.clone(), 0..6), columns(rows)))
+            .prop_map(|(values, columns)| {
+                let properties: Properties<f64> = values
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, value)| (format!("KEY{}", i), value))
+                    .collect();
+                let columns = columns
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, column)| column.with_name(format!("C{}", i).into()).into())
+                    .collect();
+                TfsDataFrame::new(
+                    properties,
+                    DataFrame::new(columns).expect("columns of the same length"),
+                )
+            })
+            .boxed()
+    }
+}
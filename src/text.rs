@@ -0,0 +1,167 @@
+//! Operations on text columns.
+//!
+//! Element names follow different conventions in different places, e.g. `BPM.12L1.B1` in
+//! MAD-X and `BPM.12L1.B2` for the other beam, or the names of the control system. The names
+//! can be translated in place, with a fixed mapping or with a regular expression:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use std::collections::HashMap;
+//! let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//!
+//! let mapping = HashMap::from([("MONITOR", "BPM"), ("DRIFT", "D")]);
+//! df.map_values("KEYWORD", &mapping).unwrap();
+//! df.replace_in_column("NAME", r"\.B1$", ".B2").unwrap();
+//! ```
+//!
+//! [`TfsDataFrame::text`] gives vectorized string operations on a text column, which return new
+//! columns or masks for [`TfsDataFrame::filter_mask`]:
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! let mut df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+//! let names = df.text("NAME").unwrap();
+//!
+//! let bpms = names.contains("^BPM").unwrap();
+//! let mut arc = names.extract(r"\.(\d+)[LR]\d", 1).unwrap();
+//! arc.rename("ARC".into());
+//!
+//! df.set_column(arc).unwrap();
+//! df.filter_mask(&bpms).unwrap();
+//! ```
+use polars::prelude::{NamedFrom, NumericNative};
+use polars::series::Series;
+use regex::Regex;
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Replaces the values of the text column `column` that are keys of `mapping` by their
+    /// values. Returns the number of replaced values, missing values are kept.
+    pub fn map_values<K, V>(
+        &mut self,
+        column: &str,
+        mapping: &HashMap<K, V>,
+    ) -> anyhow::Result<usize>
+    where
+        K: Borrow<str> + Hash + Eq,
+        V: AsRef<str>,
+    {
+        self.update_text(column, |value| {
+            mapping.get(value).map(|v| v.as_ref().to_owned())
+        })
+    }
+
+    /// Replaces the matches of the regular expression `pattern` in the text column `column` by
+    /// `replacement`, which can refer to capture groups like `$1`. Returns the number of changed
+    /// values.
+    pub fn replace_in_column(
+        &mut self,
+        column: &str,
+        pattern: &str,
+        replacement: &str,
+    ) -> anyhow::Result<usize> {
+        let regex = Regex::new(pattern)?;
+        self.update_text(column, |value| {
+            let replaced = regex.replace_all(value, replacement);
+            (replaced != value).then(|| replaced.into_owned())
+        })
+    }
+
+    /// The text column `column`, for string operations.
+    pub fn text(&self, column: &str) -> anyhow::Result<TextColumn> {
+        Ok(TextColumn {
+            name: column.to_owned(),
+            values: text_values(self.column(column)?)?,
+        })
+    }
+
+    /// Sets the values of the text column `column` for which `update` returns a new value.
+    fn update_text<F>(&mut self, column: &str, update: F) -> anyhow::Result<usize>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let values = text_values(self.column(column)?)?;
+        let mut changed = 0;
+        let values: Vec<Option<String>> = values
+            .into_iter()
+            .map(|value| {
+                let new = value.as_deref().and_then(&update);
+                changed += new.is_some() as usize;
+                new.or(value)
+            })
+            .collect();
+        self.set_column(Series::new(column.into(), values))?;
+        Ok(changed)
+    }
+}
+
+fn text_values(column: &Series) -> anyhow::Result<Vec<Option<String>>> {
+    Option::<String>::from_column(column)
+        .map_err(|_| anyhow::anyhow!("'{}' is not a text column", column.name()))
+}
+
+/// The values of a text column, see [`TfsDataFrame::text`]. The operations return columns named
+/// like this one, missing values stay missing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextColumn {
+    name: String,
+    values: Vec<Option<String>>,
+}
+
+impl TextColumn {
+    pub fn to_uppercase(&self) -> Series {
+        self.map(|value| Some(value.to_uppercase()))
+    }
+
+    pub fn to_lowercase(&self) -> Series {
+        self.map(|value| Some(value.to_lowercase()))
+    }
+
+    /// The values without `prefix`, values without it are kept.
+    pub fn strip_prefix(&self, prefix: &str) -> Series {
+        self.map(|value| Some(value.strip_prefix(prefix).unwrap_or(value).to_owned()))
+    }
+
+    /// The values without `suffix`, e.g. the beam `.B1`, values without it are kept.
+    pub fn strip_suffix(&self, suffix: &str) -> Series {
+        self.map(|value| Some(value.strip_suffix(suffix).unwrap_or(value).to_owned()))
+    }
+
+    /// Which values contain a match of the regular expression `pattern`, `false` for missing
+    /// values.
+    pub fn contains(&self, pattern: &str) -> anyhow::Result<Vec<bool>> {
+        let regex = Regex::new(pattern)?;
+        Ok(self
+            .values
+            .iter()
+            .map(|value| value.as_deref().is_some_and(|v| regex.is_match(v)))
+            .collect())
+    }
+
+    /// The capture group `group` of the first match of the regular expression `pattern`, or a
+    /// missing value if it doesn't match. Group `0` is the whole match.
+    pub fn extract(&self, pattern: &str, group: usize) -> anyhow::Result<Series> {
+        let regex = Regex::new(pattern)?;
+        anyhow::ensure!(
+            group < regex.captures_len(),
+            "'{}' has no capture group {}",
+            pattern,
+            group
+        );
+        Ok(self.map(|value| Some(regex.captures(value)?.get(group)?.as_str().to_owned())))
+    }
+
+    fn map<F: Fn(&str) -> Option<String>>(&self, f: F) -> Series {
+        let values: Vec<Option<String>> = self
+            .values
+            .iter()
+            .map(|value| value.as_deref().and_then(&f))
+            .collect();
+        Series::new(self.name.as_str().into(), values)
+    }
+}
@@ -1,14 +1,91 @@
-use polars::prelude::{DataFrame, NamedFrom, NumericNative, PolarsError};
+use chrono::NaiveDateTime;
+use polars::prelude::{
+    AnyValue, BooleanChunked, Column, DataFrame, NewChunkedArray, NumericNative, PolarsError,
+};
 use polars::series::Series;
 
-use crate::dataframe::{DataValue, DataVector, DataView, Indexer};
+use crate::compression::CompressedColumn;
+use crate::computed::VirtualColumn;
+use crate::dataframe::DataValue;
+use crate::dialect::Dialect;
+use crate::header::header_timestamp;
+use crate::join::HeaderConflict;
+use crate::lineage::{Lineage, LINEAGE_TAG};
+use crate::mask::MaskedElement;
+use crate::options::TfsReadOptions;
+use crate::parse::ParseWarning;
+use crate::paths::resolve_path;
+use crate::precision::{format_real, Precision, PrecisionLoss, RealFormat, MAX_DIGITS};
+use crate::reader::{BodyParser, ParsedHeader};
+use crate::record::{ColumnValue, TfsRecord};
+use crate::stages::TfsHeaderParser;
+use crate::stats::ColumnStats;
+use crate::timeseries::format_timestamp;
+use crate::types::ColumnKind;
+use indexmap::IndexMap;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
 use std::path::Path;
+use std::sync::RwLock;
 
 use std::fmt;
 
+/// Header key holding the number of data rows. It is optional, but if present it is checked
+/// against the rows actually read.
+pub(crate) const NROWS_KEY: &str = "NROWS";
+
+/// The header of a tfs file, in the order of the file.
+pub type Properties<T> = IndexMap<String, DataValue<T>>;
+
+/// Order in which [`TfsDataFrame`] prints its header with `Display` and `Debug`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeaderOrder {
+    /// The order of the file, new keys at the end.
+    #[default]
+    Insertion,
+    /// Sorted by key.
+    Alphabetical,
+}
+
+/// Header key of the program that wrote the file.
+const ORIGIN_KEY: &str = "ORIGIN";
+
+/// Minimum width of a column in written files.
+const COLUMN_WIDTH: usize = 24;
+
+/// Returns the last `n` non-empty lines of `file` behind the byte offset `start`, reading the file
+/// backwards in chunks.
+fn read_tail(file: &mut File, start: u64, n: usize) -> std::io::Result<Vec<String>> {
+    const CHUNK_SIZE: u64 = 64 * 1024;
+
+    let mut position = file.seek(SeekFrom::End(0))?;
+    let mut buffer: Vec<u8> = Vec::new();
+    // n + 1 newlines guarantee that the last n lines are complete
+    while position > start && buffer.iter().filter(|&&b| b == b'\n').count() <= n {
+        let chunk_start = position.saturating_sub(CHUNK_SIZE).max(start);
+        let mut chunk = vec![0; (position - chunk_start) as usize];
+        file.seek(SeekFrom::Start(chunk_start))?;
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&buffer);
+        buffer = chunk;
+        position = chunk_start;
+    }
+
+    let text = String::from_utf8_lossy(&buffer);
+    let mut lines: Vec<&str> = text.lines().collect();
+    if position > start && !lines.is_empty() {
+        lines.remove(0); // most likely only a part of a line
+    }
+    let lines: Vec<&str> = lines.into_iter().filter(|l| !l.trim().is_empty()).collect();
+    Ok(lines[lines.len().saturating_sub(n)..]
+        .iter()
+        .map(|l| l.to_string())
+        .collect())
+}
+
 /// `TfsDataFrame` is a wrapper around `polars::DataFrame` that supports the `TFS` format.
 /// A TFS file consists of a list of properties (key - value pairs) followed by a chunk of data
 /// in tabular format.
@@ -16,11 +93,54 @@ use std::fmt;
 /// The following example loads a temporary tfs file into memory and prints its data:
 ///
 pub struct TfsDataFrame<T: std::str::FromStr + polars::prelude::NumericNative> {
-    pub properties: HashMap<String, DataValue<T>>,
-    df: DataFrame,
+    pub properties: Properties<T>,
+    pub(crate) df: DataFrame,
+    pub(crate) lineage: HashMap<String, Lineage>,
+    pub(crate) warnings: Vec<ParseWarning>,
+    pub(crate) stats_cache: RwLock<HashMap<String, ColumnStats>>,
+    pub(crate) compressed: HashMap<String, CompressedColumn>,
+    pub(crate) header_order: HeaderOrder,
+    /// The type codes of the columns read from a file and how they were interpreted.
+    pub(crate) type_codes: HashMap<String, (String, ColumnKind)>,
+    /// Values written instead of `NaN`, by column.
+    pub(crate) nan_sentinels: HashMap<String, f64>,
+    /// The precision of real columns written with fewer digits, by column.
+    pub(crate) precisions: HashMap<String, Precision>,
+    pub(crate) real_format: RealFormat,
+    pub(crate) virtual_columns: IndexMap<String, VirtualColumn>,
+    pub(crate) dialect: Dialect,
+    /// The elements masked so far, see [`crate::mask`].
+    pub(crate) masked: Vec<MaskedElement>,
+    /// The header entries that differed between the frames this one was merged from.
+    pub(crate) header_conflicts: Vec<HeaderConflict<T>>,
 }
 
 impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Creates a TfsDataFrame from its header properties and a `polars::DataFrame` holding the
+    /// data.
+    pub fn new<P>(properties: P, df: DataFrame) -> TfsDataFrame<T>
+    where
+        P: IntoIterator<Item = (String, DataValue<T>)>,
+    {
+        TfsDataFrame {
+            properties: properties.into_iter().collect(),
+            df,
+            lineage: HashMap::new(),
+            warnings: Vec::new(),
+            stats_cache: RwLock::default(),
+            compressed: HashMap::new(),
+            header_order: HeaderOrder::default(),
+            type_codes: HashMap::new(),
+            nan_sentinels: HashMap::new(),
+            precisions: HashMap::new(),
+            real_format: RealFormat::default(),
+            virtual_columns: IndexMap::new(),
+            dialect: Dialect::default(),
+            masked: Vec::new(),
+            header_conflicts: Vec::new(),
+        }
+    }
+
     /// Opens a tfs file and stores the content in a TfsDataFrame. Will panic! if opening fails rather
     /// than return a `Result<>`.~
     pub fn open_expect<P>(path: P) -> TfsDataFrame<T>
@@ -31,94 +151,235 @@ impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
         TfsDataFrame::open(path).expect("couldn't open the TFS file")
     }
 
-    /// Opens a tfs file and stores the content in a TfsDataFrame.
+    /// Opens a tfs file and stores the content in a TfsDataFrame. The path may contain `~` and
+    /// environment variables and is looked up in `TFS_PATH`, see [`crate::paths`].
     pub fn open<P>(path: P) -> Result<TfsDataFrame<T>, PolarsError>
     where
         P: AsRef<Path>,
         <T as std::str::FromStr>::Err: std::fmt::Debug,
     {
-        let mut reader = BufReader::new(File::open(path.as_ref())?).lines();
-
-        let mut properties = HashMap::new();
-        let mut colnames = vec![];
-        let mut coltypes = vec![];
-
-        loop {
-            let line = reader.next().unwrap().unwrap();
-            let mut line_it = line.split_whitespace();
-
-            match line_it.next().unwrap() {
-                "*" => colnames.extend(line_it.map(|x| String::from(x))),
-                "$" => coltypes.extend(line_it.map(|x| String::from(x))),
-                "@" => {
-                    let name = String::from(line_it.next().unwrap());
-                    match line_it.next().unwrap() {
-                        "%le" => properties.insert(
-                            name,
-                            DataValue::Real(
-                                line_it
-                                    .next()
-                                    .unwrap()
-                                    .parse()
-                                    .expect("should be a valid property"),
-                            ),
-                        ),
-                        _ => properties.insert(name, DataValue::Text(line_it.collect())),
-                    };
-                }
-                _ => {}
+        TfsDataFrame::open_with(path, &TfsReadOptions::default())
+    }
+
+    /// Opens a tfs file like [`TfsDataFrame::open`], parsing it according to `options`.
+    pub fn open_with<P>(path: P, options: &TfsReadOptions) -> Result<TfsDataFrame<T>, PolarsError>
+    where
+        P: AsRef<Path>,
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("open_tfs", path = %path.as_ref().display()).entered();
+        let path = resolve_path(path.as_ref(), &options.search_path)?;
+        let reader = BufReader::new(File::open(path)?);
+        TfsHeaderParser::with_options(reader, options.clone())
+            .parse()?
+            .finish()
+    }
+
+    /// Reads the header and only the first and last `n_rows` rows of a tfs file. The end of the
+    /// file is read backwards, so this is fast even for huge files.
+    ///
+    /// Rows are not repeated if the file has less than `2 * n_rows` rows. The `NROWS` header
+    /// entry, if present, isn't checked.
+    pub fn open_preview<P>(path: P, n_rows: usize) -> Result<TfsDataFrame<T>, PolarsError>
+    where
+        P: AsRef<Path>,
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        let mut body = TfsHeaderParser::open(path)?.parse()?;
+        let head_rows = body.read_rows(n_rows)?;
+
+        if head_rows == n_rows {
+            let position = body.position();
+            for line in read_tail(body.reader_mut().get_mut(), position, n_rows)? {
+                body.parse_row(&line)?;
             }
-            if colnames.len() > 0 && coltypes.len() > 0 {
-                break; // we have parsed the header, pass on to reading the data lines
+        }
+
+        body.into_frame()
+    }
+
+    pub(crate) fn from_parsed(
+        header: ParsedHeader<T>,
+        body: BodyParser,
+    ) -> Result<TfsDataFrame<T>, PolarsError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("build_series", rows = body.rows()).entered();
+        Ok(TfsDataFrame {
+            properties: header.properties,
+            type_codes: body.type_codes(),
+            nan_sentinels: body.write_sentinels(),
+            df: DataFrame::new(body.finish())?,
+            lineage: header.lineage,
+            warnings: header.warnings,
+            stats_cache: RwLock::default(),
+            compressed: HashMap::new(),
+            header_order: HeaderOrder::default(),
+            precisions: HashMap::new(),
+            real_format: RealFormat::default(),
+            virtual_columns: IndexMap::new(),
+            dialect: header.dialect,
+            masked: Vec::new(),
+            header_conflicts: Vec::new(),
+        })
+    }
+
+    /// Writes the content of the TfsDataFrame to a tfs file.
+    ///
+    /// If the header contains an `NROWS` entry, it is written with the current number of rows.
+    pub fn write<P>(&self, path: P) -> Result<(), PolarsError>
+    where
+        P: AsRef<Path>,
+        T: fmt::Display,
+    {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("write_tfs", path = %path.as_ref().display()).entered();
+        self.write_to(BufWriter::new(File::create(path.as_ref())?))
+    }
+
+    /// Writes the frame in the tfs format to `writer`, see [`TfsDataFrame::write`].
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), PolarsError>
+    where
+        T: fmt::Display,
+    {
+        self.write_frame(writer).map(|_| ())
+    }
+
+    /// Writes the frame to `writer`, returns the columns that lost precision, see
+    /// [`TfsDataFrame::precision_losses`].
+    pub(crate) fn write_frame<W: Write>(
+        &self,
+        mut writer: W,
+    ) -> Result<Vec<PrecisionLoss>, PolarsError>
+    where
+        T: fmt::Display,
+    {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("write_frame", rows = self.len()).entered();
+        for (key, value) in &self.properties {
+            match value {
+                DataValue::Integer(_) if key == NROWS_KEY => {
+                    writeln!(writer, "@ {:<16} %d {}", key, self.len())?
+                }
+                value => {
+                    let (code, value) = self.dialect.format_value(value);
+                    writeln!(writer, "@ {:<16} {} {}", key, code, value)?
+                }
             }
         }
 
-        let mut columns: Vec<DataVector<f64>> = vec![];
+        let mut derived: Vec<_> = self.lineage.iter().collect();
+        derived.sort_by_key(|(name, _)| *name);
+        for (name, lineage) in derived {
+            writeln!(writer, "# {} {} {}", LINEAGE_TAG, name, lineage)?;
+        }
+
+        let full_df = self
+            .df()
+            .map_err(|err| PolarsError::ComputeError(err.to_string().into()))?;
+        let columns = full_df.get_columns();
+        let widths: Vec<usize> = columns
+            .iter()
+            .map(|c| c.name().len().max(COLUMN_WIDTH))
+            .collect();
 
-        // setup columns
-        for (ia, ib) in colnames.iter().zip(coltypes.iter()) {
-            match ib.as_ref() {
-                "%le" => columns.push(DataVector::RealVector(Vec::new())),
-                _ => columns.push(DataVector::TextVector(Vec::new())),
+        write!(writer, "*")?;
+        for (column, width) in columns.iter().zip(&widths) {
+            write!(writer, " {:>width$}", column.name().as_str(), width = width)?;
+        }
+        write!(writer, "\n$")?;
+        for (column, width) in columns.iter().zip(&widths) {
+            let kind = match ColumnKind::of_dtype(column.dtype()) {
+                ColumnKind::Boolean if !self.dialect.has_booleans() => ColumnKind::Integer,
+                kind => kind,
+            };
+            let coltype = match self.type_codes.get(column.name().as_str()) {
+                Some((code, read_as)) if *read_as == kind => code.as_str(),
+                _ => kind.canonical_code(),
             };
+            write!(writer, " {:>width$}", coltype, width = width)?;
         }
+        writeln!(writer)?;
 
-        for line in reader {
-            if let Ok(l) = line {
-                let line_it = l.split_whitespace();
-                for (idata, icolumn) in line_it.into_iter().zip(columns.iter_mut()) {
-                    match icolumn {
-                        DataVector::RealVector(ref mut vec) => {
-                            vec.push((*idata).parse().unwrap_or(f64::NAN))
-                        }
-                        DataVector::TextVector(ref mut vec) => {
-                            vec.push(String::from(idata).trim_matches('\"').to_owned())
-                        }
+        let sentinels: Vec<Option<f64>> = columns
+            .iter()
+            .map(|c| self.nan_sentinels.get(c.name().as_str()).copied())
+            .collect();
+        let names: Vec<&str> = columns.iter().map(|c| c.name().as_str()).collect();
+        let (digits, losses) = self
+            .written_digits(&names)
+            .map_err(|err| PolarsError::ComputeError(err.to_string().into()))?;
+        for row in 0..self.len() {
+            write!(writer, " ")?;
+            for (((column, width), sentinel), digits) in
+                columns.iter().zip(&widths).zip(&sentinels).zip(&digits)
+            {
+                match column.get(row)? {
+                    AnyValue::Float64(v) => {
+                        let v = match sentinel {
+                            Some(sentinel) if v.is_nan() => *sentinel,
+                            _ => v,
+                        };
+                        let v = match (digits, self.real_format) {
+                            (None, RealFormat::Shortest) => ryu::Buffer::new().format(v).to_owned(),
+                            (digits, _) => format_real(v, digits.unwrap_or(MAX_DIGITS)),
+                        };
+                        write!(writer, " {:>width$}", v, width = width)?
                     }
+                    AnyValue::String(t) => {
+                        write!(writer, " {:>width$}", format!("\"{}\"", t), width = width)?
+                    }
+                    AnyValue::Boolean(b) if !self.dialect.has_booleans() => {
+                        write!(writer, " {:>width$}", b as i64, width = width)?
+                    }
+                    AnyValue::Datetime(v, unit, _) => {
+                        let timestamp = format_timestamp(v, unit);
+                        write!(writer, " {:>width$}", timestamp, width = width)?
+                    }
+                    v => write!(writer, " {:>width$}", v, width = width)?,
                 }
             }
+            writeln!(writer)?;
         }
 
-        let mut serieses: Vec<Series> = vec![];
+        writer.flush()?;
+        Ok(losses)
+    }
 
-        for (name, column) in colnames.iter().zip(columns) {
-            match column {
-                DataVector::TextVector(v) => serieses.push(Series::new(name, &v)),
-                DataVector::RealVector(v) => serieses.push(Series::new(name, v)),
-            };
-        }
+    /// Sets the `NROWS` header entry to the current number of rows.
+    pub fn set_nrows(&mut self) {
+        self.properties
+            .insert(NROWS_KEY.to_owned(), DataValue::Integer(self.len() as i64));
+    }
 
-        Ok(TfsDataFrame {
-            properties,
-            df: DataFrame::new(serieses)?,
-        })
+    /// Checks that all columns have the same length and that the `NROWS` header entry, if
+    /// present, matches the number of rows.
+    pub fn is_consistent(&self) -> bool {
+        let height = self.len();
+        let nrows_matches = match self.properties.get(NROWS_KEY) {
+            Some(DataValue::Integer(nrows)) => usize::try_from(*nrows).ok() == Some(height),
+            Some(_) => false,
+            None => true,
+        };
+
+        nrows_matches && self.df.get_columns().iter().all(|c| c.len() == height)
+    }
+
+    /// Values that were only accepted because of lenient read options, see [`TfsReadOptions`].
+    pub fn parse_warnings(&self) -> &[ParseWarning] {
+        &self.warnings
     }
 
     pub fn len(&self) -> usize {
         self.df.height()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.df.height() == 0
+    }
+
     /// Returns the property `key` from the header if it is a data value, otherwise it panics.
+    /// Use [`TfsDataFrame::try_propd`] for files that may lack the entry.
     pub fn propd(&self, key: &str) -> &T {
         if let DataValue::Real(ref v) = self.properties[key] {
             return v;
@@ -130,6 +391,7 @@ impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
     }
 
     /// Returns the property `key` from the header if it is a string, otherwise it panics.
+    /// Use [`TfsDataFrame::try_props`] for files that may lack the entry.
     pub fn props(&self, key: &str) -> &String {
         if let DataValue::Text(ref t) = self.properties[key] {
             return t;
@@ -140,16 +402,335 @@ impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
         );
     }
 
+    /// Returns the property `key` from the header if it is a data value, or an error if it is
+    /// missing or of another kind.
+    pub fn try_propd(&self, key: &str) -> anyhow::Result<&T> {
+        match self.properties.get(key) {
+            Some(DataValue::Real(v)) => Ok(v),
+            Some(value) => {
+                anyhow::bail!("the header entry '{}' is {}, not a real value", key, value)
+            }
+            None => anyhow::bail!("the header has no entry '{}'", key),
+        }
+    }
+
+    /// Returns the property `key` from the header if it is a string, or an error if it is
+    /// missing or of another kind.
+    pub fn try_props(&self, key: &str) -> anyhow::Result<&String> {
+        match self.properties.get(key) {
+            Some(DataValue::Text(t)) => Ok(t),
+            Some(value) => anyhow::bail!("the header entry '{}' is {}, not a string", key, value),
+            None => anyhow::bail!("the header has no entry '{}'", key),
+        }
+    }
+
+    /// Returns the creation time of the file from the `DATE` and `TIME` headers, or `None` if
+    /// they are missing or can't be parsed. Dates are accepted as `dd/mm/yy` (MAD-X),
+    /// `dd/mm/yyyy`, `yyyy-mm-dd` and `dd.mm.yyyy`, times as `hh.mm.ss` (MAD-X) and `hh:mm:ss`.
+    /// Without a `TIME` header the time is midnight.
+    ///
+    /// ```
+    /// # use tfs::{DataValue, TfsDataFrame};
+    /// # use tfs::polars::prelude::DataFrame;
+    /// let df = TfsDataFrame::<f64>::new(
+    ///     vec![
+    ///         ("DATE".to_owned(), DataValue::Text("01/05/24".to_owned())),
+    ///         ("TIME".to_owned(), DataValue::Text("10.30.00".to_owned())),
+    ///     ],
+    ///     DataFrame::empty(),
+    /// );
+    /// assert_eq!(df.timestamp().unwrap().to_string(), "2024-05-01 10:30:00");
+    /// ```
+    pub fn timestamp(&self) -> Option<NaiveDateTime> {
+        header_timestamp(&self.properties)
+    }
+
+    /// Returns the program that wrote the file, the `ORIGIN` header (e.g. `5.07.00 Linux 64` for
+    /// MAD-X).
+    pub fn origin(&self) -> Option<&str> {
+        match self.properties.get(ORIGIN_KEY) {
+            Some(DataValue::Text(t)) => Some(t.trim()),
+            _ => None,
+        }
+    }
+
+    /// The dialect the frame is written in: the one of the file it was read from, otherwise
+    /// MAD-X.
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    /// Sets the dialect the frame is written in, see [`Dialect`].
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect;
+    }
+
+    /// Sets the value written instead of `NaN` in the real column `column`, or writes `NaN` again
+    /// if `sentinel` is `None`. Frames read with NaN sentinels (see
+    /// [`TfsReadOptions::nan_sentinel`]) write them back by default.
+    pub fn set_nan_sentinel(&mut self, column: &str, sentinel: Option<f64>) {
+        match sentinel {
+            Some(sentinel) => self.nan_sentinels.insert(column.to_owned(), sentinel),
+            None => self.nan_sentinels.remove(column),
+        };
+    }
+
+    /// Returns the header entries sorted by key.
+    pub fn sorted_properties(&self) -> Vec<(&String, &DataValue<T>)> {
+        let mut properties: Vec<_> = self.properties.iter().collect();
+        properties.sort_by_key(|(key, _)| *key);
+        properties
+    }
+
+    /// Sets the order in which `Display` and `Debug` print the header, so that the printed
+    /// output is stable, e.g. for snapshot tests.
+    pub fn set_header_order(&mut self, order: HeaderOrder) {
+        self.header_order = order;
+    }
+
+    fn ordered_properties(&self) -> Vec<(&String, &DataValue<T>)> {
+        match self.header_order {
+            HeaderOrder::Insertion => self.properties.iter().collect(),
+            HeaderOrder::Alphabetical => self.sorted_properties(),
+        }
+    }
+
+    /// Names of all columns in order, including the compressed ones.
+    pub fn column_names(&self) -> Vec<&str> {
+        let mut names = self.df.get_column_names_str();
+        let mut compressed: Vec<_> = self.compressed.iter().collect();
+        compressed.sort_by_key(|(_, c)| c.position);
+        for (name, column) in compressed {
+            names.insert(column.position.min(names.len()), name.as_str());
+        }
+        names
+    }
+
     pub fn column_count(&self) -> usize {
-        self.df.width()
+        self.df.width() + self.compressed.len()
     }
 
+    /// Returns the column `name`. Compressed columns are decompressed and virtual columns
+    /// computed on first access.
     pub fn column(&self, name: &str) -> anyhow::Result<&Series> {
-        Ok(self.df.column(name)?)
+        if let Some(compressed) = self.compressed.get(name) {
+            return compressed.series(name);
+        }
+        if let Some(column) = self.virtual_columns.get(name) {
+            return column.series(name, self);
+        }
+        Ok(self.df.column(name)?.as_materialized_series())
     }
 
-    pub fn df(&self) -> &DataFrame {
-        &self.df
+    /// The columns, without virtual columns. Compressed columns are decompressed into a copy,
+    /// which is dropped with the result, the frame is borrowed if nothing is compressed.
+    pub fn df(&self) -> anyhow::Result<Cow<'_, DataFrame>> {
+        if self.compressed.is_empty() {
+            return Ok(Cow::Borrowed(&self.df));
+        }
+        let mut compressed: Vec<_> = self.compressed.iter().collect();
+        compressed.sort_by_key(|(_, c)| c.position);
+
+        let mut df = self.df.clone();
+        for (name, column) in compressed {
+            let position = column.position.min(df.width());
+            df.insert_column(position, column.decompress(name)?)?;
+        }
+        Ok(Cow::Owned(df))
+    }
+
+    /// Splits the frame into its columns and its header, moving them instead of copying, e.g.
+    /// to hand the columns to other polars code. Compressed columns are decompressed, virtual
+    /// columns, lineage and how the frame is written are dropped.
+    ///
+    /// ```
+    /// # use tfs::TfsDataFrame;
+    /// let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+    /// let (polars, header) = df.into_polars().unwrap();
+    /// let doubled = polars.column("BETX").unwrap().as_materialized_series() * 2.0;
+    /// assert_eq!(doubled.len(), 5);
+    ///
+    /// let df = TfsDataFrame::<f64>::from_polars(polars, header);
+    /// assert_eq!(df.properties["TYPE"], tfs::DataValue::Text("TWISS".to_owned()));
+    /// ```
+    pub fn into_polars(mut self) -> anyhow::Result<(DataFrame, Properties<T>)> {
+        self.decompress_columns()?;
+        Ok((self.df, self.properties))
+    }
+
+    /// Builds a frame from polars columns and a header without copying them, the inverse of
+    /// [`TfsDataFrame::into_polars`].
+    pub fn from_polars(df: DataFrame, properties: Properties<T>) -> TfsDataFrame<T> {
+        TfsDataFrame::new(properties, df)
+    }
+
+    /// Replaces the column with the same name as `series`, or appends it if there is no such
+    /// column.
+    ///
+    /// The lineage of a replaced column and the cached statistics are dropped. A replaced
+    /// compressed column is stored uncompressed at the same position, a replaced virtual column
+    /// is removed. The values of the virtual columns are computed again.
+    pub fn set_column(&mut self, series: Series) -> anyhow::Result<()> {
+        let name = series.name().as_str();
+        self.lineage.remove(name);
+        self.virtual_columns.shift_remove(name);
+        self.clear_virtual();
+
+        if self.compressed.contains_key(name) {
+            // keep the column at its place among the uncompressed columns
+            let index = self
+                .column_names()
+                .into_iter()
+                .take_while(|c| *c != name)
+                .filter(|c| !self.compressed.contains_key(*c))
+                .count();
+            let name = name.to_owned();
+            self.df_mut().insert_column(index, series)?;
+            self.compressed.remove(&name);
+        } else {
+            self.df_mut().with_column(series)?;
+        }
+        Ok(())
+    }
+
+    /// Inserts the column `name` with `values` at position `index`, the columns from there on
+    /// move one to the right. Fails if there already is a column `name`, if `index` is past the
+    /// last column or if the number of values differs from the rows. Compressed columns are
+    /// decompressed, a virtual column `name` is removed.
+    pub fn insert_column_at<V: ColumnValue>(
+        &mut self,
+        index: usize,
+        name: &str,
+        values: Vec<V>,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !self.column_names().contains(&name),
+            "there already is a column '{}'",
+            name
+        );
+        anyhow::ensure!(
+            index <= self.column_count(),
+            "position {} is past the {} columns",
+            index,
+            self.column_count()
+        );
+        self.decompress_columns()?;
+        self.df_mut()
+            .insert_column(index, V::to_column(name, values))?;
+        self.virtual_columns.shift_remove(name);
+        self.clear_virtual();
+        Ok(())
+    }
+
+    /// Moves the column `name` to position `index`, e.g. `0` to write it first. Compressed
+    /// columns are decompressed.
+    pub fn move_column(&mut self, name: &str, index: usize) -> anyhow::Result<()> {
+        let mut names: Vec<String> = self.column_names().into_iter().map(String::from).collect();
+        let position = names
+            .iter()
+            .position(|c| c == name)
+            .ok_or_else(|| anyhow::anyhow!("there is no column '{}'", name))?;
+        anyhow::ensure!(
+            index < names.len(),
+            "position {} is past the {} columns",
+            index,
+            names.len()
+        );
+        self.decompress_columns()?;
+        let name = names.remove(position);
+        names.insert(index, name);
+        *self.df_mut() = self.df.select(names)?;
+        Ok(())
+    }
+
+    /// The columns, for changing them. Every change of the values goes through here, which
+    /// drops the cached statistics.
+    pub(crate) fn df_mut(&mut self) -> &mut DataFrame {
+        self.stats_cache.get_mut().unwrap().clear();
+        &mut self.df
+    }
+
+    /// Keeps the rows for which `keep` is `true`. Compressed columns are decompressed.
+    pub(crate) fn retain_rows(&mut self, keep: &[bool]) -> anyhow::Result<()> {
+        let mask = BooleanChunked::from_slice("mask".into(), keep);
+        self.decompress_columns()?;
+        *self.df_mut() = self.df.filter(&mask)?;
+        self.clear_virtual();
+        Ok(())
+    }
+
+    /// Creates a frame with the header and lineage of this one and the data `df`, e.g. a subset
+    /// of the rows.
+    pub(crate) fn with_rows(&self, df: DataFrame) -> TfsDataFrame<T> {
+        let mut frame = TfsDataFrame::new(self.properties.clone(), df);
+        frame.lineage = self.lineage.clone();
+        frame.header_order = self.header_order;
+        frame.type_codes = self.type_codes.clone();
+        frame.nan_sentinels = self.nan_sentinels.clone();
+        frame.precisions = self.precisions.clone();
+        frame.real_format = self.real_format;
+        frame.virtual_columns = self.virtual_columns.clone();
+        frame.dialect = self.dialect;
+        frame.masked = self.masked.clone();
+        frame.header_conflicts = self.header_conflicts.clone();
+        frame
+    }
+
+    /// The rows `rows` of the columns `cols`, with the header of this frame. The view shares
+    /// the values with this frame instead of copying them, so that statistics or fits can run
+    /// on a part of a large frame:
+    ///
+    /// ```
+    /// # use tfs::TfsDataFrame;
+    /// let df = TfsDataFrame::<f64>::open("test/test.tfs").unwrap();
+    /// let arc = df.view(1..4, &["NAME", "BETX"]).unwrap();
+    /// assert_eq!((arc.len(), arc.column_names()), (3, vec!["NAME", "BETX"]));
+    /// assert_eq!(arc.properties, df.properties);
+    /// ```
+    ///
+    /// Virtual columns in `cols` are computed, the view has no virtual columns of its own.
+    pub fn view(&self, rows: Range<usize>, cols: &[&str]) -> anyhow::Result<TfsDataFrame<T>> {
+        anyhow::ensure!(
+            rows.start <= rows.end && rows.end <= self.len(),
+            "rows {}..{} are out of the {} rows of the frame",
+            rows.start,
+            rows.end,
+            self.len()
+        );
+        let columns = cols
+            .iter()
+            .map(|name| {
+                let column = self.column(name)?;
+                Ok(column.slice(rows.start as i64, rows.len()).into())
+            })
+            .collect::<anyhow::Result<Vec<Column>>>()?;
+        let mut frame = self.with_rows(DataFrame::new(columns)?);
+        frame
+            .lineage
+            .retain(|name, _| cols.contains(&name.as_str()));
+        frame.virtual_columns.clear();
+        Ok(frame)
+    }
+
+    /// Reads all rows as records of type `R`, see [`TfsRecord`].
+    pub fn records<R: TfsRecord>(&self) -> anyhow::Result<Vec<R>> {
+        let columns = R::columns()
+            .into_iter()
+            .map(|name| self.column(name))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        R::from_series(&columns)
+    }
+
+    /// Creates a TfsDataFrame with an empty header from a list of records, see [`TfsRecord`].
+    pub fn from_records<R: TfsRecord>(records: &[R]) -> anyhow::Result<TfsDataFrame<T>> {
+        let columns = R::to_series(records)
+            .into_iter()
+            .map(Column::from)
+            .collect();
+        Ok(TfsDataFrame::new(
+            Properties::new(),
+            DataFrame::new(columns)?,
+        ))
     }
 }
 
@@ -157,19 +738,35 @@ impl<T: fmt::Debug + std::str::FromStr + NumericNative> fmt::Debug for TfsDataFr
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!("TfsDataFrame [{} rows]{{\n", self.len()))?;
         f.write_str("Header: \n")?;
-        f.debug_map().entries(&self.properties).finish()?;
+        f.debug_map().entries(self.ordered_properties()).finish()?;
         write!(f, "{:?}", self.df)?;
         f.write_str("\n}")
     }
 }
 
+/// Prints the header and the data. The alternate form (`{:#}`) adds a footer with the minimum,
+/// mean and maximum of every numeric column, see [`TfsDataFrame::stats`].
 impl<T: fmt::Display + std::str::FromStr + NumericNative> fmt::Display for TfsDataFrame<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!("TfsDataFrame [{} rows] {{\n", self.len()))?;
-        write!(f, "Header [{}]: \n", self.properties.len())?;
-        for k in &self.properties {
+        writeln!(f, "Header [{}]: ", self.properties.len())?;
+        for k in self.ordered_properties() {
             writeln!(f, "  {:32}: {:24}", k.0, k.1)?;
         }
-        write!(f, "{}", self.df)
+        write!(f, "{}", self.df)?;
+
+        if f.alternate() {
+            write!(f, "\nStatistics:")?;
+            for name in self.column_names() {
+                if let Ok(stats) = self.stats(name) {
+                    write!(
+                        f,
+                        "\n  {:32}: min {:<14.6e} mean {:<14.6e} max {:.6e}",
+                        name, stats.min, stats.mean, stats.max
+                    )?;
+                }
+            }
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,192 @@
+//! Time-stamped tables, e.g. orbit or trim histories dumped by logging tools.
+//!
+//! Timestamps are read as strings (ISO 8601) or numbers (UNIX epoch in seconds) and converted
+//! into a datetime column with [`TfsDataFrame::parse_timestamps`]. Written files contain them as
+//! ISO 8601 strings.
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::polars::prelude::*;
+//! let mut df = TfsDataFrame::<f64>::new(
+//!     vec![],
+//!     df!(
+//!         "TIME" => ["2024-05-01T10:00:00", "2024-05-01T10:00:30", "2024-05-01T10:01:10"],
+//!         "X" => [1.0, 3.0, 5.0],
+//!     )
+//!     .unwrap(),
+//! );
+//! df.parse_timestamps("TIME").unwrap();
+//!
+//! let per_minute = df.resample("TIME", chrono::Duration::minutes(1)).unwrap();
+//! assert_eq!(per_minute.len(), 2);
+//! assert_eq!(per_minute.column("X").unwrap().f64().unwrap().get(0), Some(2.0));
+//! ```
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use polars::prelude::{BooleanChunked, DataFrame, DataType, NamedFrom, NumericNative, TimeUnit};
+use polars::series::Series;
+use std::collections::BTreeMap;
+
+use crate::record::ColumnValue;
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Time unit of the datetime columns created by [`TfsDataFrame::parse_timestamps`].
+const TIME_UNIT: TimeUnit = TimeUnit::Microseconds;
+
+/// Formats without time zone accepted by [`parse_timestamp`], RFC 3339 is tried first.
+const NAIVE_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+
+/// Parses an ISO 8601 timestamp or a UNIX epoch in seconds into microseconds since the epoch.
+/// Timestamps without time zone are taken as UTC.
+fn parse_timestamp(token: &str) -> Option<i64> {
+    if let Ok(seconds) = token.parse::<f64>() {
+        return Some((seconds * 1e6).round() as i64);
+    }
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(token) {
+        return Some(datetime.timestamp_micros());
+    }
+    NAIVE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(token, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(token, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .map(|datetime| datetime.and_utc().timestamp_micros())
+}
+
+/// Formats a datetime value as written to tfs files, ISO 8601 without spaces so that it stays a
+/// single field.
+pub(crate) fn format_timestamp(value: i64, unit: TimeUnit) -> String {
+    let micros = match unit {
+        TimeUnit::Nanoseconds => value / 1000,
+        TimeUnit::Microseconds => value,
+        TimeUnit::Milliseconds => value * 1000,
+    };
+    match DateTime::from_timestamp_micros(micros) {
+        Some(datetime) => datetime
+            .naive_utc()
+            .format("%Y-%m-%dT%H:%M:%S%.f")
+            .to_string(),
+        None => value.to_string(),
+    }
+}
+
+fn to_micros(datetime: NaiveDateTime) -> i64 {
+    datetime.and_utc().timestamp_micros()
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Converts the column `name` into a datetime column. Strings are parsed as ISO 8601
+    /// (`2024-05-01T10:00:00`, `2024-05-01T10:00:00+02:00`, `2024-05-01`) or as a UNIX epoch in
+    /// seconds, real columns are taken as UNIX epoch in seconds.
+    pub fn parse_timestamps(&mut self, name: &str) -> anyhow::Result<()> {
+        let column = self.column(name)?;
+        let micros: Vec<i64> = match column.dtype() {
+            DataType::Datetime(_, _) => return Ok(()),
+            DataType::String => String::from_column(column)?
+                .iter()
+                .map(|token| {
+                    parse_timestamp(token).ok_or_else(|| {
+                        anyhow::anyhow!("'{}' in column '{}' is not a timestamp", token, name)
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?,
+            dtype if dtype.is_primitive_numeric() => f64::from_column(column)?
+                .into_iter()
+                .map(|seconds| (seconds * 1e6).round() as i64)
+                .collect(),
+            dtype => anyhow::bail!("column '{}' of type {} has no timestamps", name, dtype),
+        };
+
+        let series = Series::new(name.into(), micros).cast(&DataType::Datetime(TIME_UNIT, None))?;
+        self.set_column(series)
+    }
+
+    /// Returns the rows with a timestamp in `[start, end)`. `column` has to be a datetime column,
+    /// see [`TfsDataFrame::parse_timestamps`].
+    pub fn between_times(
+        &self,
+        column: &str,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> anyhow::Result<TfsDataFrame<T>> {
+        let (start, end) = (to_micros(start), to_micros(end));
+        let keep = self
+            .timestamps(column)?
+            .into_iter()
+            .map(|t| t.is_some_and(|t| start <= t && t < end))
+            .collect::<Vec<_>>();
+
+        let mask = BooleanChunked::new("mask".into(), keep);
+        Ok(self.with_rows(self.df()?.filter(&mask)?))
+    }
+
+    /// Groups the rows in bins of width `bin` (aligned to the UNIX epoch) and averages the real
+    /// columns in every bin. The returned frame has the same header, the datetime column holds
+    /// the start of the bins, bins without rows are left out and other columns are dropped.
+    pub fn resample(&self, column: &str, bin: chrono::Duration) -> anyhow::Result<TfsDataFrame<T>> {
+        let width = bin
+            .num_microseconds()
+            .filter(|w| *w > 0)
+            .ok_or_else(|| anyhow::anyhow!("the bin width has to be positive"))?;
+
+        let mut bins: BTreeMap<i64, Vec<usize>> = BTreeMap::new();
+        for (row, t) in self.timestamps(column)?.into_iter().enumerate() {
+            if let Some(t) = t {
+                bins.entry(t.div_euclid(width) * width)
+                    .or_default()
+                    .push(row);
+            }
+        }
+
+        let starts: Vec<i64> = bins.keys().copied().collect();
+        let mut columns = vec![Series::new(column.into(), starts)
+            .cast(&DataType::Datetime(TIME_UNIT, None))?
+            .into()];
+        for name in self.column_names() {
+            let series = self.column(name)?;
+            if name == column || !series.dtype().is_float() {
+                continue;
+            }
+            let values = f64::from_column(series)?;
+            let means: Vec<f64> = bins
+                .values()
+                .map(|rows| {
+                    let valid: Vec<f64> = rows
+                        .iter()
+                        .map(|row| values[*row])
+                        .filter(|v| !v.is_nan())
+                        .collect();
+                    valid.iter().sum::<f64>() / valid.len() as f64
+                })
+                .collect();
+            columns.push(Series::new(name.into(), means).into());
+        }
+
+        let mut resampled = self.with_rows(DataFrame::new(columns)?);
+        resampled.lineage.clear();
+        Ok(resampled)
+    }
+
+    /// Returns the datetime column `name` in microseconds since the epoch.
+    fn timestamps(&self, name: &str) -> anyhow::Result<Vec<Option<i64>>> {
+        let column = self.column(name)?;
+        let DataType::Datetime(unit, _) = column.dtype() else {
+            anyhow::bail!("column '{}' is not a datetime column", name);
+        };
+        let unit = *unit;
+        Ok(column
+            .cast(&DataType::Int64)?
+            .i64()?
+            .into_iter()
+            .map(|t| {
+                t.map(|t| match unit {
+                    TimeUnit::Nanoseconds => t / 1000,
+                    TimeUnit::Microseconds => t,
+                    TimeUnit::Milliseconds => t * 1000,
+                })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,106 @@
+//! Column type codes of the `$` line.
+//!
+//! The [`TypeRegistry`] maps type codes to the kind of values they hold. It knows `%le`, `%d` and
+//! `%s` (with an optional width like `%08d`), further codes can be registered in the
+//! [`TfsReadOptions`](crate::TfsReadOptions). Columns with unknown codes are read as text and
+//! listed in [`TfsDataFrame::parse_warnings`](crate::TfsDataFrame::parse_warnings):
+//!
+//! ```
+//! # use tfs::{ColumnKind, TfsReadOptions};
+//! let options = TfsReadOptions::new()
+//!     .register_type("%lf", ColumnKind::Real)
+//!     .register_type("%f", ColumnKind::Real);
+//! ```
+//!
+//! Columns keep their type code when written again, as long as their type didn't change.
+use polars::prelude::DataType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How the values of a column are parsed and written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnKind {
+    /// Real numbers, a `Float64` column.
+    Real,
+    /// Integers, an `Int64` column.
+    Integer,
+    /// Quoted or unquoted strings, a `String` column.
+    Text,
+    /// `true` or `false`, a `Boolean` column. Only read in dialects with booleans, see
+    /// [`Dialect`](crate::Dialect).
+    Boolean,
+}
+
+impl ColumnKind {
+    /// The type code written for columns of this kind.
+    pub fn canonical_code(&self) -> &'static str {
+        match self {
+            ColumnKind::Real => "%le",
+            ColumnKind::Integer => "%d",
+            ColumnKind::Text => "%s",
+            ColumnKind::Boolean => "%b",
+        }
+    }
+
+    /// The type of the columns read with this kind.
+    pub fn dtype(&self) -> DataType {
+        match self {
+            ColumnKind::Real => DataType::Float64,
+            ColumnKind::Integer => DataType::Int64,
+            ColumnKind::Text => DataType::String,
+            ColumnKind::Boolean => DataType::Boolean,
+        }
+    }
+
+    /// The kind used to write a column of type `dtype`.
+    pub fn of_dtype(dtype: &DataType) -> ColumnKind {
+        if dtype.is_float() {
+            ColumnKind::Real
+        } else if dtype.is_integer() {
+            ColumnKind::Integer
+        } else if dtype.is_bool() {
+            ColumnKind::Boolean
+        } else {
+            ColumnKind::Text
+        }
+    }
+}
+
+/// Maps type codes to [`ColumnKind`]s.
+#[derive(Debug, Clone)]
+pub struct TypeRegistry {
+    codes: HashMap<String, ColumnKind>,
+}
+
+impl Default for TypeRegistry {
+    fn default() -> Self {
+        let mut registry = TypeRegistry {
+            codes: HashMap::new(),
+        };
+        for kind in [ColumnKind::Real, ColumnKind::Integer, ColumnKind::Text] {
+            registry.register(kind.canonical_code(), kind);
+        }
+        registry
+    }
+}
+
+impl TypeRegistry {
+    /// Maps `code` to `kind`, replacing a previous mapping.
+    pub fn register(&mut self, code: &str, kind: ColumnKind) {
+        self.codes.insert(code.to_owned(), kind);
+    }
+
+    /// Returns the kind of `code`. Codes that are not registered are looked up again without
+    /// their width, i.e. `%08d` as `%d`. Returns `None` for unknown codes.
+    pub fn kind(&self, code: &str) -> Option<ColumnKind> {
+        if let Some(kind) = self.codes.get(code) {
+            return Some(*kind);
+        }
+        let without_width: String = code
+            .chars()
+            .filter(|c| !c.is_ascii_digit() && *c != '-')
+            .collect();
+        self.codes.get(&without_width).copied()
+    }
+}
@@ -0,0 +1,127 @@
+//! Pairing of value columns with their error columns.
+//!
+//! Measurement files (e.g. from omc3) store the uncertainty of a column `X` in a column with the
+//! same name and a prefix, `ERRX` or `STDX`, and its deviation from the model in `DELTAX`.
+//! Deviations have errors themselves, the error of `DELTABETX` is `ERRDELTABETX`.
+//!
+//! ```
+//! # use tfs::TfsDataFrame;
+//! # use tfs::polars::prelude::*;
+//! let df = TfsDataFrame::<f64>::new(
+//!     vec![],
+//!     df!(
+//!         "BETX" => [1.0, 2.0],
+//!         "ERRBETX" => [0.1, 0.2],
+//!         "DELTABETX" => [0.01, 0.02],
+//!         "ERRDELTABETX" => [0.001, 0.002],
+//!     )
+//!     .unwrap(),
+//! );
+//!
+//! let pairs = df.value_error_pairs();
+//! assert_eq!(pairs[0].error_name, "ERRBETX");
+//! assert_eq!(pairs[1].error_name, "DELTABETX");
+//! assert_eq!(pairs[2].error_name, "ERRDELTABETX");
+//! assert!(df.validate_error_columns().is_ok());
+//! ```
+use polars::prelude::NumericNative;
+use polars::series::Series;
+
+use crate::tfsdataframe::TfsDataFrame;
+
+/// Prefix marking an error column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPrefix {
+    /// `ERR`, the measurement error.
+    Err,
+    /// `STD`, the standard deviation over several measurements.
+    Std,
+    /// `DELTA`, the deviation from the model.
+    Delta,
+}
+
+impl ErrorPrefix {
+    pub const ALL: [ErrorPrefix; 3] = [ErrorPrefix::Err, ErrorPrefix::Std, ErrorPrefix::Delta];
+
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            ErrorPrefix::Err => "ERR",
+            ErrorPrefix::Std => "STD",
+            ErrorPrefix::Delta => "DELTA",
+        }
+    }
+
+    /// Returns the prefix and the name of the value column if `name` is an error column.
+    fn split(name: &str) -> Option<(ErrorPrefix, &str)> {
+        ErrorPrefix::ALL.into_iter().find_map(|prefix| {
+            name.strip_prefix(prefix.prefix())
+                .filter(|value| !value.is_empty())
+                .map(|value| (prefix, value))
+        })
+    }
+}
+
+/// A value column and its error column.
+#[derive(Debug, Clone)]
+pub struct ValueErrorPair<'a> {
+    pub value_name: &'a str,
+    pub error_name: &'a str,
+    pub prefix: ErrorPrefix,
+    pub value: &'a Series,
+    pub error: &'a Series,
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsDataFrame<T> {
+    /// Returns all value columns that have an error column, in the order of the value columns.
+    /// A value column with several error columns, e.g. `ERR` and `STD`, appears once for each.
+    pub fn value_error_pairs(&self) -> Vec<ValueErrorPair<'_>> {
+        let names = self.column_names();
+        let mut pairs = Vec::new();
+
+        for value_name in &names {
+            for error_name in &names {
+                let Some((prefix, value)) = ErrorPrefix::split(error_name) else {
+                    continue;
+                };
+                if value != *value_name {
+                    continue;
+                }
+                if let (Ok(value), Ok(error)) = (self.column(value_name), self.column(error_name)) {
+                    pairs.push(ValueErrorPair {
+                        value_name,
+                        error_name,
+                        prefix,
+                        value,
+                        error,
+                    });
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Returns the error columns without a matching value column.
+    pub fn orphaned_error_columns(&self) -> Vec<&str> {
+        let names = self.column_names();
+        names
+            .iter()
+            .filter(|name| {
+                ErrorPrefix::split(name).is_some_and(|(_, value)| !names.contains(&value))
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Fails if an error column has no matching value column, see
+    /// [`TfsDataFrame::orphaned_error_columns`].
+    pub fn validate_error_columns(&self) -> anyhow::Result<()> {
+        let orphaned = self.orphaned_error_columns();
+        if !orphaned.is_empty() {
+            anyhow::bail!(
+                "the error columns {} have no matching value column",
+                orphaned.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+//! Sets of related frames.
+//!
+//! A [`TfsWorkbook`] holds named frames, like the sheets of a spreadsheet, e.g. the twiss tables
+//! of both beams and the optics corrections computed from them. It is saved either as a
+//! directory with one `<name>.tfs` file per frame or as a single `.tfs.zip` bundle, a zip
+//! archive of the files and a `manifest.json` listing the frames, their order and their shapes:
+//!
+//! ```
+//! # use tfs::{TfsDataFrame, TfsWorkbook};
+//! let mut workbook = TfsWorkbook::new();
+//! workbook.insert("twiss", TfsDataFrame::<f64>::open("test/test.tfs").unwrap()).unwrap();
+//!
+//! let dir = tempfile::tempdir().unwrap();
+//! let bundle = dir.path().join("results.tfs.zip");
+//! workbook.save_zip(&bundle).unwrap();
+//!
+//! let loaded = TfsWorkbook::<f64>::open_zip(&bundle).unwrap();
+//! assert_eq!(loaded.names().collect::<Vec<_>>(), vec!["twiss"]);
+//! assert_eq!(loaded.get("twiss").unwrap().len(), 5);
+//! ```
+use indexmap::IndexMap;
+use polars::prelude::NumericNative;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::catalog::collect_tfs_files;
+use crate::stages::TfsHeaderParser;
+use crate::tfsdataframe::TfsDataFrame;
+use crate::zip::{read_entries, ZipWriter};
+
+const EXTENSION: &str = "tfs";
+
+/// Extension of workbooks saved as zip archives.
+pub const BUNDLE_EXTENSION: &str = "tfs.zip";
+
+/// Name of the manifest entry of a bundle.
+pub const MANIFEST: &str = "manifest.json";
+
+/// Contents of the `manifest.json` of a bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    /// Version of the bundle layout, [`BundleManifest::VERSION`] for bundles written by this
+    /// crate.
+    pub version: u32,
+    pub frames: Vec<ManifestEntry>,
+}
+
+impl BundleManifest {
+    pub const VERSION: u32 = 1;
+}
+
+/// A frame listed in a [`BundleManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    /// Path of the tfs file in the archive.
+    pub file: String,
+    pub rows: usize,
+    pub columns: usize,
+}
+
+/// Named frames, in insertion order.
+#[derive(Debug)]
+pub struct TfsWorkbook<T: std::str::FromStr + NumericNative> {
+    frames: IndexMap<String, TfsDataFrame<T>>,
+}
+
+impl<T: std::str::FromStr + NumericNative> Default for TfsWorkbook<T> {
+    fn default() -> Self {
+        TfsWorkbook::new()
+    }
+}
+
+impl<T: std::str::FromStr + NumericNative> TfsWorkbook<T> {
+    pub fn new() -> TfsWorkbook<T> {
+        TfsWorkbook {
+            frames: IndexMap::new(),
+        }
+    }
+
+    /// Adds the frame `name`, returns the frame it replaces. Names are used as file names and
+    /// must not be empty or contain path separators.
+    pub fn insert(
+        &mut self,
+        name: &str,
+        frame: TfsDataFrame<T>,
+    ) -> anyhow::Result<Option<TfsDataFrame<T>>> {
+        anyhow::ensure!(
+            !name.is_empty() && !name.contains(['/', '\\']) && name != "." && name != "..",
+            "'{}' is not a valid frame name",
+            name
+        );
+        Ok(self.frames.insert(name.to_owned(), frame))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TfsDataFrame<T>> {
+        self.frames.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut TfsDataFrame<T>> {
+        self.frames.get_mut(name)
+    }
+
+    /// Removes the frame `name`, keeping the order of the others.
+    pub fn remove(&mut self, name: &str) -> Option<TfsDataFrame<T>> {
+        self.frames.shift_remove(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.frames.keys().map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TfsDataFrame<T>)> {
+        self.frames
+            .iter()
+            .map(|(name, frame)| (name.as_str(), frame))
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Loads all tfs files directly in `dir`, named after their file stem and sorted by name.
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> anyhow::Result<TfsWorkbook<T>>
+    where
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        let mut files = Vec::new();
+        collect_tfs_files(dir.as_ref(), &mut files)?;
+        files.retain(|file| file.parent() == Some(dir.as_ref()));
+        files.sort();
+
+        let mut workbook = TfsWorkbook::new();
+        for file in files {
+            let name = file
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| anyhow::anyhow!("invalid file name {}", file.display()))?;
+            let frame = TfsDataFrame::open(&file)
+                .map_err(|err| anyhow::anyhow!("{}: {}", file.display(), err))?;
+            workbook.insert(name, frame)?;
+        }
+        Ok(workbook)
+    }
+
+    /// Writes every frame to `dir/<name>.tfs`, creating `dir` if needed.
+    pub fn save_dir<P: AsRef<Path>>(&self, dir: P) -> anyhow::Result<()>
+    where
+        T: fmt::Display,
+    {
+        std::fs::create_dir_all(dir.as_ref())?;
+        for (name, frame) in &self.frames {
+            frame.write(dir.as_ref().join(format!("{}.{}", name, EXTENSION)))?;
+        }
+        Ok(())
+    }
+
+    /// Opens a bundle written by [`TfsWorkbook::save_zip`]. The frames are loaded in the order
+    /// of the manifest and checked against the shapes it lists. Zip archives without a manifest
+    /// are accepted too, all their `.tfs` entries are loaded in the order of the archive.
+    pub fn open_zip<P: AsRef<Path>>(path: P) -> anyhow::Result<TfsWorkbook<T>>
+    where
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        let archive = std::fs::read(path.as_ref())?;
+        let mut entries: IndexMap<String, Vec<u8>> = read_entries(&archive)?.into_iter().collect();
+
+        let manifest = match entries.shift_remove(MANIFEST) {
+            Some(manifest) => {
+                let manifest: BundleManifest = serde_json::from_slice(&manifest)?;
+                anyhow::ensure!(
+                    manifest.version <= BundleManifest::VERSION,
+                    "the bundle has version {}, only versions up to {} are supported",
+                    manifest.version,
+                    BundleManifest::VERSION
+                );
+                Some(manifest)
+            }
+            None => None,
+        };
+
+        let mut workbook = TfsWorkbook::new();
+        let Some(manifest) = manifest else {
+            for (file, data) in entries {
+                if let Some(name) = file.strip_suffix(&format!(".{}", EXTENSION)) {
+                    workbook.insert(name, read_entry(&file, data)?)?;
+                }
+            }
+            return Ok(workbook);
+        };
+
+        for entry in manifest.frames {
+            let data = entries.shift_remove(&entry.file).ok_or_else(|| {
+                anyhow::anyhow!("'{}' is listed in the manifest but missing", entry.file)
+            })?;
+            let frame = read_entry(&entry.file, data)?;
+            anyhow::ensure!(
+                frame.len() == entry.rows && frame.column_count() == entry.columns,
+                "'{}' has {} rows and {} columns, the manifest lists {} and {}",
+                entry.file,
+                frame.len(),
+                frame.column_count(),
+                entry.rows,
+                entry.columns
+            );
+            workbook.insert(&entry.name, frame)?;
+        }
+        Ok(workbook)
+    }
+
+    /// The manifest describing the frames of the workbook, as written by
+    /// [`TfsWorkbook::save_zip`].
+    pub fn manifest(&self) -> BundleManifest {
+        BundleManifest {
+            version: BundleManifest::VERSION,
+            frames: self
+                .frames
+                .iter()
+                .map(|(name, frame)| ManifestEntry {
+                    name: name.clone(),
+                    file: format!("{}.{}", name, EXTENSION),
+                    rows: frame.len(),
+                    columns: frame.column_count(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Writes the workbook as a bundle to `path`: a zip archive with the [`MANIFEST`] followed by
+    /// one `<name>.tfs` entry per frame. By convention, bundles have the extension
+    /// [`BUNDLE_EXTENSION`].
+    pub fn save_zip<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()>
+    where
+        T: fmt::Display,
+    {
+        let manifest = self.manifest();
+        let mut archive = ZipWriter::default();
+        archive.add(
+            MANIFEST,
+            serde_json::to_string_pretty(&manifest)?.as_bytes(),
+        )?;
+        for (entry, frame) in manifest.frames.iter().zip(self.frames.values()) {
+            let mut data = Vec::new();
+            frame.write_to(&mut data)?;
+            archive.add(&entry.file, &data)?;
+        }
+        std::fs::write(path, archive.finish()?)?;
+        Ok(())
+    }
+}
+
+fn read_entry<T>(file: &str, data: Vec<u8>) -> anyhow::Result<TfsDataFrame<T>>
+where
+    T: std::str::FromStr + NumericNative,
+    <T as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    TfsHeaderParser::new(Cursor::new(data))
+        .parse()?
+        .finish()
+        .map_err(|err| anyhow::anyhow!("{}: {}", file, err))
+}
@@ -0,0 +1,211 @@
+//! Minimal zip archives: deflated entries without encryption, zip64 or multiple disks, which is
+//! all that is needed to bundle tfs files. Archives written by other tools can be read as long as
+//! their entries are stored or deflated.
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+/// Version 2.0, the first one with deflate.
+const VERSION: u16 = 20;
+/// Entry names are UTF-8.
+const UTF8_NAMES: u16 = 0x0800;
+/// 1980-01-01 00:00 in MS-DOS format, entries carry no time so that archives are reproducible.
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+struct CentralEntry {
+    name: String,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Writes the entries of a zip archive into a buffer.
+#[derive(Default)]
+pub(crate) struct ZipWriter {
+    buffer: Vec<u8>,
+    entries: Vec<CentralEntry>,
+}
+
+impl ZipWriter {
+    pub fn add(&mut self, name: &str, data: &[u8]) -> anyhow::Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+
+        let entry = CentralEntry {
+            name: name.to_owned(),
+            crc: crc32fast::hash(data),
+            compressed_size: to_u32(compressed.len())?,
+            size: to_u32(data.len())?,
+            offset: to_u32(self.buffer.len())?,
+        };
+
+        let b = &mut self.buffer;
+        put_u32(b, LOCAL_HEADER);
+        put_u16(b, VERSION);
+        put_u16(b, UTF8_NAMES);
+        put_u16(b, DEFLATED);
+        put_u16(b, 0);
+        put_u16(b, DOS_DATE);
+        put_u32(b, entry.crc);
+        put_u32(b, entry.compressed_size);
+        put_u32(b, entry.size);
+        put_u16(b, to_u16(name.len())?);
+        put_u16(b, 0);
+        b.extend_from_slice(name.as_bytes());
+        b.extend_from_slice(&compressed);
+
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Appends the central directory and returns the archive.
+    pub fn finish(mut self) -> anyhow::Result<Vec<u8>> {
+        let start = to_u32(self.buffer.len())?;
+        let b = &mut self.buffer;
+        for entry in &self.entries {
+            put_u32(b, CENTRAL_HEADER);
+            put_u16(b, VERSION);
+            put_u16(b, VERSION);
+            put_u16(b, UTF8_NAMES);
+            put_u16(b, DEFLATED);
+            put_u16(b, 0);
+            put_u16(b, DOS_DATE);
+            put_u32(b, entry.crc);
+            put_u32(b, entry.compressed_size);
+            put_u32(b, entry.size);
+            put_u16(b, to_u16(entry.name.len())?);
+            put_u16(b, 0);
+            put_u16(b, 0);
+            put_u16(b, 0);
+            put_u16(b, 0);
+            put_u32(b, 0);
+            put_u32(b, entry.offset);
+            b.extend_from_slice(entry.name.as_bytes());
+        }
+        let size = to_u32(b.len())? - start;
+
+        let n_entries = to_u16(self.entries.len())?;
+        put_u32(b, END_OF_CENTRAL_DIRECTORY);
+        put_u16(b, 0);
+        put_u16(b, 0);
+        put_u16(b, n_entries);
+        put_u16(b, n_entries);
+        put_u32(b, size);
+        put_u32(b, start);
+        put_u16(b, 0);
+
+        Ok(self.buffer)
+    }
+}
+
+/// Reads all entries of a zip archive, in the order of the central directory. Directories are
+/// skipped.
+pub(crate) fn read_entries(archive: &[u8]) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    // the end record is at least 22 bytes long, followed by a comment of up to 64 KiB
+    let end = (0..archive.len().saturating_sub(21))
+        .rev()
+        .take(u16::MAX as usize + 1)
+        .find(|i| get_u32(archive, *i) == Some(END_OF_CENTRAL_DIRECTORY))
+        .ok_or_else(|| anyhow::anyhow!("not a zip archive"))?;
+    let n_entries = field_u16(archive, end + 10)?;
+    let mut at = field_u32(archive, end + 16)? as usize;
+
+    let mut entries = Vec::with_capacity(n_entries as usize);
+    for _ in 0..n_entries {
+        anyhow::ensure!(
+            get_u32(archive, at) == Some(CENTRAL_HEADER),
+            "corrupted zip directory"
+        );
+        let method = field_u16(archive, at + 10)?;
+        let crc = field_u32(archive, at + 16)?;
+        let compressed_size = field_u32(archive, at + 20)? as usize;
+        let size = field_u32(archive, at + 24)? as usize;
+        let name_len = field_u16(archive, at + 28)? as usize;
+        let extra_len = field_u16(archive, at + 30)? as usize;
+        let comment_len = field_u16(archive, at + 32)? as usize;
+        let offset = field_u32(archive, at + 42)? as usize;
+        let name = String::from_utf8_lossy(slice(archive, at + 46, name_len)?).into_owned();
+        at += 46 + name_len + extra_len + comment_len;
+
+        if name.ends_with('/') {
+            continue;
+        }
+
+        anyhow::ensure!(
+            get_u32(archive, offset) == Some(LOCAL_HEADER),
+            "corrupted zip entry '{}'",
+            name
+        );
+        let data_start = offset
+            + 30
+            + field_u16(archive, offset + 26)? as usize
+            + field_u16(archive, offset + 28)? as usize;
+        let compressed = slice(archive, data_start, compressed_size)?;
+
+        let data = match method {
+            STORED => compressed.to_vec(),
+            DEFLATED => {
+                let mut data = Vec::with_capacity(size);
+                DeflateDecoder::new(compressed).read_to_end(&mut data)?;
+                data
+            }
+            method => anyhow::bail!(
+                "zip entry '{}' uses the unsupported compression method {}",
+                name,
+                method
+            ),
+        };
+        anyhow::ensure!(
+            data.len() == size && crc32fast::hash(&data) == crc,
+            "zip entry '{}' is corrupted",
+            name
+        );
+        entries.push((name, data));
+    }
+    Ok(entries)
+}
+
+fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn to_u16(value: usize) -> anyhow::Result<u16> {
+    u16::try_from(value).map_err(|_| anyhow::anyhow!("too many entries for a zip archive"))
+}
+
+fn to_u32(value: usize) -> anyhow::Result<u32> {
+    u32::try_from(value).map_err(|_| anyhow::anyhow!("zip archives are limited to 4 GiB"))
+}
+
+fn slice(archive: &[u8], start: usize, len: usize) -> anyhow::Result<&[u8]> {
+    archive
+        .get(start..start + len)
+        .ok_or_else(|| anyhow::anyhow!("truncated zip archive"))
+}
+
+fn get_u32(archive: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        archive.get(at..at + 4)?.try_into().ok()?,
+    ))
+}
+
+fn field_u16(archive: &[u8], at: usize) -> anyhow::Result<u16> {
+    Ok(u16::from_le_bytes(slice(archive, at, 2)?.try_into()?))
+}
+
+fn field_u32(archive: &[u8], at: usize) -> anyhow::Result<u32> {
+    Ok(u32::from_le_bytes(slice(archive, at, 4)?.try_into()?))
+}
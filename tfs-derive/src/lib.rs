@@ -0,0 +1,158 @@
+//! Derive macros for the `tfs` crate. Use them through the `derive` feature of `tfs` rather than
+//! depending on this crate directly.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Field, Fields, LitStr};
+
+/// Implements `tfs::FromHeader` for a struct with named fields.
+///
+/// Every field is read from the header entry with the uppercase field name, or the name given
+/// by `#[tfs(rename = "KEY")]`. Field types have to implement `tfs::FromProperty`.
+#[proc_macro_derive(TfsHeader, attributes(tfs))]
+pub fn derive_tfs_header(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match impl_tfs_header(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn impl_tfs_header(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut inits = Vec::new();
+    for field in named_fields(input)? {
+        let ident = field.ident.as_ref().expect("named fields have identifiers");
+        let key = key_of(field)?;
+        inits.push(quote! {
+            #ident: ::tfs::FromProperty::from_property(#key, properties.get(#key))?
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::tfs::FromHeader for #name #ty_generics #where_clause {
+            fn from_properties<T: Copy + Into<f64>>(
+                properties: &::tfs::Properties<T>,
+            ) -> ::std::result::Result<Self, ::tfs::HeaderError> {
+                ::std::result::Result::Ok(Self {
+                    #(#inits,)*
+                })
+            }
+        }
+    })
+}
+
+/// Implements `tfs::TfsRecord` for a struct with named fields.
+///
+/// Every field is read from the column with the uppercase field name, or the name given by
+/// `#[tfs(rename = "COLUMN")]`. Field types have to implement `tfs::ColumnValue`.
+#[proc_macro_derive(TfsRecord, attributes(tfs))]
+pub fn derive_tfs_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match impl_tfs_record(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn impl_tfs_record(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut keys = Vec::new();
+    let mut readers = Vec::new();
+    let mut inits = Vec::new();
+    let mut writers = Vec::new();
+    for (index, field) in named_fields(input)?.enumerate() {
+        let ident = field.ident.as_ref().expect("named fields have identifiers");
+        let ty = &field.ty;
+        let key = key_of(field)?;
+        let values = format_ident!("__{}", ident);
+
+        readers.push(quote! {
+            let mut #values = <#ty as ::tfs::ColumnValue>::from_column(columns[#index])?.into_iter();
+        });
+        inits.push(quote! {
+            #ident: #values.next().expect("all columns have the same length")
+        });
+        writers.push(quote! {
+            <#ty as ::tfs::ColumnValue>::to_column(
+                #key,
+                records.iter().map(|r| ::std::clone::Clone::clone(&r.#ident)).collect(),
+            )
+        });
+        keys.push(key);
+    }
+    let n_fields = keys.len();
+
+    Ok(quote! {
+        impl #impl_generics ::tfs::TfsRecord for #name #ty_generics #where_clause {
+            fn columns() -> ::std::vec::Vec<&'static str> {
+                ::std::vec![#(#keys),*]
+            }
+
+            fn from_series(
+                columns: &[&::tfs::polars::series::Series],
+            ) -> ::tfs::anyhow::Result<::std::vec::Vec<Self>> {
+                if columns.len() != #n_fields {
+                    ::tfs::anyhow::bail!(
+                        "expected {} columns but got {}",
+                        #n_fields,
+                        columns.len()
+                    );
+                }
+                let len = columns.first().map_or(0, |c| c.len());
+                #(#readers)*
+                ::std::result::Result::Ok((0..len).map(|_| Self { #(#inits,)* }).collect())
+            }
+
+            fn to_series(records: &[Self]) -> ::std::vec::Vec<::tfs::polars::series::Series> {
+                ::std::vec![#(#writers),*]
+            }
+        }
+    })
+}
+
+fn named_fields(input: &DeriveInput) -> syn::Result<impl Iterator<Item = &Field>> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(fields.named.iter()),
+            _ => Err(syn::Error::new_spanned(
+                &input.ident,
+                "only structs with named fields are supported",
+            )),
+        },
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            "only structs with named fields are supported",
+        )),
+    }
+}
+
+/// The header key (or column name) of a field: either given by `#[tfs(rename = "...")]` or the
+/// uppercase field name.
+fn key_of(field: &Field) -> syn::Result<String> {
+    let mut key = None;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("tfs")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let value: LitStr = meta.value()?.parse()?;
+                key = Some(value.value());
+                Ok(())
+            } else {
+                Err(meta.error("unsupported tfs attribute, expected `rename`"))
+            }
+        })?;
+    }
+
+    Ok(key.unwrap_or_else(|| {
+        field
+            .ident
+            .as_ref()
+            .expect("named fields have identifiers")
+            .to_string()
+            .to_uppercase()
+    }))
+}
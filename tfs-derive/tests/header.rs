@@ -0,0 +1,46 @@
+use tfs::testing::{make_frame, FrameSpec};
+use tfs::{DataValue, FromHeader, HeaderError, TfsHeader};
+
+#[derive(TfsHeader, Debug, PartialEq)]
+struct TwissHeader {
+    q1: f64,
+    q2: f64,
+    sequence: String,
+    #[tfs(rename = "TYPE")]
+    table: String,
+    nrows: Option<i64>,
+}
+
+#[test]
+fn from_frame() {
+    let df = make_frame(&FrameSpec::default());
+    let header = TwissHeader::from_frame(&df).unwrap();
+
+    assert_eq!(
+        header,
+        TwissHeader {
+            q1: 62.31,
+            q2: 60.32,
+            sequence: "SYNTH".to_owned(),
+            table: "TWISS".to_owned(),
+            nrows: None,
+        }
+    );
+}
+
+#[test]
+fn typed_errors() {
+    let mut df = make_frame(&FrameSpec::default());
+    df.properties.shift_remove("Q2");
+    assert_eq!(
+        TwissHeader::from_frame(&df),
+        Err(HeaderError::Missing("Q2".to_owned()))
+    );
+
+    df.properties
+        .insert("Q2".to_owned(), DataValue::Text("0.32".to_owned()));
+    assert!(matches!(
+        TwissHeader::from_frame(&df),
+        Err(HeaderError::WrongType { key, .. }) if key == "Q2"
+    ));
+}
@@ -0,0 +1,48 @@
+use tfs::testing::{make_frame, FrameSpec};
+use tfs::{TfsDataFrame, TfsRecord};
+
+#[derive(TfsRecord, Debug, Clone, PartialEq)]
+struct Element {
+    name: String,
+    s: f64,
+    #[tfs(rename = "BETX")]
+    beta_x: f64,
+    #[tfs(rename = "DX")]
+    dispersion: Option<f64>,
+}
+
+#[test]
+fn read_records() {
+    let df = make_frame(&FrameSpec::default());
+    let elements: Vec<Element> = df.records().unwrap();
+
+    assert_eq!(elements.len(), df.len());
+    assert_eq!(elements[1].name, "BPM.1");
+    assert_eq!(
+        elements[1].beta_x,
+        df.column("BETX").unwrap().f64().unwrap().get(1).unwrap()
+    );
+}
+
+#[test]
+fn write_records() {
+    let df = make_frame(&FrameSpec::default());
+    let elements: Vec<Element> = df.records().unwrap();
+
+    let written = TfsDataFrame::<f64>::from_records(&elements).unwrap();
+    assert_eq!(written.column_count(), 4);
+    assert_eq!(written.column("BETX").unwrap(), df.column("BETX").unwrap());
+    assert_eq!(written.records::<Element>().unwrap(), elements);
+}
+
+#[test]
+fn missing_column() {
+    #[derive(TfsRecord)]
+    struct Wrong {
+        #[allow(dead_code)]
+        bety_model: f64,
+    }
+
+    let df = make_frame(&FrameSpec::default());
+    assert!(df.records::<Wrong>().is_err());
+}